@@ -0,0 +1,63 @@
+//! Feed arbitrary bytes to the task parsing the output of GDB, it must not
+//! panic whatever GDB or the debugged program prints. The server has no
+//! library target, so the modules are included from its sources
+
+#![no_main]
+
+use std::sync::Arc;
+use std::sync::atomic::AtomicBool;
+
+use libfuzzer_sys::fuzz_target;
+use tokio::sync::mpsc;
+
+#[allow(dead_code)]
+#[path = "../../src/error.rs"]
+mod error;
+
+#[allow(dead_code)]
+#[path = "../../src/metrics.rs"]
+mod metrics;
+
+#[allow(dead_code)]
+#[path = "../../src/mi/output.rs"]
+mod output;
+
+#[allow(dead_code)]
+#[path = "../../src/mi/queue.rs"]
+mod queue;
+
+#[allow(dead_code)]
+#[path = "../../src/mi/transcript.rs"]
+mod transcript;
+
+/// Where `output` finds the other modules
+mod mi {
+    pub(crate) use super::{output, queue, transcript};
+}
+
+use output::{Failure, process_output};
+use transcript::Transcript;
+
+fuzz_target!(|data: &[u8]| {
+    let runtime = tokio::runtime::Builder::new_current_thread().build().unwrap();
+    runtime.block_on(async {
+        let (result_input, mut result_output) = mpsc::channel(100);
+        let (oob_input, mut oob_output) = queue::channel();
+        let task = tokio::spawn(process_output(
+            std::io::Cursor::new(data.to_vec()),
+            result_input,
+            oob_input,
+            Arc::new(AtomicBool::new(false)),
+            Transcript::default(),
+            Failure::default(),
+        ));
+        loop {
+            tokio::select! {
+                Some(_) = result_output.recv() => {}
+                Some(_) = oob_output.recv() => {}
+                else => break,
+            }
+        }
+        task.await.expect("the output task panicked");
+    });
+});
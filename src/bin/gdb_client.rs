@@ -140,7 +140,8 @@ async fn main() -> Result<()> {
         Some(json!({
             "session_id": session_id,
             "file": "test_app.rs",
-            "line": 5
+            // the line marked `// breakpoint` in compute()
+            "line": 13
         })),
     )
     .await?;
@@ -1,7 +1,25 @@
-use anyhow::Result;
+//! Program debugged by the integration tests, run with `--wait` to keep it
+//! running until it is interrupted
 
-#[tokio::main]
-async fn main() -> Result<()> {
+use std::hint::black_box;
+use std::time::Duration;
+
+/// Read from the memory of the program by the tests
+static MESSAGE: [u8; 20] = *b"Hello from test_app\0";
+
+#[inline(never)]
+fn compute(value: u64) -> u64 {
+    let doubled = value * 2;
+    black_box(doubled) + 1 // breakpoint
+}
+
+fn main() {
     println!("Starting test application");
-    Ok(())
+    let result = compute(black_box(20));
+    println!("{} {}", black_box(&MESSAGE).len(), result);
+    if std::env::args().any(|arg| arg == "--wait") {
+        loop {
+            std::thread::sleep(Duration::from_millis(100));
+        }
+    }
 }
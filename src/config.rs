@@ -1,26 +1,310 @@
-#[derive(Debug)]
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::OnceLock;
+
+use serde::Deserialize;
+
+use crate::error::{AppError, AppResult};
+use crate::models::Endian;
+
+static CONFIG: OnceLock<Config> = OnceLock::new();
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
 /// Server Configuration
 pub struct Config {
     /// Server URL
     pub server_ip: String,
     /// Server port
     pub server_port: u16,
+    /// Port `/healthz` and `/metrics` are served on with the SSE transport,
+    /// the one after `server_port` if not set
+    pub metrics_port: Option<u16>,
     /// GDB command execution timeout in seconds
     pub command_timeout: u64,
+    /// GDB executable used when a session doesn't specify one
+    pub gdb_path: Option<PathBuf>,
+    /// Logging options
+    pub log: LogConfig,
+    /// Maximum number of concurrent sessions, unlimited if not set
+    pub max_sessions: Option<usize>,
+    /// Demangle Rust and C++ function names in stack frames and disassembly
+    pub demangle: bool,
+    /// TUI options
+    pub tui: TuiConfig,
+    /// Context bundled with the stops
+    pub stop_context: StopContextConfig,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+/// Logging Configuration
+pub struct LogConfig {
+    /// Directory of the log files
+    pub dir: PathBuf,
+    /// How often a new log file is started
+    pub rotation: LogRotation,
+    /// Number of log files to keep, unlimited if not set
+    pub max_files: Option<usize>,
+    /// Format of the log lines
+    pub format: LogFormat,
+    /// Logs go to stderr instead of files, set by `--log-to-stderr`
+    #[serde(skip)]
+    pub stderr: bool,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+/// Log file rotation policy
+pub enum LogRotation {
+    Minutely,
+    Hourly,
+    #[default]
+    Daily,
+    Never,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+/// Log line format
+pub enum LogFormat {
+    /// Single line human readable records
+    #[default]
+    Full,
+    /// Multi-line human readable records
+    Pretty,
+    /// Newline delimited JSON records
+    Json,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+/// TUI Configuration
+pub struct TuiConfig {
+    /// Enable the TUI
+    pub enabled: bool,
+    /// Minimum interval between two redraws in milliseconds, the changes in
+    /// between are drawn together
+    pub refresh_ms: u64,
+    /// Directory the hexdumps are saved to
+    pub dump_dir: PathBuf,
+    /// Width of the words shown, 32 or 64 bits, detected from the session if
+    /// not set
+    pub pointer_bits: Option<u32>,
+    /// Byte order of the words shown, detected from the session if not set
+    pub endian: Option<Endian>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+/// Context Bundled with the Stops
+pub struct StopContextConfig {
+    /// Add the context to every stop returned by the tools, and notified
+    /// along with them
+    pub enabled: bool,
+    /// Number of innermost frames, none if 0
+    pub frames: usize,
+    /// Local variables of frame 0
+    pub locals: bool,
+    /// Registers which changed since the previous stop
+    pub registers: bool,
+    /// Bytes read around `$sp` and `$pc`, none if 0
+    pub memory_bytes: usize,
 }
 
 impl Default for Config {
     fn default() -> Self {
         Self {
-            server_ip: std::env::var("SERVER_IP").unwrap_or_else(|_| "127.0.0.1".to_string()),
-            server_port: std::env::var("SERVER_PORT")
-                .unwrap_or_else(|_| "8080".to_string())
-                .parse()
-                .expect("Invalid server port"),
-            command_timeout: std::env::var("GDB_COMMAND_TIMEOUT")
-                .ok()
-                .and_then(|v| v.parse().ok())
-                .unwrap_or(10),
+            server_ip: "127.0.0.1".to_string(),
+            server_port: 8080,
+            metrics_port: None,
+            command_timeout: 10,
+            gdb_path: None,
+            log: LogConfig::default(),
+            max_sessions: None,
+            demangle: true,
+            tui: TuiConfig::default(),
+            stop_context: StopContextConfig::default(),
+        }
+    }
+}
+
+impl Default for LogConfig {
+    fn default() -> Self {
+        Self {
+            dir: default_data_dir().join("logs"),
+            rotation: LogRotation::default(),
+            max_files: None,
+            format: LogFormat::default(),
+            stderr: false,
+        }
+    }
+}
+
+impl Default for TuiConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            refresh_ms: 10,
+            dump_dir: default_data_dir().join("dumps"),
+            pointer_bits: None,
+            endian: None,
         }
     }
 }
+
+impl Default for StopContextConfig {
+    fn default() -> Self {
+        Self { enabled: false, frames: 5, locals: true, registers: true, memory_bytes: 64 }
+    }
+}
+
+impl Config {
+    /// Port `/healthz` and `/metrics` are served on
+    pub fn metrics_port(&self) -> u16 {
+        self.metrics_port.unwrap_or(self.server_port.wrapping_add(1))
+    }
+
+    /// Load the configuration, layered as defaults < configuration file < env.
+    ///
+    /// `path` must exist if given, otherwise `~/.config/mcp-gdb/config.toml`
+    /// is read when present.
+    pub fn load(path: Option<&Path>) -> AppResult<Self> {
+        let mut config = match path {
+            Some(path) => Self::from_file(path)?,
+            None => match default_path() {
+                Some(path) if path.is_file() => Self::from_file(&path)?,
+                _ => Self::default(),
+            },
+        };
+        config.apply_env()?;
+        Ok(config)
+    }
+
+    fn from_file(path: &Path) -> AppResult<Self> {
+        let content = std::fs::read_to_string(path).map_err(|e| {
+            AppError::InvalidArgument(format!("failed to read config {}: {}", path.display(), e))
+        })?;
+        toml::from_str(&content)
+            .map_err(|e| AppError::ParseError(format!("invalid config {}: {}", path.display(), e)))
+    }
+
+    fn apply_env(&mut self) -> AppResult<()> {
+        if let Ok(ip) = std::env::var("SERVER_IP") {
+            self.server_ip = ip;
+        }
+        if let Some(port) = env_parse("SERVER_PORT")? {
+            self.server_port = port;
+        }
+        if let Some(port) = env_parse("METRICS_PORT")? {
+            self.metrics_port = Some(port);
+        }
+        if let Some(timeout) = env_parse("GDB_COMMAND_TIMEOUT")? {
+            self.command_timeout = timeout;
+        }
+        if let Some(gdb_path) = std::env::var_os("GDB_PATH") {
+            self.gdb_path = Some(PathBuf::from(gdb_path));
+        }
+        if let Some(log_dir) = std::env::var_os("GDB_LOG_DIR") {
+            self.log.dir = PathBuf::from(log_dir);
+        }
+        if let Some(max_sessions) = env_parse("GDB_MAX_SESSIONS")? {
+            self.max_sessions = Some(max_sessions);
+        }
+        Ok(())
+    }
+}
+
+/// `$XDG_CONFIG_HOME/mcp-gdb/config.toml`, or `~/.config/mcp-gdb/config.toml`
+fn default_path() -> Option<PathBuf> {
+    let config_dir = std::env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| Path::new(&home).join(".config")))?;
+    Some(config_dir.join("mcp-gdb").join("config.toml"))
+}
+
+/// Per-user data directory of the platform, the working directory is not used
+/// since MCP clients may launch the server anywhere
+fn default_data_dir() -> PathBuf {
+    let home = std::env::var_os("HOME").map(PathBuf::from);
+    let data_dir = if cfg!(windows) {
+        std::env::var_os("LOCALAPPDATA").map(PathBuf::from)
+    } else if cfg!(target_os = "macos") {
+        home.map(|home| home.join("Library").join("Application Support"))
+    } else {
+        std::env::var_os("XDG_DATA_HOME")
+            .map(PathBuf::from)
+            .or_else(|| home.map(|home| home.join(".local").join("share")))
+    };
+    data_dir.unwrap_or_else(std::env::temp_dir).join("mcp-gdb")
+}
+
+fn env_parse<T: FromStr>(name: &str) -> AppResult<Option<T>> {
+    std::env::var(name)
+        .ok()
+        .map(|value| {
+            value.parse().map_err(|_| {
+                AppError::InvalidArgument(format!("invalid value of {}: {}", name, value))
+            })
+        })
+        .transpose()
+}
+
+/// Install the configuration in effect, later calls are ignored
+pub fn init(config: Config) {
+    let _ = CONFIG.set(config);
+}
+
+/// Configuration in effect, the defaults if `init` was not called
+pub fn get() -> &'static Config {
+    CONFIG.get_or_init(Config::default)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_partial_config_file() {
+        let config: Config = toml::from_str(
+            "server_port = 9000\ngdb_path = \"/usr/bin/gdb-multiarch\"\n[tui]\nenabled = true\n",
+        )
+        .unwrap();
+        assert_eq!(config.server_port, 9000);
+        assert_eq!(config.server_ip, "127.0.0.1");
+        assert_eq!(config.gdb_path, Some(PathBuf::from("/usr/bin/gdb-multiarch")));
+        assert!(config.tui.enabled);
+        assert_eq!(config.tui.refresh_ms, 10);
+        assert_eq!(config.tui.endian, None);
+
+        let config: Config =
+            toml::from_str("[tui]\npointer_bits = 32\nendian = \"big\"\n").unwrap();
+        assert_eq!(config.tui.pointer_bits, Some(32));
+        assert_eq!(config.tui.endian, Some(Endian::Big));
+    }
+
+    #[test]
+    fn test_log_config() {
+        let config: Config =
+            toml::from_str("[log]\nrotation = \"hourly\"\nmax_files = 5\nformat = \"json\"\n")
+                .unwrap();
+        assert_eq!(config.log.rotation, LogRotation::Hourly);
+        assert_eq!(config.log.max_files, Some(5));
+        assert_eq!(config.log.format, LogFormat::Json);
+        assert!(config.log.dir.ends_with("mcp-gdb/logs"));
+    }
+
+    #[test]
+    fn test_stop_context_config() {
+        let config: Config =
+            toml::from_str("[stop_context]\nenabled = true\nmemory_bytes = 0\n").unwrap();
+        assert!(config.stop_context.enabled);
+        assert_eq!(config.stop_context.frames, 5);
+        assert_eq!(config.stop_context.memory_bytes, 0);
+    }
+
+    #[test]
+    fn test_unknown_config_key() {
+        assert!(toml::from_str::<Config>("server_prot = 9000\n").is_err());
+    }
+}
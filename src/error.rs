@@ -8,6 +8,18 @@ pub enum AppError {
     #[error("GDB error: {0}")]
     GDBError(String),
 
+    #[error("GDB command error: {msg}")]
+    GDBCommandError { msg: String, code: Option<String> },
+
+    #[error(
+        "GDB {path} is unusable: {reason}. Install gdb, pass gdb_path pointing to a working GDB, \
+         or use gdb-multiarch when debugging a foreign architecture"
+    )]
+    GDBUnusable { path: String, reason: String },
+
+    #[error("GDB server error: {0}")]
+    GDBServerError(String),
+
     #[error("GDB timeout")]
     GDBTimeout,
 
@@ -17,6 +29,9 @@ pub enum AppError {
     #[error("GDB quit")]
     GDBQuit,
 
+    #[error("Cancelled")]
+    Cancelled,
+
     #[error("Parse error: {0}")]
     ParseError(String),
 
@@ -1,66 +1,340 @@
-use std::collections::HashMap;
+pub mod annotations;
+pub mod arch;
+pub mod cache;
+pub mod call;
+pub mod console;
+pub mod container;
+pub mod context;
+pub mod corruption;
+pub mod coverage;
+pub mod deadlock;
+pub mod demangle;
+pub mod disassemble;
+pub mod discovery;
+pub mod embedded;
+pub mod events;
+pub mod frames;
+pub mod gadgets;
+pub mod got;
+pub mod hits;
+pub mod hooks;
+pub mod memory;
+pub mod modules;
+pub mod plan;
+pub mod process;
+pub mod profile;
+pub mod python;
+pub mod qemu;
+pub mod record;
+pub mod registers;
+pub mod remote;
+pub mod rtos;
+pub mod skip;
+pub mod svd;
+pub mod symbols;
+pub mod threads;
+pub mod trace;
+pub mod until;
+pub mod watches;
+
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::ffi::OsString;
 use std::path::{Path, PathBuf};
-use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
-use tokio::sync::{Mutex, mpsc};
+use serde::Serialize;
+use serde_json::Value;
+use tokio::process::Child;
+use tokio::sync::{Mutex, watch};
 use tokio::task::JoinHandle;
 use tracing::{debug, error, warn};
 use uuid::Uuid;
 
+use self::annotations::Annotations;
+use self::cache::MemoryCache;
+use self::console::{ConsoleBuffer, ConsoleOutput};
+use self::events::EventLog;
+use self::hits::HitCounts;
+use self::hooks::Hooks;
+use self::modules::{ModuleMap, load_object};
+use self::registers::RegisterTracker;
+use self::rtos::{RtosState, RtosTask};
+use self::threads::ThreadTable;
+use self::watches::MemoryWatch;
 use crate::TRANSPORT;
-use crate::config::Config;
+use crate::config::{self, Config};
 use crate::error::{AppError, AppResult};
-use crate::mi::commands::{BreakPointLocation, BreakPointNumber, MiCommand, RegisterFormat};
-use crate::mi::output::{OutOfBandRecord, ResultClass, ResultRecord};
-use crate::mi::{GDB, GDBBuilder};
+use crate::metrics::METRICS;
+use crate::mi::commands::{
+    BreakPointLocation, BreakPointNumber, MiCommand, RegisterFormat, escape_command,
+};
+use crate::mi::output::{AsyncClass, BreakPointEvent, OutOfBandRecord, ResultClass, ResultRecord};
+use crate::mi::queue::{self, OutOfBandReceiver, QueueMonitor};
+use crate::mi::transcript::TranscriptFormat;
+use crate::mi::{GDB, GDBBuilder, probe_gdb};
 use crate::models::{
-    BreakPoint, GDBSession, GDBSessionStatus, Memory, Register, StackFrame, Variable,
+    BreakPoint, GDBSession, GDBSessionStatus, Memory, PrintValue, Register, RegisterValue,
+    SchedulerLocking, StackFrame, StepTrace, ThreadInfo, Variable, VectorFormat, vector_lanes,
 };
 
 /// GDB Session Manager
-#[derive(Default)]
 pub struct GDBManager {
     /// Configuration
     config: Config,
     /// Session mapping table
     sessions: Mutex<HashMap<String, GDBSessionHandle>>,
+    /// GDB binaries which already passed the startup probe
+    probed_gdb: Mutex<HashSet<PathBuf>>,
+    /// Last command and error of all sessions
+    activity: std::sync::Mutex<Activity>,
+    /// Bumped when a session is created, restarted or closed, or its status
+    /// or architecture changes
+    sessions_changed: watch::Sender<u64>,
+}
+
+/// What GDB was last asked, shown in the status bar of the TUI
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Activity {
+    pub last_command: Option<String>,
+    /// Error of the last command, None once one succeeds
+    pub last_error: Option<String>,
+}
+
+/// What the sessions can do beyond the common tools, the tools needing a
+/// feature are listed once a session has it
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Features {
+    /// An SVD file is loaded in a session
+    pub svd: bool,
+    /// The execution is recorded in a session
+    pub recording: bool,
+}
+
+impl Default for GDBManager {
+    fn default() -> Self {
+        Self {
+            config: config::get().clone(),
+            sessions: Default::default(),
+            probed_gdb: Default::default(),
+            activity: Default::default(),
+            sessions_changed: watch::Sender::new(0),
+        }
+    }
 }
 
 /// GDB Session Handle
 struct GDBSessionHandle {
     /// Session information
     info: GDBSession,
-    /// GDB instance
-    gdb: GDB,
+    /// GDB instance, shared with the commands in progress so the session is
+    /// not locked while GDB answers
+    gdb: Arc<GDB>,
+    /// Command line of GDB, to restart it
+    builder: GDBBuilder,
     /// OOB handle
     oob_handle: JoinHandle<()>,
+    /// Stream records received from GDB
+    console: Arc<std::sync::Mutex<ConsoleBuffer>>,
+    /// Out of band records received from GDB
+    events: Arc<std::sync::Mutex<EventLog>>,
+    /// Out of band records waiting to be handled
+    output: QueueMonitor,
+    /// Threads announced by GDB
+    threads: Arc<std::sync::Mutex<ThreadTable>>,
+    /// Memory read since the program stopped
+    memory_cache: Arc<std::sync::Mutex<MemoryCache>>,
+    /// Hits of the breakpoints notified by GDB
+    hits: Arc<std::sync::Mutex<HitCounts>>,
+    /// Shared libraries notified by GDB
+    modules: Arc<std::sync::Mutex<ModuleMap>>,
+    /// Stops of the program
+    stops: watch::Receiver<StopState>,
+    /// Tools waiting for the program to stop
+    waiters: Arc<AtomicUsize>,
+    /// Bumped to cancel the waits in progress
+    cancel: watch::Sender<u64>,
+    /// Variable object names of the watch expressions, and the expressions
+    watches: BTreeMap<String, String>,
+    /// Memory regions hashed on every stop, by ID
+    memory_watches: BTreeMap<u64, MemoryWatch>,
+    /// Names and comments imported from a static analysis
+    annotations: Annotations,
+    /// Register names indexed by number, empty until first queried
+    register_names: Vec<String>,
+    /// Register snapshots of the last stops
+    registers: RegisterTracker,
+    /// GDB server launched for the session
+    server: Option<Child>,
+    /// Transport and address of the remote target, connected again when
+    /// the session is restarted
+    target: Option<(String, String)>,
+    /// Peripherals of the device, from the loaded SVD file
+    svd: Option<Arc<svd::Device>>,
+    /// The execution is recorded, as started and stopped by the tools
+    recording: bool,
+    /// RTOS awareness, off by default
+    rtos: Option<RtosState>,
+    /// Actions run when breakpoints are hit
+    hooks: Hooks,
+}
+
+impl GDBSessionHandle {
+    /// Session information, errored if GDB failed
+    fn session(&self) -> GDBSession {
+        let mut info = self.info.clone();
+        if let Some(reason) = self.gdb.failure().get() {
+            info.status = GDBSessionStatus::Errored;
+            info.error = Some(reason);
+        }
+        info
+    }
+}
+
+/// How a resumed program stopped
+#[derive(Debug, Clone)]
+pub enum Resumed {
+    /// On its own, with the results of the `*stopped` record
+    Stopped(Value),
+    /// Interrupted after a timeout
+    Interrupted(Value),
+}
+
+/// Threads of a program
+#[derive(Debug, Clone, Serialize)]
+pub struct ThreadList {
+    /// Threads as reported by GDB
+    pub threads: Vec<ThreadInfo>,
+    pub current_thread_id: Option<String>,
+    /// Tasks of the RTOS when the RTOS awareness is enabled
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rtos_threads: Option<Vec<RtosTask>>,
+}
+
+/// Liveness of a session, known without sending a command to GDB
+#[derive(Debug, Clone, Serialize)]
+pub struct SessionPing {
+    pub status: GDBSessionStatus,
+    /// Whether the GDB process is still running
+    pub debugger_alive: bool,
+    /// Whether a program is being debugged, from the thread notifications
+    pub program_alive: bool,
+    /// Whether the program is running, rather than stopped
+    pub running: bool,
+    pub live_threads: usize,
+    /// Why the session failed
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Command line of the GDB of a new session, every option is left out by
+/// default
+#[derive(Debug, Clone, Default)]
+pub struct SessionOptions {
+    /// The executable file to debug
+    pub program: Option<PathBuf>,
+    /// Do not read ~/.gdbinit file
+    pub nh: Option<bool>,
+    /// Do not read any .gdbinit files in any directory
+    pub nx: Option<bool>,
+    /// Do not print version number on startup
+    pub quiet: Option<bool>,
+    /// Change current directory to DIR
+    pub cd: Option<PathBuf>,
+    /// Serial port baud rate used for remote debugging
+    pub bps: Option<u32>,
+    /// Read symbols from SYMFILE
+    pub symbol_file: Option<PathBuf>,
+    /// Analyze the core dump COREFILE
+    pub core_file: Option<PathBuf>,
+    /// Attach to running process PID
+    pub proc_id: Option<u32>,
+    /// Execute GDB commands from FILE
+    pub command: Option<PathBuf>,
+    /// Search for source files in DIR
+    pub source_dir: Option<PathBuf>,
+    /// Arguments to be passed to the inferior program
+    pub args: Option<Vec<OsString>>,
+    /// Use TTY for input/output by the program being debugged
+    pub tty: Option<PathBuf>,
+    /// GDB executable, one matching the architecture of the program by
+    /// default
+    pub gdb_path: Option<PathBuf>,
+    /// Local copy of the root filesystem of a remote target
+    pub sysroot: Option<PathBuf>,
+}
+
+/// `*stopped` records received in a session
+#[derive(Debug, Clone, Default)]
+pub struct StopState {
+    /// Number of stops so far
+    pub count: u64,
+    /// Results of the last stop
+    pub last: Option<Value>,
+    /// Whether the last stop was notified to the client as it was received,
+    /// otherwise a tool waits for it and notifies it along with its context
+    pub notified: bool,
+}
+
+/// A tool waiting for a stop, counted while it waits
+struct StopWaiter(Arc<AtomicUsize>);
+
+impl StopWaiter {
+    fn new(waiters: &Arc<AtomicUsize>) -> Self {
+        waiters.fetch_add(1, Ordering::SeqCst);
+        Self(waiters.clone())
+    }
+}
+
+impl Drop for StopWaiter {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::SeqCst);
+    }
 }
 
 impl GDBManager {
     /// Create a new GDB session
-    pub async fn create_session(
-        &self,
-        program: Option<PathBuf>,
-        nh: Option<bool>,
-        nx: Option<bool>,
-        quiet: Option<bool>,
-        cd: Option<PathBuf>,
-        bps: Option<u32>,
-        symbol_file: Option<PathBuf>,
-        core_file: Option<PathBuf>,
-        proc_id: Option<u32>,
-        command: Option<PathBuf>,
-        source_dir: Option<PathBuf>,
-        args: Option<Vec<OsString>>,
-        tty: Option<PathBuf>,
-        gdb_path: Option<PathBuf>,
-    ) -> AppResult<String> {
+    pub async fn create_session(&self, options: SessionOptions) -> AppResult<String> {
+        let SessionOptions {
+            program,
+            nh,
+            nx,
+            quiet,
+            cd,
+            bps,
+            symbol_file,
+            core_file,
+            proc_id,
+            command,
+            source_dir,
+            args,
+            tty,
+            gdb_path,
+            sysroot,
+        } = options;
+
         // Generate unique session ID
         let session_id = Uuid::new_v4().to_string();
 
+        // fail before starting GDB, checked again when the session is stored
+        let sessions = self.sessions.lock().await;
+        self.check_session_limit(&sessions)?;
+        drop(sessions);
+
+        let gdb_path = gdb_path
+            .or_else(|| self.config.gdb_path.clone())
+            .or_else(|| program.as_deref().and_then(discovery::select_for_program))
+            .unwrap_or_else(|| PathBuf::from("gdb"));
+        if !self.probed_gdb.lock().await.contains(&gdb_path) {
+            let version =
+                probe_gdb(&gdb_path, Duration::from_secs(self.config.command_timeout)).await?;
+            debug!("Using {} ({})", gdb_path.display(), version);
+            self.probed_gdb.lock().await.insert(gdb_path.clone());
+        }
+
         let gdb_builder = GDBBuilder {
-            gdb_path: gdb_path.unwrap_or_else(|| PathBuf::from("gdb")),
+            gdb_path,
             opt_nh: nh.unwrap_or(false),
             opt_nx: nx.unwrap_or(false),
             opt_quiet: quiet.unwrap_or(false),
@@ -72,65 +346,73 @@ impl GDBManager {
             opt_command: command,
             opt_source_dir: source_dir,
             opt_args: args.unwrap_or(vec![]),
-            opt_program: program,
+            opt_program: program.clone(),
             opt_tty: tty,
         };
 
-        let (oob_src, mut oob_sink) = mpsc::channel(100);
-        let gdb = gdb_builder.try_spawn(oob_src)?;
-
-        let oob_handle = tokio::spawn(async move {
-            loop {
-                match oob_sink.recv().await {
-                    Some(record) => match record {
-                        OutOfBandRecord::AsyncRecord { results, .. } => {
-                            let transport = TRANSPORT.lock().await;
-                            if let Some(transport) = transport.as_ref() {
-                                if let Err(e) = transport
-                                    .send_notification("create_session", Some(results))
-                                    .await
-                                {
-                                    error!("Failed to send ping to session: {:?}", e);
-                                }
-                            } else {
-                                warn!("Sink Channel closed");
-                                break;
-                            }
-                        }
-                        OutOfBandRecord::StreamRecord { data, .. } => {
-                            debug!("StreamRecord: {:?}", data);
-                        }
-                    },
-                    None => {
-                        debug!("Source Channel closed");
-                        break;
-                    }
-                }
-            }
-        });
-
         // Create session information
         let session = GDBSession {
             id: session_id.clone(),
             status: GDBSessionStatus::Created,
             created_at: SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs(),
+            arch: Default::default(),
+            parameters: BTreeMap::new(),
+            error: None,
         };
 
         // Store session
-        let handle = GDBSessionHandle { info: session, gdb, oob_handle };
-
-        self.sessions.lock().await.insert(session_id.clone(), handle);
+        let handle = spawn_session(session, gdb_builder)?;
+        {
+            // with the same lock, concurrent creations cannot exceed the limit
+            let mut sessions = self.sessions.lock().await;
+            if let Err(e) = self.check_session_limit(&sessions) {
+                drop(sessions);
+                handle.oob_handle.abort();
+                handle.gdb.kill().await;
+                return Err(e);
+            }
+            sessions.insert(session_id.clone(), handle);
+        }
+        self.notify_sessions_changed();
+        METRICS.session_opened();
 
         // Send empty command to GDB to flush the welcome messages
-        let _ = self.send_command(&session_id, &MiCommand::empty()).await?;
+        if let Err(e) = self.send_command(&session_id, &MiCommand::empty()).await {
+            self.abandon_session(&session_id).await;
+            return Err(e);
+        }
+
+        if let Some(sysroot) = sysroot
+            && let Err(e) = self.set_sysroot(&session_id, &sysroot).await
+        {
+            self.abandon_session(&session_id).await;
+            return Err(e);
+        }
+
+        if let Err(e) = self.detect_arch(&session_id, program.as_deref()).await {
+            warn!("Failed to detect the target architecture: {}", e);
+        }
 
         Ok(session_id)
     }
 
+    /// Fail if no more sessions can be created
+    fn check_session_limit(&self, sessions: &HashMap<String, GDBSessionHandle>) -> AppResult<()> {
+        match self.config.max_sessions {
+            Some(max_sessions) if sessions.len() >= max_sessions => {
+                Err(AppError::InvalidArgument(format!(
+                    "Maximum number of sessions ({}) reached, close a session first",
+                    max_sessions
+                )))
+            }
+            _ => Ok(()),
+        }
+    }
+
     /// Get all sessions
     pub async fn get_all_sessions(&self) -> AppResult<Vec<GDBSession>> {
         let sessions = self.sessions.lock().await;
-        let result = sessions.values().map(|handle| handle.info.clone()).collect();
+        let result = sessions.values().map(GDBSessionHandle::session).collect();
         Ok(result)
     }
 
@@ -140,7 +422,158 @@ impl GDBManager {
         let handle = sessions
             .get(session_id)
             .ok_or_else(|| AppError::NotFound(format!("Session {} does not exist", session_id)))?;
-        Ok(handle.info.clone())
+        Ok(handle.session())
+    }
+
+    /// Check that a session is alive, without talking to GDB so it answers
+    /// while the program runs or a command is in progress
+    pub async fn ping_session(&self, session_id: &str) -> AppResult<SessionPing> {
+        let sessions = self.sessions.lock().await;
+        let handle = sessions
+            .get(session_id)
+            .ok_or_else(|| AppError::NotFound(format!("Session {} does not exist", session_id)))?;
+        let session = handle.session();
+        let (program_alive, live_threads) = {
+            let threads = handle.threads.lock().unwrap();
+            (threads.is_program_alive(), threads.live_threads())
+        };
+        Ok(SessionPing {
+            status: session.status,
+            debugger_alive: handle.gdb.is_alive().await,
+            program_alive,
+            running: handle.gdb.is_running(),
+            live_threads,
+            error: session.error,
+        })
+    }
+
+    /// Whether the program of a session is running, false if there is no
+    /// such session
+    pub async fn is_running(&self, session_id: &str) -> bool {
+        let sessions = self.sessions.lock().await;
+        sessions.get(session_id).is_some_and(|handle| handle.gdb.is_running())
+    }
+
+    /// Set the directory holding a copy of the target's root filesystem, so
+    /// shared libraries of a remote target are read locally
+    pub async fn set_sysroot(&self, session_id: &str, sysroot: &Path) -> AppResult<()> {
+        self.set_parameter(session_id, "sysroot", &escape_command(&sysroot.to_string_lossy())).await
+    }
+
+    /// Set which threads may run while one is resumed, returns the previous
+    /// mode
+    pub async fn set_scheduler_locking(
+        &self,
+        session_id: &str,
+        mode: SchedulerLocking,
+    ) -> AppResult<SchedulerLocking> {
+        let previous = self.show_setting(session_id, "scheduler-locking").await?.parse()?;
+        self.set_parameter(session_id, "scheduler-locking", mode.as_str()).await?;
+        Ok(previous)
+    }
+
+    /// Get the value of a GDB parameter, e.g. `print elements`
+    pub async fn show_setting(&self, session_id: &str, variable: &str) -> AppResult<String> {
+        let response =
+            self.send_command_with_timeout(session_id, &MiCommand::gdb_show(variable)).await?;
+        response
+            .results
+            .get("value")
+            .and_then(|value| value.as_str())
+            .map(|value| value.to_string())
+            .ok_or(AppError::NotFound("expect value".to_string()))
+    }
+
+    /// Set a GDB parameter, e.g. `pagination off`, and remember it to set it
+    /// again when the session is restarted
+    pub async fn set_parameter(
+        &self,
+        session_id: &str,
+        variable: &str,
+        value: &str,
+    ) -> AppResult<()> {
+        self.set_setting(session_id, variable, value).await?;
+        let mut sessions = self.sessions.lock().await;
+        let handle = sessions
+            .get_mut(session_id)
+            .ok_or_else(|| AppError::NotFound(format!("Session {} does not exist", session_id)))?;
+        handle.info.parameters.insert(variable.to_string(), value.to_string());
+        Ok(())
+    }
+
+    /// Set a GDB parameter for a while, e.g. around a call, it is not set
+    /// again when the session is restarted
+    pub async fn set_setting(
+        &self,
+        session_id: &str,
+        variable: &str,
+        value: &str,
+    ) -> AppResult<()> {
+        self.send_command_with_timeout(session_id, &MiCommand::gdb_set(variable, value)).await?;
+        Ok(())
+    }
+
+    /// Start GDB of a session again with the same command line, set the
+    /// parameters set so far and connect the remote target again. The
+    /// program, breakpoints and watches are gone, a GDB server launched for
+    /// the session is kept
+    pub async fn restart_session(&self, session_id: &str) -> AppResult<GDBSession> {
+        let (mut info, builder) = {
+            let sessions = self.sessions.lock().await;
+            let handle = sessions.get(session_id).ok_or_else(|| {
+                AppError::NotFound(format!("Session {} does not exist", session_id))
+            })?;
+            (handle.info.clone(), handle.builder.clone())
+        };
+        if let Err(e) = self.send_command_with_timeout(session_id, &MiCommand::exit()).await {
+            warn!("GDB exit command failed, forcing process termination: {}", e);
+        }
+
+        info.status = GDBSessionStatus::Created;
+        let program = builder.opt_program.clone();
+        let mut handle = spawn_session(info, builder)?;
+        let old = {
+            let mut sessions = self.sessions.lock().await;
+            let Some(old) = sessions.get_mut(session_id) else {
+                handle.gdb.kill().await;
+                return Err(AppError::NotFound(format!("Session {} does not exist", session_id)));
+            };
+            handle.server = old.server.take();
+            handle.target = old.target.clone();
+            std::mem::replace(old, handle)
+        };
+        old.oob_handle.abort();
+        old.gdb.kill().await;
+        self.notify_sessions_changed();
+
+        // Send empty command to GDB to flush the welcome messages
+        self.send_command(session_id, &MiCommand::empty()).await?;
+        self.restore_session(session_id).await?;
+        if let Err(e) = self.detect_arch(session_id, program.as_deref()).await {
+            warn!("Failed to detect the target architecture: {}", e);
+        }
+        self.get_session(session_id).await
+    }
+
+    /// Set the parameters of a restarted session and connect its remote
+    /// target
+    async fn restore_session(&self, session_id: &str) -> AppResult<()> {
+        let (parameters, target) = {
+            let sessions = self.sessions.lock().await;
+            let handle = sessions.get(session_id).ok_or_else(|| {
+                AppError::NotFound(format!("Session {} does not exist", session_id))
+            })?;
+            (handle.info.parameters.clone(), handle.target.clone())
+        };
+        for (variable, value) in &parameters {
+            if let Err(e) = self.set_setting(session_id, variable, value).await {
+                warn!("Failed to set {} to {} again: {}", variable, value, e);
+            }
+        }
+        if let Some((transport, address)) = target {
+            self.connect_target(session_id, &transport, &address).await?;
+        }
+        Ok(())
     }
 
     /// Close session
@@ -158,30 +591,318 @@ impl GDBManager {
         let handle = sessions.remove(session_id);
 
         if let Some(handle) = handle {
+            self.notify_sessions_changed();
+            METRICS.session_closed();
             handle.oob_handle.abort();
             // Terminate process
-            let mut process = handle.gdb.process.lock().await;
-            let _ = process.kill().await; // Ignore possible errors, process may have already terminated
+            handle.gdb.kill().await;
+            if let Some(mut server) = handle.server {
+                let _ = server.kill().await;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Number of times the program stopped in a session
+    pub async fn stop_count(&self, session_id: &str) -> AppResult<u64> {
+        let sessions = self.sessions.lock().await;
+        let handle = sessions
+            .get(session_id)
+            .ok_or_else(|| AppError::NotFound(format!("Session {} does not exist", session_id)))?;
+        Ok(handle.stops.borrow().count)
+    }
+
+    /// Changes of the sessions, to follow them without polling
+    pub fn subscribe_sessions(&self) -> watch::Receiver<u64> {
+        self.sessions_changed.subscribe()
+    }
+
+    /// Features of all the sessions
+    pub async fn features(&self) -> Features {
+        let sessions = self.sessions.lock().await;
+        sessions.values().fold(Features::default(), |features, handle| Features {
+            svd: features.svd || handle.svd.is_some(),
+            recording: features.recording || handle.recording,
+        })
+    }
+
+    fn notify_sessions_changed(&self) {
+        self.sessions_changed.send_modify(|count| *count += 1);
+    }
+
+    /// Follow the stops of the program in a session
+    pub async fn subscribe_stops(&self, session_id: &str) -> AppResult<watch::Receiver<StopState>> {
+        let sessions = self.sessions.lock().await;
+        let handle = sessions
+            .get(session_id)
+            .ok_or_else(|| AppError::NotFound(format!("Session {} does not exist", session_id)))?;
+        Ok(handle.stops.clone())
+    }
+
+    /// Wait until the program stopped more than `after` times, returns the
+    /// results of the last `*stopped` record along with the watch expressions,
+    /// the watched memory regions and the tracked registers which changed, and
+    /// the context of the stop if enabled in the configuration. The stop is
+    /// notified with them if it was not as it was received
+    pub async fn wait_for_stop(
+        &self,
+        session_id: &str,
+        after: u64,
+        timeout: Duration,
+    ) -> AppResult<Value> {
+        let state = self.wait_stop(session_id, after, timeout, true).await?;
+        let stopped = self.describe_stop(session_id, state.last.unwrap_or_default()).await?;
+        if !state.notified {
+            let (method, params) = notification(session_id, &AsyncClass::Stopped, stopped.clone());
+            notify_client(&method, params).await;
+        }
+        Ok(stopped)
+    }
+
+    /// Add to a stop the watch expressions, the watched memory regions and the
+    /// tracked registers which changed, and its context if enabled in the
+    /// configuration
+    async fn describe_stop(&self, session_id: &str, mut stopped: Value) -> AppResult<Value> {
+        match self.update_watches(session_id).await {
+            Ok(changes) if !changes.is_empty() => {
+                if let Some(stopped) = stopped.as_object_mut() {
+                    stopped.insert("watch_changes".to_string(), serde_json::to_value(changes)?);
+                }
+            }
+            Ok(_) => {}
+            Err(e) => warn!("Failed to update watch expressions: {}", e),
+        }
+        match self.update_memory_watches(session_id).await {
+            Ok(changes) if !changes.is_empty() => {
+                if let Some(stopped) = stopped.as_object_mut() {
+                    stopped.insert("memory_changes".to_string(), serde_json::to_value(changes)?);
+                }
+            }
+            Ok(_) => {}
+            Err(e) => warn!("Failed to update memory watches: {}", e),
+        }
+        match self.track_register_changes(session_id).await {
+            Ok(Some(changes)) => {
+                if let Some(stopped) = stopped.as_object_mut() {
+                    stopped.insert("register_changes".to_string(), serde_json::to_value(changes)?);
+                }
+            }
+            Ok(None) => {}
+            Err(e) => warn!("Failed to track register changes: {}", e),
+        }
+        if self.config.stop_context.enabled {
+            let context = self.stop_context(session_id, &self.config.stop_context).await;
+            if let Some(stopped) = stopped.as_object_mut() {
+                stopped.insert("context".to_string(), serde_json::to_value(context)?);
+            }
+        }
+        Ok(stopped)
+    }
+
+    /// Wait until the program stopped more than `after` times, returns the
+    /// results of the last `*stopped` record only, for the stops the loops
+    /// resume from, the changes are reported at the next stop returned to a
+    /// tool
+    async fn wait_stopped(
+        &self,
+        session_id: &str,
+        after: u64,
+        timeout: Duration,
+    ) -> AppResult<Value> {
+        let state = self.wait_stop(session_id, after, timeout, false).await?;
+        Ok(state.last.unwrap_or_default())
+    }
+
+    /// Wait until the program stopped more than `after` times, as a tool
+    /// which notifies the stop if `waiter`
+    async fn wait_stop(
+        &self,
+        session_id: &str,
+        after: u64,
+        timeout: Duration,
+        waiter: bool,
+    ) -> AppResult<StopState> {
+        let (mut stops, mut cancel, _waiter) = {
+            let sessions = self.sessions.lock().await;
+            let handle = sessions.get(session_id).ok_or_else(|| {
+                AppError::NotFound(format!("Session {} does not exist", session_id))
+            })?;
+            let waiter = waiter.then(|| StopWaiter::new(&handle.waiters));
+            (handle.stops.clone(), handle.cancel.subscribe(), waiter)
+        };
+
+        let state = tokio::select! {
+            waited = tokio::time::timeout(timeout, stops.wait_for(|s| s.count > after)) => {
+                match waited {
+                    Ok(Ok(state)) => state.clone(),
+                    Ok(Err(_)) => return Err(AppError::GDBQuit),
+                    Err(_) => return Err(AppError::GDBTimeout),
+                }
+            }
+            _ = cancel.changed() => return Err(AppError::Cancelled),
+        };
+
+        {
+            let mut sessions = self.sessions.lock().await;
+            if let Some(handle) = sessions.get_mut(session_id) {
+                handle.info.status = GDBSessionStatus::Stopped;
+            }
+        }
+        self.notify_sessions_changed();
+        if let Some(stopped) = &state.last {
+            self.update_arch(session_id, stopped).await;
         }
+        Ok(state)
+    }
 
+    /// Cancel the calls of a session waiting for the program to stop, they
+    /// fail with `Cancelled`, and interrupt the program if it is running
+    pub async fn cancel(&self, session_id: &str) -> AppResult<()> {
+        let sessions = self.sessions.lock().await;
+        let handle = sessions
+            .get(session_id)
+            .ok_or_else(|| AppError::NotFound(format!("Session {} does not exist", session_id)))?;
+        handle.cancel.send_modify(|generation| *generation += 1);
+        if handle.gdb.is_running() {
+            handle.gdb.interrupt_execution().await?;
+        }
         Ok(())
     }
 
+    /// Send a command resuming the program and wait for it to stop, the
+    /// program is interrupted if it does not stop within `timeout`
+    pub async fn resume_and_wait(
+        &self,
+        session_id: &str,
+        command: &MiCommand,
+        timeout: Duration,
+    ) -> AppResult<Resumed> {
+        let stops = self.stop_count(session_id).await?;
+        self.send_command_with_timeout(session_id, command).await?;
+        match self.wait_stopped(session_id, stops, timeout).await {
+            Ok(stopped) => Ok(Resumed::Stopped(stopped)),
+            Err(AppError::GDBTimeout) => {
+                let stops = self.stop_count(session_id).await?;
+                self.stop_debugging(session_id).await?;
+                let timeout = Duration::from_secs(self.config.command_timeout);
+                Ok(Resumed::Interrupted(self.wait_stopped(session_id, stops, timeout).await?))
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Insert a breakpoint at a location in any form GDB accepts, returns its
+    /// number
+    pub async fn insert_breakpoint_at(
+        &self,
+        session_id: &str,
+        location: &str,
+        condition: Option<&str>,
+        temporary: bool,
+    ) -> AppResult<BreakPointNumber> {
+        let command = MiCommand::insert_breakpoint_at(location, condition, temporary);
+        let response = self.send_command_with_timeout(session_id, &command).await?;
+        Ok(response
+            .results
+            .get("bkpt")
+            .and_then(|bkpt| bkpt.get("number"))
+            .and_then(|number| number.as_str())
+            .ok_or(AppError::NotFound("bkpt not found in the result".to_string()))?
+            .parse::<BreakPointNumber>()?)
+    }
+
+    /// Wait until the program is stopped, the last stop is returned right
+    /// away if it is not running
+    pub async fn wait_until_stopped(
+        &self,
+        session_id: &str,
+        timeout: Duration,
+    ) -> AppResult<Value> {
+        let (running, count) = {
+            let sessions = self.sessions.lock().await;
+            let handle = sessions.get(session_id).ok_or_else(|| {
+                AppError::NotFound(format!("Session {} does not exist", session_id))
+            })?;
+            (handle.gdb.is_running(), handle.stops.borrow().count)
+        };
+        if running {
+            self.wait_for_stop(session_id, count, timeout).await
+        } else if count > 0 {
+            // notified already
+            let stopped = self.wait_stopped(session_id, count - 1, timeout).await?;
+            self.describe_stop(session_id, stopped).await
+        } else {
+            Err(AppError::InvalidArgument("The program has not been started".to_string()))
+        }
+    }
+
+    /// Get the console, target and log output of a session
+    pub async fn get_console_output(
+        &self,
+        session_id: &str,
+        since_cursor: Option<u64>,
+    ) -> AppResult<ConsoleOutput> {
+        let sessions = self.sessions.lock().await;
+        let handle = sessions
+            .get(session_id)
+            .ok_or_else(|| AppError::NotFound(format!("Session {} does not exist", session_id)))?;
+        Ok(handle.console.lock().unwrap().since(since_cursor))
+    }
+
+    /// Export the MI commands and records of a session
+    pub async fn export_transcript(
+        &self,
+        session_id: &str,
+        format: TranscriptFormat,
+    ) -> AppResult<String> {
+        let sessions = self.sessions.lock().await;
+        let handle = sessions
+            .get(session_id)
+            .ok_or_else(|| AppError::NotFound(format!("Session {} does not exist", session_id)))?;
+
+        let mut gdb =
+            vec![handle.gdb.binary_path().display().to_string(), "--interpreter=mi".into()];
+        gdb.extend(handle.gdb.init_options().iter().map(|opt| opt.to_string_lossy().into_owned()));
+        Ok(handle.gdb.transcript().export(
+            format,
+            &format!("GDB session {}", session_id),
+            &gdb.join(" "),
+        ))
+    }
+
     /// Send GDB command
     pub async fn send_command(
         &self,
         session_id: &str,
         command: &MiCommand,
     ) -> AppResult<ResultRecord> {
-        let mut sessions = self.sessions.lock().await;
-        let handle = sessions
-            .get_mut(session_id)
-            .ok_or_else(|| AppError::NotFound(format!("Session {} does not exist", session_id)))?;
+        let (gdb, memory_cache, hits) = {
+            let sessions = self.sessions.lock().await;
+            let handle = sessions.get(session_id).ok_or_else(|| {
+                AppError::NotFound(format!("Session {} does not exist", session_id))
+            })?;
+            (handle.gdb.clone(), handle.memory_cache.clone(), handle.hits.clone())
+        };
 
-        let record = handle.gdb.execute(command).await?;
+        let start = Instant::now();
+        let record = gdb.execute(command).await?;
+        METRICS.command_answered(start.elapsed());
+        // the `*running` record may be handled after the program stopped again,
+        // and GDB does not notify what CLI commands like `monitor` write
+        if record.class == ResultClass::Running || command.may_write_memory() {
+            memory_cache.lock().unwrap().invalidate();
+        }
         let output = record.results.to_string();
 
         debug!("GDB output: {}", output);
+        if let Some(e) = record.error() {
+            return Err(e);
+        }
+        // no `=breakpoint-created` for the breakpoints inserted by MI commands
+        if command.inserts_breakpoint() {
+            hits.lock().unwrap().apply(BreakPointEvent::Created, &record.results);
+        }
         Ok(record)
     }
 
@@ -191,8 +912,10 @@ impl GDBManager {
         session_id: &str,
         command: &MiCommand,
     ) -> AppResult<ResultRecord> {
+        self.activity.lock().unwrap().last_command =
+            Some(command.command_line().to_string_lossy().into_owned());
         let command_timeout = self.config.command_timeout;
-        match tokio::time::timeout(
+        let result = match tokio::time::timeout(
             Duration::from_secs(command_timeout),
             self.send_command(session_id, command),
         )
@@ -200,8 +923,19 @@ impl GDBManager {
         {
             Ok(Ok(result)) => Ok(result),
             Ok(Err(e)) => Err(e),
-            Err(_) => Err(AppError::GDBTimeout),
-        }
+            Err(_) => {
+                METRICS.command_timed_out();
+                Err(AppError::GDBTimeout)
+            }
+        };
+        // the error is shown until a command succeeds
+        self.activity.lock().unwrap().last_error = result.as_ref().err().map(ToString::to_string);
+        result
+    }
+
+    /// Last command sent to GDB by the tools or the TUI, and last error
+    pub fn activity(&self) -> Activity {
+        self.activity.lock().unwrap().clone()
     }
 
     /// Start debugging
@@ -213,6 +947,7 @@ impl GDBManager {
         if let Some(handle) = sessions.get_mut(session_id) {
             handle.info.status = GDBSessionStatus::Running;
         }
+        self.notify_sessions_changed();
 
         Ok(response.results.to_string())
     }
@@ -227,6 +962,7 @@ impl GDBManager {
         if let Some(handle) = sessions.get_mut(session_id) {
             handle.info.status = GDBSessionStatus::Stopped;
         }
+        self.notify_sessions_changed();
 
         Ok(response.results.to_string())
     }
@@ -272,29 +1008,86 @@ impl GDBManager {
         let command = MiCommand::delete_breakpoints(
             breakpoints
                 .iter()
-                .map(|num| serde_json::from_str::<BreakPointNumber>(num))
+                .map(|num| num.parse::<BreakPointNumber>())
                 .collect::<Result<Vec<_>, _>>()?,
         );
         let response = self.send_command_with_timeout(session_id, &command).await?;
         if response.class != ResultClass::Done {
             return Err(AppError::GDBError(response.results.to_string()));
         }
+        self.remove_breakpoint_actions(session_id, &breakpoints).await;
 
         Ok(())
     }
 
+    /// Enable or disable breakpoints
+    pub async fn enable_breakpoints(
+        &self,
+        session_id: &str,
+        breakpoints: Vec<BreakPointNumber>,
+        enabled: bool,
+    ) -> AppResult<()> {
+        let command = if enabled {
+            MiCommand::enable_breakpoints(breakpoints)
+        } else {
+            MiCommand::disable_breakpoints(breakpoints)
+        };
+        self.send_command_with_timeout(session_id, &command).await?;
+        Ok(())
+    }
+
     /// Get stack frames
     pub async fn get_stack_frames(&self, session_id: &str) -> AppResult<Vec<StackFrame>> {
-        let command = MiCommand::stack_list_frames(None, None);
+        self.list_stack_frames(session_id, None, None).await
+    }
+
+    /// List the frames from the level `low_frame` to `high_frame`
+    async fn list_stack_frames(
+        &self,
+        session_id: &str,
+        low_frame: Option<usize>,
+        high_frame: Option<usize>,
+    ) -> AppResult<Vec<StackFrame>> {
+        let command = MiCommand::stack_list_frames(low_frame, high_frame);
         let response = self.send_command_with_timeout(session_id, &command).await?;
 
-        Ok(serde_json::from_value(
+        let mut frames: Vec<StackFrame> = serde_json::from_value(
             response
                 .results
                 .get("stack")
                 .ok_or(AppError::NotFound("stack not found".to_string()))?
                 .to_owned(),
-        )?)
+        )?;
+        if self.config.demangle {
+            for frame in &mut frames {
+                frame.function = demangle::demangle_or_keep(&frame.function);
+            }
+        }
+        let addresses = frames.iter().filter_map(|frame| frame.address.map(|address| address.0));
+        let mut labels = self.annotation_labels(session_id, addresses.collect::<Vec<_>>()).await;
+        for frame in &mut frames {
+            frame.annotation = frame.address.and_then(|address| labels.remove(&address.0));
+        }
+        Ok(frames)
+    }
+
+    /// Get the threads of the program, along with the tasks of the RTOS if
+    /// the RTOS awareness is enabled
+    pub async fn list_threads(&self, session_id: &str) -> AppResult<ThreadList> {
+        let command = MiCommand::thread_info(None);
+        let response = self.send_command_with_timeout(session_id, &command).await?;
+        let threads = match response.results.get("threads") {
+            Some(threads) => serde_json::from_value(threads.to_owned())?,
+            None => vec![],
+        };
+        let current_thread_id = response
+            .results
+            .get("current-thread-id")
+            .and_then(|id| id.as_str())
+            .map(|id| id.to_string());
+
+        let rtos_threads = self.rtos_tasks(session_id).await?;
+        Ok(ThreadList { threads, current_thread_id, rtos_threads })
     }
 
     /// Get local variables
@@ -302,8 +1095,9 @@ impl GDBManager {
         &self,
         session_id: &str,
         frame_id: Option<usize>,
+        print_values: Option<PrintValue>,
     ) -> AppResult<Vec<Variable>> {
-        let command = MiCommand::stack_list_variables(None, frame_id, None);
+        let command = MiCommand::stack_list_variables(None, frame_id, print_values);
         let response = self.send_command_with_timeout(session_id, &command).await?;
 
         Ok(serde_json::from_value(
@@ -315,45 +1109,43 @@ impl GDBManager {
         )?)
     }
 
-    /// Get registers
+    /// Get registers, with the lanes of the vector registers in
+    /// `vector_format` if provided
     pub async fn get_registers(
         &self,
         session_id: &str,
         reg_list: Option<Vec<String>>,
+        vector_format: Option<VectorFormat>,
     ) -> AppResult<Vec<Register>> {
         let reg_list = reg_list
             .map(|s| s.iter().map(|num| num.parse::<usize>()).collect::<Result<Vec<_>, _>>())
             .transpose()?;
-        let command = MiCommand::data_list_register_names(reg_list.clone());
-        let response = self.send_command_with_timeout(session_id, &command).await?;
-        let names: Vec<String> = serde_json::from_value(
-            response
-                .results
-                .get("register-names")
-                .ok_or(AppError::NotFound("register-names not found".to_string()))?
-                .to_owned(),
-        )?;
+        let names = self.register_names(session_id).await?;
 
         let command = MiCommand::data_list_register_values(RegisterFormat::Hex, reg_list);
         let response = self.send_command_with_timeout(session_id, &command).await?;
 
-        let registers: Vec<Register> = serde_json::from_value(
-            response
-                .results
-                .get("register-values")
-                .ok_or(AppError::NotFound("expect register-values".to_string()))?
-                .to_owned(),
-        )?;
+        let values = response
+            .results
+            .get("register-values")
+            .ok_or(AppError::NotFound("expect register-values".to_string()))?;
+        let registers: Vec<Register> = serde_json::from_value(values.to_owned())?;
+        let raw_values: Vec<RegisterValue> = serde_json::from_value(values.to_owned())?;
         Ok(registers
             .into_iter()
-            .map(|mut r| {
-                r.name = names.get(r.number).cloned();
+            .zip(raw_values)
+            .map(|(mut r, raw)| {
+                r.name = names.get(r.number).filter(|name| !name.is_empty()).cloned();
+                r.decode_flags();
+                if let Some(format) = vector_format {
+                    r.lanes = vector_lanes(&raw.value, format);
+                }
                 r
             })
             .collect::<_>())
     }
 
-    /// Get register names
+    /// Get register names, all by default
     pub async fn get_register_names(
         &self,
         session_id: &str,
@@ -362,19 +1154,21 @@ impl GDBManager {
         let reg_list = reg_list
             .map(|s| s.iter().map(|num| num.parse::<usize>()).collect::<Result<Vec<_>, _>>())
             .transpose()?;
-        let command = MiCommand::data_list_register_names(reg_list);
-        let response = self.send_command_with_timeout(session_id, &command).await?;
+        let names = self.register_names(session_id).await?;
 
-        Ok(serde_json::from_value(
-            response
-                .results
-                .get("register-values")
-                .ok_or(AppError::NotFound("expect register-values".to_string()))?
-                .to_owned(),
-        )?)
+        Ok(names
+            .into_iter()
+            .enumerate()
+            .filter(|(number, name)| {
+                !name.is_empty() && reg_list.as_ref().is_none_or(|list| list.contains(number))
+            })
+            .map(|(number, name)| Register { name: Some(name), number, ..Default::default() })
+            .collect())
     }
 
-    /// Read memory contents
+    /// Read memory contents, from the address an expression evaluates to. The
+    /// same read is served from a cache until the program resumes or the
+    /// memory is written, unless the program runs
     pub async fn read_memory(
         &self,
         session_id: &str,
@@ -382,16 +1176,61 @@ impl GDBManager {
         address: String,
         count: usize,
     ) -> AppResult<Vec<Memory>> {
-        let command = MiCommand::data_read_memory_bytes(offset, address, count);
-        let response = self.send_command_with_timeout(session_id, &command).await?;
+        let address = self.resolve_address(session_id, &address).await?;
+        let start = address.wrapping_add_signed(offset.unwrap_or(0) as i64);
+        let (cache, running) = {
+            let sessions = self.sessions.lock().await;
+            let handle = sessions.get(session_id).ok_or_else(|| {
+                AppError::NotFound(format!("Session {} does not exist", session_id))
+            })?;
+            (handle.memory_cache.clone(), handle.gdb.is_running())
+        };
+        // the memory of a running program changes under the cache
+        if running {
+            return self.read_memory_blocks(session_id, start, count).await;
+        }
+        let generation = {
+            let cache = cache.lock().unwrap();
+            if let Some(blocks) = cache.get(start, count) {
+                return Ok(blocks);
+            }
+            cache.generation()
+        };
+        let blocks = self.read_memory_blocks(session_id, start, count).await?;
+        cache.lock().unwrap().insert(generation, start, count, blocks.clone());
+        Ok(blocks)
+    }
 
-        Ok(serde_json::from_value(
-            response
-                .results
-                .get("memory")
-                .ok_or(AppError::NotFound("expect memory".to_string()))?
-                .to_owned(),
-        )?)
+    /// Read memory contents from GDB without the cache, e.g. device registers
+    /// changing by themselves
+    pub async fn read_memory_uncached(
+        &self,
+        session_id: &str,
+        address: &str,
+        count: usize,
+    ) -> AppResult<Vec<Memory>> {
+        let start = self.resolve_address(session_id, address).await?;
+        self.read_memory_blocks(session_id, start, count).await
+    }
+
+    async fn read_memory_blocks(
+        &self,
+        session_id: &str,
+        start: u64,
+        count: usize,
+    ) -> AppResult<Vec<Memory>> {
+        let mut blocks = vec![];
+        let mut chunks = memory::MemoryChunks::new(start, count);
+        while let Some(chunk) = chunks.next(self, session_id).await? {
+            for block in chunk {
+                memory::append_block(&mut blocks, block, start);
+            }
+        }
+        let unreadable = chunks.finish()?;
+        if !unreadable.is_empty() {
+            warn!("Unreadable memory left out of the read: {:?}", unreadable);
+        }
+        Ok(blocks)
     }
 
     /// Continue execution
@@ -404,6 +1243,7 @@ impl GDBManager {
         if let Some(handle) = sessions.get_mut(session_id) {
             handle.info.status = GDBSessionStatus::Running;
         }
+        self.notify_sessions_changed();
 
         Ok(response.results.to_string())
     }
@@ -421,4 +1261,637 @@ impl GDBManager {
 
         Ok(response.results.to_string())
     }
+
+    /// Step into (`into`) or over the next lines `count` times, waiting for
+    /// each step to complete, and optionally collect the location after each
+    pub async fn step_many(
+        &self,
+        session_id: &str,
+        into: bool,
+        count: usize,
+        collect: bool,
+        scheduler_locking: Option<SchedulerLocking>,
+    ) -> AppResult<StepTrace> {
+        let Some(mode) = scheduler_locking else {
+            return self.step_lines(session_id, into, count, collect).await;
+        };
+        // the mode only applies to these steps
+        let previous = self.show_setting(session_id, "scheduler-locking").await?;
+        self.set_setting(session_id, "scheduler-locking", mode.as_str()).await?;
+        let result = self.step_lines(session_id, into, count, collect).await;
+        // the steps are done, a failure to restore the mode must not hide them
+        if previous != mode.as_str()
+            && let Err(e) = self.set_setting(session_id, "scheduler-locking", &previous).await
+        {
+            warn!("Failed to set scheduler-locking back to {}: {}", previous, e);
+        }
+        result
+    }
+
+    async fn step_lines(
+        &self,
+        session_id: &str,
+        into: bool,
+        count: usize,
+        collect: bool,
+    ) -> AppResult<StepTrace> {
+        let timeout = Duration::from_secs(self.config.command_timeout);
+        let mut trace = collect.then(Vec::new);
+        let mut stopped = Value::Null;
+        let mut steps = 0;
+        while steps < count {
+            let stops = self.stop_count(session_id).await?;
+            if into {
+                self.step_execution(session_id).await?;
+            } else {
+                self.next_execution(session_id).await?;
+            }
+            stopped = self.wait_stopped(session_id, stops, timeout).await?;
+            steps += 1;
+
+            if let (Some(trace), Some(frame)) = (trace.as_mut(), stopped.get("frame")) {
+                trace.push(serde_json::from_value(frame.to_owned())?);
+            }
+            if stopped.get("reason").and_then(|reason| reason.as_str())
+                != Some("end-stepping-range")
+            {
+                break;
+            }
+        }
+        Ok(StepTrace { steps, trace, stopped })
+    }
+}
+
+/// Start GDB for a session
+fn spawn_session(info: GDBSession, builder: GDBBuilder) -> AppResult<GDBSessionHandle> {
+    let (oob_src, oob_sink) = queue::channel();
+    let gdb = builder.clone().try_spawn(oob_src)?;
+    Ok(session_handle(info, builder, gdb, oob_sink))
+}
+
+/// The notification of an async record to the client, named `gdb/` and the
+/// class of the record, with its results and the session it comes from
+fn notification(session_id: &str, class: &AsyncClass, results: Value) -> (String, Value) {
+    let mut params = results;
+    if let Some(params) = params.as_object_mut() {
+        params.insert("session_id".to_string(), session_id.into());
+    }
+    (format!("gdb/{}", class.as_str()), params)
+}
+
+/// Send a notification to the client if one is connected
+async fn notify_client(method: &str, params: Value) {
+    let transport = TRANSPORT.lock().await;
+    if let Some(transport) = transport.as_ref() {
+        if let Err(e) = transport.send_notification(method, Some(params)).await {
+            error!("Failed to send the {} notification: {:?}", method, e);
+        }
+    } else {
+        // no client yet, e.g. in the tests, the record is still logged and
+        // applied
+        debug!("No transport to send {} to", method);
+    }
+}
+
+/// Publish a stop to the waiters
+fn publish_stop(stops: &watch::Sender<StopState>, stopped: Value, notified: bool) {
+    stops.send_modify(|state| {
+        state.count += 1;
+        state.last = Some(stopped);
+        state.notified = notified;
+    });
+}
+
+/// State of a session talking to `gdb`, whose out of band records are
+/// received from `oob_sink`
+fn session_handle(
+    info: GDBSession,
+    builder: GDBBuilder,
+    gdb: GDB,
+    mut oob_sink: OutOfBandReceiver,
+) -> GDBSessionHandle {
+    let console = Arc::new(std::sync::Mutex::new(ConsoleBuffer::default()));
+    let console_clone = console.clone();
+    let events = Arc::new(std::sync::Mutex::new(EventLog::default()));
+    let events_clone = events.clone();
+    let threads = Arc::new(std::sync::Mutex::new(ThreadTable::default()));
+    let threads_clone = threads.clone();
+    let memory_cache = Arc::new(std::sync::Mutex::new(MemoryCache::default()));
+    let memory_cache_clone = memory_cache.clone();
+    let hits = Arc::new(std::sync::Mutex::new(HitCounts::default()));
+    let hits_clone = hits.clone();
+    let modules = Arc::new(std::sync::Mutex::new(ModuleMap::default()));
+    let modules_clone = modules.clone();
+    let session_id = info.id.clone();
+    let (stops_sender, stops) = watch::channel(StopState::default());
+    let waiters = Arc::new(AtomicUsize::new(0));
+    let waiters_clone = waiters.clone();
+    let failure = gdb.failure().clone();
+    let output = oob_sink.monitor();
+    let (hooks, mut hook_router) = Hooks::new();
+    let oob_handle = tokio::spawn(async move {
+        loop {
+            let record = tokio::select! {
+                record = oob_sink.recv() => record,
+                Some(stopped) = hook_router.released() => {
+                    // notified as it was received
+                    publish_stop(&stops_sender, stopped, true);
+                    continue;
+                }
+            };
+            match record {
+                Some(record) => {
+                    events_clone.lock().unwrap().push(&record);
+                    match record {
+                        OutOfBandRecord::AsyncRecord { class, results, .. } => {
+                            if class == AsyncClass::Running || class.as_str() == "memory-changed" {
+                                memory_cache_clone.lock().unwrap().invalidate();
+                            }
+                            if let AsyncClass::BreakPoint(event) = class {
+                                hits_clone.lock().unwrap().apply(event, &results);
+                            }
+                            let loaded = modules_clone.lock().unwrap().apply(&class, &results);
+                            if let Some(path) = loaded {
+                                // parsed once, as the library loads
+                                let modules = modules_clone.clone();
+                                tokio::spawn(async move {
+                                    if let Err(e) = load_object(modules, path).await {
+                                        debug!("Failed to parse a library: {}", e);
+                                    }
+                                });
+                            }
+                            // the actions of a breakpoint run before the stop is
+                            // published, a tool waiting for the stop notifies it
+                            // along with its context
+                            let mut notify = true;
+                            if class == AsyncClass::Stopped && !hook_router.take(&results) {
+                                notify = waiters_clone.load(Ordering::SeqCst) == 0;
+                                publish_stop(&stops_sender, results.clone(), notify);
+                            }
+                            if let AsyncClass::Thread(event) = class {
+                                threads_clone.lock().unwrap().apply(event, &results);
+                            }
+                            if notify {
+                                let (method, params) = notification(&session_id, &class, results);
+                                notify_client(&method, params).await;
+                            }
+                        }
+                        OutOfBandRecord::StreamRecord { kind, data } => {
+                            debug!("StreamRecord: {:?}", data);
+                            console_clone.lock().unwrap().push(kind, data);
+                        }
+                    }
+                }
+                None => {
+                    debug!("Source Channel closed");
+                    if let Some(reason) = failure.get() {
+                        error!("Session {} failed: {}", session_id, reason);
+                        let params =
+                            serde_json::json!({"session_id": session_id, "reason": reason});
+                        notify_client("gdb/session-failed", params).await;
+                    }
+                    break;
+                }
+            }
+        }
+    });
+
+    GDBSessionHandle {
+        info,
+        gdb: Arc::new(gdb),
+        builder,
+        oob_handle,
+        console,
+        events,
+        output,
+        threads,
+        memory_cache,
+        hits,
+        modules,
+        stops,
+        waiters,
+        cancel: watch::Sender::new(0),
+        watches: BTreeMap::new(),
+        memory_watches: BTreeMap::new(),
+        annotations: Annotations::default(),
+        register_names: Vec::new(),
+        registers: RegisterTracker::default(),
+        server: None,
+        svd: None,
+        recording: false,
+        rtos: None,
+        hooks,
+        target: None,
+    }
+}
+
+#[cfg(test)]
+impl GDBManager {
+    /// Create a session talking to a
+    /// [`MockBackend`](crate::mi::mock::MockBackend) replaying `script`
+    /// instead of GDB
+    pub(crate) async fn create_mock_session(&self, script: &str) -> AppResult<String> {
+        use crate::mi::mock::MockBackend;
+
+        let session_id = Uuid::new_v4().to_string();
+        let info = GDBSession {
+            id: session_id.clone(),
+            status: GDBSessionStatus::Created,
+            created_at: SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs(),
+            arch: Default::default(),
+            parameters: BTreeMap::new(),
+            error: None,
+        };
+        let (oob_src, oob_sink) = queue::channel();
+        let gdb = GDB::with_backend(
+            Box::new(MockBackend::new(script)),
+            oob_src,
+            PathBuf::from("gdb"),
+            vec![],
+        )?;
+        let handle = session_handle(info, GDBBuilder::new(PathBuf::from("gdb")), gdb, oob_sink);
+        self.sessions.lock().await.insert(session_id.clone(), handle);
+        self.notify_sessions_changed();
+        METRICS.session_opened();
+        Ok(session_id)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const SCRIPT: &str = r#"
+        <- =thread-group-added,id="i1"
+        -> -stack-list-frames
+        <- ^done,stack=[frame={level="0",addr="0x0000555555555131",func="compute",file="test_app.rs",line="13"},frame={level="1",addr="0x0000555555555160",func="main",file="test_app.rs",line="18"}]
+        -> -exec-continue
+        <- ^running
+        <- *running,thread-id="all"
+        <- *stopped,reason="exited-normally"
+    "#;
+
+    #[tokio::test]
+    async fn test_mock_session() {
+        let manager = GDBManager::default();
+        let session_id = manager.create_mock_session(SCRIPT).await.unwrap();
+
+        let frames = manager.get_stack_frames(&session_id).await.unwrap();
+        assert_eq!(
+            frames.iter().map(|frame| frame.function.as_str()).collect::<Vec<_>>(),
+            ["compute", "main"]
+        );
+        assert_eq!(frames[0].line, Some(13));
+
+        let stops = manager.stop_count(&session_id).await.unwrap();
+        manager.continue_execution(&session_id).await.unwrap();
+        let stopped =
+            manager.wait_for_stop(&session_id, stops, Duration::from_secs(5)).await.unwrap();
+        assert_eq!(stopped["reason"], "exited-normally");
+
+        // the script is over, any other command fails
+        assert!(matches!(
+            manager.list_threads(&session_id).await,
+            Err(AppError::GDBCommandError { .. })
+        ));
+        manager.close_session(&session_id).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_settings() {
+        let manager = GDBManager::default();
+        let session_id = manager
+            .create_mock_session(
+                r#"
+                -> -gdb-set scheduler-locking step
+                <- ^done
+                -> -gdb-set sysroot "/opt/target root"
+                <- ^done
+                -> -gdb-set print elements 0
+                <- ^done
+                -> -target-select extended-remote localhost:3333
+                <- ^connected
+                -> -gdb-set print elements 0
+                <- ^done
+                -> -gdb-set sysroot "/opt/target root"
+                <- ^done
+                -> -target-select extended-remote localhost:3333
+                <- ^connected
+                "#,
+            )
+            .await
+            .unwrap();
+
+        // only file names are quoted, GDB takes enums and numbers as typed
+        manager.set_setting(&session_id, "scheduler-locking", "step").await.unwrap();
+        manager.set_sysroot(&session_id, Path::new("/opt/target root")).await.unwrap();
+        manager.set_parameter(&session_id, "print elements", "0").await.unwrap();
+        manager.connect_target(&session_id, "extended-remote", "localhost:3333").await.unwrap();
+
+        // the one-off setting is not set again on restart
+        let session = manager.get_session(&session_id).await.unwrap();
+        assert_eq!(session.parameters.keys().collect::<Vec<_>>(), ["print elements", "sysroot"]);
+        manager.restore_session(&session_id).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_read_memory_in_chunks() {
+        let block = |begin: u64, length: usize, byte: &str| {
+            format!(
+                "{{begin=\"0x{:016x}\",offset=\"0x{:016x}\",end=\"0x{:016x}\",contents=\"{}\"}}",
+                begin,
+                0,
+                begin + length as u64,
+                byte.repeat(length)
+            )
+        };
+        let script = format!(
+            "-> -data-read-memory-bytes 0x1000 65536\n<- ^done,memory=[{}]\n\
+             -> -data-read-memory-bytes 0x11000 16\n<- ^done,memory=[{}]\n\
+             -> -data-read-memory-bytes 0x20000 65536\n<- ^error,msg=\"Unable to read memory.\"\n\
+             -> -data-read-memory-bytes 0x30000 8\n<- ^done,memory=[{}]\n\
+             -> -data-read-memory-bytes 0x20000 65536\n<- ^error,msg=\"Unable to read memory.\"\n\
+             -> -data-read-memory-bytes 0x30000 8\n<- ^done,memory=[{}]\n",
+            block(0x1000, 0x10000, "00"),
+            block(0x11000, 16, "ff"),
+            block(0x30000, 8, "aa"),
+            block(0x30000, 8, "aa")
+        );
+        let manager = GDBManager::default();
+        let session_id = manager.create_mock_session(&script).await.unwrap();
+
+        let memory =
+            manager.read_memory(&session_id, None, "0x1000".to_string(), 0x10010).await.unwrap();
+        assert_eq!(memory.len(), 1);
+        assert_eq!(memory[0].end, "0x0000000000011010");
+        assert_eq!(memory[0].contents.len(), 0x10010 * 2);
+        assert!(memory[0].contents.ends_with(&"ff".repeat(16)));
+
+        // the unreadable chunks are skipped
+        let memory = manager
+            .read_memory(&session_id, Some(0x10), "0x1fff0".to_string(), 0x10008)
+            .await
+            .unwrap();
+        assert_eq!(memory.len(), 1);
+        assert_eq!(memory[0].offset, "0x10000");
+        assert_eq!(memory[0].contents, "aa".repeat(8));
+
+        // and reported by dumps
+        let path = std::env::temp_dir().join(format!("dump-{}.bin", session_id));
+        let dump = manager.dump_memory(&session_id, "0x20000", 0x10008, &path).await.unwrap();
+        let bytes = std::fs::read(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(
+            (dump.readable[0].begin.as_str(), dump.readable[0].end.as_str()),
+            ("0x30000", "0x30008")
+        );
+        assert_eq!(
+            (dump.unreadable[0].begin.as_str(), dump.unreadable[0].end.as_str()),
+            ("0x20000", "0x30000")
+        );
+        assert_eq!(bytes.len(), 0x10008);
+        assert_eq!(bytes[0x10000..], [0xaa; 8]);
+    }
+
+    #[tokio::test]
+    async fn test_memory_cache() {
+        let manager = GDBManager::default();
+        let session_id = manager
+            .create_mock_session(
+                r#"
+                -> -data-read-memory-bytes 0x1000 4
+                <- ^done,memory=[{begin="0x1000",offset="0x0",end="0x1004",contents="01020304"}]
+                -> -interpreter-exec console "set {char}0x1000 = 9"
+                <- =memory-changed,thread-group="i1",addr="0x1000",len="0x1"
+                <- ^done
+                -> -data-read-memory-bytes 0x1000 4
+                <- ^done,memory=[{begin="0x1000",offset="0x0",end="0x1004",contents="09020304"}]
+                -> -exec-continue
+                <- ^running
+                <- *running,thread-id="all"
+                <- *stopped,reason="breakpoint-hit"
+                -> -data-read-memory-bytes 0x1000 4
+                <- ^done,memory=[{begin="0x1000",offset="0x0",end="0x1004",contents="0a020304"}]
+                -> -interpreter-exec console "monitor reset halt"
+                <- ^done
+                -> -data-read-memory-bytes 0x1000 4
+                <- ^done,memory=[{begin="0x1000",offset="0x0",end="0x1004",contents="00000000"}]
+                -> -data-read-memory-bytes 0x1000 4
+                <- ^done,memory=[{begin="0x1000",offset="0x0",end="0x1004",contents="00000001"}]
+                "#,
+            )
+            .await
+            .unwrap();
+        let read = || async {
+            let memory =
+                manager.read_memory(&session_id, None, "0x1000".to_string(), 4).await.unwrap();
+            memory[0].contents.clone()
+        };
+
+        // read once from GDB, the script fails any other read
+        assert_eq!(read().await, "01020304");
+        assert_eq!(read().await, "01020304");
+
+        // written by a command, GDB notifies it
+        let stops = manager.stop_count(&session_id).await.unwrap();
+        manager
+            .send_command(&session_id, &MiCommand::cli_exec("set {char}0x1000 = 9"))
+            .await
+            .unwrap();
+        let changed = tokio::time::timeout(Duration::from_secs(5), async {
+            while manager.get_session_events(&session_id, None).await.unwrap().events.is_empty() {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        });
+        changed.await.unwrap();
+        assert_eq!(read().await, "09020304");
+
+        manager.continue_execution(&session_id).await.unwrap();
+        manager.wait_for_stop(&session_id, stops, Duration::from_secs(5)).await.unwrap();
+        assert_eq!(read().await, "0a020304");
+        assert_eq!(read().await, "0a020304");
+
+        // not notified but written by the server
+        manager
+            .send_command(&session_id, &MiCommand::cli_exec("monitor reset halt"))
+            .await
+            .unwrap();
+        assert_eq!(read().await, "00000000");
+        let memory = manager.read_memory_uncached(&session_id, "0x1000", 4).await.unwrap();
+        assert_eq!(memory[0].contents, "00000001");
+    }
+
+    #[tokio::test]
+    async fn test_ping_session() {
+        let manager = GDBManager::default();
+        let session_id = manager
+            .create_mock_session(
+                r#"
+                <- =thread-group-started,id="i1",pid="42"
+                <- =thread-created,id="1",group-id="i1"
+                <- *stopped,reason="signal-received"
+                -> -exec-continue
+                <- ^running
+                <- *running,thread-id="all"
+                -> <interrupt>
+                <- =thread-exited,id="1",group-id="i1"
+                <- =thread-group-exited,id="i1",exit-code="0"
+                <- *stopped,reason="exited-normally"
+                <- <eof>
+                "#,
+            )
+            .await
+            .unwrap();
+
+        manager.wait_for_stop(&session_id, 0, Duration::from_secs(5)).await.unwrap();
+        let ping = manager.ping_session(&session_id).await.unwrap();
+        assert!(ping.debugger_alive && ping.program_alive && !ping.running);
+        assert_eq!(ping.live_threads, 1);
+
+        // answered while the program runs, when the commands are refused
+        manager.continue_execution(&session_id).await.unwrap();
+        let ping = manager.ping_session(&session_id).await.unwrap();
+        assert!(ping.debugger_alive && ping.program_alive && ping.running);
+
+        manager.cancel(&session_id).await.unwrap();
+        manager.wait_for_stop(&session_id, 1, Duration::from_secs(5)).await.unwrap();
+        let ping = manager.ping_session(&session_id).await.unwrap();
+        assert!(!ping.program_alive && ping.live_threads == 0);
+
+        // then GDB dies
+        let ping = tokio::time::timeout(Duration::from_secs(5), async {
+            loop {
+                let ping = manager.ping_session(&session_id).await.unwrap();
+                if ping.status == GDBSessionStatus::Errored {
+                    return ping;
+                }
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .unwrap();
+        assert!(!ping.debugger_alive);
+        assert_eq!(ping.error.as_deref(), Some("GDB exited unexpectedly"));
+    }
+
+    #[tokio::test]
+    async fn test_subscribe_sessions() {
+        let manager = GDBManager::default();
+        let mut sessions_changed = manager.subscribe_sessions();
+        let session_id = manager
+            .create_mock_session(
+                r#"
+                -> -exec-continue
+                <- ^running
+                "#,
+            )
+            .await
+            .unwrap();
+        assert!(sessions_changed.has_changed().unwrap());
+        sessions_changed.borrow_and_update();
+
+        manager.continue_execution(&session_id).await.unwrap();
+        assert!(sessions_changed.has_changed().unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_stop_notified_by_waiter() {
+        let manager = GDBManager::default();
+        let session_id = manager
+            .create_mock_session(
+                r#"
+                -> -exec-interrupt
+                <- ^done
+                <- *stopped,reason="signal-received",signal-name="SIGINT"
+                -> -exec-interrupt
+                <- ^done
+                <- *stopped,reason="signal-received",signal-name="SIGINT"
+                "#,
+            )
+            .await
+            .unwrap();
+        let timeout = Duration::from_secs(5);
+
+        // a tool waits for the stop, it notifies the stop with its context
+        let stops = manager.stop_count(&session_id).await.unwrap();
+        let interrupt = async {
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            manager.stop_debugging(&session_id).await.unwrap();
+        };
+        let (stopped, _) =
+            tokio::join!(manager.wait_for_stop(&session_id, stops, timeout), interrupt);
+        assert_eq!(stopped.unwrap()["reason"], "signal-received");
+        let state = manager.subscribe_stops(&session_id).await.unwrap().borrow().clone();
+        assert_eq!(state.count, 1);
+        assert!(!state.notified);
+
+        // a loop resuming from the stop does not
+        let interrupt = async {
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            manager.stop_debugging(&session_id).await.unwrap();
+        };
+        let (stopped, _) = tokio::join!(manager.wait_stopped(&session_id, 1, timeout), interrupt);
+        assert!(stopped.is_ok());
+        let state = manager.subscribe_stops(&session_id).await.unwrap().borrow().clone();
+        assert_eq!(state.count, 2);
+        assert!(state.notified);
+    }
+
+    #[test]
+    fn test_notification() {
+        let results = serde_json::json!({"reason": "breakpoint-hit", "bkptno": "1"});
+        let (method, params) = notification("s1", &AsyncClass::Stopped, results);
+        assert_eq!(method, "gdb/stopped");
+        assert_eq!(params["session_id"], "s1");
+        assert_eq!(params["bkptno"], "1");
+
+        let results = serde_json::json!({"id": "2", "group-id": "i1"});
+        let class = AsyncClass::Thread(crate::mi::output::ThreadEvent::Exited);
+        let (method, params) = notification("s1", &class, results);
+        assert_eq!(method, "gdb/thread-exited");
+        assert_eq!(params["session_id"], "s1");
+    }
+
+    #[tokio::test]
+    async fn test_ping_while_busy() {
+        let manager = GDBManager::default();
+        let session_id =
+            manager.create_mock_session("-> -data-evaluate-expression \"spin()\"\n").await.unwrap();
+
+        // GDB does not answer the command, the session is not locked meanwhile
+        let command = MiCommand::data_evaluate_expression("spin()".to_string());
+        let ping = async {
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            tokio::time::timeout(Duration::from_secs(1), manager.ping_session(&session_id)).await
+        };
+        tokio::select! {
+            _ = manager.send_command(&session_id, &command) => panic!("the command was answered"),
+            ping = ping => {
+                let ping = ping.expect("ping blocked by the command").unwrap();
+                assert!(ping.debugger_alive && !ping.running);
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_gdb_crash() {
+        let manager = GDBManager::default();
+        let session_id = manager
+            .create_mock_session("-> -exec-continue\n<- ^running\n<- <eof>\n")
+            .await
+            .unwrap();
+
+        let stops = manager.stop_count(&session_id).await.unwrap();
+        manager.continue_execution(&session_id).await.unwrap();
+        assert!(matches!(
+            manager.wait_for_stop(&session_id, stops, Duration::from_secs(5)).await,
+            Err(AppError::GDBQuit)
+        ));
+        let session = manager.get_session(&session_id).await.unwrap();
+        assert_eq!(session.status, GDBSessionStatus::Errored);
+        assert_eq!(session.error.as_deref(), Some("GDB exited unexpectedly"));
+        // the other sessions are not affected
+        let other = manager.create_mock_session(SCRIPT).await.unwrap();
+        assert_eq!(manager.get_stack_frames(&other).await.unwrap().len(), 2);
+    }
 }
@@ -0,0 +1,195 @@
+//! Names and comments of addresses imported from a static analysis, e.g.
+//! exported from Ghidra or IDA, shown along the addresses of the program
+
+use std::collections::{BTreeMap, HashMap};
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_with::skip_serializing_none;
+
+use super::GDBManager;
+use super::memory::parse_number;
+use crate::error::{AppError, AppResult};
+use crate::models::Address64;
+
+/// A named or commented address
+#[skip_serializing_none]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct Annotation {
+    /// In hexadecimal, with or without `0x`
+    #[schemars(with = "String")]
+    pub address: Address64,
+    pub name: Option<String>,
+    pub comment: Option<String>,
+    /// Bytes covered, e.g. the size of a function, the addresses inside are
+    /// shown as `name+offset`
+    pub size: Option<u64>,
+    /// The program or library `address` is a static address of, by path or
+    /// file name, a runtime address if not set
+    pub module: Option<String>,
+}
+
+/// Annotations by module, then by address
+#[derive(Debug, Clone, Default)]
+pub struct Annotations(BTreeMap<Option<String>, BTreeMap<u64, Annotation>>);
+
+impl Annotations {
+    pub fn insert(&mut self, annotation: Annotation) {
+        let addresses = self.0.entry(annotation.module.clone()).or_default();
+        addresses.insert(annotation.address.0, annotation);
+    }
+
+    pub fn clear(&mut self) {
+        self.0.clear();
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Annotation> {
+        self.0.values().flat_map(BTreeMap::values)
+    }
+
+    /// The modules with annotations
+    pub fn modules(&self) -> impl Iterator<Item = &str> {
+        self.0.keys().flatten().map(String::as_str)
+    }
+
+    /// What to show along `address` of `module`: the name and comment of its
+    /// annotation, or the name and offset in the nearest sized annotation
+    /// before it if it covers it
+    pub fn label(&self, module: Option<&str>, address: u64) -> Option<String> {
+        let addresses = self.0.get(&module.map(str::to_string))?;
+        if let Some(annotation) = addresses.get(&address) {
+            match (&annotation.name, &annotation.comment) {
+                (Some(name), Some(comment)) => return Some(format!("{}: {}", name, comment)),
+                (Some(text), None) | (None, Some(text)) => return Some(text.clone()),
+                (None, None) => {}
+            }
+        }
+        let (start, annotation) =
+            addresses.range(..address).rev().find(|(_, annotation)| annotation.size.is_some())?;
+        let offset = address - start;
+        let name = annotation.name.as_ref()?;
+        (offset < annotation.size.unwrap_or(0)).then(|| format!("{}+0x{:x}", name, offset))
+    }
+}
+
+impl GDBManager {
+    /// Add annotations, replacing all the previous ones if `replace`. The
+    /// addresses without a module are static ones of `module` if given.
+    /// Returns the number of annotations of the session
+    pub async fn import_annotations(
+        &self,
+        session_id: &str,
+        annotations: Vec<Annotation>,
+        module: Option<&str>,
+        replace: bool,
+    ) -> AppResult<usize> {
+        let mut sessions = self.sessions.lock().await;
+        let handle = sessions
+            .get_mut(session_id)
+            .ok_or_else(|| AppError::NotFound(format!("Session {} does not exist", session_id)))?;
+        if replace {
+            handle.annotations.clear();
+        }
+        for mut annotation in annotations {
+            annotation.module = annotation.module.or_else(|| module.map(str::to_string));
+            handle.annotations.insert(annotation);
+        }
+        Ok(handle.annotations.iter().count())
+    }
+
+    /// The annotations of a session as imported, by module and address
+    pub async fn export_annotations(&self, session_id: &str) -> AppResult<Vec<Annotation>> {
+        let sessions = self.sessions.lock().await;
+        let handle = sessions
+            .get(session_id)
+            .ok_or_else(|| AppError::NotFound(format!("Session {} does not exist", session_id)))?;
+        Ok(handle.annotations.iter().cloned().collect())
+    }
+
+    /// Labels of the annotated ones among the runtime `addresses`, the
+    /// annotations of a module are slid by its load bias, the ones of a module
+    /// not loaded are left out
+    pub(super) async fn annotation_labels(
+        &self,
+        session_id: &str,
+        addresses: impl IntoIterator<Item = u64>,
+    ) -> HashMap<u64, String> {
+        let names = {
+            let sessions = self.sessions.lock().await;
+            let Some(handle) = sessions.get(session_id) else {
+                return HashMap::new();
+            };
+            handle.annotations.modules().map(str::to_string).collect::<Vec<_>>()
+        };
+        let mut biases = vec![];
+        if !names.is_empty() {
+            let modules = self.get_loaded_modules(session_id).await.unwrap_or_default();
+            for name in names {
+                let bias = modules
+                    .iter()
+                    .find(|module| module.matches(&name))
+                    .and_then(|module| module.load_bias.as_deref().and_then(parse_number));
+                if let Some(bias) = bias {
+                    biases.push((name, bias));
+                }
+            }
+        }
+
+        let sessions = self.sessions.lock().await;
+        let Some(handle) = sessions.get(session_id) else {
+            return HashMap::new();
+        };
+        let label = |address: u64| {
+            handle.annotations.label(None, address).or_else(|| {
+                biases.iter().find_map(|(name, bias)| {
+                    handle.annotations.label(Some(name), address.wrapping_sub(*bias))
+                })
+            })
+        };
+        addresses.into_iter().filter_map(|address| Some((address, label(address)?))).collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::models::Address;
+
+    #[test]
+    fn test_labels() {
+        let annotations = serde_json::from_str::<Vec<Annotation>>(
+            r#"[
+                {"address": "0x401000", "name": "parse_packet", "size": 64},
+                {"address": "00401010", "comment": "length check"},
+                {"address": "0x402000", "name": "g_config", "comment": "struct config"}
+            ]"#,
+        )
+        .unwrap();
+        let mut store = Annotations::default();
+        for annotation in annotations {
+            store.insert(annotation);
+        }
+
+        let label = |address| store.label(None, address);
+        assert_eq!(label(0x401000).as_deref(), Some("parse_packet"));
+        assert_eq!(label(0x401010).as_deref(), Some("length check"));
+        // the comment has no size, the function covers the address
+        assert_eq!(label(0x401016).as_deref(), Some("parse_packet+0x16"));
+        assert_eq!(label(0x401008).as_deref(), Some("parse_packet+0x8"));
+        assert_eq!(label(0x401040), None);
+        assert_eq!(label(0x402000).as_deref(), Some("g_config: struct config"));
+        assert_eq!(label(0x400000), None);
+
+        // static addresses of a library are kept apart
+        store.insert(Annotation {
+            address: Address(0x1000),
+            name: Some("inflate".to_string()),
+            comment: None,
+            size: Some(0x100),
+            module: Some("libz.so.1".to_string()),
+        });
+        assert_eq!(store.modules().collect::<Vec<_>>(), ["libz.so.1"]);
+        assert_eq!(store.label(Some("libz.so.1"), 0x1010).as_deref(), Some("inflate+0x10"));
+        assert_eq!(store.label(None, 0x1010), None);
+    }
+}
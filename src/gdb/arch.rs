@@ -0,0 +1,126 @@
+use std::path::Path;
+
+use serde_json::Value;
+use tracing::debug;
+
+use super::{GDBManager, discovery};
+use crate::error::{AppError, AppResult};
+use crate::mi::commands::MiCommand;
+use crate::models::{Endian, TargetArch};
+
+impl GDBManager {
+    /// Detect the architecture of the target and store it in the session.
+    ///
+    /// The name comes from the selected frame once the program runs, the
+    /// pointer width from GDB, the rest from the ELF header of `program`
+    pub(super) async fn detect_arch(
+        &self,
+        session_id: &str,
+        program: Option<&Path>,
+    ) -> AppResult<TargetArch> {
+        let mut arch = TargetArch::default();
+
+        let command = MiCommand::stack_info_frame(None);
+        if let Ok(response) = self.send_command_with_timeout(session_id, &command).await {
+            arch.name = frame_arch(response.results.get("frame"));
+        }
+
+        match self.pointer_bits(session_id).await {
+            Ok(pointer_bits) => arch.pointer_bits = pointer_bits,
+            Err(e) => debug!("No pointer width from GDB: {}", e),
+        }
+
+        if let Some(program) = program {
+            if let Some((pointer_bits, endian)) = discovery::elf_layout(program) {
+                arch.pointer_bits = arch.pointer_bits.or(Some(pointer_bits));
+                arch.endian = Some(endian);
+            }
+            if arch.name.is_none() {
+                arch.name = discovery::elf_arch(program);
+            }
+        }
+
+        let mut sessions = self.sessions.lock().await;
+        let handle = sessions
+            .get_mut(session_id)
+            .ok_or_else(|| AppError::NotFound(format!("Session {} does not exist", session_id)))?;
+        handle.info.arch = arch.clone();
+        self.notify_sessions_changed();
+        Ok(arch)
+    }
+
+    /// Set the architecture of a target which does not describe itself,
+    /// `auto` lets GDB pick it again
+    pub async fn set_architecture(
+        &self,
+        session_id: &str,
+        architecture: &str,
+    ) -> AppResult<TargetArch> {
+        self.set_parameter(session_id, "architecture", architecture).await?;
+        let pointer_bits = self.pointer_bits(session_id).await?;
+
+        let mut sessions = self.sessions.lock().await;
+        let handle = sessions
+            .get_mut(session_id)
+            .ok_or_else(|| AppError::NotFound(format!("Session {} does not exist", session_id)))?;
+        // the registers are those of the new architecture
+        handle.register_names.clear();
+        handle.registers = Default::default();
+        handle.info.arch.name = (architecture != "auto").then(|| architecture.to_string());
+        handle.info.arch.pointer_bits = pointer_bits;
+        self.notify_sessions_changed();
+        Ok(handle.info.arch.clone())
+    }
+
+    /// Set the byte order of the target, None lets GDB pick it again
+    pub async fn set_endian(
+        &self,
+        session_id: &str,
+        endian: Option<Endian>,
+    ) -> AppResult<TargetArch> {
+        let value = match endian {
+            Some(Endian::Little) => "little",
+            Some(Endian::Big) => "big",
+            None => "auto",
+        };
+        self.set_parameter(session_id, "endian", value).await?;
+
+        let mut sessions = self.sessions.lock().await;
+        let handle = sessions
+            .get_mut(session_id)
+            .ok_or_else(|| AppError::NotFound(format!("Session {} does not exist", session_id)))?;
+        if endian.is_some() {
+            handle.info.arch.endian = endian;
+        }
+        self.notify_sessions_changed();
+        Ok(handle.info.arch.clone())
+    }
+
+    /// Width of a pointer in bits according to GDB
+    async fn pointer_bits(&self, session_id: &str) -> AppResult<Option<u32>> {
+        let command = MiCommand::data_evaluate_expression("sizeof(void *)".to_string());
+        let response = self.send_command_with_timeout(session_id, &command).await?;
+        Ok(response
+            .results
+            .get("value")
+            .and_then(|value| value.as_str())
+            .and_then(|value| value.parse::<u32>().ok())
+            .map(|bytes| bytes * 8))
+    }
+
+    /// Take the architecture name from the frame of a `*stopped` record, GDB
+    /// only knows it for sure once the program runs
+    pub(super) async fn update_arch(&self, session_id: &str, stopped: &Value) {
+        let Some(name) = frame_arch(stopped.get("frame")) else {
+            return;
+        };
+        let mut sessions = self.sessions.lock().await;
+        if let Some(handle) = sessions.get_mut(session_id) {
+            handle.info.arch.name = Some(name);
+        }
+    }
+}
+
+fn frame_arch(frame: Option<&Value>) -> Option<String> {
+    frame?.get("arch")?.as_str().map(|arch| arch.to_string())
+}
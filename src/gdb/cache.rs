@@ -0,0 +1,115 @@
+//! Memory read while the program is stopped, served again without asking GDB
+//! until the program resumes or the memory is written, since the same regions
+//! tend to be read over and over between two stops
+
+use std::collections::{HashMap, VecDeque};
+
+use super::GDBManager;
+use crate::error::{AppError, AppResult};
+use crate::models::Memory;
+
+/// Bytes requested by the cached reads at most, the oldest are evicted first
+const MAX_CACHED_BYTES: usize = 16 * 1024 * 1024;
+
+/// Blocks read by address and length
+#[derive(Debug, Default)]
+pub struct MemoryCache {
+    /// Bumped when the cache is invalidated, the reads started before are not
+    /// cached
+    generation: u64,
+    entries: HashMap<(u64, usize), Vec<Memory>>,
+    /// Keys by insertion
+    order: VecDeque<(u64, usize)>,
+    bytes: usize,
+}
+
+impl MemoryCache {
+    /// Take it before reading, to pass it to [`MemoryCache::insert`]
+    pub fn generation(&self) -> u64 {
+        self.generation
+    }
+
+    pub fn get(&self, address: u64, length: usize) -> Option<Vec<Memory>> {
+        self.entries.get(&(address, length)).cloned()
+    }
+
+    /// Cache the blocks of a read started at `generation`, unless the memory
+    /// may have changed since
+    pub fn insert(&mut self, generation: u64, address: u64, length: usize, blocks: Vec<Memory>) {
+        if generation != self.generation || length > MAX_CACHED_BYTES {
+            return;
+        }
+        if self.entries.insert((address, length), blocks).is_some() {
+            return;
+        }
+        self.order.push_back((address, length));
+        self.bytes += length;
+        while self.bytes > MAX_CACHED_BYTES {
+            let Some(key) = self.order.pop_front() else {
+                break;
+            };
+            self.entries.remove(&key);
+            self.bytes -= key.1;
+        }
+    }
+
+    /// The program resumed or the memory was written
+    pub fn invalidate(&mut self) {
+        self.generation += 1;
+        self.entries.clear();
+        self.order.clear();
+        self.bytes = 0;
+    }
+}
+
+impl GDBManager {
+    /// Forget the memory read so far, after a command which may have written
+    /// to the memory without GDB notifying it
+    pub async fn invalidate_memory_cache(&self, session_id: &str) -> AppResult<()> {
+        let sessions = self.sessions.lock().await;
+        let handle = sessions
+            .get(session_id)
+            .ok_or_else(|| AppError::NotFound(format!("Session {} does not exist", session_id)))?;
+        handle.memory_cache.lock().unwrap().invalidate();
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn block(begin: u64, contents: &str) -> Vec<Memory> {
+        vec![Memory {
+            begin: format!("0x{:x}", begin),
+            end: format!("0x{:x}", begin + contents.len() as u64 / 2),
+            offset: "0x0".to_string(),
+            contents: contents.to_string(),
+        }]
+    }
+
+    #[test]
+    fn test_memory_cache() {
+        let mut cache = MemoryCache::default();
+        let generation = cache.generation();
+        cache.insert(generation, 0x1000, 2, block(0x1000, "abcd"));
+        assert_eq!(cache.get(0x1000, 2).unwrap()[0].contents, "abcd");
+        assert!(cache.get(0x1000, 4).is_none());
+
+        // a read in progress when the program resumes is not cached
+        let generation = cache.generation();
+        cache.invalidate();
+        assert!(cache.get(0x1000, 2).is_none());
+        cache.insert(generation, 0x1000, 2, block(0x1000, "abcd"));
+        assert!(cache.get(0x1000, 2).is_none());
+
+        // the oldest reads are evicted
+        let generation = cache.generation();
+        cache.insert(generation, 0, MAX_CACHED_BYTES / 2, vec![]);
+        cache.insert(generation, 1, MAX_CACHED_BYTES / 2, vec![]);
+        cache.insert(generation, 2, 1, vec![]);
+        assert!(cache.get(0, MAX_CACHED_BYTES / 2).is_none());
+        assert!(cache.get(1, MAX_CACHED_BYTES / 2).is_some());
+        assert_eq!(cache.bytes, MAX_CACHED_BYTES / 2 + 1);
+    }
+}
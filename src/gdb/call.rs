@@ -0,0 +1,154 @@
+//! Calls of functions of the program made by GDB
+
+use std::time::Duration;
+
+use tracing::warn;
+
+use super::GDBManager;
+use crate::error::{AppError, AppResult};
+use crate::metrics::METRICS;
+use crate::mi::commands::MiCommand;
+
+impl GDBManager {
+    /// Call a function of the program with `args` in the selected thread and
+    /// return the value it returned. The frame is restored if the call gets a
+    /// signal, and the call is abandoned after `timeout` when GDB supports it
+    pub async fn call_function(
+        &self,
+        session_id: &str,
+        function: &str,
+        args: &[String],
+        timeout: Duration,
+    ) -> AppResult<String> {
+        let unwind = self.show_setting(session_id, "unwindonsignal").await?;
+        self.set_setting(session_id, "unwindonsignal", "on").await?;
+        // since GDB 14
+        let call_timeout = match self.show_setting(session_id, "direct-call-timeout").await {
+            Ok(previous) => {
+                self.set_setting(session_id, "direct-call-timeout", &timeout.as_secs().to_string())
+                    .await?;
+                Some(previous)
+            }
+            Err(e) => {
+                warn!("Inferior calls cannot time out: {}", e);
+                None
+            }
+        };
+
+        let result = self.evaluate_call(session_id, function, args, timeout).await;
+        // the function may write to the memory
+        self.invalidate_memory_cache(session_id).await?;
+
+        // the call is done, a failure to restore the settings must not hide it
+        if let Err(e) = self.set_setting(session_id, "unwindonsignal", &unwind).await {
+            warn!("Failed to set unwindonsignal back to {}: {}", unwind, e);
+        }
+        if let Some(call_timeout) = call_timeout
+            && let Err(e) = self.set_setting(session_id, "direct-call-timeout", &call_timeout).await
+        {
+            warn!("Failed to set direct-call-timeout back to {}: {}", call_timeout, e);
+        }
+        result
+    }
+
+    async fn evaluate_call(
+        &self,
+        session_id: &str,
+        function: &str,
+        args: &[String],
+        timeout: Duration,
+    ) -> AppResult<String> {
+        let expression = format!("{}({})", function, args.join(", "));
+        let command = MiCommand::data_evaluate_expression(expression);
+        // GDB abandons the call first, leave it time to report it
+        let timeout = timeout + Duration::from_secs(self.config.command_timeout);
+        let call = self.send_command(session_id, &command);
+        tokio::pin!(call);
+        let response = match tokio::time::timeout(timeout, &mut call).await {
+            Ok(response) => response?,
+            Err(_) => {
+                METRICS.command_timed_out();
+                // stop the function, and take the answer of GDB abandoning the
+                // call so it is not taken for the one of the next command
+                self.interrupt_call(session_id).await?;
+                let grace = Duration::from_secs(self.config.command_timeout);
+                if let Ok(Err(e)) = tokio::time::timeout(grace, call).await {
+                    warn!("Call of {} interrupted: {}", function, e);
+                }
+                return Err(AppError::GDBTimeout);
+            }
+        };
+        Ok(response
+            .results
+            .get("value")
+            .and_then(|value| value.as_str())
+            .unwrap_or_default()
+            .to_string())
+    }
+
+    /// Interrupt the function GDB is calling
+    async fn interrupt_call(&self, session_id: &str) -> AppResult<()> {
+        let gdb = {
+            let sessions = self.sessions.lock().await;
+            let handle = sessions.get(session_id).ok_or_else(|| {
+                AppError::NotFound(format!("Session {} does not exist", session_id))
+            })?;
+            handle.gdb.clone()
+        };
+        gdb.interrupt_execution().await
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::config::Config;
+
+    #[tokio::test]
+    async fn test_call_function() {
+        let manager = GDBManager {
+            config: Config { command_timeout: 1, ..Config::default() },
+            ..Default::default()
+        };
+        let session_id = manager
+            .create_mock_session(
+                r#"
+                -> -gdb-show unwindonsignal
+                <- ^done,value="off"
+                -> -gdb-set unwindonsignal on
+                <- ^done
+                -> -gdb-show direct-call-timeout
+                <- ^error,msg="Undefined show command: \"direct-call-timeout\"."
+                -> -data-evaluate-expression "add(1, 2)"
+                <- ^done,value="3"
+                -> -gdb-set unwindonsignal off
+                <- ^error,msg="Cannot access memory"
+                -> -gdb-show unwindonsignal
+                <- ^done,value="off"
+                -> -gdb-set unwindonsignal on
+                <- ^done
+                -> -gdb-show direct-call-timeout
+                <- ^error,msg="Undefined show command: \"direct-call-timeout\"."
+                -> -data-evaluate-expression "spin()"
+                -> <interrupt>
+                <- ^error,msg="The program being debugged was signaled while in a function called from GDB."
+                -> -gdb-set unwindonsignal off
+                <- ^done
+                -> -gdb-show unwindonsignal
+                <- ^done,value="off"
+                "#,
+            )
+            .await
+            .unwrap();
+
+        // the value is returned though the setting could not be restored
+        let args = ["1".to_string(), "2".to_string()];
+        let value = manager.call_function(&session_id, "add", &args, Duration::ZERO).await;
+        assert_eq!(value.unwrap(), "3");
+
+        // interrupted, GDB answers the next command as expected
+        let value = manager.call_function(&session_id, "spin", &[], Duration::ZERO).await;
+        assert!(matches!(value, Err(AppError::GDBTimeout)));
+        assert_eq!(manager.show_setting(&session_id, "unwindonsignal").await.unwrap(), "off");
+    }
+}
@@ -0,0 +1,172 @@
+use std::collections::VecDeque;
+
+use serde::Serialize;
+
+use super::GDBManager;
+use crate::error::AppResult;
+use crate::mi::commands::MiCommand;
+use crate::mi::output::StreamKind;
+
+/// Number of stream records kept per session
+const CAPACITY: usize = 1000;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ConsoleRecord {
+    /// Position of the record in the stream
+    pub cursor: u64,
+    pub stream: StreamKind,
+    pub text: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ConsoleOutput {
+    /// Pass it as `since_cursor` to only get the records after these
+    pub cursor: u64,
+    /// Some records after the requested cursor were already dropped
+    pub truncated: bool,
+    pub records: Vec<ConsoleRecord>,
+}
+
+/// What a command run at the start of a session printed, or why it failed
+#[derive(Debug, Clone, Serialize)]
+pub struct CommandResult {
+    pub command: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub output: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Ring buffer of the console, target and log stream records of a session
+#[derive(Debug, Default)]
+pub struct ConsoleBuffer {
+    records: VecDeque<ConsoleRecord>,
+    next_cursor: u64,
+}
+
+impl ConsoleBuffer {
+    pub fn push(&mut self, stream: StreamKind, text: String) {
+        if self.records.len() == CAPACITY {
+            self.records.pop_front();
+        }
+        self.records.push_back(ConsoleRecord { cursor: self.next_cursor, stream, text });
+        self.next_cursor += 1;
+    }
+
+    /// Records with a cursor not less than `cursor`, all buffered records if
+    /// None
+    pub fn since(&self, cursor: Option<u64>) -> ConsoleOutput {
+        let cursor = cursor.unwrap_or(0);
+        let oldest = self.records.front().map_or(self.next_cursor, |record| record.cursor);
+        ConsoleOutput {
+            cursor: self.next_cursor,
+            truncated: cursor < oldest,
+            records: self
+                .records
+                .iter()
+                .filter(|record| record.cursor >= cursor)
+                .cloned()
+                .collect(),
+        }
+    }
+}
+
+impl GDBManager {
+    /// Run a CLI command typed by a user, queued with the commands of the
+    /// tools, and return what it printed
+    pub async fn execute_cli(&self, session_id: &str, command: &str) -> AppResult<String> {
+        self.capture_console(session_id, command).await
+    }
+
+    /// Run CLI commands one after the other, a failed one does not stop the
+    /// next ones
+    pub async fn run_init_commands(
+        &self,
+        session_id: &str,
+        commands: &[String],
+    ) -> Vec<CommandResult> {
+        let mut results = vec![];
+        for command in commands {
+            let (output, error) = match self.capture_console(session_id, command).await {
+                Ok(output) => (Some(output), None),
+                Err(e) => (None, Some(e.to_string())),
+            };
+            results.push(CommandResult { command: command.clone(), output, error });
+        }
+        results
+    }
+
+    /// Run a CLI command and return what it printed on the console, the
+    /// console records GDB outputs before the result of the command
+    pub(super) async fn capture_console(
+        &self,
+        session_id: &str,
+        command: &str,
+    ) -> AppResult<String> {
+        let record =
+            self.send_command_with_timeout(session_id, &MiCommand::cli_exec(command)).await?;
+        Ok(record.console)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use super::*;
+
+    #[test]
+    fn test_console_buffer() {
+        let mut buffer = ConsoleBuffer::default();
+        for i in 0..CAPACITY + 2 {
+            buffer.push(StreamKind::Console, format!("line {}\n", i));
+        }
+
+        let output = buffer.since(None);
+        assert!(output.truncated);
+        assert_eq!(output.records.len(), CAPACITY);
+        assert_eq!(output.records[0].text, "line 2\n");
+
+        let output = buffer.since(Some(output.cursor - 1));
+        assert!(!output.truncated);
+        assert_eq!(output.records.len(), 1);
+        assert_eq!(output.cursor, CAPACITY as u64 + 2);
+        assert!(buffer.since(Some(output.cursor)).records.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_capture_console() {
+        let manager = GDBManager::default();
+        let session_id = manager
+            .create_mock_session(
+                r#"
+                <- ~"\nBreakpoint 1, main () at test_app.rs:12\n"
+                <- *stopped,reason="breakpoint-hit",bkptno="1"
+                -> -interpreter-exec console "info proc"
+                <- ~"process 42\n"
+                <- ~"cmdline = '/bin/true'\n"
+                <- ^done
+                "#,
+            )
+            .await
+            .unwrap();
+
+        let output = manager.capture_console(&session_id, "info proc").await.unwrap();
+        assert_eq!(output, "process 42\ncmdline = '/bin/true'\n");
+        // nothing but what GDB printed is added to the console
+        let console = tokio::time::timeout(Duration::from_secs(5), async {
+            loop {
+                let console = manager.get_console_output(&session_id, None).await.unwrap();
+                if console.records.len() >= 3 {
+                    return console;
+                }
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        });
+        let text = console.await.unwrap().records.into_iter().map(|record| record.text);
+        assert_eq!(
+            text.collect::<String>(),
+            "\nBreakpoint 1, main () at test_app.rs:12\nprocess 42\ncmdline = '/bin/true'\n"
+        );
+    }
+}
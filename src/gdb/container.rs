@@ -0,0 +1,203 @@
+//! Sessions attached to a process running in a local container
+
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use serde::Serialize;
+use tokio::process::Command;
+use tracing::debug;
+
+use super::{GDBManager, SessionOptions};
+use crate::error::{AppError, AppResult};
+use crate::mi::commands::escape_command;
+
+/// Container engines tried in turn when none is given
+const RUNTIMES: &[&str] = &["docker", "podman"];
+
+/// A process found in a container
+#[derive(Debug, Clone, Serialize)]
+pub struct ContainerProcess {
+    pub container: String,
+    pub runtime: String,
+    /// PID on the host, GDB attaches to it
+    pub pid: u32,
+    pub command: String,
+    /// The filesystem of the container seen from the host, the sysroot of
+    /// the session
+    pub root: PathBuf,
+}
+
+impl GDBManager {
+    /// Find the process whose command line contains `process` among those of
+    /// a running container and attach a new session to it, reading the
+    /// shared libraries from the filesystem of the container. The sources
+    /// under `source_dir` in the container are read from there too. Needs the
+    /// permission to ptrace the process, usually root
+    pub async fn create_container_session(
+        &self,
+        container: &str,
+        process: &str,
+        runtime: Option<&str>,
+        source_dir: Option<&str>,
+        gdb_path: Option<PathBuf>,
+    ) -> AppResult<(String, ContainerProcess)> {
+        let (runtime, init_pid) = self.container_pid(container, runtime).await?;
+        let name = process.to_string();
+        let (pid, command) =
+            tokio::task::spawn_blocking(move || find_in_namespace(init_pid, &name)).await??;
+        let root = PathBuf::from(format!("/proc/{}/root", pid));
+
+        let session_id = self
+            .create_session(SessionOptions {
+                proc_id: Some(pid),
+                gdb_path,
+                sysroot: Some(root.clone()),
+                ..Default::default()
+            })
+            .await?;
+        if let Some(source_dir) = source_dir {
+            let rule = format!(
+                "{} {}",
+                escape_command(source_dir),
+                escape_command(&format!("{}{}", root.display(), source_dir))
+            );
+            if let Err(e) = self.set_parameter(&session_id, "substitute-path", &rule).await {
+                self.abandon_session(&session_id).await;
+                return Err(e);
+            }
+        }
+        Ok((
+            session_id,
+            ContainerProcess { container: container.to_string(), runtime, pid, command, root },
+        ))
+    }
+
+    /// PID on the host of the first process of a running container, with the
+    /// engine running it
+    async fn container_pid(
+        &self,
+        container: &str,
+        runtime: Option<&str>,
+    ) -> AppResult<(String, u32)> {
+        let runtimes = runtime.map_or(RUNTIMES.to_vec(), |runtime| vec![runtime]);
+        let timeout = Duration::from_secs(self.config.command_timeout);
+        let mut errors = vec![];
+        for runtime in runtimes {
+            let command = Command::new(runtime)
+                .args(["inspect", "--format", "{{.State.Pid}}", container])
+                .output();
+            let output = match tokio::time::timeout(timeout, command).await {
+                Ok(Ok(output)) => output,
+                Ok(Err(e)) => {
+                    errors.push(format!("{}: {}", runtime, e));
+                    continue;
+                }
+                Err(_) => return Err(AppError::GDBTimeout),
+            };
+            if !output.status.success() {
+                errors.push(format!(
+                    "{}: {}",
+                    runtime,
+                    String::from_utf8_lossy(&output.stderr).trim()
+                ));
+                continue;
+            }
+            let pid = String::from_utf8_lossy(&output.stdout).trim().to_string();
+            debug!("Container {} of {} has PID {}", container, runtime, pid);
+            return match pid.parse() {
+                Ok(0) | Err(_) => Err(AppError::InvalidArgument(format!(
+                    "container {} is not running",
+                    container
+                ))),
+                Ok(pid) => Ok((runtime.to_string(), pid)),
+            };
+        }
+        Err(AppError::NotFound(format!("Container {} not found: {}", container, errors.join(", "))))
+    }
+}
+
+/// The process in the PID namespace of `init_pid` whose command line
+/// contains `name`, ignoring case
+fn find_in_namespace(init_pid: u32, name: &str) -> AppResult<(u32, String)> {
+    let proc_root = Path::new("/proc");
+    let namespace = std::fs::read_link(proc_root.join(init_pid.to_string()).join("ns/pid"))?;
+    let lowercase = name.to_lowercase();
+    let mut found = vec![];
+    for entry in std::fs::read_dir(proc_root)?.flatten() {
+        let Some(pid) = entry.file_name().to_str().and_then(|pid| pid.parse::<u32>().ok()) else {
+            continue;
+        };
+        if std::fs::read_link(entry.path().join("ns/pid")).ok().as_ref() != Some(&namespace) {
+            continue;
+        }
+        let Ok(cmdline) = std::fs::read(entry.path().join("cmdline")) else {
+            continue;
+        };
+        let command = parse_cmdline(&cmdline);
+        // kernel threads and zombies have no command line
+        if !command.is_empty() && command.to_lowercase().contains(&lowercase) {
+            found.push((pid, command));
+        }
+    }
+    single_match(found, name)
+}
+
+/// The only process found, attaching to one of several picked by chance
+/// could debug the wrong worker
+fn single_match(mut found: Vec<(u32, String)>, name: &str) -> AppResult<(u32, String)> {
+    match found.len() {
+        0 => Err(AppError::NotFound(format!("No process matching {} in the container", name))),
+        1 => Ok(found.remove(0)),
+        _ => {
+            found.sort();
+            let processes = found
+                .iter()
+                .map(|(pid, command)| format!("{} ({})", pid, command))
+                .collect::<Vec<_>>();
+            Err(AppError::InvalidArgument(format!(
+                "{} processes match {} in the container, give a longer part of the command \
+                 line: {}",
+                found.len(),
+                name,
+                processes.join(", ")
+            )))
+        }
+    }
+}
+
+/// `/proc/<pid>/cmdline`, arguments separated by NUL
+fn parse_cmdline(cmdline: &[u8]) -> String {
+    String::from_utf8_lossy(cmdline)
+        .split('\0')
+        .filter(|arg| !arg.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_cmdline() {
+        assert_eq!(
+            parse_cmdline(b"/usr/bin/nginx\0-g\0daemon off;\0"),
+            "/usr/bin/nginx -g daemon off;"
+        );
+        assert_eq!(parse_cmdline(b""), "");
+    }
+
+    #[test]
+    fn test_single_match() {
+        let found = vec![(7, "nginx: worker process".to_string())];
+        assert_eq!(single_match(found, "nginx").unwrap().0, 7);
+
+        let found = vec![
+            (9, "nginx: worker process".to_string()),
+            (1, "nginx: master process".to_string()),
+        ];
+        assert!(matches!(single_match(found, "nginx"), Err(AppError::InvalidArgument(msg))
+            if msg.contains("1 (nginx: master process), 9 (nginx: worker process)")));
+        assert!(matches!(single_match(vec![], "nginx"), Err(AppError::NotFound(_))));
+    }
+}
@@ -0,0 +1,58 @@
+use serde::Serialize;
+use serde_with::skip_serializing_none;
+use tracing::warn;
+
+use super::GDBManager;
+use super::registers::RegisterChange;
+use crate::config::StopContextConfig;
+use crate::error::AppResult;
+use crate::models::{Memory, StackFrame, Variable};
+
+/// What an agent usually asks for after a stop, bundled with it. Parts which
+/// could not be read are left out
+#[skip_serializing_none]
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct StopContext {
+    /// The innermost frames
+    pub frames: Option<Vec<StackFrame>>,
+    /// Local variables of frame 0
+    pub locals: Option<Vec<Variable>>,
+    /// Registers which changed since the previous stop
+    pub register_changes: Option<Vec<RegisterChange>>,
+    /// Memory centered on `$sp`
+    pub sp_memory: Option<Vec<Memory>>,
+    /// Memory centered on `$pc`
+    pub pc_memory: Option<Vec<Memory>>,
+}
+
+impl GDBManager {
+    /// Collect the context of the last stop as configured
+    pub async fn stop_context(&self, session_id: &str, config: &StopContextConfig) -> StopContext {
+        let mut context = StopContext::default();
+        if config.frames > 0 {
+            let frames = self.list_stack_frames(session_id, Some(0), Some(config.frames - 1));
+            context.frames = ok_or_warn(frames.await, "frames");
+        }
+        if config.locals {
+            context.locals =
+                ok_or_warn(self.get_local_variables(session_id, None, None).await, "locals");
+        }
+        if config.registers {
+            context.register_changes =
+                ok_or_warn(self.get_register_changes(session_id).await, "register changes");
+        }
+        if config.memory_bytes > 0 {
+            let before = Some(-((config.memory_bytes / 2) as isize));
+            let read = |address: &str| {
+                self.read_memory(session_id, before, address.to_string(), config.memory_bytes)
+            };
+            context.sp_memory = ok_or_warn(read("$sp").await, "memory at $sp");
+            context.pc_memory = ok_or_warn(read("$pc").await, "memory at $pc");
+        }
+        context
+    }
+}
+
+fn ok_or_warn<T>(result: AppResult<T>, part: &str) -> Option<T> {
+    result.inspect_err(|e| warn!("Failed to collect the {} of the stop: {}", part, e)).ok()
+}
@@ -0,0 +1,180 @@
+//! Backtrace rebuilt from the words of the stack which look like return
+//! addresses, for when the unwinder of GDB gives up on a corrupted stack
+
+use iced_x86::{Decoder, DecoderOptions, FlowControl};
+use serde::Serialize;
+use serde_with::skip_serializing_none;
+use tracing::warn;
+
+use super::GDBManager;
+use super::frames::Backtrace;
+use super::got::parse_symbol;
+use super::memory::parse_number;
+use crate::error::{AppError, AppResult};
+use crate::models::{Endian, MemoryType};
+use crate::resources::decode_hex;
+
+/// Longest call instruction looked for before a return address, with its
+/// prefixes
+const MAX_CALL_BYTES: usize = 15;
+
+/// What GDB unwound of the stack and what the stack words suggest
+#[skip_serializing_none]
+#[derive(Debug, Clone, Serialize)]
+pub struct StackCorruption {
+    /// Frames unwound by GDB, not set if it failed to
+    pub backtrace: Option<Backtrace>,
+    /// GDB did not unwind past frame 0
+    pub unwinder_gave_up: bool,
+    pub sp: String,
+    /// Words of the stack scanned from $sp
+    pub words_scanned: usize,
+    /// Frame 0 then the likely return addresses found on the stack, innermost
+    /// first
+    pub frames: Vec<ScannedFrame>,
+}
+
+/// A frame of the rebuilt backtrace
+#[skip_serializing_none]
+#[derive(Debug, Clone, Serialize)]
+pub struct ScannedFrame {
+    /// Where the return address is stored, not set for frame 0
+    pub stack_address: Option<String>,
+    /// Offset from $sp in bytes
+    pub offset: Option<usize>,
+    pub address: String,
+    /// e.g. `main+22`
+    pub symbol: Option<String>,
+    /// The instruction before the address is a call, only checked on x86
+    pub after_call: Option<bool>,
+}
+
+impl GDBManager {
+    /// Scan `words` words from `$sp` for the values pointing into executable
+    /// mappings which follow a call, and rebuild a backtrace of at most
+    /// `max_frames` frames from them
+    pub async fn analyze_stack_corruption(
+        &self,
+        session_id: &str,
+        words: usize,
+        max_frames: usize,
+    ) -> AppResult<StackCorruption> {
+        let arch = self.get_session(session_id).await?.arch;
+        let word = arch.pointer_bits.unwrap_or(64) as usize / 8;
+        let endian = arch.endian.unwrap_or(Endian::Little);
+        // the return sites are not checked if the architecture is unknown
+        let x86 =
+            arch.name.as_deref().is_some_and(|name| name.contains("i386") || name.contains("x86"));
+        let mappings = self.memory_mappings(session_id).await?;
+        if mappings.is_empty() {
+            return Err(AppError::NotFound(
+                "No memory mappings, the process must be started".to_string(),
+            ));
+        }
+
+        let backtrace = self
+            .get_backtrace(session_id, Some(max_frames))
+            .await
+            .inspect_err(|e| warn!("GDB failed to unwind the stack: {}", e))
+            .ok();
+        let unwinder_gave_up =
+            backtrace.as_ref().is_none_or(|backtrace| backtrace.frames.len() <= 1);
+
+        let pc = self.resolve_address(session_id, "$pc").await?;
+        let mut frames = vec![ScannedFrame {
+            stack_address: None,
+            offset: None,
+            address: format!("0x{:x}", pc),
+            symbol: self.symbol_of(session_id, pc).await,
+            after_call: None,
+        }];
+        let sp = self.resolve_address(session_id, "$sp").await?;
+        let stack = self.read_words(session_id, sp, words, word, endian).await?;
+        for (i, value) in stack.iter().enumerate() {
+            if frames.len() >= max_frames {
+                break;
+            }
+            if MemoryType::classify(&mappings, *value, None) != MemoryType::Exec {
+                continue;
+            }
+            let after_call =
+                if x86 { Some(self.follows_call(session_id, *value, word).await) } else { None };
+            if after_call == Some(false) {
+                continue;
+            }
+            frames.push(ScannedFrame {
+                stack_address: Some(format!("0x{:x}", sp + (i * word) as u64)),
+                offset: Some(i * word),
+                address: format!("0x{:x}", value),
+                symbol: self.symbol_of(session_id, *value).await,
+                after_call,
+            });
+        }
+        Ok(StackCorruption {
+            backtrace,
+            unwinder_gave_up,
+            sp: format!("0x{:x}", sp),
+            words_scanned: stack.len(),
+            frames,
+        })
+    }
+
+    /// The symbol GDB prints with an address, e.g. `main+22`
+    async fn symbol_of(&self, session_id: &str, address: u64) -> Option<String> {
+        self.evaluate_expression(session_id, &format!("(void *) 0x{:x}", address))
+            .await
+            .ok()
+            .and_then(|value| parse_symbol(&value))
+    }
+
+    /// Whether the bytes before `address` end with a call instruction
+    async fn follows_call(&self, session_id: &str, address: u64, word: usize) -> bool {
+        let Some(start) = address.checked_sub(MAX_CALL_BYTES as u64) else {
+            return false;
+        };
+        let memory =
+            self.read_memory(session_id, None, format!("0x{:x}", start), MAX_CALL_BYTES).await;
+        let code = match memory.as_ref().ok().and_then(|memory| memory.first()) {
+            Some(block) if parse_number(&block.begin) == Some(start) => {
+                decode_hex(&block.contents).unwrap_or_default()
+            }
+            _ => return false,
+        };
+        ends_with_call(&code, address, (word * 8) as u32)
+    }
+}
+
+/// Whether an instruction of `code`, which ends at `address`, is a call
+fn ends_with_call(code: &[u8], address: u64, bitness: u32) -> bool {
+    (1..=code.len()).any(|length| {
+        let start = code.len() - length;
+        let mut decoder = Decoder::with_ip(
+            bitness,
+            &code[start..],
+            address - length as u64,
+            DecoderOptions::NONE,
+        );
+        let instruction = decoder.decode();
+        !instruction.is_invalid()
+            && instruction.len() == length
+            && matches!(instruction.flow_control(), FlowControl::Call | FlowControl::IndirectCall)
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_ends_with_call() {
+        // call rel32
+        assert!(ends_with_call(&[0x90, 0xe8, 0x10, 0x00, 0x00, 0x00], 0x1006, 64));
+        // call rax
+        assert!(ends_with_call(&[0x90, 0x90, 0xff, 0xd0], 0x1004, 64));
+        // call qword ptr [rip+0x2fe2]
+        assert!(ends_with_call(&[0xff, 0x15, 0xe2, 0x2f, 0x00, 0x00], 0x1006, 64));
+        // mov eax, 1
+        assert!(!ends_with_call(&[0xb8, 0x01, 0x00, 0x00, 0x00], 0x1005, 64));
+        assert!(!ends_with_call(&[], 0x1000, 64));
+    }
+}
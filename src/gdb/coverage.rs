@@ -0,0 +1,242 @@
+use std::collections::BTreeMap;
+use std::str::FromStr;
+use std::time::Duration;
+
+use serde::Serialize;
+use serde_json::Value;
+use tokio::time::Instant;
+use tracing::warn;
+
+use super::{GDBManager, Resumed};
+use crate::error::{AppError, AppResult};
+use crate::mi::commands::{BreakPointNumber, MiCommand};
+use crate::models::GDBSessionStatus;
+
+/// Breakpoints are inserted one by one, refuse plans larger than this
+const MAX_BREAKPOINTS: usize = 2000;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CoverageMode {
+    /// Every function, or those of a source file
+    Functions,
+    /// Every line with code in a source file
+    Lines,
+}
+
+impl FromStr for CoverageMode {
+    type Err = AppError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "functions" => Ok(Self::Functions),
+            "lines" => Ok(Self::Lines),
+            _ => Err(AppError::InvalidArgument(format!(
+                "unknown coverage mode {}, expecting functions or lines",
+                s
+            ))),
+        }
+    }
+}
+
+/// Outcome of `collect_coverage`
+#[derive(Debug, Clone, Serialize)]
+pub struct Coverage {
+    pub hit: Vec<String>,
+    pub not_hit: Vec<String>,
+    /// Locations where no breakpoint could be inserted
+    pub failed: Vec<String>,
+    /// Why collection ended: exited, timeout or the stop reason
+    pub ended: String,
+    /// Results of the last `*stopped` record
+    pub stopped: Value,
+}
+
+impl GDBManager {
+    /// Insert a temporary breakpoint on every function or line, run or
+    /// continue the program until it exits, stops for another reason or
+    /// `timeout` elapses, and report which locations were reached
+    pub async fn collect_coverage(
+        &self,
+        session_id: &str,
+        mode: CoverageMode,
+        file: Option<&str>,
+        name_regexp: Option<&str>,
+        timeout: Duration,
+    ) -> AppResult<Coverage> {
+        let locations = match mode {
+            CoverageMode::Functions => self.list_functions(session_id, file, name_regexp).await?,
+            CoverageMode::Lines => {
+                let file = file.ok_or_else(|| {
+                    AppError::InvalidArgument("file is required to collect line coverage".into())
+                })?;
+                self.list_lines(session_id, file).await?
+            }
+        };
+        if locations.len() > MAX_BREAKPOINTS {
+            return Err(AppError::InvalidArgument(format!(
+                "{} locations to cover, at most {} are supported, narrow them with file or \
+                 name_regexp",
+                locations.len(),
+                MAX_BREAKPOINTS
+            )));
+        }
+
+        let mut pending = BTreeMap::new();
+        let mut failed = vec![];
+        for location in locations {
+            match self.insert_breakpoint_at(session_id, &location, None, true).await {
+                Ok(number) => {
+                    pending.insert(number.to_string(), location);
+                }
+                Err(e) => {
+                    warn!("No coverage breakpoint at {}: {}", location, e);
+                    failed.push(location);
+                }
+            }
+        }
+
+        let deadline = Instant::now() + timeout;
+        let mut hit = vec![];
+        let started = self.get_session(session_id).await?.status != GDBSessionStatus::Created;
+        let mut command = if started { MiCommand::exec_continue() } else { MiCommand::exec_run() };
+        let (ended, stopped) = loop {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            let stopped = match self.resume_and_wait(session_id, &command, remaining).await? {
+                Resumed::Stopped(stopped) => stopped,
+                Resumed::Interrupted(stopped) => break ("timeout".to_string(), stopped),
+            };
+            let reason = stopped.get("reason").and_then(|r| r.as_str()).unwrap_or_default();
+            let location = stopped
+                .get("bkptno")
+                .and_then(|n| n.as_str())
+                .and_then(|number| pending.remove(number));
+            match (reason, location) {
+                ("breakpoint-hit", Some(location)) => hit.push(location),
+                _ => {
+                    let ended = if reason.starts_with("exited") { "exited" } else { reason };
+                    break (ended.to_string(), stopped);
+                }
+            }
+            command = MiCommand::exec_continue();
+        };
+
+        if !pending.is_empty() && ended != "exited" {
+            let numbers = pending
+                .keys()
+                .map(|number| number.parse::<BreakPointNumber>())
+                .collect::<Result<Vec<_>, _>>()?;
+            let command = MiCommand::delete_breakpoints(numbers);
+            if let Err(e) = self.send_command_with_timeout(session_id, &command).await {
+                warn!("Failed to delete the coverage breakpoints: {}", e);
+            }
+        }
+        Ok(Coverage { hit, not_hit: pending.into_values().collect(), failed, ended, stopped })
+    }
+
+    /// Functions with debug information as `file:function`, optionally only
+    /// those of `file`
+    async fn list_functions(
+        &self,
+        session_id: &str,
+        file: Option<&str>,
+        name_regexp: Option<&str>,
+    ) -> AppResult<Vec<String>> {
+        let command = MiCommand::symbol_info_functions(name_regexp, false);
+        let response = self.send_command_with_timeout(session_id, &command).await?;
+        let sources = response
+            .results
+            .get("symbols")
+            .and_then(|symbols| symbols.get("debug"))
+            .and_then(|debug| debug.as_array())
+            .ok_or(AppError::NotFound("symbols not found".to_string()))?;
+
+        let mut functions = vec![];
+        for source in sources {
+            let filename = source.get("filename").and_then(|f| f.as_str()).unwrap_or_default();
+            let fullname = source.get("fullname").and_then(|f| f.as_str()).unwrap_or_default();
+            if file.is_some_and(|file| !filename.ends_with(file) && !fullname.ends_with(file)) {
+                continue;
+            }
+            let symbols = source.get("symbols").and_then(|s| s.as_array());
+            for symbol in symbols.into_iter().flatten() {
+                if let Some(name) = symbol.get("name").and_then(|name| name.as_str()) {
+                    functions.push(format!("{}:{}", filename, name));
+                }
+            }
+        }
+        Ok(functions)
+    }
+
+    /// Lines of `file` which have code as `file:line`
+    async fn list_lines(&self, session_id: &str, file: &str) -> AppResult<Vec<String>> {
+        let command = MiCommand::symbol_list_lines(file);
+        let response = self.send_command_with_timeout(session_id, &command).await?;
+        let lines = response
+            .results
+            .get("lines")
+            .and_then(|lines| lines.as_array())
+            .ok_or(AppError::NotFound("lines not found".to_string()))?;
+
+        let mut numbers = lines
+            .iter()
+            .filter_map(|line| line.get("line")?.as_str()?.parse::<u32>().ok())
+            .filter(|line| *line > 0)
+            .collect::<Vec<_>>();
+        numbers.sort_unstable();
+        numbers.dedup();
+        Ok(numbers.into_iter().map(|line| format!("{}:{}", file, line)).collect())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_line_coverage() {
+        let manager = GDBManager::default();
+        let session_id = manager
+            .create_mock_session(
+                r#"
+                -> -symbol-list-lines test_app.rs
+                <- ^done,lines=[{pc="0x1000",line="5"},{pc="0x1004",line="5"},{pc="0x1010",line="6"},{pc="0x1020",line="7"}]
+                -> -break-insert -t test_app.rs:5
+                <- ^done,bkpt={number="1",type="breakpoint",disp="del",enabled="y",addr="0x1000",times="0"}
+                -> -break-insert -t test_app.rs:6
+                <- ^error,msg="No line 6 in file \"test_app.rs\"."
+                -> -break-insert -t test_app.rs:7
+                <- ^done,bkpt={number="2",type="breakpoint",disp="del",enabled="y",addr="0x1020",times="0"}
+                -> -exec-run
+                <- ^running
+                <- *running,thread-id="all"
+                <- *stopped,reason="breakpoint-hit",disp="del",bkptno="1",frame={addr="0x1000",func="main",args=[]},thread-id="1"
+                -> -exec-continue
+                <- ^running
+                <- *running,thread-id="all"
+                <- *stopped,reason="signal-received",signal-name="SIGSEGV",frame={addr="0x1010",func="main",args=[]},thread-id="1"
+                -> -break-delete 2
+                <- ^done
+                "#,
+            )
+            .await
+            .unwrap();
+
+        let coverage = manager
+            .collect_coverage(
+                &session_id,
+                CoverageMode::Lines,
+                Some("test_app.rs"),
+                None,
+                Duration::from_secs(5),
+            )
+            .await
+            .unwrap();
+        assert_eq!(coverage.hit, ["test_app.rs:5"]);
+        assert_eq!(coverage.not_hit, ["test_app.rs:7"]);
+        assert_eq!(coverage.failed, ["test_app.rs:6"]);
+        assert_eq!(coverage.ended, "signal-received");
+        // the breakpoints left are deleted, the script is over after
+        assert!(matches!(manager.list_threads(&session_id).await,
+            Err(AppError::GDBCommandError { msg, .. }) if msg.contains("expecting none")));
+    }
+}
@@ -0,0 +1,199 @@
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+use serde::Serialize;
+use tracing::warn;
+
+use super::GDBManager;
+use crate::error::{AppError, AppResult};
+use crate::mi::commands::MiCommand;
+use crate::models::StackFrame;
+
+/// Functions a thread blocked on a mutex waits in
+const LOCK_FUNCTIONS: &[&str] = &[
+    "__lll_lock_wait",
+    "pthread_mutex_lock",
+    "pthread_mutex_timedlock",
+    "pthread_mutex_clocklock",
+];
+
+/// Offset of `__owner` in the glibc `pthread_mutex_t`, after `__lock` and
+/// `__count`, on 32 and 64 bits
+const OWNER_OFFSET: u64 = 8;
+
+/// The threads of a program and the cycle of the ones waiting for each other
+#[derive(Debug, Clone, Serialize)]
+pub struct DeadlockReport {
+    /// The program was running and had to be interrupted
+    pub interrupted: bool,
+    pub threads: Vec<ThreadWait>,
+    /// IDs of the threads in the cycle, each waiting for a mutex held by the
+    /// next one and the last by the first
+    pub cycle: Option<Vec<String>>,
+}
+
+/// The backtrace of a thread and the mutex it waits for
+#[derive(Debug, Clone, Serialize)]
+pub struct ThreadWait {
+    pub id: String,
+    pub target_id: Option<String>,
+    /// Kernel thread ID, the mutex owners are recorded with it
+    pub lwp: Option<u64>,
+    pub frames: Vec<StackFrame>,
+    pub waiting_on: Option<MutexWait>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct MutexWait {
+    pub mutex: String,
+    /// None when the owner field could not be read, 0 if unlocked
+    pub owner_lwp: Option<u64>,
+    /// ID of the owner among the threads of the program
+    pub owner_thread: Option<String>,
+}
+
+impl GDBManager {
+    /// Interrupt the program if it is running, then find the threads blocked
+    /// on a glibc mutex, the owner of the mutex and the cycle they form.
+    ///
+    /// The mutex is the `mutex` argument of `pthread_mutex_lock`, which needs
+    /// the debug information of glibc, or `$rdi` of the innermost frame on
+    /// x86-64, the futex the thread sleeps on
+    pub async fn analyze_deadlock(&self, session_id: &str) -> AppResult<DeadlockReport> {
+        let (running, stops) = {
+            let sessions = self.sessions.lock().await;
+            let handle = sessions.get(session_id).ok_or_else(|| {
+                AppError::NotFound(format!("Session {} does not exist", session_id))
+            })?;
+            (handle.gdb.is_running(), handle.stops.borrow().count)
+        };
+        if running {
+            self.stop_debugging(session_id).await?;
+            let timeout = Duration::from_secs(self.config.command_timeout);
+            self.wait_stopped(session_id, stops, timeout).await?;
+        }
+
+        let list = self.list_threads(session_id).await?;
+        let mut threads = vec![];
+        for thread in list.threads {
+            let id = thread.id.to_string();
+            let target_id = Some(thread.target_id);
+            let lwp = target_id.as_deref().and_then(parse_lwp);
+            match self.thread_wait(session_id, &id).await {
+                Ok((frames, waiting_on)) => {
+                    threads.push(ThreadWait { id, target_id, lwp, frames, waiting_on })
+                }
+                Err(e) => warn!("Failed to inspect thread {}: {}", id, e),
+            }
+        }
+        if let Some(current) = &list.current_thread_id {
+            self.send_command_with_timeout(session_id, &MiCommand::thread_select(current)).await?;
+        }
+
+        let owners = threads
+            .iter()
+            .filter_map(|thread| Some((thread.lwp?, thread.id.clone())))
+            .collect::<BTreeMap<_, _>>();
+        let mut waits = BTreeMap::new();
+        for thread in &mut threads {
+            if let Some(wait) = &mut thread.waiting_on {
+                wait.owner_thread = wait.owner_lwp.and_then(|lwp| owners.get(&lwp)).cloned();
+                if let Some(owner) = &wait.owner_thread {
+                    waits.insert(thread.id.clone(), owner.clone());
+                }
+            }
+        }
+        Ok(DeadlockReport { interrupted: running, threads, cycle: find_cycle(&waits) })
+    }
+
+    /// Backtrace of a thread, and the mutex it waits for if it is blocked in
+    /// a lock function
+    async fn thread_wait(
+        &self,
+        session_id: &str,
+        id: &str,
+    ) -> AppResult<(Vec<StackFrame>, Option<MutexWait>)> {
+        self.send_command_with_timeout(session_id, &MiCommand::thread_select(id)).await?;
+        let frames = self.get_stack_frames(session_id).await?;
+        let Some(lock_frame) = frames
+            .iter()
+            .find(|frame| LOCK_FUNCTIONS.iter().any(|function| frame.function.contains(function)))
+        else {
+            return Ok((frames, None));
+        };
+
+        let mut mutex = None;
+        if let Some(frame) = frames.iter().find(|frame| frame.function.contains("pthread_mutex_")) {
+            self.send_command_with_timeout(
+                session_id,
+                &MiCommand::select_frame(frame.level as u64),
+            )
+            .await?;
+            mutex = self.resolve_address(session_id, "mutex").await.ok();
+            self.send_command_with_timeout(session_id, &MiCommand::select_frame(0)).await?;
+        }
+        if mutex.is_none() && lock_frame.function.contains("__lll_lock_wait") {
+            mutex = self.resolve_address(session_id, "$rdi").await.ok();
+        }
+        let Some(mutex) = mutex else {
+            return Ok((frames, None));
+        };
+
+        let owner = format!("*(int *)0x{:x}", mutex + OWNER_OFFSET);
+        let owner_lwp = match self.evaluate_expression(session_id, &owner).await {
+            Ok(owner) => owner.trim().parse().ok(),
+            Err(e) => {
+                warn!("Failed to read the owner of the mutex at 0x{:x}: {}", mutex, e);
+                None
+            }
+        };
+        Ok((
+            frames,
+            Some(MutexWait { mutex: format!("0x{:x}", mutex), owner_lwp, owner_thread: None }),
+        ))
+    }
+}
+
+/// The LWP of a thread from its target ID, e.g. `Thread 0x7ffff7d8a740 (LWP
+/// 1234)`
+fn parse_lwp(target_id: &str) -> Option<u64> {
+    let start = target_id.find("LWP ")? + 4;
+    target_id[start..].split(|c: char| !c.is_ascii_digit()).next()?.parse().ok()
+}
+
+/// The first cycle of the threads waiting for each other, given the thread
+/// each one waits for
+fn find_cycle(waits: &BTreeMap<String, String>) -> Option<Vec<String>> {
+    for start in waits.keys() {
+        let mut path = vec![start];
+        let mut next = waits.get(start);
+        while let Some(thread) = next {
+            if let Some(position) = path.iter().position(|seen| *seen == thread) {
+                return Some(path[position..].iter().map(|thread| thread.to_string()).collect());
+            }
+            path.push(thread);
+            next = waits.get(thread);
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_find_cycle() {
+        assert_eq!(parse_lwp("Thread 0x7ffff7d8a740 (LWP 1234)"), Some(1234));
+        assert_eq!(parse_lwp("process 1234"), None);
+
+        let waits = [("1", "2"), ("2", "3"), ("3", "2"), ("4", "1")]
+            .into_iter()
+            .map(|(thread, owner)| (thread.to_string(), owner.to_string()))
+            .collect::<BTreeMap<_, _>>();
+        assert_eq!(find_cycle(&waits), Some(vec!["2".to_string(), "3".to_string()]));
+
+        let waits = BTreeMap::from([("1".to_string(), "2".to_string())]);
+        assert_eq!(find_cycle(&waits), None);
+    }
+}
@@ -0,0 +1,38 @@
+//! Demangling of Rust and C++ symbol names
+
+use cpp_demangle::{DemangleOptions, Symbol};
+
+/// Demangle a Rust or C++ symbol name, None if it is not mangled. The hash
+/// of legacy Rust symbols is dropped
+pub fn demangle(name: &str) -> Option<String> {
+    if let Ok(symbol) = rustc_demangle::try_demangle(name) {
+        return Some(format!("{:#}", symbol));
+    }
+    // plain C names like `main` are not valid mangled names
+    Symbol::new(name).ok()?.demangle(&DemangleOptions::default()).ok()
+}
+
+/// Demangle a symbol name, or keep it as is if it is not mangled
+pub fn demangle_or_keep(name: &str) -> String {
+    demangle(name).unwrap_or_else(|| name.to_string())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_demangle() {
+        assert_eq!(
+            demangle("_ZN4core3fmt9Formatter3pad17h2e4a4c9b9b0f1a2bE").as_deref(),
+            Some("core::fmt::Formatter::pad")
+        );
+        assert_eq!(demangle("_RNvCs1234_7mycrate4main").as_deref(), Some("mycrate::main"));
+        assert_eq!(
+            demangle("_ZNSt6vectorIiSaIiEE9push_backERKi").as_deref(),
+            Some("std::vector<int, std::allocator<int> >::push_back(int const&)")
+        );
+        assert_eq!(demangle("main"), None);
+        assert_eq!(demangle_or_keep("main"), "main");
+    }
+}
@@ -0,0 +1,311 @@
+//! Disassembly of the function of the selected frame
+
+use std::collections::HashMap;
+use std::fs;
+
+use serde::Serialize;
+use serde_with::skip_serializing_none;
+
+use super::GDBManager;
+use super::demangle::demangle_or_keep;
+use super::memory::parse_number;
+use crate::error::{AppError, AppResult};
+use crate::mi::commands::{DisassembleMode, MiCommand};
+use crate::models::{Address64, DisassembledInstruction, DisassembledItem};
+
+/// Bytes disassembled from the PC when no function contains it
+const FALLBACK_BYTES: u64 = 64;
+
+/// A disassembled function
+#[skip_serializing_none]
+#[derive(Debug, Clone, Serialize)]
+pub struct Disassembly {
+    pub function: Option<String>,
+    /// PC of the selected frame
+    pub pc: Address64,
+    /// Instructions grouped by the source line they were generated from
+    pub blocks: Vec<SourceBlock>,
+}
+
+/// Instructions of a source line, or of unknown lines
+#[skip_serializing_none]
+#[derive(Debug, Clone, Serialize)]
+pub struct SourceBlock {
+    pub file: Option<String>,
+    pub line: Option<u32>,
+    /// Text of the line when the source file is readable
+    pub source: Option<String>,
+    pub instructions: Vec<Instruction>,
+    #[serde(skip)]
+    fullname: Option<String>,
+}
+
+#[skip_serializing_none]
+#[derive(Debug, Clone, Serialize)]
+pub struct Instruction {
+    pub address: Address64,
+    pub function: Option<String>,
+    /// Offset from the start of the function
+    pub offset: Option<u64>,
+    /// Raw bytes in hex
+    pub opcodes: Option<String>,
+    pub inst: String,
+    /// Name or comment imported for the address
+    pub annotation: Option<String>,
+    /// The instruction at the PC
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub is_pc: bool,
+}
+
+/// Where the program of a session stopped, in one call
+#[skip_serializing_none]
+#[derive(Debug, Clone, Serialize)]
+pub struct Location {
+    pub pc: Address64,
+    pub function: Option<String>,
+    pub file: Option<String>,
+    pub line: Option<u32>,
+    /// Text of the line when the source file is readable
+    pub source: Option<String>,
+    /// The instructions around the PC
+    pub instructions: Vec<Instruction>,
+}
+
+impl GDBManager {
+    /// The PC of the selected frame with its function, source line and at
+    /// most `context` instructions before and after it
+    pub async fn get_current_location(
+        &self,
+        session_id: &str,
+        context: usize,
+    ) -> AppResult<Location> {
+        let disassembly = self.disassemble_current_function(session_id).await?;
+        Ok(location(disassembly, context))
+    }
+
+    /// Disassemble the function of the selected frame, interleaved with its
+    /// source lines when there is line information, marking the instruction
+    /// at the PC. Some instructions from the PC are disassembled if no
+    /// function contains it
+    pub async fn disassemble_current_function(&self, session_id: &str) -> AppResult<Disassembly> {
+        let response =
+            self.send_command_with_timeout(session_id, &MiCommand::stack_info_frame(None)).await?;
+        let frame = response
+            .results
+            .get("frame")
+            .ok_or(AppError::NotFound("frame not found".to_string()))?;
+        let pc = Address64::from(
+            frame
+                .get("addr")
+                .and_then(|addr| addr.as_str())
+                .ok_or(AppError::NotFound("addr not found in the frame".to_string()))?
+                .to_string(),
+        );
+        let function =
+            frame.get("func").and_then(|func| func.as_str()).map(|func| self.symbol_name(func));
+
+        let command = MiCommand::data_disassemble_function(
+            pc.0,
+            DisassembleMode::SourceAndDisassemblyWithRawOpcodes,
+        );
+        let insns = match self.send_command_with_timeout(session_id, &command).await {
+            Ok(response) => response.results.get("asm_insns").cloned(),
+            // e.g. code without symbols
+            Err(AppError::GDBCommandError { .. }) => {
+                let command = MiCommand::data_disassemble_address(
+                    pc.0 as usize,
+                    (pc.0 + FALLBACK_BYTES) as usize,
+                    DisassembleMode::DisassemblyWithRawOpcodes,
+                );
+                let response = self.send_command_with_timeout(session_id, &command).await?;
+                response.results.get("asm_insns").cloned()
+            }
+            Err(e) => return Err(e),
+        };
+        let insns = serde_json::from_value(
+            insns.ok_or(AppError::NotFound("asm_insns not found".to_string()))?,
+        )?;
+
+        let mut blocks = parse_asm_insns(insns, pc.0, |name| self.symbol_name(name));
+        let instructions = blocks.iter().flat_map(|block| &block.instructions);
+        let addresses = instructions
+            .flat_map(|insn| [Some(insn.address.0), operand_address(&insn.inst)])
+            .flatten()
+            .collect::<Vec<_>>();
+        let labels = self.annotation_labels(session_id, addresses).await;
+        for insn in blocks.iter_mut().flat_map(|block| &mut block.instructions) {
+            insn.annotation = labels.get(&insn.address.0).cloned();
+            // GDB names the targets it has symbols for
+            if let Some(label) = operand_address(&insn.inst).and_then(|target| labels.get(&target))
+            {
+                insn.inst = format!("{} <{}>", insn.inst, label);
+            }
+        }
+        let mut sources = HashMap::new();
+        for block in &mut blocks {
+            let (Some(fullname), Some(line)) = (&block.fullname, block.line) else {
+                continue;
+            };
+            let lines = sources.entry(fullname.clone()).or_insert_with(|| {
+                fs::read_to_string(fullname)
+                    .map(|text| text.lines().map(str::to_string).collect::<Vec<_>>())
+                    .unwrap_or_default()
+            });
+            block.source = (line as usize).checked_sub(1).and_then(|i| lines.get(i)).cloned();
+        }
+        Ok(Disassembly { function, pc, blocks })
+    }
+
+    /// A symbol name as configured, demangled or not
+    fn symbol_name(&self, name: &str) -> String {
+        if self.config.demangle { demangle_or_keep(name) } else { name.to_string() }
+    }
+}
+
+/// Location of the PC of a disassembly, with the `context` instructions
+/// around it
+fn location(disassembly: Disassembly, context: usize) -> Location {
+    let block = disassembly
+        .blocks
+        .iter()
+        .find(|block| block.instructions.iter().any(|instruction| instruction.is_pc));
+    let (file, line, source) = match block {
+        Some(block) => (block.file.clone(), block.line, block.source.clone()),
+        None => (None, None, None),
+    };
+    let instructions =
+        disassembly.blocks.into_iter().flat_map(|block| block.instructions).collect::<Vec<_>>();
+    let pc = instructions.iter().position(|instruction| instruction.is_pc).unwrap_or_default();
+    let start = pc.saturating_sub(context);
+    let end = (pc + context + 1).min(instructions.len());
+    Location {
+        pc: disassembly.pc,
+        function: disassembly.function,
+        file,
+        line,
+        source,
+        instructions: instructions[start..end].to_vec(),
+    }
+}
+
+/// Group the `asm_insns` of `-data-disassemble`, either source lines with
+/// their instructions or bare instructions
+fn parse_asm_insns(
+    insns: Vec<DisassembledItem>,
+    pc: u64,
+    name: impl Fn(&str) -> String,
+) -> Vec<SourceBlock> {
+    let instruction = |insn: DisassembledInstruction| Instruction {
+        is_pc: insn.address.0 == pc,
+        address: insn.address,
+        function: insn.function.as_deref().map(&name),
+        offset: insn.offset,
+        opcodes: insn.opcodes,
+        inst: insn.inst,
+        annotation: None,
+    };
+
+    let mut blocks = vec![];
+    let mut bare = vec![];
+    for insn in insns {
+        match insn {
+            // lines without code are listed too
+            DisassembledItem::Line(line) if line.instructions.is_empty() => {}
+            DisassembledItem::Line(line) => blocks.push(SourceBlock {
+                file: line.file,
+                line: line.line,
+                source: None,
+                instructions: line.instructions.into_iter().map(instruction).collect(),
+                fullname: line.fullname,
+            }),
+            DisassembledItem::Instruction(insn) => bare.push(instruction(insn)),
+        }
+    }
+    if !bare.is_empty() {
+        blocks.push(SourceBlock {
+            file: None,
+            line: None,
+            source: None,
+            instructions: bare,
+            fullname: None,
+        });
+    }
+    blocks
+}
+
+/// Address ending an instruction without a symbol, e.g. the target of
+/// `call 0x401126`
+fn operand_address(inst: &str) -> Option<u64> {
+    if inst.ends_with('>') {
+        return None;
+    }
+    let operand = inst.rsplit([' ', ',']).next()?;
+    parse_number(operand).filter(|_| operand.starts_with("0x"))
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json::{Value, json};
+
+    use super::*;
+
+    fn items(insns: Value) -> Vec<DisassembledItem> {
+        serde_json::from_value(insns).unwrap()
+    }
+
+    #[test]
+    fn test_parse_asm_insns() {
+        let insns = json!([
+            {"line": "3", "file": "a.c", "fullname": "/tmp/a.c", "line_asm_insn": [
+                {"address": "0x1000", "func-name": "main", "offset": "0", "opcodes": "55",
+                 "inst": "push %rbp"},
+                {"address": "0x1001", "func-name": "main", "offset": "1", "opcodes": "48 89 e5",
+                 "inst": "mov %rsp,%rbp"}
+            ]},
+            {"line": "4", "file": "a.c", "fullname": "/tmp/a.c", "line_asm_insn": []}
+        ]);
+        let blocks = parse_asm_insns(items(insns), 0x1001, str::to_string);
+        assert_eq!(blocks.len(), 1);
+        assert_eq!(blocks[0].line, Some(3));
+        assert_eq!(blocks[0].instructions.len(), 2);
+        assert!(!blocks[0].instructions[0].is_pc);
+        assert!(blocks[0].instructions[1].is_pc);
+        assert_eq!(blocks[0].instructions[1].offset, Some(1));
+        assert_eq!(blocks[0].fullname.as_deref(), Some("/tmp/a.c"));
+
+        let insns = json!([{"address": "0x2000", "inst": "nop"}]);
+        let blocks = parse_asm_insns(items(insns), 0x2000, str::to_string);
+        assert_eq!(blocks.len(), 1);
+        assert!(blocks[0].line.is_none());
+        assert!(blocks[0].instructions[0].is_pc);
+    }
+
+    #[test]
+    fn test_location() {
+        let insns = json!([
+            {"line": "3", "file": "a.c", "fullname": "/tmp/a.c", "line_asm_insn": [
+                {"address": "0x1000", "inst": "push %rbp"},
+                {"address": "0x1001", "inst": "mov %rsp,%rbp"}
+            ]},
+            {"line": "4", "file": "a.c", "fullname": "/tmp/a.c", "line_asm_insn": [
+                {"address": "0x1004", "inst": "mov $0x0,%eax"},
+                {"address": "0x1009", "inst": "pop %rbp"},
+                {"address": "0x100a", "inst": "ret"}
+            ]}
+        ]);
+        let disassembly = Disassembly {
+            function: Some("main".to_string()),
+            pc: Address64::from("0x1004".to_string()),
+            blocks: parse_asm_insns(items(insns), 0x1004, str::to_string),
+        };
+        let location = location(disassembly, 1);
+        assert_eq!(location.line, Some(4));
+        assert_eq!(location.file.as_deref(), Some("a.c"));
+        let addresses = location
+            .instructions
+            .iter()
+            .map(|instruction| instruction.address.0)
+            .collect::<Vec<_>>();
+        assert_eq!(addresses, vec![0x1001, 0x1004, 0x1009]);
+    }
+}
@@ -0,0 +1,192 @@
+//! Discovery of the GDB binaries installed on the host
+
+use std::collections::HashSet;
+use std::fs::File;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+use serde::Serialize;
+
+use crate::models::Endian;
+
+/// A GDB binary found in `PATH`
+#[derive(Debug, Clone, Serialize)]
+pub struct Debugger {
+    /// File name of the binary
+    pub name: String,
+    /// Full path of the binary
+    pub path: PathBuf,
+    /// Target triple prefix for cross debuggers, e.g. `arm-none-eabi`
+    pub target: Option<String>,
+    /// Whether the binary supports multiple architectures
+    pub multiarch: bool,
+}
+
+impl Debugger {
+    fn from_path(path: PathBuf) -> Option<Self> {
+        let name = path.file_name()?.to_str()?.to_string();
+        let target = match name.as_str() {
+            "gdb" | "gdb-multiarch" => None,
+            _ => Some(name.strip_suffix("-gdb")?.to_string()),
+        };
+        Some(Debugger { multiarch: name == "gdb-multiarch", name, path, target })
+    }
+
+    /// Whether the debugger is built for the `arch` of the target
+    fn supports(&self, arch: &str) -> bool {
+        match &self.target {
+            Some(target) => {
+                let target_arch = normalize_arch(target.split('-').next().unwrap_or(""));
+                target_arch == arch || (target_arch == "riscv" && arch.starts_with("riscv"))
+            }
+            None => self.multiarch || is_host_compatible(arch),
+        }
+    }
+}
+
+/// Find all the GDB binaries in `PATH`, a name shadowed by an earlier `PATH`
+/// entry is skipped like the shell would do
+pub fn find_debuggers() -> Vec<Debugger> {
+    let Some(paths) = std::env::var_os("PATH") else {
+        return vec![];
+    };
+    let mut seen = HashSet::new();
+    let mut debuggers = vec![];
+    for dir in std::env::split_paths(&paths) {
+        let Ok(entries) = std::fs::read_dir(&dir) else {
+            continue;
+        };
+        let mut found: Vec<Debugger> = entries
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.path())
+            .filter(|path| is_executable(path))
+            .filter_map(Debugger::from_path)
+            .filter(|debugger| seen.insert(debugger.name.clone()))
+            .collect();
+        found.sort_by(|a, b| a.name.cmp(&b.name));
+        debuggers.append(&mut found);
+    }
+    debuggers
+}
+
+/// Pick the debugger best suited for `program` based on the machine type in
+/// its ELF header: the host gdb for native programs, otherwise a cross gdb for
+/// that architecture, falling back to gdb-multiarch
+pub fn select_for_program(program: &Path) -> Option<PathBuf> {
+    let arch = elf_arch(program)?;
+    let debuggers = find_debuggers();
+    let native = debuggers.iter().find(|d| d.name == "gdb" && is_host_compatible(&arch));
+    let cross = debuggers.iter().find(|d| d.target.is_some() && d.supports(&arch));
+    let multiarch = debuggers.iter().find(|d| d.multiarch);
+    native.or(cross).or(multiarch).map(|d| d.path.clone())
+}
+
+/// Read the architecture of an ELF file, using the names of
+/// `std::env::consts::ARCH`
+pub fn elf_arch(path: &Path) -> Option<String> {
+    let header = elf_header(path)?;
+    let is_64 = header[4] == 2;
+    let machine = match header[5] {
+        2 => u16::from_be_bytes([header[18], header[19]]),
+        _ => u16::from_le_bytes([header[18], header[19]]),
+    };
+    let arch = match machine {
+        3 => "x86",
+        62 => "x86_64",
+        40 => "arm",
+        183 => "aarch64",
+        243 if is_64 => "riscv64",
+        243 => "riscv32",
+        8 if is_64 => "mips64",
+        8 => "mips",
+        20 => "powerpc",
+        21 => "powerpc64",
+        22 => "s390x",
+        83 => "avr",
+        94 => "xtensa",
+        _ => return None,
+    };
+    Some(arch.to_string())
+}
+
+/// Read the pointer width in bits and the byte order of an ELF file
+pub fn elf_layout(path: &Path) -> Option<(u32, Endian)> {
+    let header = elf_header(path)?;
+    let pointer_bits = match header[4] {
+        1 => 32,
+        2 => 64,
+        _ => return None,
+    };
+    let endian = match header[5] {
+        1 => Endian::Little,
+        2 => Endian::Big,
+        _ => return None,
+    };
+    Some((pointer_bits, endian))
+}
+
+fn elf_header(path: &Path) -> Option<[u8; 20]> {
+    let mut header = [0u8; 20];
+    File::open(path).ok()?.read_exact(&mut header).ok()?;
+    (header[..4] == *b"\x7fELF").then_some(header)
+}
+
+/// Map the architecture part of a target triple to `std::env::consts::ARCH`
+/// naming
+fn normalize_arch(arch: &str) -> &str {
+    match arch {
+        "i386" | "i486" | "i586" | "i686" => "x86",
+        "armeb" => "arm",
+        "ppc" | "powerpcle" => "powerpc",
+        "ppc64" | "ppc64le" | "powerpc64le" => "powerpc64",
+        "mipsel" => "mips",
+        "mips64el" => "mips64",
+        a if a.starts_with("armv") || a.starts_with("thumb") => "arm",
+        a => a,
+    }
+}
+
+/// Whether the host gdb can debug programs of `arch`
+fn is_host_compatible(arch: &str) -> bool {
+    let host = std::env::consts::ARCH;
+    arch == host || (host == "x86_64" && arch == "x86")
+}
+
+#[cfg(unix)]
+fn is_executable(path: &Path) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+
+    std::fs::metadata(path).is_ok_and(|m| m.is_file() && m.permissions().mode() & 0o111 != 0)
+}
+
+#[cfg(windows)]
+fn is_executable(path: &Path) -> bool {
+    path.is_file()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn test_elf_arch_of_host_binary() {
+        let exe = std::env::current_exe().unwrap();
+        assert_eq!(elf_arch(&exe).as_deref(), Some(std::env::consts::ARCH));
+        let (pointer_bits, _) = elf_layout(&exe).unwrap();
+        assert_eq!(pointer_bits as usize, usize::BITS as usize);
+    }
+
+    #[test]
+    fn test_cross_debugger_supports() {
+        let debugger = Debugger::from_path(PathBuf::from("/usr/bin/arm-none-eabi-gdb")).unwrap();
+        assert_eq!(debugger.target.as_deref(), Some("arm-none-eabi"));
+        assert!(debugger.supports("arm"));
+        assert!(!debugger.supports("aarch64"));
+
+        let debugger =
+            Debugger::from_path(PathBuf::from("/opt/bin/riscv64-unknown-elf-gdb")).unwrap();
+        assert!(debugger.supports("riscv64"));
+        assert!(Debugger::from_path(PathBuf::from("/usr/bin/gdb-add-index")).is_none());
+    }
+}
@@ -0,0 +1,188 @@
+//! Sessions debugging a microcontroller through a GDB server such as OpenOCD
+//! or the SEGGER J-Link GDB server
+
+use std::path::PathBuf;
+use std::str::FromStr;
+
+use tokio::process::Command;
+
+use super::GDBManager;
+use super::remote::{address_port, spawn_server};
+use crate::error::{AppError, AppResult};
+use crate::mi::commands::MiCommand;
+
+/// A GDB server the session can launch
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum GdbServer {
+    OpenOcd,
+    JLink,
+}
+
+impl FromStr for GdbServer {
+    type Err = AppError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "openocd" => Ok(Self::OpenOcd),
+            "jlink" => Ok(Self::JLink),
+            _ => Err(AppError::InvalidArgument(format!(
+                "unknown GDB server {}, expecting openocd or jlink",
+                s
+            ))),
+        }
+    }
+}
+
+impl GdbServer {
+    fn default_path(&self) -> &'static str {
+        match self {
+            GdbServer::OpenOcd => "openocd",
+            GdbServer::JLink => "JLinkGDBServer",
+        }
+    }
+
+    fn default_port(&self) -> u16 {
+        match self {
+            GdbServer::OpenOcd => 3333,
+            GdbServer::JLink => 2331,
+        }
+    }
+
+    /// Monitor commands resetting and halting the target
+    fn reset_commands(&self) -> &'static [&'static str] {
+        match self {
+            GdbServer::OpenOcd => &["monitor reset halt"],
+            GdbServer::JLink => &["monitor reset", "monitor halt"],
+        }
+    }
+}
+
+/// How to reach the target of an embedded session
+#[derive(Debug, Clone, Default)]
+pub struct EmbeddedOptions {
+    /// GDB server to launch, None to connect to one already running
+    pub server: Option<GdbServer>,
+    /// Path to the GDB server executable, found in `PATH` by default
+    pub server_path: Option<PathBuf>,
+    /// OpenOCD configuration files, e.g. `interface/stlink.cfg`
+    pub config_files: Vec<String>,
+    /// J-Link device name, e.g. `STM32F407VG`
+    pub device: Option<String>,
+    /// J-Link target interface, SWD by default
+    pub interface: Option<String>,
+    /// `host:port` of the GDB server, the default port of the server on
+    /// localhost by default
+    pub address: Option<String>,
+    /// Reset and halt the target once connected
+    pub reset: bool,
+    /// Flash the program into the target
+    pub load: bool,
+}
+
+impl GDBManager {
+    /// Create a session connected to a microcontroller through a GDB server,
+    /// launching the server first if asked to. The server is killed along
+    /// with the session
+    pub async fn create_embedded_session(
+        &self,
+        program: Option<PathBuf>,
+        gdb_path: Option<PathBuf>,
+        options: EmbeddedOptions,
+    ) -> AppResult<String> {
+        let default_port = options.server.unwrap_or(GdbServer::OpenOcd).default_port();
+        let address =
+            options.address.clone().unwrap_or_else(|| format!("localhost:{}", default_port));
+        let port = address_port(&address).unwrap_or(default_port);
+
+        let server = match options.server {
+            Some(server) => Some(spawn_server(server_command(server, &options, port)?)?),
+            None => None,
+        };
+        let session_id = self
+            .create_remote_session(program, gdb_path, server, "extended-remote", &address)
+            .await?;
+
+        if let Err(e) = self.prepare_target(&session_id, &options).await {
+            self.abandon_session(&session_id).await;
+            return Err(e);
+        }
+        Ok(session_id)
+    }
+
+    /// Reset and flash the target as asked
+    async fn prepare_target(&self, session_id: &str, options: &EmbeddedOptions) -> AppResult<()> {
+        let server = options.server.unwrap_or(GdbServer::OpenOcd);
+        if options.reset {
+            self.send_monitor_commands(session_id, server.reset_commands()).await?;
+        }
+        if options.load {
+            self.send_command_with_timeout(session_id, &MiCommand::target_download()).await?;
+            if options.reset {
+                self.send_monitor_commands(session_id, server.reset_commands()).await?;
+            }
+        }
+        Ok(())
+    }
+
+    async fn send_monitor_commands(&self, session_id: &str, commands: &[&str]) -> AppResult<()> {
+        for command in commands {
+            self.send_command_with_timeout(session_id, &MiCommand::cli_exec(command)).await?;
+        }
+        Ok(())
+    }
+}
+
+fn server_command(server: GdbServer, options: &EmbeddedOptions, port: u16) -> AppResult<Command> {
+    let path = options.server_path.clone().unwrap_or_else(|| PathBuf::from(server.default_path()));
+    let mut command = Command::new(&path);
+    match server {
+        GdbServer::OpenOcd => {
+            for file in &options.config_files {
+                command.arg("-f").arg(file);
+            }
+            command.arg("-c").arg(format!("gdb_port {}", port));
+        }
+        GdbServer::JLink => {
+            let device = options.device.as_deref().ok_or_else(|| {
+                AppError::InvalidArgument("device is required to launch the J-Link server".into())
+            })?;
+            let interface = options.interface.as_deref().unwrap_or("SWD");
+            command.args(["-device", device, "-if", interface, "-port", &port.to_string()]);
+            // not -singlerun, probing the port would count as a GDB connection
+            command.arg("-nogui");
+        }
+    }
+    Ok(command)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn args(command: &Command) -> Vec<String> {
+        command.as_std().get_args().map(|arg| arg.to_string_lossy().into_owned()).collect()
+    }
+
+    #[test]
+    fn test_server_command() {
+        let options = EmbeddedOptions {
+            config_files: vec!["interface/stlink.cfg".into(), "target/stm32f4x.cfg".into()],
+            ..Default::default()
+        };
+        let command = server_command(GdbServer::OpenOcd, &options, 3334).unwrap();
+        assert_eq!(command.as_std().get_program(), "openocd");
+        assert_eq!(
+            args(&command),
+            ["-f", "interface/stlink.cfg", "-f", "target/stm32f4x.cfg", "-c", "gdb_port 3334"]
+        );
+
+        // J-Link needs the device
+        assert!(server_command(GdbServer::JLink, &options, 2331).is_err());
+        let options = EmbeddedOptions { device: Some("STM32F407VG".into()), ..options };
+        let command = server_command(GdbServer::JLink, &options, 2331).unwrap();
+        assert_eq!(
+            args(&command),
+            ["-device", "STM32F407VG", "-if", "SWD", "-port", "2331", "-nogui"]
+        );
+    }
+}
@@ -0,0 +1,146 @@
+//! Log of the out of band records received from GDB
+
+use std::collections::VecDeque;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::Serialize;
+use serde_json::Value;
+
+use super::GDBManager;
+use crate::error::{AppError, AppResult};
+use crate::mi::output::{AsyncKind, OutOfBandRecord, StreamKind};
+use crate::mi::queue::QueueStats;
+
+/// Number of records kept per session
+const CAPACITY: usize = 5000;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SessionEvent {
+    /// Position of the event in the log
+    pub cursor: u64,
+    /// Milliseconds since the Unix epoch when the record was received
+    pub timestamp: u64,
+    #[serde(flatten)]
+    pub record: EventRecord,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum EventRecord {
+    /// e.g. `=library-loaded` or `*stopped`
+    Async { kind: AsyncKind, class: String, results: Value },
+    /// Text printed on the console, by the program or in the log
+    Stream { stream: StreamKind, text: String },
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SessionEvents {
+    /// Pass it as `since` to only get the events after these
+    pub cursor: u64,
+    /// Some events after the requested cursor were already dropped
+    pub truncated: bool,
+    pub events: Vec<SessionEvent>,
+}
+
+/// Ring buffer of the out of band records of a session
+#[derive(Debug, Default)]
+pub struct EventLog {
+    events: VecDeque<SessionEvent>,
+    next_cursor: u64,
+}
+
+impl EventLog {
+    pub fn push(&mut self, record: &OutOfBandRecord) {
+        let record = match record.clone() {
+            OutOfBandRecord::AsyncRecord { kind, class, results, .. } => {
+                EventRecord::Async { kind, class: class.as_str().to_string(), results }
+            }
+            OutOfBandRecord::StreamRecord { kind, data } => {
+                EventRecord::Stream { stream: kind, text: data }
+            }
+        };
+        if self.events.len() == CAPACITY {
+            self.events.pop_front();
+        }
+        let timestamp = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_millis() as u64;
+        self.events.push_back(SessionEvent { cursor: self.next_cursor, timestamp, record });
+        self.next_cursor += 1;
+    }
+
+    /// Events with a cursor not less than `cursor`, all logged events if None
+    pub fn since(&self, cursor: Option<u64>) -> SessionEvents {
+        let cursor = cursor.unwrap_or(0);
+        let oldest = self.events.front().map_or(self.next_cursor, |event| event.cursor);
+        SessionEvents {
+            cursor: self.next_cursor,
+            truncated: cursor < oldest,
+            events: self.events.iter().filter(|event| event.cursor >= cursor).cloned().collect(),
+        }
+    }
+}
+
+impl GDBManager {
+    /// The async and stream records received in a session since a cursor
+    pub async fn get_session_events(
+        &self,
+        session_id: &str,
+        since: Option<u64>,
+    ) -> AppResult<SessionEvents> {
+        let sessions = self.sessions.lock().await;
+        let handle = sessions
+            .get(session_id)
+            .ok_or_else(|| AppError::NotFound(format!("Session {} does not exist", session_id)))?;
+        Ok(handle.events.lock().unwrap().since(since))
+    }
+
+    /// How many out of band records of a session wait to be handled and how
+    /// many stream records were dropped because GDB output them too fast
+    pub async fn get_output_stats(&self, session_id: &str) -> AppResult<QueueStats> {
+        let sessions = self.sessions.lock().await;
+        let handle = sessions
+            .get(session_id)
+            .ok_or_else(|| AppError::NotFound(format!("Session {} does not exist", session_id)))?;
+        Ok(handle.output.stats())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::mi::output::AsyncClass;
+
+    #[test]
+    fn test_event_log() {
+        let mut log = EventLog::default();
+        log.push(&OutOfBandRecord::AsyncRecord {
+            token: None,
+            kind: AsyncKind::Notify,
+            class: AsyncClass::LibraryLoaded,
+            results: serde_json::json!({"id": "/lib/libc.so.6"}),
+        });
+        for i in 0..CAPACITY {
+            log.push(&OutOfBandRecord::StreamRecord {
+                kind: StreamKind::Target,
+                data: format!("line {}\n", i),
+            });
+        }
+
+        let events = log.since(Some(0));
+        assert!(events.truncated);
+        assert_eq!(events.events[0].cursor, 1);
+        assert_eq!(events.cursor, CAPACITY as u64 + 1);
+
+        let mut log = EventLog::default();
+        log.push(&OutOfBandRecord::AsyncRecord {
+            token: None,
+            kind: AsyncKind::Notify,
+            class: AsyncClass::LibraryLoaded,
+            results: serde_json::json!({"id": "/lib/libc.so.6"}),
+        });
+        let event = serde_json::to_value(&log.since(None).events[0]).unwrap();
+        assert_eq!(event["type"], "async");
+        assert_eq!(event["kind"], "notify");
+        assert_eq!(event["class"], "library-loaded");
+        assert!(log.since(Some(1)).events.is_empty());
+    }
+}
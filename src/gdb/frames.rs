@@ -0,0 +1,329 @@
+use std::collections::BTreeMap;
+
+use serde::Serialize;
+use serde_with::skip_serializing_none;
+
+use super::{GDBManager, demangle};
+use crate::error::{AppError, AppResult};
+use crate::mi::commands::MiCommand;
+use crate::models::{Address, Address64, FrameArguments, PrintValue, StackFrame, Variable};
+
+/// Frames listed when the client does not limit them, a runaway recursion
+/// can have far more than GDB lists in time
+pub const DEFAULT_MAX_FRAMES: usize = 256;
+
+/// What is usually inspected of a frame, read at once
+#[derive(Debug, Clone, Serialize)]
+pub struct FrameContext {
+    pub frame: StackFrame,
+    /// Arguments the function was called with
+    pub arguments: Vec<Variable>,
+    pub locals: Vec<Variable>,
+}
+
+/// Where a frame lies on the stack, from `info frame`, to look into a stack
+/// the backtrace can't be trusted of
+#[skip_serializing_none]
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct FrameDetails {
+    pub frame: Option<StackFrame>,
+    /// Canonical frame address, the stack pointer of the caller before the
+    /// call
+    pub cfa: Option<Address64>,
+    /// Register of the PC, e.g. rip or pc
+    pub pc_register: Option<String>,
+    pub pc: Option<Address64>,
+    /// Where the function returns to
+    pub saved_pc: Option<Address64>,
+    /// CFA of the frame of the caller, not set for the outermost frame
+    pub caller_frame: Option<Address64>,
+    /// CFA of the frame called, not set for frame 0
+    pub callee_frame: Option<Address64>,
+    pub arglist: Option<Address64>,
+    pub locals: Option<Address64>,
+    /// Stack pointer of the caller
+    pub caller_sp: Option<Address64>,
+    /// Where the registers of the caller are saved, by register
+    pub saved_registers: BTreeMap<String, Address64>,
+}
+
+/// The innermost frames of the stack
+#[skip_serializing_none]
+#[derive(Debug, Clone, Serialize)]
+pub struct Backtrace {
+    pub frames: Vec<StackFrame>,
+    /// Set when the outermost frames are left out, e.g. `truncated at 256
+    /// frames`
+    pub truncated: Option<String>,
+}
+
+impl GDBManager {
+    /// List at most `max_frames` frames, counting one more first so a deep
+    /// stack is neither listed nor counted in full
+    pub async fn get_backtrace(
+        &self,
+        session_id: &str,
+        max_frames: Option<usize>,
+    ) -> AppResult<Backtrace> {
+        let max_frames = max_frames.unwrap_or(DEFAULT_MAX_FRAMES);
+        if max_frames == 0 {
+            return Err(AppError::InvalidArgument("max_frames must be at least 1".to_string()));
+        }
+        let depth = self.stack_depth(session_id, Some(max_frames + 1)).await?;
+        if depth <= max_frames {
+            let frames = self.list_stack_frames(session_id, None, None).await?;
+            return Ok(Backtrace { frames, truncated: None });
+        }
+        let frames = self.list_stack_frames(session_id, Some(0), Some(max_frames - 1)).await?;
+        let truncated = Some(format!("truncated at {} frames", frames.len()));
+        Ok(Backtrace { frames, truncated })
+    }
+
+    /// Arguments of the frames from `low_frame`, 0 by default, to
+    /// `high_frame`, at most `DEFAULT_MAX_FRAMES` frames by default
+    pub async fn get_function_arguments(
+        &self,
+        session_id: &str,
+        low_frame: Option<usize>,
+        high_frame: Option<usize>,
+        print_values: Option<PrintValue>,
+    ) -> AppResult<Vec<FrameArguments>> {
+        let low_frame = low_frame.unwrap_or(0);
+        let high_frame = high_frame.unwrap_or(low_frame + DEFAULT_MAX_FRAMES - 1);
+        if high_frame < low_frame {
+            return Err(AppError::InvalidArgument(
+                "high_frame must not be below low_frame".to_string(),
+            ));
+        }
+        let command = MiCommand::stack_list_arguments(print_values, low_frame, high_frame);
+        let response = self.send_command_with_timeout(session_id, &command).await?;
+        Ok(serde_json::from_value(
+            response
+                .results
+                .get("stack-args")
+                .ok_or(AppError::NotFound("stack-args not found".to_string()))?
+                .to_owned(),
+        )?)
+    }
+
+    /// The frame at level `frame_id`, 0 by default, with its arguments and
+    /// local variables
+    pub async fn get_frame_context(
+        &self,
+        session_id: &str,
+        frame_id: Option<usize>,
+        print_values: Option<PrintValue>,
+    ) -> AppResult<FrameContext> {
+        let level = frame_id.unwrap_or(0);
+        let frame = self
+            .list_stack_frames(session_id, Some(level), Some(level))
+            .await?
+            .pop()
+            .ok_or_else(|| AppError::NotFound(format!("frame {} not found", level)))?;
+        // the arguments are listed along the locals, flagged
+        let (arguments, locals) = self
+            .get_local_variables(session_id, Some(level), print_values)
+            .await?
+            .into_iter()
+            .partition(|variable| variable.arg.is_some());
+        Ok(FrameContext { frame, arguments, locals })
+    }
+
+    /// Addresses of the frame at level `frame_id`, the selected frame by
+    /// default
+    pub async fn get_frame_details(
+        &self,
+        session_id: &str,
+        frame_id: Option<usize>,
+    ) -> AppResult<FrameDetails> {
+        let command = MiCommand::stack_info_frame(frame_id.map(|level| level as u64));
+        let response = self.send_command_with_timeout(session_id, &command).await?;
+        let mut frame: Option<StackFrame> = response
+            .results
+            .get("frame")
+            .map(|frame| serde_json::from_value(frame.to_owned()))
+            .transpose()?;
+        if let Some(frame) = frame.as_mut().filter(|_| self.config.demangle) {
+            frame.function = demangle::demangle_or_keep(&frame.function);
+        }
+
+        let command = match frame_id {
+            Some(level) => format!("info frame {}", level),
+            None => "info frame".to_string(),
+        };
+        let output = self.capture_console(session_id, &command).await?;
+        Ok(FrameDetails { frame, ..parse_info_frame(&output) })
+    }
+
+    /// Number of frames on the stack of the selected thread, counted up to
+    /// `max_depth` since GDB unwinds the whole stack otherwise
+    pub async fn stack_depth(
+        &self,
+        session_id: &str,
+        max_depth: Option<usize>,
+    ) -> AppResult<usize> {
+        let command = MiCommand::stack_info_depth(max_depth);
+        let response = self.send_command_with_timeout(session_id, &command).await?;
+        response
+            .results
+            .get("depth")
+            .and_then(|depth| depth.as_str())
+            .and_then(|depth| depth.parse().ok())
+            .ok_or(AppError::NotFound("depth not found".to_string()))
+    }
+}
+
+/// Parse the output of `info frame`:
+///
+/// ```text
+/// Stack level 1, frame at 0x7fffffffe0f0:
+///  rip = 0x555555555160 in main (src/main.rs:18); saved rip = 0x7ffff7c29d90
+///  called by frame at 0x7fffffffe1b0, caller of frame at 0x7fffffffe0c0
+///  source language rust.
+///  Arglist at 0x7fffffffe0e0, args:
+///  Locals at 0x7fffffffe0e0, Previous frame's sp is 0x7fffffffe0f0
+///  Saved registers:
+///   rbp at 0x7fffffffe0e0, rip at 0x7fffffffe0e8
+/// ```
+fn parse_info_frame(output: &str) -> FrameDetails {
+    let mut details = FrameDetails::default();
+    let mut saved_registers = false;
+    for line in output.lines().map(str::trim) {
+        if saved_registers {
+            for saved in line.split(',') {
+                let saved = saved.trim().split_once(" at ");
+                if let Some((register, at)) = saved.and_then(|(r, at)| Some((r, address(at)?))) {
+                    details.saved_registers.insert(register.to_string(), at);
+                }
+            }
+        } else if let Some(rest) = line.strip_prefix("Stack level ") {
+            details.cfa = rest.split_once("frame at ").and_then(|(_, cfa)| address(cfa));
+        } else if let Some(rest) = line.strip_prefix("Arglist at ") {
+            details.arglist = address(rest);
+        } else if let Some(rest) = line.strip_prefix("Locals at ") {
+            details.locals = address(rest);
+            details.caller_sp =
+                rest.split_once("Previous frame's sp is ").and_then(|(_, sp)| address(sp));
+        } else if line.starts_with("Saved registers:") {
+            saved_registers = true;
+        } else if line.contains("frame at ") {
+            for part in line.split(", ") {
+                if let Some(at) = part.strip_prefix("called by frame at ") {
+                    details.caller_frame = address(at);
+                } else if let Some(at) = part.strip_prefix("caller of frame at ") {
+                    details.callee_frame = address(at);
+                }
+            }
+        } else if let Some((pc, saved_pc)) = line.split_once("; saved ") {
+            if let Some((register, value)) = pc.split_once(" = ") {
+                details.pc_register = Some(register.to_string());
+                details.pc = address(value);
+            }
+            details.saved_pc = saved_pc.split_once(" = ").and_then(|(_, value)| address(value));
+        }
+    }
+    details
+}
+
+/// The address at the start of `text`, e.g. `0x7fffffffe0f0:`
+fn address(text: &str) -> Option<Address64> {
+    let hex = text.trim_start().strip_prefix("0x")?;
+    let end = hex.find(|c: char| !c.is_ascii_hexdigit()).unwrap_or(hex.len());
+    u64::from_str_radix(&hex[..end], 16).ok().map(Address)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const SCRIPT: &str = r#"
+        -> -stack-info-depth 3
+        <- ^done,depth="3"
+        -> -stack-list-frames 0 1
+        <- ^done,stack=[frame={level="0",addr="0x1000",func="recurse"},frame={level="1",addr="0x1010",func="recurse"}]
+        -> -stack-info-depth 257
+        <- ^done,depth="1"
+        -> -stack-list-frames
+        <- ^done,stack=[frame={level="0",addr="0x2000",func="main"}]
+    "#;
+
+    #[tokio::test]
+    async fn test_backtrace() {
+        let manager = GDBManager::default();
+        let session_id = manager.create_mock_session(SCRIPT).await.unwrap();
+
+        let backtrace = manager.get_backtrace(&session_id, Some(2)).await.unwrap();
+        assert_eq!(backtrace.frames.len(), 2);
+        assert_eq!(backtrace.truncated.as_deref(), Some("truncated at 2 frames"));
+
+        let backtrace = manager.get_backtrace(&session_id, None).await.unwrap();
+        assert_eq!(backtrace.frames[0].function, "main");
+        assert!(backtrace.truncated.is_none());
+
+        assert!(matches!(
+            manager.get_backtrace(&session_id, Some(0)).await,
+            Err(AppError::InvalidArgument(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_frame_context() {
+        let manager = GDBManager::default();
+        let session_id = manager
+            .create_mock_session(
+                r#"
+                -> -stack-list-frames 1 1
+                <- ^done,stack=[frame={level="1",addr="0x1010",func="compute",file="test_app.rs",line="13"}]
+                -> -stack-list-variables --frame 1 --simple-values
+                <- ^done,variables=[{name="x",arg="1",type="int",value="10"},{name="y",type="int",value="20"}]
+                -> -stack-list-arguments --no-values 0 255
+                <- ^done,stack-args=[frame={level="0",args=[name="argc",name="argv"]},frame={level="1",args=[]}]
+                "#,
+            )
+            .await
+            .unwrap();
+
+        let context = manager.get_frame_context(&session_id, Some(1), None).await.unwrap();
+        assert_eq!(context.frame.function, "compute");
+        assert_eq!(context.arguments[0].value.as_deref(), Some("10"));
+        assert_eq!(context.locals[0].name, "y");
+
+        let arguments = manager
+            .get_function_arguments(&session_id, None, None, Some(PrintValue::NoValues))
+            .await
+            .unwrap();
+        assert_eq!(
+            arguments[0].args.iter().map(|arg| arg.name.as_str()).collect::<Vec<_>>(),
+            ["argc", "argv"]
+        );
+        assert!(arguments[1].args.is_empty());
+        assert!(matches!(
+            manager.get_function_arguments(&session_id, Some(2), Some(1), None).await,
+            Err(AppError::InvalidArgument(_))
+        ));
+    }
+
+    #[test]
+    fn test_parse_info_frame() {
+        let details = parse_info_frame(
+            "Stack level 1, frame at 0x7fffffffe0f0:\n \
+             rip = 0x555555555160 in main (src/main.rs:18); saved rip = 0x7ffff7c29d90\n \
+             called by frame at 0x7fffffffe1b0, caller of frame at 0x7fffffffe0c0\n \
+             source language rust.\n \
+             Arglist at 0x7fffffffe0e0, args: \n \
+             Locals at 0x7fffffffe0e0, Previous frame's sp is 0x7fffffffe0f0\n \
+             Saved registers:\n  \
+             rbp at 0x7fffffffe0e0, rip at 0x7fffffffe0e8\n",
+        );
+        assert_eq!(details.cfa, Some(Address(0x7fffffffe0f0)));
+        assert_eq!(details.pc_register.as_deref(), Some("rip"));
+        assert_eq!(details.pc, Some(Address(0x555555555160)));
+        assert_eq!(details.saved_pc, Some(Address(0x7ffff7c29d90)));
+        assert_eq!(details.caller_frame, Some(Address(0x7fffffffe1b0)));
+        assert_eq!(details.callee_frame, Some(Address(0x7fffffffe0c0)));
+        assert_eq!(details.locals, Some(Address(0x7fffffffe0e0)));
+        assert_eq!(details.caller_sp, Some(Address(0x7fffffffe0f0)));
+        assert_eq!(details.saved_registers["rip"], Address(0x7fffffffe0e8));
+        assert_eq!(details.saved_registers.len(), 2);
+    }
+}
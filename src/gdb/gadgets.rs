@@ -0,0 +1,256 @@
+//! ROP gadgets in the executable memory of the debugged process
+
+use std::collections::HashSet;
+
+use iced_x86::{Decoder, DecoderOptions, FlowControl, Formatter, Instruction, IntelFormatter};
+use regex::Regex;
+use serde::Serialize;
+
+use super::GDBManager;
+use super::memory::parse_number;
+use crate::error::{AppError, AppResult};
+use crate::resources::decode_hex;
+
+/// Bytes read from the target at once
+const CHUNK_BYTES: usize = 1024 * 1024;
+/// Largest mapping scanned, the rest is ignored
+const MAX_MAPPING_BYTES: usize = 64 * 1024 * 1024;
+/// Bytes before the last instruction where a gadget may start
+const MAX_GADGET_BYTES: usize = 24;
+/// Longest x86 instruction
+const MAX_INSTRUCTION_BYTES: usize = 15;
+
+/// Gadgets found in the executable mappings
+#[derive(Debug, Clone, Serialize)]
+pub struct FoundGadgets {
+    pub gadgets: Vec<Gadget>,
+    /// More gadgets were found than returned
+    pub truncated: bool,
+}
+
+/// Gadgets collected while scanning, up to a limit
+struct GadgetSet {
+    filter: Option<Regex>,
+    limit: usize,
+    seen: HashSet<String>,
+    found: FoundGadgets,
+}
+
+impl GadgetSet {
+    /// Keep a gadget if it matches and was not seen, returns whether to go on
+    /// scanning
+    fn add(&mut self, address: u64, instructions: String) -> bool {
+        if self.filter.as_ref().is_some_and(|filter| !filter.is_match(&instructions)) {
+            return true;
+        }
+        if !self.seen.insert(instructions.clone()) {
+            return true;
+        }
+        if self.found.gadgets.len() == self.limit {
+            self.found.truncated = true;
+            return false;
+        }
+        self.found.gadgets.push(Gadget { address: format!("0x{:x}", address), instructions });
+        true
+    }
+}
+
+/// A sequence of instructions ending with a return or an indirect jump or
+/// call, the first address it is found at
+#[derive(Debug, Clone, Serialize)]
+pub struct Gadget {
+    pub address: String,
+    /// Instructions in Intel syntax separated by ` ; `
+    pub instructions: String,
+}
+
+impl GDBManager {
+    /// Find the gadgets of at most `max_instructions` instructions in the
+    /// executable mappings, those of the objects whose path contains `object`
+    /// if given, and whose instructions match `filter` if given. x86 only
+    pub async fn find_gadgets(
+        &self,
+        session_id: &str,
+        filter: Option<&str>,
+        object: Option<&str>,
+        max_instructions: usize,
+        limit: usize,
+    ) -> AppResult<FoundGadgets> {
+        let arch = self.get_session(session_id).await?.arch;
+        if arch.name.as_deref().is_some_and(|name| !name.contains("i386") && !name.contains("x86"))
+        {
+            return Err(AppError::InvalidArgument(format!(
+                "gadgets can only be found on x86, not on {}",
+                arch.name.unwrap_or_default()
+            )));
+        }
+        let bitness = if arch.pointer_bits == Some(32) { 32 } else { 64 };
+        let filter = filter
+            .map(|filter| {
+                Regex::new(filter).map_err(|e| {
+                    AppError::InvalidArgument(format!("invalid regex {}: {}", filter, e))
+                })
+            })
+            .transpose()?;
+
+        let mappings = self.memory_mappings(session_id).await?;
+        if mappings.is_empty() {
+            return Err(AppError::NotFound(
+                "No memory mappings, the process must be started".to_string(),
+            ));
+        }
+
+        let mut set = GadgetSet {
+            filter,
+            limit,
+            seen: HashSet::new(),
+            found: FoundGadgets { gadgets: vec![], truncated: false },
+        };
+        for mapping in mappings.iter().filter(|mapping| mapping.is_exec()) {
+            if object.is_some_and(|object| {
+                !mapping.path.as_ref().is_some_and(|path| path.to_string_lossy().contains(object))
+            }) {
+                continue;
+            }
+            let code =
+                self.read_mapping(session_id, mapping.start_address, mapping.size as usize).await?;
+            let start = mapping.start_address;
+            // decoding megabytes of code takes a while
+            set = tokio::task::spawn_blocking(move || {
+                scan_gadgets(&code, start, bitness, max_instructions, |address, instructions| {
+                    set.add(address, instructions)
+                });
+                set
+            })
+            .await?;
+            if set.found.truncated {
+                break;
+            }
+        }
+        Ok(set.found)
+    }
+
+    /// The readable bytes at the beginning of a mapping
+    async fn read_mapping(&self, session_id: &str, start: u64, size: usize) -> AppResult<Vec<u8>> {
+        let size = size.min(MAX_MAPPING_BYTES);
+        let mut code = Vec::with_capacity(size);
+        while code.len() < size {
+            let address = start + code.len() as u64;
+            let count = CHUNK_BYTES.min(size - code.len());
+            let memory =
+                self.read_memory(session_id, None, format!("0x{:x}", address), count).await?;
+            let bytes = match memory.first() {
+                Some(block) if parse_number(&block.begin) == Some(address) => {
+                    decode_hex(&block.contents)?
+                }
+                _ => break,
+            };
+            let complete = bytes.len() == count;
+            code.extend(bytes);
+            if !complete {
+                break;
+            }
+        }
+        Ok(code)
+    }
+}
+
+/// A return, or a jump or a call through a register or memory
+fn is_gadget_end(instruction: &Instruction) -> bool {
+    matches!(
+        instruction.flow_control(),
+        FlowControl::Return | FlowControl::IndirectBranch | FlowControl::IndirectCall
+    )
+}
+
+/// Pass the gadgets of `code` loaded at `address` to `found` with their
+/// address, by address, until it returns false
+fn scan_gadgets(
+    code: &[u8],
+    address: u64,
+    bitness: u32,
+    max_instructions: usize,
+    mut found: impl FnMut(u64, String) -> bool,
+) {
+    let decode = |start: usize, end: usize| {
+        let end = end.min(code.len());
+        Decoder::with_ip(bitness, &code[start..end], address + start as u64, DecoderOptions::NONE)
+    };
+    let mut formatter = IntelFormatter::new();
+    for end in 0..code.len() {
+        let last = decode(end, end + MAX_INSTRUCTION_BYTES).decode();
+        if last.is_invalid() || !is_gadget_end(&last) {
+            continue;
+        }
+        let stop = end + last.len();
+        // the longest gadgets first
+        for start in end.saturating_sub(MAX_GADGET_BYTES)..=end {
+            let instructions = decode(start, stop).into_iter().collect::<Vec<_>>();
+            let valid = instructions.len() <= max_instructions
+                && instructions.last().is_some_and(|last| last.ip() == address + end as u64)
+                && instructions.iter().all(|instruction| !instruction.is_invalid())
+                && instructions[..instructions.len() - 1]
+                    .iter()
+                    .all(|instruction| instruction.flow_control() == FlowControl::Next);
+            if !valid {
+                continue;
+            }
+            let text = instructions
+                .iter()
+                .map(|instruction| {
+                    let mut text = String::new();
+                    formatter.format(instruction, &mut text);
+                    text
+                })
+                .collect::<Vec<_>>()
+                .join(" ; ");
+            if !found(address + start as u64, text) {
+                return;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_scan_gadgets() {
+        // nop; pop rdi; ret; jmp rax
+        let code = [0x90, 0x5f, 0xc3, 0xff, 0xe0];
+        let scan = |max_instructions| {
+            let mut gadgets = vec![];
+            scan_gadgets(&code, 0x1000, 64, max_instructions, |address, text| {
+                gadgets.push((address, text));
+                true
+            });
+            gadgets
+        };
+        let gadgets = scan(5);
+        let text = |address| {
+            gadgets.iter().find(|(found, _)| *found == address).map(|(_, text)| text.as_str())
+        };
+        assert_eq!(text(0x1000), Some("nop ; pop rdi ; ret"));
+        assert_eq!(text(0x1001), Some("pop rdi ; ret"));
+        assert_eq!(text(0x1002), Some("ret"));
+        assert_eq!(text(0x1003), Some("jmp rax"));
+        assert_eq!(scan(1).len(), 2);
+
+        // the scan stops at the limit
+        let mut set = GadgetSet {
+            filter: Regex::new("ret").ok(),
+            limit: 1,
+            seen: HashSet::new(),
+            found: FoundGadgets { gadgets: vec![], truncated: false },
+        };
+        let mut scanned = 0;
+        scan_gadgets(&code, 0x1000, 64, 5, |address, text| {
+            scanned += 1;
+            set.add(address, text)
+        });
+        assert_eq!(set.found.gadgets.len(), 1);
+        assert!(set.found.truncated);
+        assert_eq!(scanned, 2);
+    }
+}
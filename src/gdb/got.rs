@@ -0,0 +1,288 @@
+//! The GOT slots of the objects loaded in the debugged process
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use goblin::elf::Elf;
+use goblin::elf::header::{EM_386, EM_AARCH64, EM_ARM, EM_X86_64, ET_EXEC};
+use goblin::elf::reloc::{R_386_GLOB_DAT, R_AARCH64_GLOB_DAT, R_ARM_GLOB_DAT, R_X86_64_GLOB_DAT};
+use serde::Serialize;
+use tracing::warn;
+
+use super::GDBManager;
+use crate::error::{AppError, AppResult};
+use crate::models::{Endian, MemoryMapping};
+
+/// The GOT slots of an object
+#[derive(Debug, Clone, Serialize)]
+pub struct GotObject {
+    pub path: PathBuf,
+    /// Address the object is loaded at, 0 for a non PIE executable
+    pub base: String,
+    pub entries: Vec<GotEntry>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct GotEntry {
+    /// Address of the slot
+    pub slot: String,
+    pub kind: SlotKind,
+    /// The symbol the slot is relocated to
+    pub symbol: String,
+    /// The value of the slot
+    pub value: String,
+    /// Object mapped at the value
+    pub target_object: Option<PathBuf>,
+    /// Symbol at the value according to GDB
+    pub target_symbol: Option<String>,
+    pub status: SlotStatus,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SlotKind {
+    /// A function called through the PLT, possibly bound lazily
+    Plt,
+    /// An address of a data or function symbol
+    Got,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SlotStatus {
+    /// Points to the code of a loaded object
+    Resolved,
+    /// Not bound yet, a PLT slot still pointing into its own object or a null
+    /// slot of an undefined weak symbol
+    Unresolved,
+    /// A PLT slot pointing outside the code of the loaded objects, e.g. into
+    /// the heap or the stack, as after a GOT overwrite
+    Suspicious,
+}
+
+/// The GOT slots of an ELF file loaded at `base`
+#[derive(Debug, Clone)]
+struct GotLayout {
+    path: PathBuf,
+    base: u64,
+    /// Size of a slot
+    word: usize,
+    endian: Endian,
+    slots: Vec<Slot>,
+}
+
+/// A GOT relocation read from the ELF file
+#[derive(Debug, Clone)]
+struct Slot {
+    offset: u64,
+    kind: SlotKind,
+    symbol: String,
+}
+
+impl GDBManager {
+    /// Read the GOT slots of the PLT and the GLOB_DAT relocations of the
+    /// executable and the libraries mapped in the process, those whose path
+    /// contains `object` if given. The ELF files are parsed from the disk, so
+    /// the process must run on this host
+    pub async fn dump_got(
+        &self,
+        session_id: &str,
+        object: Option<&str>,
+    ) -> AppResult<Vec<GotObject>> {
+        let mappings = self.memory_mappings(session_id).await?;
+        if mappings.is_empty() {
+            return Err(AppError::NotFound(
+                "No memory mappings, the process must be started and run on this host".to_string(),
+            ));
+        }
+
+        let loaded: Vec<_> = loaded_objects(&mappings)
+            .into_iter()
+            .filter(|(path, _)| object.is_none_or(|object| path.to_string_lossy().contains(object)))
+            .collect();
+        // the files can be large, read and parse them off the runtime
+        let layouts = tokio::task::spawn_blocking(move || {
+            loaded
+                .into_iter()
+                .filter_map(|(path, start)| got_layout(path, start))
+                .collect::<Vec<_>>()
+        })
+        .await?;
+
+        let mut objects = vec![];
+        for GotLayout { path, base, word, endian, slots } in layouts {
+            let (Some(first), Some(last)) = (
+                slots.iter().map(|slot| slot.offset).min(),
+                slots.iter().map(|slot| slot.offset).max(),
+            ) else {
+                continue;
+            };
+
+            let count = (last - first) as usize / word + 1;
+            let values = self.read_words(session_id, base + first, count, word, endian).await?;
+            let mut entries = vec![];
+            for slot in slots {
+                let Some(&value) = values.get((slot.offset - first) as usize / word) else {
+                    continue;
+                };
+                let target = mappings.iter().find(|mapping| mapping.contains(value));
+                let target_symbol = match value {
+                    0 => None,
+                    _ => self
+                        .evaluate_expression(session_id, &format!("(void *) 0x{:x}", value))
+                        .await
+                        .ok()
+                        .and_then(|value| parse_symbol(&value)),
+                };
+                entries.push(GotEntry {
+                    slot: format!("0x{:x}", base + slot.offset),
+                    kind: slot.kind,
+                    symbol: slot.symbol,
+                    value: format!("0x{:x}", value),
+                    target_object: target.and_then(|target| target.path.clone()),
+                    target_symbol,
+                    status: slot_status(slot.kind, value, target, &path),
+                });
+            }
+            objects.push(GotObject { path, base: format!("0x{:x}", base), entries });
+        }
+        Ok(objects)
+    }
+}
+
+/// The files mapped in the process with their lowest address, in the order
+/// they are mapped
+fn loaded_objects(mappings: &[MemoryMapping]) -> Vec<(PathBuf, u64)> {
+    let mut starts = BTreeMap::new();
+    let mut order = vec![];
+    for mapping in mappings {
+        // pseudo files like [heap] and [vdso]
+        let Some(path) = mapping.path.as_ref().filter(|path| path.is_absolute()) else {
+            continue;
+        };
+        let base = mapping.start_address.saturating_sub(mapping.offset);
+        let start = starts.entry(path.clone()).or_insert_with(|| {
+            order.push(path.clone());
+            base
+        });
+        *start = (*start).min(base);
+    }
+    order.into_iter().map(|path| (path.clone(), starts[&path])).collect()
+}
+
+/// Read the GOT slots of a file mapped from `start`, None if it cannot be
+/// parsed
+fn got_layout(path: PathBuf, start: u64) -> Option<GotLayout> {
+    let bytes = match std::fs::read(&path) {
+        Ok(bytes) => bytes,
+        Err(e) => {
+            warn!("Failed to read {}: {}", path.display(), e);
+            return None;
+        }
+    };
+    let elf = match Elf::parse(&bytes) {
+        Ok(elf) => elf,
+        Err(e) => {
+            warn!("Failed to parse {}: {}", path.display(), e);
+            return None;
+        }
+    };
+    Some(GotLayout {
+        base: if elf.header.e_type == ET_EXEC { 0 } else { start },
+        word: if elf.is_64 { 8 } else { 4 },
+        endian: if elf.little_endian { Endian::Little } else { Endian::Big },
+        slots: got_slots(&elf),
+        path,
+    })
+}
+
+/// The PLT relocations and the GLOB_DAT ones of an ELF file
+fn got_slots(elf: &Elf) -> Vec<Slot> {
+    let glob_dat = match elf.header.e_machine {
+        EM_X86_64 => Some(R_X86_64_GLOB_DAT),
+        EM_386 => Some(R_386_GLOB_DAT),
+        EM_AARCH64 => Some(R_AARCH64_GLOB_DAT),
+        EM_ARM => Some(R_ARM_GLOB_DAT),
+        _ => None,
+    };
+    let symbol = |index| {
+        elf.dynsyms
+            .get(index)
+            .and_then(|symbol| elf.dynstrtab.get_at(symbol.st_name))
+            .unwrap_or_default()
+            .to_string()
+    };
+    let plt = elf.pltrelocs.iter().map(|reloc| Slot {
+        offset: reloc.r_offset,
+        kind: SlotKind::Plt,
+        symbol: symbol(reloc.r_sym),
+    });
+    let got = elf
+        .dynrelas
+        .iter()
+        .chain(elf.dynrels.iter())
+        .filter(|reloc| reloc.r_sym != 0 && Some(reloc.r_type) == glob_dat)
+        .map(|reloc| Slot {
+            offset: reloc.r_offset,
+            kind: SlotKind::Got,
+            symbol: symbol(reloc.r_sym),
+        });
+    plt.chain(got).collect()
+}
+
+fn slot_status(
+    kind: SlotKind,
+    value: u64,
+    target: Option<&MemoryMapping>,
+    object: &Path,
+) -> SlotStatus {
+    if value == 0 {
+        return SlotStatus::Unresolved;
+    }
+    match kind {
+        // data symbols are not in code
+        SlotKind::Got => SlotStatus::Resolved,
+        SlotKind::Plt => match target {
+            Some(target) if target.path.as_deref() == Some(object) => SlotStatus::Unresolved,
+            Some(target) if target.is_exec() && target.path.is_some() => SlotStatus::Resolved,
+            _ => SlotStatus::Suspicious,
+        },
+    }
+}
+
+/// The symbol of a pointer printed by GDB, e.g. `(void *) 0x7ffff7e50e10
+/// <malloc>` or `<puts+4>`
+pub(super) fn parse_symbol(value: &str) -> Option<String> {
+    let start = value.find('<')? + 1;
+    let end = value.rfind('>')?;
+    value.get(start..end).map(str::to_string)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_slot_status() {
+        let mapping = |start, path: &str, permissions: &str| MemoryMapping {
+            start_address: start,
+            end_address: start + 0x1000,
+            size: 0x1000,
+            offset: 0,
+            permissions: Some(permissions.to_string()),
+            path: Some(PathBuf::from(path)),
+        };
+        let exe = Path::new("/tmp/a.out");
+        let plt = mapping(0x1000, "/tmp/a.out", "r-xp");
+        let libc = mapping(0x7000, "/usr/lib/libc.so.6", "r-xp");
+        let heap = mapping(0x9000, "[heap]", "rw-p");
+        assert_eq!(slot_status(SlotKind::Plt, 0x1010, Some(&plt), exe), SlotStatus::Unresolved);
+        assert_eq!(slot_status(SlotKind::Plt, 0x7010, Some(&libc), exe), SlotStatus::Resolved);
+        assert_eq!(slot_status(SlotKind::Plt, 0x9010, Some(&heap), exe), SlotStatus::Suspicious);
+        assert_eq!(slot_status(SlotKind::Plt, 0xdead, None, exe), SlotStatus::Suspicious);
+        assert_eq!(slot_status(SlotKind::Got, 0x9010, Some(&heap), exe), SlotStatus::Resolved);
+
+        assert_eq!(parse_symbol("(void *) 0x7010 <puts+4>").as_deref(), Some("puts+4"));
+        assert_eq!(parse_symbol("(void *) 0x9010"), None);
+    }
+}
@@ -0,0 +1,157 @@
+//! Hit counts of the breakpoints, from their insertion then as notified by
+//! GDB every time one is hit
+
+use std::collections::HashMap;
+use std::time::Duration;
+
+use serde::Serialize;
+use serde_json::Value;
+use tracing::warn;
+
+use super::{GDBManager, Resumed};
+use crate::error::{AppError, AppResult};
+use crate::mi::commands::{BreakPointNumber, MiCommand};
+use crate::mi::output::BreakPointEvent;
+
+/// Hits by breakpoint
+#[derive(Debug, Clone, Default)]
+pub struct HitCounts(HashMap<BreakPointNumber, u32>);
+
+/// Outcome of `run_to_hit_count`
+#[derive(Debug, Clone, Serialize)]
+pub struct HitCountReached {
+    /// The breakpoint was hit the given number of times, the program may stop
+    /// earlier at another breakpoint
+    pub reached: bool,
+    /// Times the breakpoint was hit so far
+    pub hits: u32,
+    /// Results of the last `*stopped` record
+    pub stopped: Value,
+}
+
+impl HitCounts {
+    /// Update the counts with a breakpoint notification
+    pub fn apply(&mut self, event: BreakPointEvent, results: &Value) {
+        match event {
+            BreakPointEvent::Created | BreakPointEvent::Modified => {
+                let bkpt = &results["bkpt"];
+                let number = bkpt["number"].as_str().and_then(|n| n.parse().ok());
+                let hits = bkpt["times"].as_str().and_then(|n| n.parse().ok());
+                if let (Some(number), Some(hits)) = (number, hits) {
+                    self.0.insert(number, hits);
+                }
+            }
+            BreakPointEvent::Deleted => {
+                if let Some(number) = results["id"].as_str().and_then(|n| n.parse().ok()) {
+                    self.0.remove(&number);
+                }
+            }
+        }
+    }
+
+    pub fn get(&self, number: BreakPointNumber) -> Option<u32> {
+        self.0.get(&number).copied()
+    }
+}
+
+impl GDBManager {
+    /// Times a breakpoint was hit, None if it was not inserted by the server
+    /// nor notified by GDB
+    pub async fn breakpoint_hits(
+        &self,
+        session_id: &str,
+        number: BreakPointNumber,
+    ) -> AppResult<Option<u32>> {
+        let sessions = self.sessions.lock().await;
+        let handle = sessions
+            .get(session_id)
+            .ok_or_else(|| AppError::NotFound(format!("Session {} does not exist", session_id)))?;
+        Ok(handle.hits.lock().unwrap().get(number))
+    }
+
+    /// Continue the program until a breakpoint is hit for the `hits`th time,
+    /// letting GDB ignore the hits before
+    pub async fn run_to_hit_count(
+        &self,
+        session_id: &str,
+        number: BreakPointNumber,
+        hits: u32,
+        timeout: Duration,
+    ) -> AppResult<HitCountReached> {
+        let current = match self.breakpoint_hits(session_id, number).await? {
+            Some(current) => current,
+            None => self
+                .get_breakpoints(session_id)
+                .await?
+                .into_iter()
+                .find(|bkpt| bkpt.number == number)
+                .ok_or_else(|| AppError::NotFound(format!("breakpoint {} not found", number)))?
+                .hits
+                .unwrap_or(0),
+        };
+        if hits <= current {
+            return Err(AppError::InvalidArgument(format!(
+                "breakpoint {} was already hit {} times",
+                number, current
+            )));
+        }
+
+        let command = MiCommand::break_after(number, hits - current - 1);
+        self.send_command_with_timeout(session_id, &command).await?;
+        let stopped =
+            match self.resume_and_wait(session_id, &MiCommand::exec_continue(), timeout).await? {
+                Resumed::Stopped(stopped) | Resumed::Interrupted(stopped) => stopped,
+            };
+
+        let reached =
+            stopped.get("bkptno").and_then(|n| n.as_str()) == Some(number.to_string().as_str());
+        if !reached {
+            // the hits left to ignore would be skipped by the next continue
+            let command = MiCommand::break_after(number, 0);
+            if let Err(e) = self.send_command_with_timeout(session_id, &command).await {
+                warn!("Failed to reset the ignore count of breakpoint {}: {}", number, e);
+            }
+        }
+        let hits = self.breakpoint_hits(session_id, number).await?.unwrap_or(current);
+        Ok(HitCountReached { reached, hits, stopped })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const SCRIPT: &str = r#"
+        -> -break-insert compute
+        <- ^done,bkpt={number="1",type="breakpoint",disp="keep",enabled="y",addr="0x1000",func="compute",times="0"}
+        -> -break-after 1 2
+        <- ^done
+        -> -exec-continue
+        <- ^running
+        <- *running,thread-id="all"
+        <- =breakpoint-modified,bkpt={number="1",type="breakpoint",disp="keep",enabled="y",addr="0x1000",func="compute",times="1",ignore="1"}
+        <- =breakpoint-modified,bkpt={number="1",type="breakpoint",disp="keep",enabled="y",addr="0x1000",func="compute",times="2",ignore="0"}
+        <- =breakpoint-modified,bkpt={number="1",type="breakpoint",disp="keep",enabled="y",addr="0x1000",func="compute",times="3"}
+        <- *stopped,reason="breakpoint-hit",disp="keep",bkptno="1",thread-id="1"
+    "#;
+
+    #[tokio::test]
+    async fn test_run_to_hit_count() {
+        let manager = GDBManager::default();
+        let session_id = manager.create_mock_session(SCRIPT).await.unwrap();
+        let number =
+            manager.insert_breakpoint_at(&session_id, "compute", None, false).await.unwrap();
+        // counted from the result, GDB does not notify the insertion
+        assert_eq!(manager.breakpoint_hits(&session_id, number).await.unwrap(), Some(0));
+
+        let reached =
+            manager.run_to_hit_count(&session_id, number, 3, Duration::from_secs(5)).await.unwrap();
+        assert!(reached.reached);
+        assert_eq!(reached.hits, 3);
+
+        assert!(matches!(
+            manager.run_to_hit_count(&session_id, number, 2, Duration::from_secs(5)).await,
+            Err(AppError::InvalidArgument(_))
+        ));
+    }
+}
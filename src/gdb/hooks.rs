@@ -0,0 +1,278 @@
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
+
+use serde::Serialize;
+use serde_json::Value;
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+use tracing::warn;
+
+use super::GDBManager;
+use super::plan::{PlanStep, StepResult};
+use crate::error::{AppError, AppResult};
+
+/// Number of breakpoint hits kept per session
+const MAX_HITS: usize = 1000;
+
+/// Actions by breakpoint number
+type Actions = Arc<Mutex<HashMap<String, Vec<PlanStep>>>>;
+
+/// The results of the actions of a breakpoint when it was hit
+#[derive(Debug, Clone, Serialize)]
+pub struct BreakpointHit {
+    pub breakpoint: String,
+    /// Number of stops of the session published before the hit
+    pub stop: u64,
+    pub frame: Option<Value>,
+    pub results: Vec<StepResult>,
+}
+
+/// Actions of the breakpoints of a session and what they collected
+#[derive(Debug)]
+pub struct Hooks {
+    actions: Actions,
+    hits: VecDeque<BreakpointHit>,
+    /// Stops at breakpoints with actions, until the task running them starts
+    stops: Option<mpsc::UnboundedReceiver<Value>>,
+    /// Gives the stops back to be published once their actions ran
+    release: mpsc::UnboundedSender<Value>,
+    /// Runs the actions once a breakpoint has some
+    task: Option<JoinHandle<()>>,
+}
+
+/// Takes the stops at breakpoints with actions away from the waiters, for
+/// the actions to run first
+#[derive(Debug)]
+pub struct HookRouter {
+    actions: Actions,
+    stops: mpsc::UnboundedSender<Value>,
+    released: mpsc::UnboundedReceiver<Value>,
+}
+
+impl HookRouter {
+    /// Whether the `*stopped` results are taken to run the actions of their
+    /// breakpoint, they are published later if the actions do not resume
+    /// the program
+    pub fn take(&self, stopped: &Value) -> bool {
+        if stopped.get("reason").and_then(Value::as_str) != Some("breakpoint-hit") {
+            return false;
+        }
+        let Some(breakpoint) = stopped.get("bkptno").and_then(Value::as_str) else {
+            return false;
+        };
+        self.actions.lock().unwrap().contains_key(breakpoint)
+            && self.stops.send(stopped.clone()).is_ok()
+    }
+
+    /// The next stop taken whose actions ran without resuming the program
+    pub async fn released(&mut self) -> Option<Value> {
+        self.released.recv().await
+    }
+}
+
+impl Hooks {
+    /// The hooks of a session and the router of its stops
+    pub fn new() -> (Self, HookRouter) {
+        let actions = Actions::default();
+        let (sender, stops) = mpsc::unbounded_channel();
+        let (release, released) = mpsc::unbounded_channel();
+        let hooks = Hooks {
+            actions: actions.clone(),
+            hits: VecDeque::new(),
+            stops: Some(stops),
+            release,
+            task: None,
+        };
+        (hooks, HookRouter { actions, stops: sender, released })
+    }
+}
+
+impl Drop for Hooks {
+    fn drop(&mut self) {
+        if let Some(task) = self.task.take() {
+            task.abort();
+        }
+    }
+}
+
+impl GDBManager {
+    /// Run `actions` whenever a breakpoint is hit, replacing its previous
+    /// ones, none removes them. The stop is published once they ran, unless
+    /// they resumed the program
+    pub async fn set_breakpoint_actions(
+        self: &Arc<Self>,
+        session_id: &str,
+        breakpoint: &str,
+        actions: Vec<PlanStep>,
+    ) -> AppResult<()> {
+        let mut sessions = self.sessions.lock().await;
+        let handle = sessions
+            .get_mut(session_id)
+            .ok_or_else(|| AppError::NotFound(format!("Session {} does not exist", session_id)))?;
+        let hooks = &mut handle.hooks;
+        if actions.is_empty() {
+            hooks.actions.lock().unwrap().remove(breakpoint);
+            return Ok(());
+        }
+        hooks.actions.lock().unwrap().insert(breakpoint.to_string(), actions);
+        if let Some(stops) = hooks.stops.take() {
+            let manager = self.clone();
+            let session_id = session_id.to_string();
+            let release = hooks.release.clone();
+            hooks.task = Some(tokio::spawn(async move {
+                manager.follow_hooks(&session_id, stops, release).await
+            }));
+        }
+        Ok(())
+    }
+
+    /// The results collected by the actions of the breakpoints, oldest first,
+    /// of a single breakpoint if set. They are removed if `clear` is set
+    pub async fn get_breakpoint_hits(
+        &self,
+        session_id: &str,
+        breakpoint: Option<&str>,
+        clear: bool,
+    ) -> AppResult<Vec<BreakpointHit>> {
+        let mut sessions = self.sessions.lock().await;
+        let handle = sessions
+            .get_mut(session_id)
+            .ok_or_else(|| AppError::NotFound(format!("Session {} does not exist", session_id)))?;
+        let matches =
+            |hit: &BreakpointHit| breakpoint.is_none_or(|number| hit.breakpoint == number);
+        let hits = handle.hooks.hits.iter().filter(|hit| matches(hit)).cloned().collect();
+        if clear {
+            handle.hooks.hits.retain(|hit| !matches(hit));
+        }
+        Ok(hits)
+    }
+
+    /// Forget the actions of deleted breakpoints
+    pub(super) async fn remove_breakpoint_actions(&self, session_id: &str, breakpoints: &[String]) {
+        let sessions = self.sessions.lock().await;
+        if let Some(handle) = sessions.get(session_id) {
+            let mut actions = handle.hooks.actions.lock().unwrap();
+            for breakpoint in breakpoints {
+                actions.remove(breakpoint);
+            }
+        }
+    }
+
+    /// Run the actions of the breakpoints hit until the session is closed,
+    /// before the waiters see the stop
+    async fn follow_hooks(
+        &self,
+        session_id: &str,
+        mut stops: mpsc::UnboundedReceiver<Value>,
+        release: mpsc::UnboundedSender<Value>,
+    ) {
+        while let Some(stopped) = stops.recv().await {
+            let breakpoint =
+                stopped.get("bkptno").and_then(Value::as_str).unwrap_or_default().to_string();
+            let (actions, published) = {
+                let sessions = self.sessions.lock().await;
+                let Some(handle) = sessions.get(session_id) else { break };
+                let actions = handle.hooks.actions.lock().unwrap().get(&breakpoint).cloned();
+                (actions.unwrap_or_default(), handle.stops.borrow().count)
+            };
+
+            let mut resumed_at = published;
+            let mut resumed = false;
+            let mut results = Vec::with_capacity(actions.len());
+            for step in actions {
+                let op = step.name();
+                let resumes = step.resumes();
+                results.push(match self.run_plan_step(session_id, step, &mut resumed_at).await {
+                    Ok(result) => {
+                        resumed |= resumes;
+                        StepResult { op, result: Some(result), error: None }
+                    }
+                    Err(e) => {
+                        warn!("Action {} of breakpoint {} failed: {}", op, breakpoint, e);
+                        StepResult { op, result: None, error: Some(e.to_string()) }
+                    }
+                });
+            }
+
+            let frame = stopped.get("frame").cloned();
+            if !resumed {
+                let _ = release.send(stopped);
+            }
+            let mut sessions = self.sessions.lock().await;
+            let Some(handle) = sessions.get_mut(session_id) else { break };
+            if handle.hooks.hits.len() == MAX_HITS {
+                handle.hooks.hits.pop_front();
+            }
+            handle.hooks.hits.push_back(BreakpointHit {
+                breakpoint,
+                stop: published,
+                frame,
+                results,
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use super::*;
+
+    #[test]
+    fn test_parse_actions() {
+        let actions: Vec<PlanStep> = serde_json::from_str(
+            r#"[
+                {"op": "evaluate", "expression": "len"},
+                {"op": "read_memory", "address": "buf", "count": 16},
+                {"op": "continue"}
+            ]"#,
+        )
+        .unwrap();
+        assert_eq!(actions.len(), 3);
+        assert!(matches!(actions[1], PlanStep::ReadMemory { count: 16, .. }));
+        assert!(serde_json::from_str::<PlanStep>(r#"{"op": "jump"}"#).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_actions_before_stop() {
+        let manager = Arc::new(GDBManager::default());
+        let session_id = manager
+            .create_mock_session(
+                r#"
+                -> -exec-continue
+                <- ^running
+                <- *running,thread-id="all"
+                <- *stopped,reason="breakpoint-hit",bkptno="1",frame={addr="0x401136",func="parse"}
+                -> -data-evaluate-expression "len"
+                <- ^done,value="16"
+                -> -exec-continue
+                <- ^running
+                <- *running,thread-id="all"
+                <- *stopped,reason="breakpoint-hit",bkptno="2",frame={addr="0x401150",func="parse"}
+                -> -data-evaluate-expression "len"
+                <- ^done,value="0"
+                "#,
+            )
+            .await
+            .unwrap();
+        let actions = |json| serde_json::from_str::<Vec<PlanStep>>(json).unwrap();
+        let evaluate = r#"[{"op": "evaluate", "expression": "len"}]"#;
+        let continued = r#"[{"op": "evaluate", "expression": "len"}, {"op": "continue"}]"#;
+        manager.set_breakpoint_actions(&session_id, "1", actions(continued)).await.unwrap();
+        manager.set_breakpoint_actions(&session_id, "2", actions(evaluate)).await.unwrap();
+
+        // the first stop is taken by its actions, the second is returned once
+        // its actions ran
+        let stops = manager.stop_count(&session_id).await.unwrap();
+        manager.continue_execution(&session_id).await.unwrap();
+        let stopped =
+            manager.wait_for_stop(&session_id, stops, Duration::from_secs(5)).await.unwrap();
+        assert_eq!(stopped["bkptno"], "2");
+        assert_eq!(manager.stop_count(&session_id).await.unwrap(), stops + 1);
+
+        let hits = manager.get_breakpoint_hits(&session_id, None, false).await.unwrap();
+        let values = hits.iter().map(|hit| hit.results[0].result.clone()).collect::<Vec<_>>();
+        assert_eq!(values, [Some(Value::from("16")), Some(Value::from("0"))]);
+    }
+}
@@ -0,0 +1,637 @@
+use std::io::SeekFrom;
+use std::path::Path;
+use std::str::FromStr;
+
+use serde::Serialize;
+use serde_json::Value;
+use serde_with::skip_serializing_none;
+use tokio::fs::File;
+use tokio::io::{AsyncSeekExt, AsyncWriteExt};
+use tracing::debug;
+
+use super::GDBManager;
+use crate::error::{AppError, AppResult};
+use crate::mi::commands::MiCommand;
+use crate::models::{Endian, Memory, MemoryType, ResolveSymbol};
+use crate::resources::decode_hex;
+
+/// Bytes read by a command at most, larger reads are split so GDB never
+/// answers with a huge record
+const MEMORY_CHUNK: usize = 64 * 1024;
+/// Largest region `find_strings` scans
+const MAX_SCAN_BYTES: usize = 1024 * 1024;
+/// Most strings `find_strings` returns
+const MAX_STRINGS: usize = 1000;
+/// Most values `read_typed_memory` returns
+const MAX_TYPED_VALUES: usize = 4096;
+
+/// A byte of text, a printable ASCII character or a whitespace
+pub fn is_text(byte: u8) -> bool {
+    byte.is_ascii_graphic() || byte.is_ascii_whitespace()
+}
+
+/// Scalar type of the values of `read_typed_memory`
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ScalarType {
+    U8,
+    U16,
+    U32,
+    U64,
+    F32,
+    F64,
+}
+
+impl FromStr for ScalarType {
+    type Err = AppError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "u8" => Ok(ScalarType::U8),
+            "u16" => Ok(ScalarType::U16),
+            "u32" => Ok(ScalarType::U32),
+            "u64" => Ok(ScalarType::U64),
+            "f32" => Ok(ScalarType::F32),
+            "f64" => Ok(ScalarType::F64),
+            _ => Err(AppError::InvalidArgument(format!(
+                "unknown type {}, expecting u8, u16, u32, u64, f32 or f64",
+                s
+            ))),
+        }
+    }
+}
+
+impl ScalarType {
+    /// Size in bytes
+    pub fn size(self) -> usize {
+        match self {
+            ScalarType::U8 => 1,
+            ScalarType::U16 => 2,
+            ScalarType::U32 | ScalarType::F32 => 4,
+            ScalarType::U64 | ScalarType::F64 => 8,
+        }
+    }
+
+    /// Value of `size()` bytes, null for a NaN which JSON cannot hold
+    fn decode(self, bytes: &[u8], endian: Endian) -> Value {
+        let bits = decode_word(bytes, endian);
+        match self {
+            ScalarType::F32 => Value::from(f32::from_bits(bits as u32) as f64),
+            ScalarType::F64 => Value::from(f64::from_bits(bits)),
+            _ => Value::from(bits),
+        }
+    }
+}
+
+/// Memory decoded as an array of scalars
+#[derive(Debug, Clone, Serialize)]
+pub struct TypedMemory {
+    /// Address of the first value
+    pub address: String,
+    #[serde(rename = "type")]
+    pub type_: ScalarType,
+    pub endian: Endian,
+    pub values: Vec<Value>,
+    /// More values were asked for than returned
+    pub truncated: bool,
+}
+
+/// A NUL terminated string read from memory
+#[derive(Debug, Clone, Serialize)]
+pub struct MemoryString {
+    pub address: String,
+    /// Bytes which are not UTF-8 are replaced
+    pub value: String,
+    /// Length in bytes, without the NUL
+    pub length: usize,
+    /// The NUL was found before the maximum length
+    pub terminated: bool,
+}
+
+/// Printable strings found in a region, like `strings`
+#[derive(Debug, Clone, Serialize)]
+pub struct FoundStrings {
+    pub strings: Vec<FoundString>,
+    /// More strings were found than returned
+    pub truncated: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct FoundString {
+    pub address: String,
+    pub value: String,
+}
+
+/// Range of a memory dump
+#[derive(Debug, Clone, Serialize)]
+pub struct MemoryRange {
+    pub begin: String,
+    pub end: String,
+}
+
+/// What a memory dump could read
+#[derive(Debug, Clone, Serialize)]
+pub struct MemoryDump {
+    pub readable: Vec<MemoryRange>,
+    /// Chunks GDB could not read any byte of, left as zeros in the file
+    pub unreadable: Vec<MemoryRange>,
+}
+
+/// A word of the stack with the values it leads to, like the stack view of
+/// the TUI
+#[derive(Debug, Clone, Serialize)]
+pub struct StackEntry {
+    pub address: String,
+    /// Offset from $sp in bytes
+    pub offset: usize,
+    /// The word, then the values it points to
+    pub chain: Vec<ChainValue>,
+    /// The chain goes back to one of its values
+    pub looped: bool,
+    /// The last value of the chain read as text, when all its bytes are
+    pub text: Option<String>,
+}
+
+#[skip_serializing_none]
+#[derive(Debug, Clone, Serialize)]
+pub struct ChainValue {
+    pub value: String,
+    pub kind: MemoryType,
+    /// Name or comment imported for the address
+    pub annotation: Option<String>,
+}
+
+/// Reads `count` bytes from `start` a chunk at a time. The chunks without
+/// any readable byte are skipped and reported, unless none is readable
+pub(super) struct MemoryChunks {
+    start: u64,
+    count: usize,
+    done: usize,
+    readable: bool,
+    error: Option<AppError>,
+    unreadable: Vec<(u64, u64)>,
+}
+
+impl MemoryChunks {
+    pub(super) fn new(start: u64, count: usize) -> Self {
+        MemoryChunks { start, count, done: 0, readable: false, error: None, unreadable: vec![] }
+    }
+
+    /// The readable blocks of the next chunk, none if it is skipped, or None
+    /// once all are read
+    pub(super) async fn next(
+        &mut self,
+        manager: &GDBManager,
+        session_id: &str,
+    ) -> AppResult<Option<Vec<Memory>>> {
+        if self.done >= self.count {
+            return Ok(None);
+        }
+        let length = MEMORY_CHUNK.min(self.count - self.done);
+        let address = self.start.wrapping_add(self.done as u64);
+        self.done += length;
+        let command = MiCommand::data_read_memory_bytes(None, format!("0x{:x}", address), length);
+        match manager.send_command_with_timeout(session_id, &command).await {
+            Ok(response) => {
+                let memory = response
+                    .results
+                    .get("memory")
+                    .ok_or(AppError::NotFound("expect memory".to_string()))?;
+                self.readable = true;
+                Ok(Some(serde_json::from_value(memory.to_owned())?))
+            }
+            Err(e @ AppError::GDBCommandError { .. }) => {
+                debug!("Skipped {} unreadable bytes at 0x{:x}: {}", length, address, e);
+                push_range(&mut self.unreadable, address, address.wrapping_add(length as u64));
+                self.error.get_or_insert(e);
+                Ok(Some(vec![]))
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    /// The skipped ranges, or why the first chunk could not be read if none
+    /// is readable
+    pub(super) fn finish(self) -> AppResult<Vec<MemoryRange>> {
+        match self.error {
+            Some(e) if !self.readable => Err(e),
+            _ => Ok(memory_ranges(self.unreadable)),
+        }
+    }
+}
+
+impl GDBManager {
+    /// Address an expression evaluates to, e.g. `&buffer`, `$sp` or
+    /// `my_global + 0x10`. Numbers are taken as is without asking GDB.
+    /// Arrays and functions give their address, like structures which do not
+    /// convert to a number
+    pub async fn resolve_address(&self, session_id: &str, expression: &str) -> AppResult<u64> {
+        if let Some(address) = parse_number(expression.trim()) {
+            return Ok(address);
+        }
+        let mut error = None;
+        for cast in address_casts(expression) {
+            match self.evaluate_expression(session_id, &cast).await {
+                Ok(value) => {
+                    if let Some(address) = parse_number(value.trim()) {
+                        return Ok(address);
+                    }
+                }
+                Err(AppError::GDBCommandError { msg, .. }) => {
+                    error.get_or_insert(msg);
+                }
+                Err(e) => return Err(e),
+            }
+        }
+        Err(AppError::InvalidArgument(format!(
+            "{} is not an address: {}",
+            expression,
+            error.unwrap_or_default()
+        )))
+    }
+
+    /// Write `count` bytes from the address an expression evaluates to to a
+    /// file as they are read, the unreadable ones are left as zeros
+    pub async fn dump_memory(
+        &self,
+        session_id: &str,
+        address: &str,
+        count: usize,
+        path: &Path,
+    ) -> AppResult<MemoryDump> {
+        let start = self.resolve_address(session_id, address).await?;
+        let mut file = File::create(path).await?;
+        file.set_len(count as u64).await?;
+        let mut readable = vec![];
+        let mut chunks = MemoryChunks::new(start, count);
+        while let Some(blocks) = chunks.next(self, session_id).await? {
+            for block in blocks {
+                let (Some(begin), Some(end)) =
+                    (parse_number(&block.begin), parse_number(&block.end))
+                else {
+                    continue;
+                };
+                file.seek(SeekFrom::Start(begin.wrapping_sub(start))).await?;
+                file.write_all(&decode_hex(&block.contents)?).await?;
+                push_range(&mut readable, begin, end);
+            }
+        }
+        let unreadable = chunks.finish()?;
+        file.flush().await?;
+        Ok(MemoryDump { readable: memory_ranges(readable), unreadable })
+    }
+
+    /// Read `count` values, at most `MAX_TYPED_VALUES`, of a scalar type from
+    /// the address an expression evaluates to, in the byte order of the
+    /// session if `endian` is not set
+    pub async fn read_typed_memory(
+        &self,
+        session_id: &str,
+        address: &str,
+        type_: ScalarType,
+        count: usize,
+        endian: Option<Endian>,
+    ) -> AppResult<TypedMemory> {
+        let endian = match endian {
+            Some(endian) => endian,
+            None => self.get_session(session_id).await?.arch.endian.unwrap_or(Endian::Little),
+        };
+        let address = self.resolve_address(session_id, address).await?;
+        let truncated = count > MAX_TYPED_VALUES;
+        let size = type_.size() * count.min(MAX_TYPED_VALUES);
+        let memory = self.read_memory(session_id, None, format!("0x{:x}", address), size).await?;
+        // the values are read from the beginning only
+        let bytes = match memory.first() {
+            Some(block) if parse_number(&block.begin) == Some(address) => {
+                decode_hex(&block.contents)?
+            }
+            _ => vec![],
+        };
+        if bytes.len() < size {
+            return Err(AppError::NotFound(format!(
+                "only {} of the {} bytes at 0x{:x} are readable",
+                bytes.len(),
+                size,
+                address
+            )));
+        }
+        Ok(TypedMemory {
+            address: format!("0x{:x}", address),
+            type_,
+            endian,
+            values: bytes[..size]
+                .chunks_exact(type_.size())
+                .map(|bytes| type_.decode(bytes, endian))
+                .collect(),
+            truncated,
+        })
+    }
+
+    /// Read a NUL terminated string of at most `max_length` bytes
+    pub async fn read_string(
+        &self,
+        session_id: &str,
+        address: &str,
+        max_length: usize,
+    ) -> AppResult<MemoryString> {
+        let address = self.resolve_address(session_id, address).await?;
+        let memory =
+            self.read_memory(session_id, None, format!("0x{:x}", address), max_length).await?;
+        let bytes = match memory.first() {
+            Some(block) if parse_number(&block.begin) == Some(address) => {
+                decode_hex(&block.contents)?
+            }
+            _ => return Err(AppError::NotFound(format!("no readable memory at 0x{:x}", address))),
+        };
+        let length = bytes.iter().position(|byte| *byte == 0);
+        let bytes = &bytes[..length.unwrap_or(bytes.len())];
+        Ok(MemoryString {
+            address: format!("0x{:x}", address),
+            value: String::from_utf8_lossy(bytes).into_owned(),
+            length: bytes.len(),
+            terminated: length.is_some(),
+        })
+    }
+
+    /// Read `words` words from `$sp` and follow those which point into the
+    /// mapped memory for at most `depth` more values. Nothing is followed when
+    /// the mappings are unknown, e.g. on a remote target
+    pub async fn get_stack_memory(
+        &self,
+        session_id: &str,
+        words: usize,
+        depth: usize,
+    ) -> AppResult<Vec<StackEntry>> {
+        let arch = self.get_session(session_id).await?.arch;
+        let word = arch.pointer_bits.unwrap_or(64) as usize / 8;
+        let endian = arch.endian.unwrap_or(Endian::Little);
+        let memory_map = self.memory_mappings(session_id).await.unwrap_or_default();
+        let sp = self.resolve_address(session_id, "$sp").await?;
+
+        let mut entries = vec![];
+        let stack = self.read_words(session_id, sp, words, word, endian).await?;
+        for (i, value) in stack.into_iter().enumerate() {
+            let mut resolve = ResolveSymbol::default();
+            resolve.try_push(value);
+            let mut last = value;
+            while resolve.map.len() <= depth && memory_map.iter().any(|m| m.contains(last)) {
+                let next = match self.read_words(session_id, last, 1, word, endian).await {
+                    Ok(next) => next.first().copied(),
+                    Err(_) => None,
+                };
+                match next {
+                    Some(next) if resolve.try_push(next) => last = next,
+                    _ => break,
+                }
+            }
+            let bytes = match endian {
+                Endian::Little => last.to_le_bytes()[..word].to_vec(),
+                Endian::Big => last.to_be_bytes()[8 - word..].to_vec(),
+            };
+            entries.push(StackEntry {
+                address: format!("0x{:x}", sp + (i * word) as u64),
+                offset: i * word,
+                chain: resolve
+                    .map
+                    .iter()
+                    .map(|value| ChainValue {
+                        value: format!("0x{:x}", value),
+                        kind: MemoryType::classify(&memory_map, *value, None),
+                        annotation: None,
+                    })
+                    .collect(),
+                looped: resolve.repeated_pattern,
+                text: (last > 0xff && bytes.iter().all(|byte| is_text(*byte)))
+                    .then(|| String::from_utf8_lossy(&bytes).into_owned()),
+            });
+        }
+        let values = entries.iter().flat_map(|entry| &entry.chain);
+        let values = values.filter_map(|value| parse_number(&value.value)).collect::<Vec<_>>();
+        let labels = self.annotation_labels(session_id, values).await;
+        for value in entries.iter_mut().flat_map(|entry| &mut entry.chain) {
+            value.annotation =
+                parse_number(&value.value).and_then(|value| labels.get(&value)).cloned();
+        }
+        Ok(entries)
+    }
+
+    /// Words of `size` bytes from an address, as many as readable
+    pub(super) async fn read_words(
+        &self,
+        session_id: &str,
+        address: u64,
+        count: usize,
+        size: usize,
+        endian: Endian,
+    ) -> AppResult<Vec<u64>> {
+        let memory =
+            self.read_memory(session_id, None, format!("0x{:x}", address), count * size).await?;
+        let bytes = match memory.first() {
+            Some(block) if parse_number(&block.begin) == Some(address) => {
+                decode_hex(&block.contents)?
+            }
+            _ => vec![],
+        };
+        Ok(bytes.chunks_exact(size).map(|bytes| decode_word(bytes, endian)).collect())
+    }
+
+    /// Find the runs of at least `min_length` text bytes in the readable
+    /// parts of a region
+    pub async fn find_strings(
+        &self,
+        session_id: &str,
+        address: &str,
+        length: usize,
+        min_length: usize,
+    ) -> AppResult<FoundStrings> {
+        if length > MAX_SCAN_BYTES {
+            return Err(AppError::InvalidArgument(format!(
+                "cannot scan more than {} bytes",
+                MAX_SCAN_BYTES
+            )));
+        }
+        let address = self.resolve_address(session_id, address).await?;
+        let memory = self.read_memory(session_id, None, format!("0x{:x}", address), length).await?;
+
+        let mut strings = vec![];
+        for block in memory {
+            let begin = parse_number(&block.begin)
+                .ok_or_else(|| AppError::ParseError(format!("invalid address {}", block.begin)))?;
+            let bytes = decode_hex(&block.contents)?;
+            strings.extend(text_runs(&bytes, min_length.max(1)).map(|(offset, run)| FoundString {
+                address: format!("0x{:x}", begin + offset as u64),
+                value: String::from_utf8_lossy(run).into_owned(),
+            }));
+        }
+        let truncated = strings.len() > MAX_STRINGS;
+        strings.truncate(MAX_STRINGS);
+        Ok(FoundStrings { strings, truncated })
+    }
+}
+
+/// Unsigned value of at most 8 bytes
+fn decode_word(bytes: &[u8], endian: Endian) -> u64 {
+    let mut raw = [0; 8];
+    match endian {
+        Endian::Little => {
+            raw[..bytes.len()].copy_from_slice(bytes);
+            u64::from_le_bytes(raw)
+        }
+        Endian::Big => {
+            raw[8 - bytes.len()..].copy_from_slice(bytes);
+            u64::from_be_bytes(raw)
+        }
+    }
+}
+
+/// Offsets and bytes of the runs of at least `min_length` text bytes
+fn text_runs(bytes: &[u8], min_length: usize) -> impl Iterator<Item = (usize, &[u8])> {
+    let mut offset = 0;
+    bytes.split(|byte| !is_text(*byte)).filter_map(move |run| {
+        let start = offset;
+        offset += run.len() + 1;
+        (run.len() >= min_length).then_some((start, run))
+    })
+}
+
+/// Add a block read from `start` on, merged with the last one if it
+/// continues it
+pub(super) fn append_block(blocks: &mut Vec<Memory>, mut block: Memory, start: u64) {
+    let begin = parse_number(&block.begin);
+    if let Some(last) = blocks.last_mut()
+        && begin.is_some()
+        && parse_number(&last.end) == begin
+    {
+        last.end = block.end;
+        last.contents.push_str(&block.contents);
+        return;
+    }
+    // the offsets GDB reports are relative to the start of the chunk
+    if let Some(begin) = begin {
+        let offset = begin.wrapping_sub(start);
+        if parse_number(&block.offset) != Some(offset) {
+            block.offset = format!("0x{:x}", offset);
+        }
+    }
+    blocks.push(block);
+}
+
+/// Extend the last range with `begin..end` if it continues it
+fn push_range(ranges: &mut Vec<(u64, u64)>, begin: u64, end: u64) {
+    match ranges.last_mut() {
+        Some(last) if last.1 == begin => last.1 = end,
+        _ => ranges.push((begin, end)),
+    }
+}
+
+fn memory_ranges(ranges: Vec<(u64, u64)>) -> Vec<MemoryRange> {
+    ranges
+        .into_iter()
+        .map(|(begin, end)| MemoryRange {
+            begin: format!("0x{:x}", begin),
+            end: format!("0x{:x}", end),
+        })
+        .collect()
+}
+
+/// A number in hex with the `0x` prefix, or in decimal
+pub(super) fn parse_number(text: &str) -> Option<u64> {
+    match text.strip_prefix("0x").or_else(|| text.strip_prefix("0X")) {
+        Some(hex) => u64::from_str_radix(hex, 16).ok(),
+        None => text.parse().ok(),
+    }
+}
+
+/// The expression cast to an integer, printed by GDB as a plain number. In
+/// the C syntax then in the Rust one, as GDB parses the language of the
+/// frame, and of the address for the values which do not convert
+fn address_casts(expression: &str) -> [String; 4] {
+    [
+        format!("(unsigned long long)({})", expression),
+        format!("({}) as u64", expression),
+        format!("(unsigned long long)&({})", expression),
+        format!("&({}) as u64", expression),
+    ]
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_resolve_address() {
+        let manager = GDBManager::default();
+        let session_id = manager
+            .create_mock_session(
+                r#"
+                -> -data-evaluate-expression "(unsigned long long)(&buffer)"
+                <- ^done,value="16400"
+                -> -data-evaluate-expression "(unsigned long long)(config)"
+                <- ^error,msg="Invalid cast."
+                -> -data-evaluate-expression "(config) as u64"
+                <- ^error,msg="A syntax error in expression, near `as u64'."
+                -> -data-evaluate-expression "(unsigned long long)&(config)"
+                <- ^done,value="16512"
+                -> -data-evaluate-expression "(unsigned long long)(no_such)"
+                <- ^error,msg="No symbol \"no_such\" in current context."
+                -> -data-evaluate-expression "(no_such) as u64"
+                <- ^error,msg="No symbol \"no_such\" in current context."
+                -> -data-evaluate-expression "(unsigned long long)&(no_such)"
+                <- ^error,msg="No symbol \"no_such\" in current context."
+                -> -data-evaluate-expression "&(no_such) as u64"
+                <- ^error,msg="No symbol \"no_such\" in current context."
+                "#,
+            )
+            .await
+            .unwrap();
+
+        // numbers are not evaluated
+        assert_eq!(manager.resolve_address(&session_id, "0x1010").await.unwrap(), 0x1010);
+        assert_eq!(manager.resolve_address(&session_id, "&buffer").await.unwrap(), 0x4010);
+        // a structure gives its address
+        assert_eq!(manager.resolve_address(&session_id, "config").await.unwrap(), 0x4080);
+        assert!(matches!(manager.resolve_address(&session_id, "no_such").await,
+            Err(AppError::InvalidArgument(msg)) if msg.contains("No symbol")));
+    }
+
+    #[tokio::test]
+    async fn test_read_typed_memory() {
+        let script = format!(
+            "-> -data-read-memory-bytes 0x1000 8192\n\
+             <- ^done,memory=[{{begin=\"0x1000\",offset=\"0x0\",end=\"0x3000\",contents=\"{}\"}}]\n",
+            "0100".repeat(4096)
+        );
+        let manager = GDBManager::default();
+        let session_id = manager.create_mock_session(&script).await.unwrap();
+
+        // clamped to the most values returned
+        let memory = manager
+            .read_typed_memory(&session_id, "0x1000", ScalarType::U16, 5000, Some(Endian::Little))
+            .await
+            .unwrap();
+        assert_eq!(memory.values.len(), MAX_TYPED_VALUES);
+        assert_eq!(memory.values[0], 1);
+        assert!(memory.truncated);
+    }
+
+    #[test]
+    fn test_decode_scalar() {
+        assert_eq!(ScalarType::U16.decode(&[0x34, 0x12], Endian::Little), Value::from(0x1234));
+        assert_eq!(ScalarType::U16.decode(&[0x12, 0x34], Endian::Big), Value::from(0x1234));
+        assert_eq!(ScalarType::F32.decode(&1.5f32.to_le_bytes(), Endian::Little), Value::from(1.5));
+        assert_eq!(
+            ScalarType::F64.decode(&(-2.0f64).to_be_bytes(), Endian::Big),
+            Value::from(-2.0)
+        );
+        assert_eq!(ScalarType::F64.decode(&f64::NAN.to_le_bytes(), Endian::Little), Value::Null);
+        assert!("i32".parse::<ScalarType>().is_err());
+    }
+
+    #[test]
+    fn test_text_runs() {
+        let bytes = b"\x00\x01hello world\x00ab\x00\xffpath/to\x7f";
+        let runs = text_runs(bytes, 4).collect::<Vec<_>>();
+        assert_eq!(runs, vec![(2, &b"hello world"[..]), (18, &b"path/to"[..])]);
+        assert_eq!(text_runs(bytes, 2).count(), 3);
+    }
+}
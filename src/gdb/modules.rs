@@ -0,0 +1,515 @@
+//! Objects loaded in the program, as notified by GDB for the shared libraries,
+//! with the load bias telling how far they are from their link addresses
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+
+use goblin::elf::Elf;
+use goblin::elf::program_header::PT_LOAD;
+use goblin::elf::section_header::{SHF_ALLOC, SectionHeader};
+use serde::Serialize;
+use serde_json::Value;
+use serde_with::skip_serializing_none;
+
+use super::GDBManager;
+use super::memory::parse_number;
+use crate::error::{AppError, AppResult};
+use crate::mi::output::AsyncClass;
+use crate::models::MemoryMapping;
+
+/// Alignment of the mappings of the segments
+const PAGE_SIZE: u64 = 0x1000;
+
+/// An object loaded in the program
+#[skip_serializing_none]
+#[derive(Debug, Clone, Serialize)]
+pub struct Module {
+    /// The path on the target, e.g. `/lib/x86_64-linux-gnu/libc.so.6`
+    pub id: String,
+    /// The file GDB read the symbols from
+    pub path: Option<PathBuf>,
+    /// The program itself rather than a shared library
+    pub main: bool,
+    pub symbols_loaded: Option<bool>,
+    pub thread_group: Option<String>,
+    /// Where `.text` is in memory, at its link addresses if the load bias is
+    /// unknown
+    pub ranges: Vec<AddressRange>,
+    /// Runtime minus link address, the slide of a PIE or a library
+    pub load_bias: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct AddressRange {
+    pub from: String,
+    pub to: String,
+}
+
+impl Module {
+    /// `name` is the path of the module on the target or the host, or its
+    /// file name, e.g. `libc.so.6`
+    pub fn matches(&self, name: &str) -> bool {
+        self.id == name
+            || self.path.as_deref() == Some(Path::new(name))
+            || Path::new(&self.id).file_name().is_some_and(|file_name| file_name == name)
+    }
+}
+
+/// What an address given to `rebase_address` is relative to
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AddressKind {
+    /// In the memory of the program
+    Runtime,
+    /// Link address, as disassemblers show it
+    Static,
+    /// Offset in the file of the module
+    FileOffset,
+}
+
+impl FromStr for AddressKind {
+    type Err = AppError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "runtime" => Ok(Self::Runtime),
+            "static" => Ok(Self::Static),
+            "offset" => Ok(Self::FileOffset),
+            _ => Err(AppError::InvalidArgument(format!(
+                "unknown address kind {}, expecting runtime, static or offset",
+                s
+            ))),
+        }
+    }
+}
+
+/// An address of a module translated by `rebase_address`
+#[skip_serializing_none]
+#[derive(Debug, Clone, Serialize)]
+pub struct Rebased {
+    pub module: String,
+    pub load_bias: String,
+    pub runtime_address: String,
+    pub static_address: String,
+    /// Not set for memory which is not in the file, e.g. `.bss`
+    pub file_offset: Option<String>,
+    pub section: Option<String>,
+}
+
+/// Where an address is in an object
+#[derive(Debug, Clone, PartialEq)]
+struct Location {
+    static_address: u64,
+    file_offset: Option<u64>,
+    section: Option<String>,
+}
+
+/// What is needed of an object file to translate its addresses, parsed once
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ObjectInfo {
+    /// The file parsed, with the symbolic links resolved
+    pub path: PathBuf,
+    /// Link address and size of `.text`
+    pub text: Option<(u64, u64)>,
+    segments: Vec<Segment>,
+    sections: Vec<Section>,
+}
+
+/// A loadable segment
+#[derive(Debug, Clone, PartialEq)]
+struct Segment {
+    address: u64,
+    memory_size: u64,
+    offset: u64,
+    file_size: u64,
+}
+
+/// A section allocated in memory
+#[derive(Debug, Clone, PartialEq)]
+struct Section {
+    name: String,
+    address: u64,
+    size: u64,
+}
+
+impl ObjectInfo {
+    /// Read and parse the ELF file at `path`, blocking
+    pub fn parse(path: &Path) -> AppResult<Self> {
+        let path = path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+        let bytes = std::fs::read(&path)?;
+        let elf = Elf::parse(&bytes).map_err(|e| {
+            AppError::InvalidArgument(format!("{} is not an ELF file: {}", path.display(), e))
+        })?;
+        Ok(Self::from_elf(path, &elf))
+    }
+
+    fn from_elf(path: PathBuf, elf: &Elf) -> Self {
+        let section_name = |header: &SectionHeader| elf.shdr_strtab.get_at(header.sh_name);
+        let text = elf
+            .section_headers
+            .iter()
+            .find(|header| section_name(header) == Some(".text"))
+            .map(|header| (header.sh_addr, header.sh_size));
+        let segments = elf
+            .program_headers
+            .iter()
+            .filter(|header| header.p_type == PT_LOAD)
+            .map(|header| Segment {
+                address: header.p_vaddr,
+                memory_size: header.p_memsz,
+                offset: header.p_offset,
+                file_size: header.p_filesz,
+            })
+            .collect();
+        let sections = elf
+            .section_headers
+            .iter()
+            .filter(|header| header.sh_flags & u64::from(SHF_ALLOC) != 0)
+            .filter_map(|header| {
+                Some(Section {
+                    name: section_name(header)?.to_string(),
+                    address: header.sh_addr,
+                    size: header.sh_size,
+                })
+            })
+            .collect();
+        Self { path, text, segments, sections }
+    }
+}
+
+/// Shared libraries by load, and the objects parsed by path
+#[derive(Debug, Clone, Default)]
+pub struct ModuleMap {
+    modules: Vec<Module>,
+    objects: HashMap<PathBuf, Arc<ObjectInfo>>,
+}
+
+impl ModuleMap {
+    /// Update the map with a library notification, returns the file of the
+    /// library loaded to parse
+    pub fn apply(&mut self, class: &AsyncClass, results: &Value) -> Option<PathBuf> {
+        let field = |key| results.get(key).and_then(Value::as_str).map(str::to_string);
+        let id = field("id")?;
+        match class.as_str() {
+            "library-loaded" => {
+                // a new run loads the libraries again
+                self.modules.retain(|module| module.id != id);
+                // GDB before 10 gives a single range
+                let ranges = match results.get("ranges").and_then(Value::as_array) {
+                    Some(ranges) => ranges
+                        .iter()
+                        .filter_map(|range| {
+                            Some(AddressRange {
+                                from: range.get("from")?.as_str()?.to_string(),
+                                to: range.get("to")?.as_str()?.to_string(),
+                            })
+                        })
+                        .collect(),
+                    None => field("low-address")
+                        .zip(field("high-address"))
+                        .map(|(from, to)| AddressRange { from, to })
+                        .into_iter()
+                        .collect(),
+                };
+                let path = field("host-name").map(PathBuf::from);
+                self.modules.push(Module {
+                    id,
+                    path: path.clone(),
+                    main: false,
+                    symbols_loaded: field("symbols-loaded").map(|loaded| loaded == "1"),
+                    thread_group: field("thread-group"),
+                    ranges,
+                    load_bias: None,
+                });
+                path
+            }
+            "library-unloaded" => {
+                self.modules.retain(|module| module.id != id);
+                None
+            }
+            _ => None,
+        }
+    }
+
+    pub fn modules(&self) -> &[Module] {
+        &self.modules
+    }
+}
+
+/// Parse the object at `path` on a blocking thread and cache it in `modules`,
+/// replacing the one parsed before, e.g. a library rebuilt between two runs
+pub async fn load_object(
+    modules: Arc<Mutex<ModuleMap>>,
+    path: PathBuf,
+) -> AppResult<Arc<ObjectInfo>> {
+    let parse_path = path.clone();
+    let object =
+        Arc::new(tokio::task::spawn_blocking(move || ObjectInfo::parse(&parse_path)).await??);
+    modules.lock().unwrap().objects.insert(path, object.clone());
+    Ok(object)
+}
+
+/// The object at `path` as cached in `modules`, parsed if it is not yet
+async fn cached_object(modules: &Arc<Mutex<ModuleMap>>, path: &Path) -> AppResult<Arc<ObjectInfo>> {
+    let cached = modules.lock().unwrap().objects.get(path).cloned();
+    match cached {
+        Some(object) => Ok(object),
+        None => load_object(modules.clone(), path.to_path_buf()).await,
+    }
+}
+
+impl GDBManager {
+    /// The program then the shared libraries loaded, with their load biases
+    pub async fn get_loaded_modules(&self, session_id: &str) -> AppResult<Vec<Module>> {
+        let (program, map) = self.module_map(session_id).await?;
+        let libraries = map.lock().unwrap().modules().to_vec();
+
+        let mut modules = Vec::with_capacity(libraries.len() + 1);
+        if let Some(program) = program {
+            let object = cached_object(&map, &program).await?;
+            // not started or no mappings on the target, e.g. a bare metal one
+            let mappings = self.memory_mappings(session_id).await.unwrap_or_default();
+            modules.push(main_module(&program, &object, &mappings));
+        }
+        for mut library in libraries {
+            // the ranges notified are the ones of .text
+            let text = match library.path.as_deref() {
+                Some(path) => cached_object(&map, path).await.ok().and_then(|object| object.text),
+                None => None,
+            };
+            let from = library.ranges.first().and_then(|range| parse_number(&range.from));
+            if let (Some((address, _)), Some(from)) = (text, from) {
+                library.load_bias = Some(format!("0x{:x}", from.wrapping_sub(address)));
+            }
+            modules.push(library);
+        }
+        Ok(modules)
+    }
+
+    /// The program of a session and its module map
+    async fn module_map(
+        &self,
+        session_id: &str,
+    ) -> AppResult<(Option<PathBuf>, Arc<Mutex<ModuleMap>>)> {
+        let sessions = self.sessions.lock().await;
+        let handle = sessions
+            .get(session_id)
+            .ok_or_else(|| AppError::NotFound(format!("Session {} does not exist", session_id)))?;
+        Ok((handle.builder.opt_program.clone(), handle.modules.clone()))
+    }
+
+    /// Translate between a runtime address and the static address or file
+    /// offset in a module, the program by default. A runtime address, which
+    /// may be an expression like `$pc`, is looked for in all the modules
+    /// unless one is given
+    pub async fn rebase_address(
+        &self,
+        session_id: &str,
+        address: &str,
+        kind: AddressKind,
+        module: Option<&str>,
+    ) -> AppResult<Rebased> {
+        let address = match kind {
+            AddressKind::Runtime => self.resolve_address(session_id, address).await?,
+            AddressKind::Static | AddressKind::FileOffset => parse_number(address)
+                .ok_or_else(|| AppError::InvalidArgument(format!("invalid number {}", address)))?,
+        };
+        let modules = self.get_loaded_modules(session_id).await?;
+        let (_, map) = self.module_map(session_id).await?;
+        let candidates = modules
+            .iter()
+            .filter(|candidate| match module {
+                Some(name) => candidate.matches(name),
+                None => kind == AddressKind::Runtime || candidate.main,
+            })
+            .collect::<Vec<_>>();
+        if candidates.is_empty() {
+            return Err(AppError::NotFound(match module {
+                Some(name) => format!("module {} is not loaded", name),
+                None => "The session has no program, give the module".to_string(),
+            }));
+        }
+
+        for candidate in candidates {
+            let bias = candidate.load_bias.as_deref().and_then(parse_number);
+            let (Some(path), Some(bias)) = (candidate.path.as_deref(), bias) else {
+                if kind == AddressKind::Runtime {
+                    continue;
+                }
+                return Err(AppError::NotFound(format!(
+                    "The load bias of {} is unknown, the program must be started",
+                    candidate.id
+                )));
+            };
+            let object = cached_object(&map, path).await?;
+            let Some(location) = locate(&object, bias, address, kind) else {
+                if kind == AddressKind::Runtime {
+                    continue;
+                }
+                return Err(AppError::InvalidArgument(format!(
+                    "0x{:x} is not in a loaded segment of {}",
+                    address, candidate.id
+                )));
+            };
+            return Ok(Rebased {
+                module: candidate.id.clone(),
+                load_bias: format!("0x{:x}", bias),
+                runtime_address: format!("0x{:x}", location.static_address.wrapping_add(bias)),
+                static_address: format!("0x{:x}", location.static_address),
+                file_offset: location.file_offset.map(|offset| format!("0x{:x}", offset)),
+                section: location.section,
+            });
+        }
+        Err(AppError::NotFound(format!("0x{:x} is in no loaded module", address)))
+    }
+}
+
+/// Where `address` of the kind given is in `object` loaded `bias` bytes from
+/// its link addresses, None if it is not in a loaded segment
+fn locate(object: &ObjectInfo, bias: u64, address: u64, kind: AddressKind) -> Option<Location> {
+    let mut segments = object.segments.iter();
+    let (static_address, segment) = match kind {
+        AddressKind::Runtime | AddressKind::Static => {
+            let static_address =
+                if kind == AddressKind::Runtime { address.wrapping_sub(bias) } else { address };
+            let segment = segments.find(|segment| {
+                (segment.address..segment.address + segment.memory_size).contains(&static_address)
+            })?;
+            (static_address, segment)
+        }
+        AddressKind::FileOffset => {
+            let segment = segments.find(|segment| {
+                (segment.offset..segment.offset + segment.file_size).contains(&address)
+            })?;
+            (address - segment.offset + segment.address, segment)
+        }
+    };
+    let in_segment = static_address - segment.address;
+    let file_offset = (in_segment < segment.file_size).then(|| segment.offset + in_segment);
+    let section = object
+        .sections
+        .iter()
+        .find(|section| (section.address..section.address + section.size).contains(&static_address))
+        .map(|section| section.name.clone());
+    Some(Location { static_address, file_offset, section })
+}
+
+/// The program, slid by as much as its first segment is from its mapping at
+/// file offset 0
+fn main_module(program: &Path, object: &ObjectInfo, mappings: &[MemoryMapping]) -> Module {
+    let first_segment =
+        object.segments.iter().map(|segment| segment.address & !(PAGE_SIZE - 1)).min();
+    let mapped = mappings
+        .iter()
+        .filter(|mapping| {
+            mapping.offset == 0 && mapping.path.as_deref() == Some(object.path.as_path())
+        })
+        .map(|mapping| mapping.start_address)
+        .min();
+    let load_bias = first_segment.zip(mapped).map(|(first, mapped)| mapped.wrapping_sub(first));
+
+    let ranges = object
+        .text
+        .map(|(address, size)| {
+            let from = address.wrapping_add(load_bias.unwrap_or(0));
+            AddressRange { from: format!("0x{:x}", from), to: format!("0x{:x}", from + size) }
+        })
+        .into_iter()
+        .collect();
+    Module {
+        id: program.display().to_string(),
+        path: Some(object.path.clone()),
+        main: true,
+        symbols_loaded: None,
+        thread_group: None,
+        ranges,
+        load_bias: load_bias.map(|bias| format!("0x{:x}", bias)),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn test_module_map() {
+        let mut map = ModuleMap::default();
+        map.apply(
+            &AsyncClass::LibraryLoaded,
+            &json!({
+                "id": "/lib/libc.so.6",
+                "host-name": "/lib/libc.so.6",
+                "symbols-loaded": "0",
+                "thread-group": "i1",
+                "ranges": [{"from": "0x7ffff7c28800", "to": "0x7ffff7dbd93d"}]
+            }),
+        );
+        map.apply(
+            &AsyncClass::LibraryLoaded,
+            &json!({
+                "id": "/lib/libm.so.6",
+                "low-address": "0x7ffff7e00000",
+                "high-address": "0x7ffff7e80000"
+            }),
+        );
+        assert_eq!(map.modules().len(), 2);
+        assert_eq!(
+            map.modules()[0].ranges,
+            [AddressRange { from: "0x7ffff7c28800".to_string(), to: "0x7ffff7dbd93d".to_string() }]
+        );
+        assert_eq!(map.modules()[1].ranges[0].from, "0x7ffff7e00000");
+
+        map.apply(
+            &AsyncClass::Other("library-unloaded".to_string()),
+            &json!({"id": "/lib/libc.so.6", "thread-group": "i1"}),
+        );
+        assert_eq!(map.modules().len(), 1);
+        assert_eq!(map.modules()[0].id, "/lib/libm.so.6");
+    }
+
+    #[cfg(target_os = "linux")]
+    #[tokio::test]
+    async fn test_main_module() {
+        // not started, the program is at its link addresses
+        let program = std::env::current_exe().unwrap();
+        let map = Arc::new(Mutex::new(ModuleMap::default()));
+        let object = cached_object(&map, &program).await.unwrap();
+        let module = main_module(&program, &object, &[]);
+        assert!(module.main && module.load_bias.is_none());
+        let (address, _) = object.text.unwrap();
+        assert_eq!(module.ranges[0].from, format!("0x{:x}", address));
+
+        // parsed once
+        let cached = cached_object(&map, &program).await.unwrap();
+        assert!(Arc::ptr_eq(&object, &cached));
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_locate() {
+        let path = std::env::current_exe().unwrap();
+        let bytes = std::fs::read(&path).unwrap();
+        let elf = Elf::parse(&bytes).unwrap();
+        let text = elf
+            .section_headers
+            .iter()
+            .find(|header| elf.shdr_strtab.get_at(header.sh_name) == Some(".text"))
+            .unwrap();
+        let expected = Location {
+            static_address: text.sh_addr + 0x10,
+            file_offset: Some(text.sh_offset + 0x10),
+            section: Some(".text".to_string()),
+        };
+        let object = ObjectInfo::from_elf(path, &elf);
+        let bias = 0x5555_5555_4000;
+
+        let offset = text.sh_offset + 0x10;
+        assert_eq!(locate(&object, bias, offset, AddressKind::FileOffset).unwrap(), expected);
+        let runtime = text.sh_addr + 0x10 + bias;
+        assert_eq!(locate(&object, bias, runtime, AddressKind::Runtime).unwrap(), expected);
+        assert!(locate(&object, bias, text.sh_addr, AddressKind::Runtime).is_none());
+    }
+}
@@ -0,0 +1,188 @@
+use std::path::PathBuf;
+use std::time::Duration;
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use super::GDBManager;
+use crate::error::AppResult;
+
+/// A primitive operation of a debug plan
+#[derive(Debug, Clone, Deserialize, JsonSchema)]
+#[serde(tag = "op", rename_all = "snake_case", deny_unknown_fields)]
+pub enum PlanStep {
+    /// Set a breakpoint at a line of a source file
+    SetBreakpoint { file: PathBuf, line: usize },
+    /// Delete breakpoints by number
+    DeleteBreakpoints { breakpoints: Vec<String> },
+    /// List the breakpoints
+    Breakpoints,
+    /// Start the program, follow with wait_for_stop
+    Run,
+    /// Continue the program, follow with wait_for_stop
+    Continue,
+    /// Step into the next line and wait until it is reached
+    Step,
+    /// Step over the next line and wait until it is reached
+    Next,
+    /// Interrupt the running program
+    Interrupt,
+    /// Wait until the program stops after the last run or continue
+    WaitForStop {
+        /// Defaults to the command timeout
+        timeout_secs: Option<u64>,
+    },
+    /// Capture the backtrace
+    Backtrace,
+    /// Capture the local variables of a frame, the current frame by default
+    Locals { frame_id: Option<usize> },
+    /// Capture registers, all by default
+    Registers { reg_list: Option<Vec<String>> },
+    /// Read memory at an address expression
+    ReadMemory { address: String, count: usize, offset: Option<isize> },
+    /// Evaluate an expression in the selected frame
+    Evaluate { expression: String },
+}
+
+impl PlanStep {
+    pub(super) fn name(&self) -> &'static str {
+        match self {
+            PlanStep::SetBreakpoint { .. } => "set_breakpoint",
+            PlanStep::DeleteBreakpoints { .. } => "delete_breakpoints",
+            PlanStep::Breakpoints => "breakpoints",
+            PlanStep::Run => "run",
+            PlanStep::Continue => "continue",
+            PlanStep::Step => "step",
+            PlanStep::Next => "next",
+            PlanStep::Interrupt => "interrupt",
+            PlanStep::WaitForStop { .. } => "wait_for_stop",
+            PlanStep::Backtrace => "backtrace",
+            PlanStep::Locals { .. } => "locals",
+            PlanStep::Registers { .. } => "registers",
+            PlanStep::ReadMemory { .. } => "read_memory",
+            PlanStep::Evaluate { .. } => "evaluate",
+        }
+    }
+
+    /// Whether the step lets the program run
+    pub(super) fn resumes(&self) -> bool {
+        matches!(self, PlanStep::Run | PlanStep::Continue | PlanStep::Step | PlanStep::Next)
+    }
+}
+
+/// Outcome of a plan step
+#[derive(Debug, Clone, Serialize)]
+pub struct StepResult {
+    pub op: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub result: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl GDBManager {
+    /// Execute the steps in order, the plan ends at the first failed step
+    /// unless `keep_going` is set
+    pub async fn run_debug_plan(
+        &self,
+        session_id: &str,
+        steps: Vec<PlanStep>,
+        keep_going: bool,
+    ) -> AppResult<Vec<StepResult>> {
+        let mut results = Vec::with_capacity(steps.len());
+        // stops seen before the last resume, wait_for_stop waits for a newer one
+        let mut resumed_at = self.stop_count(session_id).await?;
+
+        for step in steps {
+            let op = step.name();
+            let result = self.run_plan_step(session_id, step, &mut resumed_at).await;
+            let failed = result.is_err();
+            results.push(match result {
+                Ok(result) => StepResult { op, result: Some(result), error: None },
+                Err(e) => StepResult { op, result: None, error: Some(e.to_string()) },
+            });
+            if failed && !keep_going {
+                break;
+            }
+        }
+        Ok(results)
+    }
+
+    pub(super) async fn run_plan_step(
+        &self,
+        session_id: &str,
+        step: PlanStep,
+        resumed_at: &mut u64,
+    ) -> AppResult<Value> {
+        let timeout = Duration::from_secs(self.config.command_timeout);
+        Ok(match step {
+            PlanStep::SetBreakpoint { file, line } => {
+                serde_json::to_value(self.set_breakpoint(session_id, &file, line).await?)?
+            }
+            PlanStep::DeleteBreakpoints { breakpoints } => {
+                self.delete_breakpoint(session_id, breakpoints).await?;
+                Value::Null
+            }
+            PlanStep::Breakpoints => serde_json::to_value(self.get_breakpoints(session_id).await?)?,
+            PlanStep::Run => {
+                *resumed_at = self.stop_count(session_id).await?;
+                Value::String(self.start_debugging(session_id).await?)
+            }
+            PlanStep::Continue => {
+                *resumed_at = self.stop_count(session_id).await?;
+                Value::String(self.continue_execution(session_id).await?)
+            }
+            PlanStep::Step => {
+                *resumed_at = self.stop_count(session_id).await?;
+                self.step_execution(session_id).await?;
+                self.wait_stopped(session_id, *resumed_at, timeout).await?
+            }
+            PlanStep::Next => {
+                *resumed_at = self.stop_count(session_id).await?;
+                self.next_execution(session_id).await?;
+                self.wait_stopped(session_id, *resumed_at, timeout).await?
+            }
+            PlanStep::Interrupt => Value::String(self.stop_debugging(session_id).await?),
+            PlanStep::WaitForStop { timeout_secs } => {
+                let timeout = timeout_secs.map_or(timeout, Duration::from_secs);
+                self.wait_stopped(session_id, *resumed_at, timeout).await?
+            }
+            PlanStep::Backtrace => serde_json::to_value(self.get_stack_frames(session_id).await?)?,
+            PlanStep::Locals { frame_id } => {
+                serde_json::to_value(self.get_local_variables(session_id, frame_id, None).await?)?
+            }
+            PlanStep::Registers { reg_list } => {
+                serde_json::to_value(self.get_registers(session_id, reg_list, None).await?)?
+            }
+            PlanStep::ReadMemory { address, count, offset } => {
+                serde_json::to_value(self.read_memory(session_id, offset, address, count).await?)?
+            }
+            PlanStep::Evaluate { expression } => {
+                Value::String(self.evaluate_expression(session_id, &expression).await?)
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_plan() {
+        let steps: Vec<PlanStep> = serde_json::from_str(
+            r#"[
+                {"op": "set_breakpoint", "file": "main.c", "line": 12},
+                {"op": "run"},
+                {"op": "wait_for_stop", "timeout_secs": 5},
+                {"op": "locals"}
+            ]"#,
+        )
+        .unwrap();
+        assert_eq!(steps.len(), 4);
+        assert!(matches!(steps[2], PlanStep::WaitForStop { timeout_secs: Some(5) }));
+        assert!(matches!(steps[3], PlanStep::Locals { frame_id: None }));
+        assert!(serde_json::from_str::<PlanStep>(r#"{"op": "jump"}"#).is_err());
+    }
+}
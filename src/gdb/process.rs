@@ -0,0 +1,203 @@
+//! The process debugged in a session
+
+use std::fs;
+use std::path::Path;
+
+use serde::Serialize;
+use serde_json::Value;
+
+use super::GDBManager;
+use crate::error::{AppError, AppResult};
+use crate::mi::commands::MiCommand;
+use crate::models::{MemoryMapping, parse_memory_mappings_new, parse_memory_mappings_old};
+
+/// The debugged process, from `info proc`
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ProcessInfo {
+    pub pid: u32,
+    pub cmdline: Option<String>,
+    pub cwd: Option<String>,
+    pub exe: Option<String>,
+    /// Whether the process runs on this host, then its status is read from
+    /// `/proc`
+    pub local: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub status: Option<ProcessStatus>,
+}
+
+/// Resource usage of a local process, from `/proc/<pid>`
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ProcessStatus {
+    /// e.g. `t (tracing stop)`
+    pub state: Option<String>,
+    pub ppid: Option<u32>,
+    pub threads: Option<u32>,
+    pub vm_size_kb: Option<u64>,
+    pub vm_rss_kb: Option<u64>,
+    pub vm_peak_kb: Option<u64>,
+    pub open_files: Option<usize>,
+}
+
+/// A process GDB could attach to
+#[derive(Debug, Clone, Serialize)]
+pub struct AvailableProcess {
+    pub pid: u32,
+    pub user: Option<String>,
+    /// Command line
+    pub command: Option<String>,
+    /// Cores the process runs on
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cores: Option<Value>,
+}
+
+impl GDBManager {
+    /// Get the PID, command line and working directory of the debugged
+    /// process, and its resource usage when it runs on this host
+    pub async fn get_process_info(&self, session_id: &str) -> AppResult<ProcessInfo> {
+        let output = self.capture_console(session_id, "info proc").await?;
+        let mut info = parse_info_proc(&output).ok_or_else(|| {
+            AppError::NotFound(format!("No process information: {}", output.trim()))
+        })?;
+
+        // a PID of a remote target may be another process here
+        let proc_dir = Path::new("/proc").join(info.pid.to_string());
+        let exe = fs::read_link(proc_dir.join("exe")).ok();
+        info.local = match (&exe, &info.exe) {
+            (Some(exe), Some(expected)) => exe == Path::new(expected),
+            _ => false,
+        };
+        if info.local {
+            info.status = Some(read_status(&proc_dir));
+        }
+        Ok(info)
+    }
+
+    /// Get the memory mappings of the debugged process
+    pub async fn memory_mappings(&self, session_id: &str) -> AppResult<Vec<MemoryMapping>> {
+        let output = self.capture_console(session_id, "info proc mappings").await?;
+        // the table starts with its header, the permissions column was added
+        // in GDB 12
+        let Some(start) = output.find("Start Addr") else {
+            return Ok(vec![]);
+        };
+        let table = &output[start..];
+        let header = table.lines().next().unwrap_or_default();
+        Ok(if header.contains("Perms") {
+            parse_memory_mappings_new(table)
+        } else {
+            parse_memory_mappings_old(table)
+        })
+    }
+
+    /// List the processes of the target GDB could attach to, whose command
+    /// line contains `name` if given
+    pub async fn list_processes(
+        &self,
+        session_id: &str,
+        name: Option<&str>,
+    ) -> AppResult<Vec<AvailableProcess>> {
+        let command = MiCommand::list_thread_groups(true, &[]);
+        let response = self.send_command_with_timeout(session_id, &command).await?;
+        let groups = response
+            .results
+            .get("groups")
+            .and_then(|groups| groups.as_array())
+            .ok_or(AppError::NotFound("expect groups".to_string()))?;
+
+        let name = name.map(|name| name.to_lowercase());
+        Ok(groups
+            .iter()
+            .filter(|group| group.get("type").and_then(|kind| kind.as_str()) == Some("process"))
+            .filter_map(|group| {
+                let field =
+                    |key| group.get(key).and_then(|value| value.as_str()).map(str::to_string);
+                Some(AvailableProcess {
+                    pid: field("id")?.parse().ok()?,
+                    user: field("user"),
+                    command: field("description"),
+                    cores: group.get("cores").cloned(),
+                })
+            })
+            .filter(|process| match (&name, &process.command) {
+                (Some(name), Some(command)) => command.to_lowercase().contains(name),
+                (Some(_), None) => false,
+                (None, _) => true,
+            })
+            .collect())
+    }
+}
+
+/// Parse the output of `info proc`:
+///
+/// ```text
+/// process 1234
+/// cmdline = '/usr/bin/foo --bar'
+/// cwd = '/home/user'
+/// exe = '/usr/bin/foo'
+/// ```
+fn parse_info_proc(output: &str) -> Option<ProcessInfo> {
+    let mut info = None;
+    for line in output.lines() {
+        if let Some(pid) = line.strip_prefix("process ") {
+            info = Some(ProcessInfo { pid: pid.trim().parse().ok()?, ..Default::default() });
+            continue;
+        }
+        let (Some(info), Some((key, value))) = (info.as_mut(), line.split_once(" = ")) else {
+            continue;
+        };
+        let value = value.trim().trim_matches('\'').to_string();
+        match key.trim() {
+            "cmdline" => info.cmdline = Some(value),
+            "cwd" => info.cwd = Some(value),
+            "exe" => info.exe = Some(value),
+            _ => {}
+        }
+    }
+    info
+}
+
+fn read_status(proc_dir: &Path) -> ProcessStatus {
+    let mut status = ProcessStatus {
+        open_files: fs::read_dir(proc_dir.join("fd")).ok().map(|entries| entries.count()),
+        ..Default::default()
+    };
+    let Ok(content) = fs::read_to_string(proc_dir.join("status")) else {
+        return status;
+    };
+    for line in content.lines() {
+        let Some((key, value)) = line.split_once(':') else {
+            continue;
+        };
+        let value = value.trim();
+        // sizes are given as `1234 kB`
+        let size = || value.trim_end_matches("kB").trim().parse().ok();
+        match key {
+            "State" => status.state = Some(value.to_string()),
+            "PPid" => status.ppid = value.parse().ok(),
+            "Threads" => status.threads = value.parse().ok(),
+            "VmSize" => status.vm_size_kb = size(),
+            "VmRSS" => status.vm_rss_kb = size(),
+            "VmPeak" => status.vm_peak_kb = size(),
+            _ => {}
+        }
+    }
+    status
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_info_proc() {
+        let output = "process 4242\ncmdline = '/tmp/a.out one two'\ncwd = '/tmp'\n\
+                      exe = '/tmp/a.out'\n";
+        let info = parse_info_proc(output).unwrap();
+        assert_eq!(info.pid, 4242);
+        assert_eq!(info.cmdline.as_deref(), Some("/tmp/a.out one two"));
+        assert_eq!(info.cwd.as_deref(), Some("/tmp"));
+        assert_eq!(info.exe.as_deref(), Some("/tmp/a.out"));
+
+        assert!(parse_info_proc("No current process: you must name one.\n").is_none());
+    }
+}
@@ -0,0 +1,160 @@
+use std::collections::HashMap;
+use std::time::Duration;
+
+use serde::Serialize;
+use serde_json::Value;
+use tokio::time::Instant;
+
+use super::{GDBManager, Resumed};
+use crate::error::AppResult;
+use crate::mi::commands::MiCommand;
+use crate::models::GDBSessionStatus;
+
+/// Most stacks returned by `continue_and_sample`
+const MAX_STACKS: usize = 50;
+
+/// Where a program spent its time, from backtraces sampled at intervals
+#[derive(Debug, Clone, Serialize)]
+pub struct Profile {
+    pub samples: usize,
+    /// Why sampling ended: timeout, exited or the stop reason
+    pub ended: String,
+    /// Functions by samples spent in them or in their callees
+    pub functions: Vec<FunctionSamples>,
+    /// The most sampled stacks, outermost function first separated by `;`,
+    /// as in the folded input of flame graph tools
+    pub stacks: Vec<StackSamples>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct FunctionSamples {
+    pub function: String,
+    /// Samples where it was the innermost frame
+    pub self_samples: usize,
+    /// Samples where it was on the stack
+    pub total_samples: usize,
+    /// `total_samples` in percent of all samples
+    pub percent: f64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct StackSamples {
+    pub stack: String,
+    pub samples: usize,
+}
+
+impl GDBManager {
+    /// Run or continue the program for `duration`, interrupting it every
+    /// `interval` to sample the `depth` innermost frames. The program is
+    /// left running unless it stopped on its own, e.g. at a breakpoint
+    pub async fn continue_and_sample(
+        &self,
+        session_id: &str,
+        duration: Duration,
+        interval: Duration,
+        depth: usize,
+    ) -> AppResult<Profile> {
+        let deadline = Instant::now() + duration;
+        let mut stacks = Vec::<Vec<String>>::new();
+
+        let status = self.get_session(session_id).await?.status;
+        if status == GDBSessionStatus::Running {
+            let stops = self.stop_count(session_id).await?;
+            self.stop_debugging(session_id).await?;
+            let timeout = Duration::from_secs(self.config.command_timeout);
+            self.wait_stopped(session_id, stops, timeout).await?;
+        }
+        let mut command = match status {
+            GDBSessionStatus::Created => MiCommand::exec_run(),
+            _ => MiCommand::exec_continue(),
+        };
+        let ended = loop {
+            if let Resumed::Stopped(stopped) =
+                self.resume_and_wait(session_id, &command, interval).await?
+            {
+                let reason = stopped.get("reason").and_then(Value::as_str).unwrap_or_default();
+                break if reason.starts_with("exited") { "exited" } else { reason }.to_string();
+            }
+            let frames = self.get_stack_frames(session_id).await?;
+            // outermost first
+            stacks.push(frames.into_iter().take(depth).rev().map(|frame| frame.function).collect());
+            if Instant::now() >= deadline {
+                self.continue_execution(session_id).await?;
+                break "timeout".to_string();
+            }
+            command = MiCommand::exec_continue();
+        };
+        Ok(summarize(stacks, ended))
+    }
+}
+
+fn summarize(stacks: Vec<Vec<String>>, ended: String) -> Profile {
+    let samples = stacks.len();
+    let mut functions = HashMap::<String, (usize, usize)>::new();
+    let mut folded = HashMap::<String, usize>::new();
+    for stack in &stacks {
+        if let Some(innermost) = stack.last() {
+            functions.entry(innermost.clone()).or_default().0 += 1;
+        }
+        let mut seen = stack.clone();
+        seen.sort();
+        seen.dedup();
+        // recursive functions count once per sample
+        for function in seen {
+            functions.entry(function).or_default().1 += 1;
+        }
+        *folded.entry(stack.join(";")).or_default() += 1;
+    }
+
+    let mut functions = functions
+        .into_iter()
+        .map(|(function, (self_samples, total_samples))| FunctionSamples {
+            function,
+            self_samples,
+            total_samples,
+            percent: total_samples as f64 * 100.0 / samples.max(1) as f64,
+        })
+        .collect::<Vec<_>>();
+    functions.sort_by(|a, b| {
+        (b.total_samples, b.self_samples, &a.function).cmp(&(
+            a.total_samples,
+            a.self_samples,
+            &b.function,
+        ))
+    });
+    let mut stacks = folded
+        .into_iter()
+        .map(|(stack, samples)| StackSamples { stack, samples })
+        .collect::<Vec<_>>();
+    stacks.sort_by(|a, b| b.samples.cmp(&a.samples).then_with(|| a.stack.cmp(&b.stack)));
+    stacks.truncate(MAX_STACKS);
+    Profile { samples, ended, functions, stacks }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_summarize() {
+        let stack = |functions: &[&str]| functions.iter().map(|f| f.to_string()).collect();
+        let profile = summarize(
+            vec![
+                stack(&["main", "work", "hash"]),
+                stack(&["main", "work", "hash"]),
+                stack(&["main", "work"]),
+                stack(&["main", "fib", "fib"]),
+            ],
+            "timeout".to_string(),
+        );
+        assert_eq!(profile.samples, 4);
+        assert_eq!(profile.functions[0].function, "main");
+        assert_eq!(profile.functions[0].percent, 100.0);
+        assert_eq!(profile.functions[1].function, "work");
+        assert_eq!((profile.functions[1].self_samples, profile.functions[1].total_samples), (1, 3));
+        let fib = profile.functions.iter().find(|f| f.function == "fib").unwrap();
+        assert_eq!((fib.self_samples, fib.total_samples), (1, 1));
+        assert_eq!(profile.stacks[0].stack, "main;work;hash");
+        assert_eq!(profile.stacks[0].samples, 2);
+    }
+}
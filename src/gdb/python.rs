@@ -0,0 +1,43 @@
+//! Python scripts run by the GDB embedded interpreter
+
+use super::GDBManager;
+use crate::error::AppResult;
+
+impl GDBManager {
+    /// Run Python code in GDB and return what it printed. The code runs in
+    /// the globals of `__main__`, shared with the scripts sourced in GDB like
+    /// GEF or pwndbg, and an uncaught exception prints its traceback
+    pub async fn run_python(&self, session_id: &str, code: &str) -> AppResult<String> {
+        self.capture_console(session_id, &python_command(code)).await
+    }
+}
+
+/// A Python string literal of `text`, a JSON string is one
+fn python_literal(text: &str) -> String {
+    serde_json::Value::from(text).to_string()
+}
+
+/// A one line `python` command running `code`, the multi line form of the
+/// command can not be sent through MI
+fn python_command(code: &str) -> String {
+    let wrapper = format!(
+        "try:\n    exec(compile({}, '<run_python>', 'exec'), globals())\nexcept Exception:\n    \
+         import sys, traceback\n    traceback.print_exc(file=sys.stdout)\n",
+        python_literal(code)
+    );
+    format!("python exec({})", python_literal(&wrapper))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_python_command() {
+        assert_eq!(python_literal("print(\"a\\b\")\n"), r#""print(\"a\\b\")\n""#);
+        let command = python_command("x = 1\nprint(x)");
+        assert!(command.starts_with("python exec(\"try:\\n    exec(compile("));
+        assert!(!command.contains('\n'));
+        assert!(command.contains(r#"\"x = 1\\nprint(x)\""#));
+    }
+}
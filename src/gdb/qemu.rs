@@ -0,0 +1,143 @@
+//! Sessions debugging a virtual machine through the gdbstub of QEMU
+
+use std::path::PathBuf;
+
+use tokio::process::Command;
+
+use super::remote::spawn_server;
+use super::{GDBManager, discovery};
+use crate::error::{AppError, AppResult};
+
+/// Port of the gdbstub enabled by `-s`
+const DEFAULT_PORT: u16 = 1234;
+
+/// How to launch QEMU
+#[derive(Debug, Clone, Default)]
+pub struct QemuOptions {
+    /// QEMU executable, `qemu-system-<arch>` of the kernel by default
+    pub qemu_path: Option<PathBuf>,
+    /// Machine type, e.g. `virt`
+    pub machine: Option<String>,
+    pub cpu: Option<String>,
+    /// RAM size, e.g. `512M`
+    pub memory: Option<String>,
+    /// Kernel image to boot
+    pub kernel: Option<PathBuf>,
+    /// Kernel command line
+    pub append: Option<String>,
+    /// Extra arguments passed as is
+    pub args: Vec<String>,
+    /// Port of the gdbstub, 1234 like `-s` by default
+    pub port: Option<u16>,
+}
+
+impl GDBManager {
+    /// Launch QEMU frozen at startup with its gdbstub enabled, the equivalent
+    /// of `-s -S`, and create a session connected to it. QEMU is killed along
+    /// with the session. The symbols are read from `program`, the kernel by
+    /// default
+    pub async fn create_qemu_session(
+        &self,
+        program: Option<PathBuf>,
+        gdb_path: Option<PathBuf>,
+        options: QemuOptions,
+    ) -> AppResult<String> {
+        let port = options.port.unwrap_or(DEFAULT_PORT);
+        let program = program.or_else(|| options.kernel.clone());
+        let qemu = spawn_server(qemu_command(&options, port)?)?;
+        self.create_remote_session(
+            program,
+            gdb_path,
+            Some(qemu),
+            "remote",
+            &format!("localhost:{}", port),
+        )
+        .await
+    }
+}
+
+fn qemu_command(options: &QemuOptions, port: u16) -> AppResult<Command> {
+    let arch = options.kernel.as_deref().and_then(discovery::elf_arch);
+    let path = match (&options.qemu_path, arch) {
+        (Some(path), _) => path.clone(),
+        (None, Some(arch)) => PathBuf::from(format!("qemu-system-{}", qemu_arch(&arch))),
+        (None, None) => {
+            return Err(AppError::InvalidArgument(
+                "qemu_path is required unless kernel is an ELF file of a known architecture".into(),
+            ));
+        }
+    };
+
+    let mut command = Command::new(path);
+    if let Some(machine) = &options.machine {
+        command.args(["-machine", machine]);
+    }
+    if let Some(cpu) = &options.cpu {
+        command.args(["-cpu", cpu]);
+    }
+    if let Some(memory) = &options.memory {
+        command.args(["-m", memory]);
+    }
+    if let Some(kernel) = &options.kernel {
+        command.arg("-kernel").arg(kernel);
+    }
+    if let Some(append) = &options.append {
+        command.args(["-append", append]);
+    }
+    // the serial console ends up in the server log
+    if !options.args.iter().any(|arg| arg == "-nographic" || arg == "-display") {
+        command.arg("-nographic");
+    }
+    command.args(&options.args);
+    command.args(["-gdb", &format!("tcp::{}", port), "-S"]);
+    Ok(command)
+}
+
+/// QEMU system emulator suffix of an architecture named after
+/// `std::env::consts::ARCH`
+fn qemu_arch(arch: &str) -> &str {
+    match arch {
+        "x86" => "i386",
+        "powerpc" => "ppc",
+        "powerpc64" => "ppc64",
+        arch => arch,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_qemu_command() {
+        let options = QemuOptions {
+            qemu_path: Some(PathBuf::from("qemu-system-aarch64")),
+            machine: Some("virt".into()),
+            memory: Some("512M".into()),
+            args: vec!["-smp".into(), "2".into()],
+            ..Default::default()
+        };
+        let command = qemu_command(&options, 1235).unwrap();
+        assert_eq!(command.as_std().get_program(), "qemu-system-aarch64");
+        assert_eq!(
+            command.as_std().get_args().collect::<Vec<_>>(),
+            [
+                "-machine",
+                "virt",
+                "-m",
+                "512M",
+                "-nographic",
+                "-smp",
+                "2",
+                "-gdb",
+                "tcp::1235",
+                "-S"
+            ]
+        );
+
+        // the executable is named after the architecture of the kernel
+        assert!(qemu_command(&QemuOptions::default(), DEFAULT_PORT).is_err());
+        assert_eq!(qemu_arch("x86"), "i386");
+        assert_eq!(qemu_arch("riscv64"), "riscv64");
+    }
+}
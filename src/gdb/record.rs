@@ -0,0 +1,298 @@
+//! Execution recording, `record btrace` with Intel PT or BTS, or `record full`
+
+use std::str::FromStr;
+
+use serde::Serialize;
+
+use super::GDBManager;
+use crate::error::{AppError, AppResult};
+
+/// Instructions or function calls returned when no range is given
+const DEFAULT_HISTORY: u64 = 50;
+
+/// How the execution is recorded
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RecordMethod {
+    /// Branch trace in the format the CPU supports, Intel PT if possible
+    Btrace,
+    /// Branch trace with Intel Processor Trace
+    Pt,
+    /// Branch trace with Branch Trace Store
+    Bts,
+    /// Every instruction is recorded by GDB, slow but supported everywhere and
+    /// able to reverse execute
+    Full,
+}
+
+impl FromStr for RecordMethod {
+    type Err = AppError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "btrace" => Ok(RecordMethod::Btrace),
+            "pt" => Ok(RecordMethod::Pt),
+            "bts" => Ok(RecordMethod::Bts),
+            "full" => Ok(RecordMethod::Full),
+            _ => Err(AppError::InvalidArgument(format!(
+                "unknown record method {}, expecting btrace, pt, bts or full",
+                s
+            ))),
+        }
+    }
+}
+
+impl RecordMethod {
+    fn command(self) -> &'static str {
+        match self {
+            RecordMethod::Btrace => "record btrace",
+            RecordMethod::Pt => "record btrace pt",
+            RecordMethod::Bts => "record btrace bts",
+            RecordMethod::Full => "record full",
+        }
+    }
+}
+
+/// What is being recorded, from `info record`
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct RecordStatus {
+    /// e.g. `record-btrace`, None if nothing is recorded
+    pub target: Option<String>,
+    /// e.g. `Intel Processor Trace`
+    pub format: Option<String>,
+    pub instructions: Option<u64>,
+    /// Not known for `record full`
+    pub functions: Option<u64>,
+}
+
+/// A function of `record function-call-history`, one per contiguous segment
+/// of execution in it
+#[derive(Debug, Clone, Serialize)]
+pub struct FunctionSegment {
+    pub index: u64,
+    /// Call depth relative to the outermost recorded function
+    pub level: usize,
+    /// `??` when unknown
+    pub function: String,
+    pub first_instruction: Option<u64>,
+    pub last_instruction: Option<u64>,
+    pub file: Option<String>,
+    pub first_line: Option<u32>,
+    pub last_line: Option<u32>,
+}
+
+/// An instruction of `record instruction-history`
+#[derive(Debug, Clone, Serialize)]
+pub struct RecordedInstruction {
+    pub index: u64,
+    pub address: String,
+    /// e.g. `main+4`
+    pub symbol: Option<String>,
+    pub instruction: String,
+}
+
+impl GDBManager {
+    /// Start recording the execution of the program from now on
+    pub async fn start_recording(
+        &self,
+        session_id: &str,
+        method: RecordMethod,
+    ) -> AppResult<RecordStatus> {
+        self.capture_console(session_id, method.command()).await?;
+        let status = self.record_status(session_id).await?;
+        self.set_recording(session_id, status.target.is_some()).await?;
+        Ok(status)
+    }
+
+    /// Stop recording and discard the recorded execution
+    pub async fn stop_recording(&self, session_id: &str) -> AppResult<()> {
+        self.capture_console(session_id, "record stop").await?;
+        self.set_recording(session_id, false).await
+    }
+
+    async fn set_recording(&self, session_id: &str, recording: bool) -> AppResult<()> {
+        let mut sessions = self.sessions.lock().await;
+        let handle = sessions
+            .get_mut(session_id)
+            .ok_or_else(|| AppError::NotFound(format!("Session {} does not exist", session_id)))?;
+        handle.recording = recording;
+        drop(sessions);
+        self.notify_sessions_changed();
+        Ok(())
+    }
+
+    /// What is being recorded
+    pub async fn record_status(&self, session_id: &str) -> AppResult<RecordStatus> {
+        let output = self.capture_console(session_id, "info record").await?;
+        Ok(parse_info_record(&output))
+    }
+
+    /// The recorded functions numbered from `from` to `to`, both included,
+    /// the last `DEFAULT_HISTORY` if neither is given
+    pub async fn function_call_history(
+        &self,
+        session_id: &str,
+        from: Option<u64>,
+        to: Option<u64>,
+    ) -> AppResult<Vec<FunctionSegment>> {
+        let status = self.record_status(session_id).await?;
+        let range = history_range(from, to, status.functions)?;
+        let command = format!("record function-call-history /ilc {}", range);
+        let output = self.capture_console(session_id, &command).await?;
+        Ok(output.lines().filter_map(parse_function_segment).collect())
+    }
+
+    /// The recorded instructions numbered from `from` to `to`, both included,
+    /// the last `DEFAULT_HISTORY` if neither is given
+    pub async fn instruction_history(
+        &self,
+        session_id: &str,
+        from: Option<u64>,
+        to: Option<u64>,
+    ) -> AppResult<Vec<RecordedInstruction>> {
+        let status = self.record_status(session_id).await?;
+        let range = history_range(from, to, status.instructions)?;
+        let command = format!("record instruction-history {}", range);
+        let output = self.capture_console(session_id, &command).await?;
+        Ok(output.lines().filter_map(parse_recorded_instruction).collect())
+    }
+}
+
+/// Range argument of the history commands
+fn history_range(from: Option<u64>, to: Option<u64>, total: Option<u64>) -> AppResult<String> {
+    let last = || {
+        total.filter(|total| *total > 0).ok_or_else(|| {
+            AppError::NotFound("Nothing is recorded, start recording first".to_string())
+        })
+    };
+    let (from, to) = match (from, to) {
+        (Some(from), Some(to)) => (from, to),
+        (Some(from), None) => (from, last()?),
+        (None, Some(to)) => (to.saturating_sub(DEFAULT_HISTORY - 1).max(1), to),
+        (None, None) => {
+            let last = last()?;
+            (last.saturating_sub(DEFAULT_HISTORY - 1).max(1), last)
+        }
+    };
+    if from > to {
+        return Err(AppError::InvalidArgument(format!("empty range {},{}", from, to)));
+    }
+    Ok(format!("{},{}", from, to))
+}
+
+/// Parse the output of `info record`:
+///
+/// ```text
+/// Active record target: record-btrace
+/// Recording format: Intel Processor Trace.
+/// Buffer size: 16kB.
+/// Recorded 840 instructions in 24 functions (0 gaps) for thread 1 (process 1234).
+/// ```
+fn parse_info_record(output: &str) -> RecordStatus {
+    let mut status = RecordStatus::default();
+    for line in output.lines() {
+        let number = |prefix: &str| {
+            line.split(prefix).nth(1)?.split_whitespace().next()?.trim_end_matches('.').parse().ok()
+        };
+        if let Some(target) = line.strip_prefix("Active record target: ") {
+            status.target = Some(target.trim().to_string());
+        } else if let Some(format) = line.strip_prefix("Recording format: ") {
+            status.format = Some(format.trim().trim_end_matches('.').to_string());
+        } else if line.starts_with("Recorded ") {
+            status.instructions = number("Recorded ");
+            status.functions = number(" instructions in ");
+        } else if line.starts_with("Log contains ") {
+            // record full
+            status.instructions = number("Log contains ");
+        }
+    }
+    status
+}
+
+/// Parse a line of `record function-call-history /ilc`, tab separated:
+/// `12`, the function indented by 2 spaces per level, `inst 40,52`,
+/// `at a.c:3,5`
+fn parse_function_segment(line: &str) -> Option<FunctionSegment> {
+    let mut fields = line.split('\t');
+    let index = fields.next()?.trim().parse().ok()?;
+    let function = fields.next()?;
+    let name = function.trim_start();
+    let mut segment = FunctionSegment {
+        index,
+        level: (function.len() - name.len()) / 2,
+        function: name.trim_end().to_string(),
+        first_instruction: None,
+        last_instruction: None,
+        file: None,
+        first_line: None,
+        last_line: None,
+    };
+    for field in fields {
+        if let Some(range) = field.trim().strip_prefix("inst ") {
+            let (first, last) = range.split_once(',').unwrap_or((range, range));
+            segment.first_instruction = first.trim().parse().ok();
+            segment.last_instruction = last.trim().parse().ok();
+        } else if let Some(position) = field.trim().strip_prefix("at ") {
+            let (file, lines) = position.rsplit_once(':')?;
+            let (first, last) = lines.split_once(',').unwrap_or((lines, lines));
+            segment.file = Some(file.to_string());
+            segment.first_line = first.trim().parse().ok();
+            segment.last_line = last.trim().parse().ok();
+        }
+    }
+    Some(segment)
+}
+
+/// Parse a line of `record instruction-history`:
+/// `12\t   0x0000000000401126 <main+4>:\tmov    $0x0,%eax`
+fn parse_recorded_instruction(line: &str) -> Option<RecordedInstruction> {
+    let (index, rest) = line.split_once('\t')?;
+    let index = index.trim().parse().ok()?;
+    let (location, instruction) = rest.split_once(":\t")?;
+    let location = location.trim().trim_start_matches("=>").trim();
+    let (address, symbol) = match location.split_once(' ') {
+        Some((address, symbol)) => (address, Some(symbol.trim_matches(['<', '>']).to_string())),
+        None => (location, None),
+    };
+    Some(RecordedInstruction {
+        index,
+        address: address.to_string(),
+        symbol,
+        instruction: instruction.trim().to_string(),
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_history() {
+        let status = parse_info_record(
+            "Active record target: record-btrace\nRecording format: Intel Processor Trace.\n\
+             Buffer size: 16kB.\nRecorded 840 instructions in 24 functions (0 gaps) for thread \
+             1 (process 1234).\n",
+        );
+        assert_eq!(status.format.as_deref(), Some("Intel Processor Trace"));
+        assert_eq!(status.instructions, Some(840));
+        assert_eq!(status.functions, Some(24));
+        assert_eq!(history_range(None, None, status.functions).unwrap(), "1,24");
+        assert_eq!(history_range(None, None, status.instructions).unwrap(), "791,840");
+        assert!(history_range(None, None, None).is_err());
+
+        let segment = parse_function_segment("2\t  foo\tinst 6,12\tat /tmp/a.c:3,5").unwrap();
+        assert_eq!(segment.level, 1);
+        assert_eq!(segment.function, "foo");
+        assert_eq!(segment.last_instruction, Some(12));
+        assert_eq!(segment.file.as_deref(), Some("/tmp/a.c"));
+        assert_eq!(segment.first_line, Some(3));
+        let segment = parse_function_segment("3\t??\tinst 13,13").unwrap();
+        assert_eq!((segment.level, segment.first_line), (0, None));
+
+        let instruction =
+            parse_recorded_instruction("12\t   0x0000000000401126 <main+4>:\tmov    $0x0,%eax")
+                .unwrap();
+        assert_eq!(instruction.address, "0x0000000000401126");
+        assert_eq!(instruction.symbol.as_deref(), Some("main+4"));
+        assert_eq!(instruction.instruction, "mov    $0x0,%eax");
+    }
+}
@@ -0,0 +1,254 @@
+use std::collections::{BTreeMap, HashSet};
+use std::str::FromStr;
+
+use serde::Serialize;
+
+use super::GDBManager;
+use crate::error::{AppError, AppResult};
+use crate::mi::commands::{MiCommand, RegisterFormat};
+use crate::models::RegisterValue;
+
+/// Register groups of GDB, as `info registers` takes them
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RegisterGroup {
+    General,
+    Vector,
+    Float,
+    System,
+}
+
+impl RegisterGroup {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RegisterGroup::General => "general",
+            RegisterGroup::Vector => "vector",
+            RegisterGroup::Float => "float",
+            RegisterGroup::System => "system",
+        }
+    }
+}
+
+impl FromStr for RegisterGroup {
+    type Err = AppError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "general" => Ok(RegisterGroup::General),
+            "vector" => Ok(RegisterGroup::Vector),
+            "float" => Ok(RegisterGroup::Float),
+            "system" => Ok(RegisterGroup::System),
+            _ => Err(AppError::InvalidArgument(format!(
+                "unknown register group {}, expecting general, vector, float or system",
+                s
+            ))),
+        }
+    }
+}
+
+/// Register values at a stop
+#[derive(Debug, Clone)]
+struct Snapshot {
+    /// Stop count when the snapshot was taken
+    stop: u64,
+    values: BTreeMap<usize, String>,
+}
+
+/// Register values of the last two stops of a session
+#[derive(Debug, Default)]
+pub struct RegisterTracker {
+    previous: Option<Snapshot>,
+    current: Option<Snapshot>,
+}
+
+impl RegisterTracker {
+    fn changes(&self, names: &[String]) -> Vec<RegisterChange> {
+        let (Some(previous), Some(current)) = (&self.previous, &self.current) else {
+            return vec![];
+        };
+        current
+            .values
+            .iter()
+            .filter(|(number, value)| previous.values.get(number) != Some(value))
+            .map(|(number, value)| RegisterChange {
+                number: *number,
+                name: names.get(*number).filter(|name| !name.is_empty()).cloned(),
+                old: previous.values.get(number).cloned(),
+                new: value.clone(),
+            })
+            .collect()
+    }
+}
+
+/// A register whose value differs between the last two stops
+#[derive(Debug, Clone, Serialize)]
+pub struct RegisterChange {
+    pub number: usize,
+    pub name: Option<String>,
+    pub old: Option<String>,
+    pub new: String,
+}
+
+impl GDBManager {
+    /// Registers which changed between the previous stop and the last one.
+    ///
+    /// Snapshots are taken on demand, once this was called the snapshot of
+    /// every stop is taken by `wait_for_stop`
+    pub async fn get_register_changes(&self, session_id: &str) -> AppResult<Vec<RegisterChange>> {
+        let stop = self.stop_count(session_id).await?;
+        let names = self.register_names(session_id).await?;
+        let stale = {
+            let sessions = self.sessions.lock().await;
+            let handle = sessions.get(session_id).ok_or_else(|| {
+                AppError::NotFound(format!("Session {} does not exist", session_id))
+            })?;
+            handle.registers.current.as_ref().is_none_or(|snapshot| snapshot.stop != stop)
+        };
+
+        if stale {
+            let values = self.register_values(session_id).await?;
+
+            let mut sessions = self.sessions.lock().await;
+            if let Some(handle) = sessions.get_mut(session_id) {
+                let tracker = &mut handle.registers;
+                tracker.previous = tracker.current.take();
+                tracker.current = Some(Snapshot { stop, values });
+            }
+        }
+
+        let sessions = self.sessions.lock().await;
+        Ok(sessions
+            .get(session_id)
+            .map(|handle| handle.registers.changes(&names))
+            .unwrap_or_default())
+    }
+
+    /// Register changes at the last stop if they are tracked
+    pub(super) async fn track_register_changes(
+        &self,
+        session_id: &str,
+    ) -> AppResult<Option<Vec<RegisterChange>>> {
+        let tracked = {
+            let sessions = self.sessions.lock().await;
+            sessions.get(session_id).is_some_and(|handle| handle.registers.current.is_some())
+        };
+        if !tracked {
+            return Ok(None);
+        }
+        self.get_register_changes(session_id).await.map(Some)
+    }
+
+    /// Names of all registers indexed by number, queried once per session.
+    /// Numbers without a register have an empty name
+    pub(super) async fn register_names(&self, session_id: &str) -> AppResult<Vec<String>> {
+        {
+            let sessions = self.sessions.lock().await;
+            let handle = sessions.get(session_id).ok_or_else(|| {
+                AppError::NotFound(format!("Session {} does not exist", session_id))
+            })?;
+            if !handle.register_names.is_empty() {
+                return Ok(handle.register_names.clone());
+            }
+        }
+
+        let command = MiCommand::data_list_register_names(None);
+        let response = self.send_command_with_timeout(session_id, &command).await?;
+        let names: Vec<String> = serde_json::from_value(
+            response
+                .results
+                .get("register-names")
+                .ok_or(AppError::NotFound("register-names not found".to_string()))?
+                .to_owned(),
+        )?;
+
+        if let Some(handle) = self.sessions.lock().await.get_mut(session_id) {
+            handle.register_names = names.clone();
+        }
+        Ok(names)
+    }
+
+    /// Numbers of the registers named `names`, e.g. `rip` or `$rsp`, and in
+    /// `group`, all of them if neither is given
+    pub async fn register_numbers(
+        &self,
+        session_id: &str,
+        names: Option<&[String]>,
+        group: Option<RegisterGroup>,
+    ) -> AppResult<Vec<String>> {
+        let all = self.register_names(session_id).await?;
+        let names = names.map(|names| {
+            names.iter().map(|name| name.trim_start_matches('$').to_string()).collect::<Vec<_>>()
+        });
+        if let Some(names) = &names {
+            let unknown = names.iter().filter(|name| !all.contains(name)).collect::<Vec<_>>();
+            if !unknown.is_empty() {
+                return Err(AppError::InvalidArgument(format!("unknown registers {:?}", unknown)));
+            }
+        }
+        let output = match group {
+            Some(group) => {
+                let command = format!("info registers {}", group.as_str());
+                Some(self.capture_console(session_id, &command).await?)
+            }
+            None => None,
+        };
+        let in_group = output.as_deref().map(group_members);
+
+        let numbers = all
+            .iter()
+            .enumerate()
+            .filter(|(_, name)| !name.is_empty())
+            .filter(|(_, name)| names.as_ref().is_none_or(|names| names.contains(name)))
+            .filter(|(_, name)| in_group.as_ref().is_none_or(|group| group.contains(name.as_str())))
+            .map(|(number, _)| number.to_string())
+            .collect::<Vec<_>>();
+        if numbers.is_empty() {
+            return Err(AppError::NotFound(match group {
+                Some(group) => format!("no register of the {} group matches", group.as_str()),
+                None => "no register matches".to_string(),
+            }));
+        }
+        Ok(numbers)
+    }
+
+    /// Raw values of all registers in hex
+    async fn register_values(&self, session_id: &str) -> AppResult<BTreeMap<usize, String>> {
+        let command = MiCommand::data_list_register_values(RegisterFormat::Hex, None);
+        let response = self.send_command_with_timeout(session_id, &command).await?;
+        let values: Vec<RegisterValue> = serde_json::from_value(
+            response
+                .results
+                .get("register-values")
+                .ok_or(AppError::NotFound("expect register-values".to_string()))?
+                .to_owned(),
+        )?;
+        Ok(values.into_iter().map(|register| (register.number, register.value)).collect())
+    }
+}
+
+/// Names of the registers listed by `info registers <group>`, one per line
+/// with the values of the vector registers possibly wrapped on the next ones
+fn group_members(output: &str) -> HashSet<&str> {
+    output
+        .lines()
+        .filter(|line| !line.starts_with(char::is_whitespace))
+        .filter_map(|line| line.split_whitespace().next())
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_group_members() {
+        let output = "\
+rax            0x1c                28
+rsp            0x7fffffffe3a0      0x7fffffffe3a0
+xmm0           {v8_bfloat16 = {0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0},
+  v4_float = {0x0, 0x0, 0x0, 0x0}}
+eflags         0x246               [ IF ZF PF ]
+";
+        let members = group_members(output);
+        assert_eq!(members, HashSet::from(["rax", "rsp", "xmm0", "eflags"]));
+    }
+}
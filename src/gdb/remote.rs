@@ -0,0 +1,145 @@
+//! Sessions connected to a remote target served by a GDB server or stub the
+//! session may have launched, e.g. OpenOCD or QEMU
+
+use std::path::PathBuf;
+use std::process::Stdio;
+use std::time::Duration;
+
+use tokio::io::{AsyncBufReadExt, AsyncRead, BufReader};
+use tokio::net::TcpStream;
+use tokio::process::{Child, Command};
+use tokio::time::Instant;
+use tracing::{debug, warn};
+
+use super::{GDBManager, SessionOptions};
+use crate::error::{AppError, AppResult};
+use crate::mi::commands::MiCommand;
+use crate::models::GDBSessionStatus;
+
+impl GDBManager {
+    /// Wait for the GDB server at `address`, create a session and connect it
+    /// with `-target-select`. The launched `server`, if any, is killed along
+    /// with the session
+    pub(super) async fn create_remote_session(
+        &self,
+        program: Option<PathBuf>,
+        gdb_path: Option<PathBuf>,
+        mut server: Option<Child>,
+        transport: &str,
+        address: &str,
+    ) -> AppResult<String> {
+        let timeout = Duration::from_secs(self.config.command_timeout);
+        wait_for_server(address, server.as_mut(), timeout).await?;
+
+        let session_id = self
+            .create_session(SessionOptions {
+                program: program.clone(),
+                gdb_path,
+                ..Default::default()
+            })
+            .await?;
+        {
+            let mut sessions = self.sessions.lock().await;
+            if let Some(handle) = sessions.get_mut(&session_id) {
+                handle.server = server;
+            }
+        }
+
+        if let Err(e) = self.connect_target(&session_id, transport, address).await {
+            self.abandon_session(&session_id).await;
+            return Err(e);
+        }
+
+        if let Err(e) = self.detect_arch(&session_id, program.as_deref()).await {
+            warn!("Failed to detect the target architecture: {}", e);
+        }
+        Ok(session_id)
+    }
+
+    /// Connect a session to a remote target with `-target-select`, again
+    /// when the session is restarted
+    pub(super) async fn connect_target(
+        &self,
+        session_id: &str,
+        transport: &str,
+        address: &str,
+    ) -> AppResult<()> {
+        let command = MiCommand::target_select(transport, address);
+        self.send_command_with_timeout(session_id, &command).await?;
+        // the target is halted once GDB is connected
+        let mut sessions = self.sessions.lock().await;
+        if let Some(handle) = sessions.get_mut(session_id) {
+            handle.info.status = GDBSessionStatus::Stopped;
+            handle.target = Some((transport.to_string(), address.to_string()));
+        }
+        self.notify_sessions_changed();
+        Ok(())
+    }
+
+    /// Close a session which could not be set up
+    pub(super) async fn abandon_session(&self, session_id: &str) {
+        if let Err(e) = self.close_session(session_id).await {
+            warn!("Failed to close session {}: {}", session_id, e);
+        }
+    }
+}
+
+/// Spawn a GDB server, its output goes to the server log
+pub(super) fn spawn_server(mut command: Command) -> AppResult<Child> {
+    debug!("Starting GDB server with command: {:?}", command);
+    let program = command.as_std().get_program().to_string_lossy().into_owned();
+    let mut child = command
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()
+        .map_err(|e| AppError::GDBServerError(format!("failed to start {}: {}", program, e)))?;
+    if let Some(stdout) = child.stdout.take() {
+        tokio::spawn(forward_output(stdout));
+    }
+    if let Some(stderr) = child.stderr.take() {
+        tokio::spawn(forward_output(stderr));
+    }
+    Ok(child)
+}
+
+/// Log the output of a GDB server, it has to be drained anyway
+async fn forward_output(output: impl AsyncRead + Unpin) {
+    let mut lines = BufReader::new(output).lines();
+    while let Ok(Some(line)) = lines.next_line().await {
+        debug!("GDB server: {}", line);
+    }
+}
+
+/// Wait until the GDB server accepts connections, or the launched server
+/// exits
+async fn wait_for_server(
+    address: &str,
+    mut server: Option<&mut Child>,
+    timeout: Duration,
+) -> AppResult<()> {
+    let deadline = Instant::now() + timeout;
+    loop {
+        if TcpStream::connect(address).await.is_ok() {
+            return Ok(());
+        }
+        if let Some(server) = server.as_mut()
+            && let Some(status) = server.try_wait()?
+        {
+            return Err(AppError::GDBServerError(format!(
+                "exited with {} before listening on {}, see get_server_logs",
+                status, address
+            )));
+        }
+        if Instant::now() >= deadline {
+            return Err(AppError::GDBServerError(format!("nothing listening on {}", address)));
+        }
+        tokio::time::sleep(Duration::from_millis(200)).await;
+    }
+}
+
+/// Port of a `host:port` address
+pub(super) fn address_port(address: &str) -> Option<u16> {
+    address.rsplit_once(':').and_then(|(_, port)| port.parse().ok())
+}
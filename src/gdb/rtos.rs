@@ -0,0 +1,350 @@
+//! Tasks of an RTOS running on a bare metal target, found by walking the
+//! scheduler structures of the kernel through GDB expressions
+
+use std::collections::BTreeMap;
+use std::str::FromStr;
+
+use serde::Serialize;
+
+use super::GDBManager;
+use crate::error::{AppError, AppResult};
+
+/// Bound on the tasks walked, a corrupted list must not loop forever
+const MAX_TASKS: u64 = 256;
+/// Bound on the stack bytes scanned for the high-water mark
+const MAX_STACK_SCAN: u64 = 64 * 1024;
+/// Bytes read at once when scanning a stack
+const SCAN_CHUNK: u64 = 256;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RtosKind {
+    FreeRtos,
+    Zephyr,
+}
+
+impl FromStr for RtosKind {
+    type Err = AppError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "freertos" => Ok(Self::FreeRtos),
+            "zephyr" => Ok(Self::Zephyr),
+            _ => Err(AppError::InvalidArgument(format!(
+                "unknown RTOS {}, expecting freertos or zephyr",
+                s
+            ))),
+        }
+    }
+}
+
+impl RtosKind {
+    /// Symbol and type names used to walk the tasks, by role
+    fn default_symbols(&self) -> &'static [(&'static str, &'static str)] {
+        match self {
+            RtosKind::FreeRtos => &[
+                ("current", "pxCurrentTCB"),
+                ("ready", "pxReadyTasksLists"),
+                ("delayed1", "xDelayedTaskList1"),
+                ("delayed2", "xDelayedTaskList2"),
+                ("pending", "xPendingReadyList"),
+                ("suspended", "xSuspendedTaskList"),
+                ("terminating", "xTasksWaitingTermination"),
+                ("tcb_type", "TCB_t"),
+                ("list_item_type", "ListItem_t"),
+            ],
+            RtosKind::Zephyr => &[("kernel", "_kernel"), ("thread_type", "struct k_thread")],
+        }
+    }
+
+    /// Byte the kernel fills unused stacks with
+    fn stack_fill(&self) -> u8 {
+        match self {
+            RtosKind::FreeRtos => 0xa5,
+            RtosKind::Zephyr => 0xaa,
+        }
+    }
+}
+
+/// RTOS awareness of a session
+#[derive(Debug, Clone)]
+pub struct RtosState {
+    kind: RtosKind,
+    symbols: BTreeMap<String, String>,
+    /// Tasks walked at a stop, by stop count
+    tasks: Option<(u64, Vec<RtosTask>)>,
+}
+
+impl RtosState {
+    /// `overrides` replace the default symbol names, as `role=symbol`
+    pub fn new(kind: RtosKind, overrides: &[String]) -> AppResult<Self> {
+        let mut symbols: BTreeMap<String, String> = kind
+            .default_symbols()
+            .iter()
+            .map(|(role, symbol)| (role.to_string(), symbol.to_string()))
+            .collect();
+        for entry in overrides {
+            let (role, symbol) = entry.split_once('=').ok_or_else(|| {
+                AppError::InvalidArgument(format!("expecting role=symbol, got {}", entry))
+            })?;
+            let Some(value) = symbols.get_mut(role.trim()) else {
+                let roles = symbols.keys().cloned().collect::<Vec<_>>().join(", ");
+                return Err(AppError::InvalidArgument(format!(
+                    "unknown role {}, expecting one of {}",
+                    role, roles
+                )));
+            };
+            *value = symbol.trim().to_string();
+        }
+        Ok(RtosState { kind, symbols, tasks: None })
+    }
+
+    fn symbol(&self, role: &str) -> &str {
+        self.symbols.get(role).map(|s| s.as_str()).unwrap_or_default()
+    }
+}
+
+/// A task of the RTOS
+#[derive(Debug, Clone, Serialize)]
+pub struct RtosTask {
+    pub name: Option<String>,
+    /// Address of the task control block
+    pub address: String,
+    pub priority: Option<i64>,
+    /// running, ready, blocked, suspended, deleted or prestart
+    pub state: &'static str,
+    /// Stack bytes never used so far, None if the stack is not known
+    pub stack_high_water: Option<u64>,
+}
+
+impl GDBManager {
+    /// Enable the RTOS awareness of a session, or disable it with None
+    pub async fn set_rtos(&self, session_id: &str, rtos: Option<RtosState>) -> AppResult<()> {
+        let mut sessions = self.sessions.lock().await;
+        let handle = sessions
+            .get_mut(session_id)
+            .ok_or_else(|| AppError::NotFound(format!("Session {} does not exist", session_id)))?;
+        handle.rtos = rtos;
+        Ok(())
+    }
+
+    /// Tasks of the RTOS at the last stop if the RTOS awareness is enabled,
+    /// they are walked once per stop
+    pub(super) async fn rtos_tasks(&self, session_id: &str) -> AppResult<Option<Vec<RtosTask>>> {
+        let stop = self.stop_count(session_id).await?;
+        let rtos = {
+            let sessions = self.sessions.lock().await;
+            match sessions.get(session_id).and_then(|handle| handle.rtos.clone()) {
+                Some(rtos) => rtos,
+                None => return Ok(None),
+            }
+        };
+        if let Some((walked_at, tasks)) = &rtos.tasks
+            && *walked_at == stop
+        {
+            return Ok(Some(tasks.clone()));
+        }
+
+        let tasks = match rtos.kind {
+            RtosKind::FreeRtos => self.freertos_tasks(session_id, &rtos).await?,
+            RtosKind::Zephyr => self.zephyr_tasks(session_id, &rtos).await?,
+        };
+        let mut sessions = self.sessions.lock().await;
+        if let Some(state) = sessions.get_mut(session_id).and_then(|handle| handle.rtos.as_mut()) {
+            state.tasks = Some((stop, tasks.clone()));
+        }
+        Ok(Some(tasks))
+    }
+
+    async fn freertos_tasks(&self, session_id: &str, rtos: &RtosState) -> AppResult<Vec<RtosTask>> {
+        let current = self.eval_address(session_id, rtos.symbol("current")).await.ok();
+        let ready = rtos.symbol("ready");
+        let priorities =
+            self.eval_address(session_id, &format!("sizeof({0}) / sizeof({0}[0])", ready)).await?;
+
+        let mut lists = (0..priorities)
+            .map(|priority| (format!("{}[{}]", ready, priority), "ready"))
+            .collect::<Vec<_>>();
+        for (role, state) in [
+            ("delayed1", "blocked"),
+            ("delayed2", "blocked"),
+            ("pending", "ready"),
+            ("suspended", "suspended"),
+            ("terminating", "deleted"),
+        ] {
+            lists.push((rtos.symbol(role).to_string(), state));
+        }
+
+        let item_type = rtos.symbol("list_item_type");
+        let mut tasks = vec![];
+        for (list, state) in lists {
+            // lists of features disabled in the kernel configuration do not exist
+            let Ok(count) =
+                self.eval_address(session_id, &format!("{}.uxNumberOfItems", list)).await
+            else {
+                continue;
+            };
+            let mut item =
+                self.eval_address(session_id, &format!("{}.xListEnd.pxNext", list)).await?;
+            for _ in 0..count.min(MAX_TASKS) {
+                let owner = self
+                    .eval_address(session_id, &format!("(({} *) {:#x})->pvOwner", item_type, item))
+                    .await?;
+                let state = if Some(owner) == current { "running" } else { state };
+                tasks.push(self.freertos_task(session_id, rtos, owner, state).await?);
+                item = self
+                    .eval_address(session_id, &format!("(({} *) {:#x})->pxNext", item_type, item))
+                    .await?;
+            }
+        }
+        Ok(tasks)
+    }
+
+    async fn freertos_task(
+        &self,
+        session_id: &str,
+        rtos: &RtosState,
+        address: u64,
+        state: &'static str,
+    ) -> AppResult<RtosTask> {
+        let tcb = format!("(({} *) {:#x})", rtos.symbol("tcb_type"), address);
+        let name = self.evaluate_expression(session_id, &format!("{}->pcTaskName", tcb)).await?;
+        let priority =
+            self.evaluate_expression(session_id, &format!("{}->uxPriority", tcb)).await?;
+        let stack = self.eval_address(session_id, &format!("{}->pxStack", tcb)).await?;
+        Ok(RtosTask {
+            name: parse_c_string(&name),
+            address: format!("{:#x}", address),
+            priority: parse_integer(&priority).and_then(|p| p.try_into().ok()),
+            state,
+            stack_high_water: self.unused_stack(session_id, stack, None, rtos.kind).await.ok(),
+        })
+    }
+
+    async fn zephyr_tasks(&self, session_id: &str, rtos: &RtosState) -> AppResult<Vec<RtosTask>> {
+        let kernel = rtos.symbol("kernel");
+        let current =
+            self.eval_address(session_id, &format!("{}.cpus[0].current", kernel)).await.ok();
+        let mut address = self.eval_address(session_id, &format!("{}.threads", kernel)).await?;
+
+        let mut tasks = vec![];
+        while address != 0 && (tasks.len() as u64) < MAX_TASKS {
+            let thread = format!("(({} *) {:#x})", rtos.symbol("thread_type"), address);
+            // thread names need CONFIG_THREAD_NAME
+            let name = self.evaluate_expression(session_id, &format!("{}->name", thread)).await;
+            let priority =
+                self.evaluate_expression(session_id, &format!("{}->base.prio", thread)).await?;
+            let flags =
+                self.eval_address(session_id, &format!("{}->base.thread_state", thread)).await?;
+            let start =
+                self.eval_address(session_id, &format!("{}->stack_info.start", thread)).await;
+            let size = self.eval_address(session_id, &format!("{}->stack_info.size", thread)).await;
+            let stack_high_water = match (start, size) {
+                (Ok(start), Ok(size)) => {
+                    self.unused_stack(session_id, start, Some(size), rtos.kind).await.ok()
+                }
+                _ => None,
+            };
+            tasks.push(RtosTask {
+                name: name.ok().as_deref().and_then(parse_c_string),
+                address: format!("{:#x}", address),
+                priority: parse_integer(&priority).and_then(|p| p.try_into().ok()),
+                state: if Some(address) == current { "running" } else { zephyr_state(flags) },
+                stack_high_water,
+            });
+            address = self.eval_address(session_id, &format!("{}->next_thread", thread)).await?;
+        }
+        Ok(tasks)
+    }
+
+    /// Evaluate an expression yielding an address or an unsigned number
+    async fn eval_address(&self, session_id: &str, expression: &str) -> AppResult<u64> {
+        let value = self.evaluate_expression(session_id, expression).await?;
+        parse_integer(&value).and_then(|n| u64::try_from(n).ok()).ok_or_else(|| {
+            AppError::ParseError(format!("{} is not a number: {}", expression, value))
+        })
+    }
+
+    /// Number of bytes at the lowest end of a stack still holding the fill
+    /// pattern of the kernel
+    async fn unused_stack(
+        &self,
+        session_id: &str,
+        start: u64,
+        size: Option<u64>,
+        kind: RtosKind,
+    ) -> AppResult<u64> {
+        let limit = size.unwrap_or(MAX_STACK_SCAN).min(MAX_STACK_SCAN);
+        let fill = format!("{:02x}", kind.stack_fill());
+        let mut unused = 0;
+        while unused < limit {
+            let count = SCAN_CHUNK.min(limit - unused);
+            let address = format!("{:#x}", start + unused);
+            let memory = self.read_memory(session_id, None, address, count as usize).await?;
+            let contents = memory.first().map(|m| m.contents.as_str()).unwrap_or_default();
+            let filled =
+                contents.as_bytes().chunks(2).take_while(|byte| *byte == fill.as_bytes()).count();
+            unused += filled as u64;
+            if filled as u64 != count {
+                break;
+            }
+        }
+        Ok(unused)
+    }
+}
+
+/// State of a Zephyr thread from the `_THREAD_*` flags of `base.thread_state`
+fn zephyr_state(flags: u64) -> &'static str {
+    if flags & 0x08 != 0 {
+        "deleted"
+    } else if flags & 0x04 != 0 {
+        "prestart"
+    } else if flags & 0x10 != 0 {
+        "suspended"
+    } else if flags & 0x02 != 0 {
+        "blocked"
+    } else {
+        "ready"
+    }
+}
+
+/// First integer of a value printed by GDB, e.g. `(TCB_t *) 0x2000 <ucHeap>`
+/// or `-1 '\377'`
+fn parse_integer(value: &str) -> Option<i128> {
+    value.split_whitespace().find_map(|word| {
+        let word = word.trim_end_matches(',');
+        match word.strip_prefix("0x") {
+            Some(hex) => i128::from_str_radix(hex, 16).ok(),
+            None => word.parse().ok(),
+        }
+    })
+}
+
+/// Content of a char array printed by GDB, e.g. `"IDLE", '\000' <repeats 11
+/// times>` or `"Tmr Svc\000\000"`
+fn parse_c_string(value: &str) -> Option<String> {
+    let start = value.find('"')? + 1;
+    let end = value[start..].find('"').map_or(value.len(), |end| start + end);
+    let text = &value[start..end];
+    let text = text.split("\\000").next().unwrap_or_default();
+    (!text.is_empty()).then(|| text.to_string())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_values() {
+        assert_eq!(parse_integer("(TCB_t *) 0x20000100 <ucHeap+256>"), Some(0x20000100));
+        assert_eq!(parse_integer("-1 '\\377'"), Some(-1));
+        assert_eq!(parse_c_string(r#""IDLE", '\000' <repeats 11 times>"#).as_deref(), Some("IDLE"));
+        assert_eq!(parse_c_string(r#""Tmr Svc\000\000""#).as_deref(), Some("Tmr Svc"));
+        assert_eq!(parse_c_string("0x0"), None);
+        assert_eq!(zephyr_state(0x02), "blocked");
+
+        let rtos = RtosState::new(RtosKind::FreeRtos, &["tcb_type=tskTCB".to_string()]).unwrap();
+        assert_eq!(rtos.symbol("tcb_type"), "tskTCB");
+        assert!(RtosState::new(RtosKind::Zephyr, &["ready=x".to_string()]).is_err());
+    }
+}
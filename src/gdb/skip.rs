@@ -0,0 +1,162 @@
+//! Functions and files stepping does not descend into, GDB's `skip`
+
+use std::str::FromStr;
+
+use serde::Serialize;
+
+use super::GDBManager;
+use crate::error::{AppError, AppResult};
+use crate::mi::commands::MiCommand;
+
+/// What a skip entry matches
+#[derive(Debug, Clone, PartialEq)]
+pub enum SkipTarget {
+    Function(String),
+    FunctionRegex(String),
+    File(String),
+    FileGlob(String),
+}
+
+impl SkipTarget {
+    fn command(&self) -> String {
+        let (option, value) = match self {
+            SkipTarget::Function(name) => ("-fu", name),
+            SkipTarget::FunctionRegex(regex) => ("-rfu", regex),
+            SkipTarget::File(file) => ("-fi", file),
+            SkipTarget::FileGlob(glob) => ("-gfi", glob),
+        };
+        format!("skip {} {}", option, quote(value))
+    }
+}
+
+/// Standard library internals of a language
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SkipPreset {
+    Rust,
+    Cpp,
+}
+
+impl FromStr for SkipPreset {
+    type Err = AppError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "rust" => Ok(Self::Rust),
+            "cpp" => Ok(Self::Cpp),
+            _ => Err(AppError::InvalidArgument(format!(
+                "unknown skip preset {}, expecting rust or cpp",
+                s
+            ))),
+        }
+    }
+}
+
+impl SkipPreset {
+    pub fn targets(&self) -> Vec<SkipTarget> {
+        let regex = match self {
+            // trait methods are named like `<alloc::vec::Vec<T> as core::ops::Index>::index`
+            SkipPreset::Rust => "^<?(core|std|alloc)::",
+            SkipPreset::Cpp => "^(std|__gnu_cxx|__cxxabiv1)::",
+        };
+        vec![SkipTarget::FunctionRegex(regex.to_string())]
+    }
+}
+
+/// An entry of `info skip`
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Skip {
+    pub number: u32,
+    pub enabled: bool,
+    pub file: Option<String>,
+    /// `file` is a glob pattern
+    pub file_glob: bool,
+    pub function: Option<String>,
+    /// `function` is a regular expression
+    pub function_regex: bool,
+}
+
+impl GDBManager {
+    /// Skip functions and files when stepping into, returns all the skip
+    /// entries
+    pub async fn add_skips(
+        &self,
+        session_id: &str,
+        targets: &[SkipTarget],
+    ) -> AppResult<Vec<Skip>> {
+        for target in targets {
+            let command = MiCommand::cli_exec(&target.command());
+            self.send_command_with_timeout(session_id, &command).await?;
+        }
+        self.list_skips(session_id).await
+    }
+
+    /// List the skip entries
+    pub async fn list_skips(&self, session_id: &str) -> AppResult<Vec<Skip>> {
+        let output = self.capture_console(session_id, "info skip").await?;
+        Ok(parse_info_skip(&output))
+    }
+
+    /// Delete a skip entry, all of them if `number` is None. Returns the
+    /// remaining entries
+    pub async fn delete_skip(&self, session_id: &str, number: Option<u32>) -> AppResult<Vec<Skip>> {
+        let command = match number {
+            Some(number) => format!("skip delete {}", number),
+            None => "skip delete".to_string(),
+        };
+        self.send_command_with_timeout(session_id, &MiCommand::cli_exec(&command)).await?;
+        self.list_skips(session_id).await
+    }
+}
+
+/// Quote an argument of `skip`, which splits its arguments like a shell
+fn quote(value: &str) -> String {
+    format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+/// Parse the output of `info skip`:
+///
+/// ```text
+/// Num   Enb Glob File                 RE Function
+/// 1     y      n <none>                n foo
+/// 2     y      y /usr/include/*        n <none>
+/// ```
+fn parse_info_skip(output: &str) -> Vec<Skip> {
+    let field = |value: &str| (value != "<none>").then(|| value.to_string());
+    output
+        .lines()
+        .filter_map(|line| {
+            let mut columns = line.split_whitespace();
+            let number = columns.next()?.parse().ok()?;
+            let enabled = columns.next()? == "y";
+            let file_glob = columns.next()? == "y";
+            let file = field(columns.next()?);
+            let function_regex = columns.next()? == "y";
+            // function names may contain spaces, e.g. `<T as Trait>::f`
+            let function = field(&columns.collect::<Vec<_>>().join(" "));
+            Some(Skip { number, enabled, file, file_glob, function, function_regex })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_info_skip() {
+        let output = "Num   Enb Glob File                 RE Function\n\
+                      1     y      n <none>                n foo\n\
+                      2     n      y /usr/include/*        n <none>\n\
+                      3     y      n <none>                y ^<?(core|std|alloc)::\n";
+        let skips = parse_info_skip(output);
+        assert_eq!(skips.len(), 3);
+        assert_eq!(skips[0].function.as_deref(), Some("foo"));
+        assert!(skips[0].file.is_none());
+        assert!(!skips[1].enabled);
+        assert!(skips[1].file_glob);
+        assert_eq!(skips[1].file.as_deref(), Some("/usr/include/*"));
+        assert!(skips[2].function_regex);
+
+        assert!(parse_info_skip("Not skipping any files or functions.\n").is_empty());
+    }
+}
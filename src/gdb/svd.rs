@@ -0,0 +1,431 @@
+//! Peripheral registers of a microcontroller described by a CMSIS-SVD file
+
+use std::path::Path;
+use std::sync::Arc;
+
+use roxmltree::Node;
+use serde::Serialize;
+
+use super::GDBManager;
+use crate::error::{AppError, AppResult};
+use crate::models::Endian;
+
+/// A device described by an SVD file
+#[derive(Debug, Clone, Serialize)]
+pub struct Device {
+    pub name: String,
+    pub peripherals: Vec<Peripheral>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Peripheral {
+    pub name: String,
+    pub base_address: u64,
+    pub description: Option<String>,
+    pub registers: Vec<PeripheralRegister>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PeripheralRegister {
+    pub name: String,
+    /// Offset from the base address of the peripheral, clusters included
+    pub offset: u64,
+    /// Width in bits
+    pub size: u32,
+    pub description: Option<String>,
+    pub fields: Vec<Field>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Field {
+    pub name: String,
+    pub lsb: u32,
+    pub width: u32,
+    pub description: Option<String>,
+}
+
+/// Summary of a peripheral for `list_peripherals`
+#[derive(Debug, Clone, Serialize)]
+pub struct PeripheralSummary {
+    pub name: String,
+    pub base_address: String,
+    pub description: Option<String>,
+    pub registers: usize,
+}
+
+/// A peripheral register read from the target
+#[derive(Debug, Clone, Serialize)]
+pub struct RegisterValue {
+    pub name: String,
+    pub address: String,
+    pub value: String,
+    pub fields: Vec<FieldValue>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct FieldValue {
+    pub name: String,
+    /// Bit range as `[msb:lsb]`
+    pub bits: String,
+    pub value: u64,
+}
+
+impl Device {
+    fn peripheral(&self, name: &str) -> AppResult<&Peripheral> {
+        self.peripherals
+            .iter()
+            .find(|p| p.name.eq_ignore_ascii_case(name))
+            .ok_or_else(|| AppError::NotFound(format!("No peripheral {} in {}", name, self.name)))
+    }
+}
+
+impl GDBManager {
+    /// Load the SVD file describing the device debugged in a session
+    pub async fn load_svd(
+        &self,
+        session_id: &str,
+        path: &Path,
+    ) -> AppResult<Vec<PeripheralSummary>> {
+        let text = tokio::fs::read_to_string(path).await?;
+        let device = parse_svd(&text)?;
+        let summary = summarize(&device);
+
+        let mut sessions = self.sessions.lock().await;
+        let handle = sessions
+            .get_mut(session_id)
+            .ok_or_else(|| AppError::NotFound(format!("Session {} does not exist", session_id)))?;
+        handle.svd = Some(Arc::new(device));
+        drop(sessions);
+        self.notify_sessions_changed();
+        Ok(summary)
+    }
+
+    /// The peripherals of the loaded SVD file
+    pub async fn list_peripherals(&self, session_id: &str) -> AppResult<Vec<PeripheralSummary>> {
+        let device = self.svd(session_id).await?;
+        Ok(summarize(&device))
+    }
+
+    /// Read registers of a peripheral, all of them by default, and decode
+    /// their fields
+    pub async fn read_peripheral_register(
+        &self,
+        session_id: &str,
+        peripheral: &str,
+        register: Option<&str>,
+    ) -> AppResult<Vec<RegisterValue>> {
+        let device = self.svd(session_id).await?;
+        let peripheral = device.peripheral(peripheral)?;
+        let registers = match register {
+            Some(name) => vec![
+                peripheral
+                    .registers
+                    .iter()
+                    .find(|r| r.name.eq_ignore_ascii_case(name))
+                    .ok_or_else(|| {
+                        AppError::NotFound(format!("No register {} in {}", name, peripheral.name))
+                    })?,
+            ],
+            None => peripheral.registers.iter().collect(),
+        };
+        let endian = self.get_session(session_id).await?.arch.endian.unwrap_or(Endian::Little);
+
+        let mut values = Vec::with_capacity(registers.len());
+        for register in registers {
+            let address = peripheral.base_address + register.offset;
+            let bytes = (register.size as usize).div_ceil(8);
+            // device registers change by themselves, they are never cached
+            let memory =
+                self.read_memory_uncached(session_id, &format!("{:#x}", address), bytes).await?;
+            let contents = memory.first().map(|m| m.contents.as_str()).unwrap_or_default();
+            let value = decode_value(contents, endian)?;
+            values.push(RegisterValue {
+                name: register.name.clone(),
+                address: format!("{:#x}", address),
+                value: format!("{:#0width$x}", value, width = bytes * 2 + 2),
+                fields: register
+                    .fields
+                    .iter()
+                    .map(|field| FieldValue {
+                        name: field.name.clone(),
+                        bits: format!("[{}:{}]", field.lsb + field.width - 1, field.lsb),
+                        value: (value >> field.lsb) & mask(field.width),
+                    })
+                    .collect(),
+            });
+        }
+        Ok(values)
+    }
+
+    async fn svd(&self, session_id: &str) -> AppResult<Arc<Device>> {
+        let sessions = self.sessions.lock().await;
+        let handle = sessions
+            .get(session_id)
+            .ok_or_else(|| AppError::NotFound(format!("Session {} does not exist", session_id)))?;
+        handle.svd.clone().ok_or_else(|| {
+            AppError::NotFound("No SVD file loaded, call load_svd first".to_string())
+        })
+    }
+}
+
+fn summarize(device: &Device) -> Vec<PeripheralSummary> {
+    device
+        .peripherals
+        .iter()
+        .map(|p| PeripheralSummary {
+            name: p.name.clone(),
+            base_address: format!("{:#x}", p.base_address),
+            description: p.description.clone(),
+            registers: p.registers.len(),
+        })
+        .collect()
+}
+
+fn mask(width: u32) -> u64 {
+    if width >= 64 { u64::MAX } else { (1 << width) - 1 }
+}
+
+/// Value of the hex bytes read from the target
+fn decode_value(contents: &str, endian: Endian) -> AppResult<u64> {
+    let mut bytes = (0..contents.len() / 2)
+        .map(|i| u8::from_str_radix(&contents[i * 2..i * 2 + 2], 16))
+        .collect::<Result<Vec<_>, _>>()?;
+    if bytes.is_empty() || bytes.len() > 8 {
+        return Err(AppError::ParseError(format!("unexpected register contents {}", contents)));
+    }
+    if endian == Endian::Little {
+        bytes.reverse();
+    }
+    Ok(bytes.iter().fold(0, |value, byte| value << 8 | *byte as u64))
+}
+
+/// Parse an SVD file, peripherals derived from another one get its registers
+pub fn parse_svd(text: &str) -> AppResult<Device> {
+    let document = roxmltree::Document::parse(text)
+        .map_err(|e| AppError::ParseError(format!("invalid SVD file: {}", e)))?;
+    let root = document.root_element();
+    let default_size = child_number(root, "size")?.unwrap_or(32) as u32;
+
+    let nodes = child(root, "peripherals")
+        .map(|p| p.children().filter(|n| n.has_tag_name("peripheral")).collect::<Vec<_>>())
+        .unwrap_or_default();
+    let mut peripherals: Vec<Peripheral> = Vec::with_capacity(nodes.len());
+    for node in nodes {
+        let name = child_text(node, "name")
+            .ok_or_else(|| AppError::ParseError("peripheral without a name".to_string()))?;
+        let base_address = child_number(node, "baseAddress")?.unwrap_or(0);
+        let size = child_number(node, "size")?.map_or(default_size, |size| size as u32);
+        let mut registers = match child(node, "registers") {
+            Some(registers) => parse_registers(registers, 0, "", size)?,
+            None => vec![],
+        };
+        let mut description = child_text(node, "description");
+        if let Some(base) = node.attribute("derivedFrom")
+            && let Some(base) = peripherals.iter().find(|p| p.name == base)
+        {
+            if registers.is_empty() {
+                registers = base.registers.clone();
+            }
+            description = description.or_else(|| base.description.clone());
+        }
+        peripherals.push(Peripheral { name, base_address, description, registers });
+    }
+
+    Ok(Device { name: child_text(root, "name").unwrap_or_default(), peripherals })
+}
+
+/// Registers of a `registers` or `cluster` element, clusters are flattened
+/// with their name as a prefix
+fn parse_registers(
+    node: Node,
+    offset: u64,
+    prefix: &str,
+    size: u32,
+) -> AppResult<Vec<PeripheralRegister>> {
+    let mut registers = vec![];
+    for node in node.children().filter(|n| n.is_element()) {
+        let name = child_text(node, "name").unwrap_or_default();
+        let address_offset = offset + child_number(node, "addressOffset")?.unwrap_or(0);
+        for (name, offset) in expand_dim(node, &name, address_offset)? {
+            match node.tag_name().name() {
+                "register" => {
+                    let size = child_number(node, "size")?.map_or(size, |size| size as u32);
+                    registers.push(PeripheralRegister {
+                        name: format!("{}{}", prefix, name),
+                        offset,
+                        size,
+                        description: child_text(node, "description"),
+                        fields: match child(node, "fields") {
+                            Some(fields) => parse_fields(fields)?,
+                            None => vec![],
+                        },
+                    });
+                }
+                "cluster" => {
+                    let prefix = format!("{}{}.", prefix, name);
+                    registers.extend(parse_registers(node, offset, &prefix, size)?);
+                }
+                _ => {}
+            }
+        }
+    }
+    Ok(registers)
+}
+
+/// Names and offsets of the elements of an array register or cluster
+fn expand_dim(node: Node, name: &str, offset: u64) -> AppResult<Vec<(String, u64)>> {
+    let Some(dim) = child_number(node, "dim")? else {
+        return Ok(vec![(name.to_string(), offset)]);
+    };
+    let increment = child_number(node, "dimIncrement")?.unwrap_or(0);
+    let indices = match child_text(node, "dimIndex") {
+        Some(index) => expand_dim_index(&index),
+        None => (0..dim).map(|i| i.to_string()).collect(),
+    };
+    Ok(indices
+        .into_iter()
+        .take(dim as usize)
+        .enumerate()
+        .map(|(i, index)| {
+            let name = if name.contains("[%s]") {
+                name.replace("[%s]", &index)
+            } else {
+                name.replace("%s", &index)
+            };
+            (name, offset + i as u64 * increment)
+        })
+        .collect())
+}
+
+/// `dimIndex` is either a comma separated list or a range such as `0-3`
+fn expand_dim_index(index: &str) -> Vec<String> {
+    if let Some((start, end)) = index.split_once('-') {
+        if let (Ok(start), Ok(end)) = (start.parse::<u64>(), end.parse::<u64>()) {
+            return (start..=end).map(|i| i.to_string()).collect();
+        }
+        if let (Some(start), Some(end)) = (start.chars().next(), end.chars().next()) {
+            return (start..=end).map(|c| c.to_string()).collect();
+        }
+    }
+    index.split(',').map(|s| s.trim().to_string()).collect()
+}
+
+fn parse_fields(node: Node) -> AppResult<Vec<Field>> {
+    let mut fields = vec![];
+    for node in node.children().filter(|n| n.has_tag_name("field")) {
+        let (lsb, width) = if let Some(range) = child_text(node, "bitRange") {
+            let range = range.trim_matches(|c| c == '[' || c == ']');
+            let (msb, lsb) = range
+                .split_once(':')
+                .ok_or_else(|| AppError::ParseError(format!("invalid bitRange {}", range)))?;
+            bit_range(msb.trim().parse()?, lsb.trim().parse()?)?
+        } else if let Some(lsb) = child_number(node, "lsb")? {
+            bit_range(child_number(node, "msb")?.unwrap_or(lsb), lsb)?
+        } else {
+            let lsb = child_number(node, "bitOffset")?.unwrap_or(0);
+            (lsb as u32, child_number(node, "bitWidth")?.unwrap_or(1) as u32)
+        };
+        fields.push(Field {
+            name: child_text(node, "name").unwrap_or_default(),
+            lsb,
+            width,
+            description: child_text(node, "description"),
+        });
+    }
+    fields.sort_by_key(|field| std::cmp::Reverse(field.lsb));
+    Ok(fields)
+}
+
+/// `(lsb, width)` of the bits from `msb` down to `lsb`
+fn bit_range(msb: u64, lsb: u64) -> AppResult<(u32, u32)> {
+    if msb < lsb || msb >= 64 {
+        return Err(AppError::ParseError(format!("invalid bit range [{}:{}]", msb, lsb)));
+    }
+    Ok((lsb as u32, (msb - lsb) as u32 + 1))
+}
+
+fn child<'a, 'input>(node: Node<'a, 'input>, name: &str) -> Option<Node<'a, 'input>> {
+    node.children().find(|n| n.has_tag_name(name))
+}
+
+fn child_text(node: Node, name: &str) -> Option<String> {
+    let text = child(node, name)?.text()?;
+    Some(text.split_whitespace().collect::<Vec<_>>().join(" "))
+}
+
+/// A number in SVD notation: decimal, `0x` hex or `#` binary
+fn child_number(node: Node, name: &str) -> AppResult<Option<u64>> {
+    let Some(text) = child_text(node, name) else {
+        return Ok(None);
+    };
+    let number = if let Some(hex) = text.strip_prefix("0x").or_else(|| text.strip_prefix("0X")) {
+        u64::from_str_radix(hex, 16)
+    } else if let Some(binary) = text.strip_prefix('#') {
+        u64::from_str_radix(binary, 2)
+    } else {
+        text.parse::<u64>()
+    };
+    number.map(Some).map_err(|_| AppError::ParseError(format!("invalid {} {}", name, text)))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const SVD: &str = r#"<?xml version="1.0" encoding="utf-8"?>
+        <device>
+          <name>STM32F4</name>
+          <size>32</size>
+          <peripherals>
+            <peripheral>
+              <name>GPIOA</name>
+              <description>General purpose I/O</description>
+              <baseAddress>0x40020000</baseAddress>
+              <registers>
+                <register>
+                  <name>MODER</name>
+                  <addressOffset>0x0</addressOffset>
+                  <fields>
+                    <field><name>MODER0</name><bitOffset>0</bitOffset><bitWidth>2</bitWidth></field>
+                    <field><name>MODER1</name><bitRange>[3:2]</bitRange></field>
+                  </fields>
+                </register>
+                <register>
+                  <dim>2</dim>
+                  <dimIncrement>4</dimIncrement>
+                  <name>AFR%s</name>
+                  <dimIndex>L,H</dimIndex>
+                  <addressOffset>0x20</addressOffset>
+                </register>
+              </registers>
+            </peripheral>
+            <peripheral derivedFrom="GPIOA">
+              <name>GPIOB</name>
+              <baseAddress>0x40020400</baseAddress>
+            </peripheral>
+          </peripherals>
+        </device>"#;
+
+    #[test]
+    fn test_parse_svd() {
+        let device = parse_svd(SVD).unwrap();
+        assert_eq!(device.name, "STM32F4");
+        let gpiob = device.peripheral("gpiob").unwrap();
+        assert_eq!(gpiob.base_address, 0x40020400);
+        assert_eq!(gpiob.description.as_deref(), Some("General purpose I/O"));
+
+        let registers = &gpiob.registers;
+        assert_eq!(registers.len(), 3);
+        assert_eq!(registers[2].name, "AFRH");
+        assert_eq!(registers[2].offset, 0x24);
+        let fields = &registers[0].fields;
+        assert_eq!((fields[0].name.as_str(), fields[0].lsb, fields[0].width), ("MODER1", 2, 2));
+
+        assert_eq!(decode_value("78563412", Endian::Little).unwrap(), 0x12345678);
+        assert_eq!(decode_value("12345678", Endian::Big).unwrap(), 0x12345678);
+
+        let reversed = SVD.replace("[3:2]", "[2:3]");
+        assert!(matches!(parse_svd(&reversed), Err(AppError::ParseError(msg))
+            if msg == "invalid bit range [2:3]"));
+    }
+}
@@ -0,0 +1,178 @@
+//! Debug information detached from stripped binaries, found as GDB looks for
+//! it: by build ID and by the `.gnu_debuglink` section
+
+use std::path::{Path, PathBuf};
+
+use goblin::elf::Elf;
+use goblin::elf::note::NT_GNU_BUILD_ID;
+use serde::Serialize;
+use serde_with::skip_serializing_none;
+
+use super::GDBManager;
+use crate::error::{AppError, AppResult};
+use crate::mi::commands::escape_command;
+
+/// Where the distributions install the debug files
+pub const DEBUG_DIR: &str = "/usr/lib/debug";
+
+/// The debug information of an object and where its debug file may be
+#[skip_serializing_none]
+#[derive(Debug, Clone, Serialize)]
+pub struct DebugFiles {
+    pub object: PathBuf,
+    /// In hexadecimal
+    pub build_id: Option<String>,
+    /// File name in the `.gnu_debuglink` section
+    pub debuglink: Option<String>,
+    /// The object has a `.debug_info` section itself
+    pub has_debug_info: bool,
+    /// Paths looked at, in order
+    pub candidates: Vec<PathBuf>,
+    /// The first candidate which exists, to load with `add_symbol_file`
+    pub found: Option<PathBuf>,
+}
+
+impl GDBManager {
+    /// Load the symbols of `path`, at `text_addr` the address of its `.text`
+    /// section if it is not linked at its load address, and return what GDB
+    /// printed
+    pub async fn add_symbol_file(
+        &self,
+        session_id: &str,
+        path: &Path,
+        text_addr: Option<&str>,
+    ) -> AppResult<String> {
+        let mut command = format!("add-symbol-file {}", escape_command(&path.to_string_lossy()));
+        if let Some(text_addr) = text_addr {
+            command.push(' ');
+            command.push_str(text_addr);
+        }
+        self.capture_console(session_id, &command).await
+    }
+
+    /// Look for the debug file of `object`, the program of the session by
+    /// default, in the debug directories then in `search_dirs`
+    pub async fn find_debug_files(
+        &self,
+        session_id: &str,
+        object: Option<PathBuf>,
+        search_dirs: &[PathBuf],
+    ) -> AppResult<DebugFiles> {
+        let object = match object {
+            Some(object) => object,
+            None => {
+                let sessions = self.sessions.lock().await;
+                let handle = sessions.get(session_id).ok_or_else(|| {
+                    AppError::NotFound(format!("Session {} does not exist", session_id))
+                })?;
+                handle.builder.opt_program.clone().ok_or_else(|| {
+                    AppError::InvalidArgument("The session has no program, give one".to_string())
+                })?
+            }
+        };
+        let mut debug_dirs = vec![PathBuf::from(DEBUG_DIR)];
+        debug_dirs.extend_from_slice(search_dirs);
+        // reads the whole object and looks at the file system
+        tokio::task::spawn_blocking(move || debug_files(&object, &debug_dirs)).await?
+    }
+}
+
+/// The debug information of `object` and its candidate debug files in
+/// `debug_dirs`
+pub fn debug_files(object: &Path, debug_dirs: &[PathBuf]) -> AppResult<DebugFiles> {
+    let bytes = std::fs::read(object)?;
+    let elf = Elf::parse(&bytes).map_err(|e| {
+        AppError::InvalidArgument(format!("{} is not an ELF file: {}", object.display(), e))
+    })?;
+
+    let build_id = elf
+        .iter_note_sections(&bytes, Some(".note.gnu.build-id"))
+        .into_iter()
+        .flatten()
+        .filter_map(Result::ok)
+        .find(|note| note.n_type == NT_GNU_BUILD_ID && note.name == "GNU")
+        .map(|note| note.desc.iter().map(|byte| format!("{:02x}", byte)).collect::<String>());
+    let section = |name: &str| {
+        elf.section_headers
+            .iter()
+            .find(|header| elf.shdr_strtab.get_at(header.sh_name) == Some(name))
+    };
+    // the file name is followed by padding and a CRC
+    let debuglink = section(".gnu_debuglink")
+        .and_then(|header| bytes.get(header.file_range()?))
+        .and_then(|data| data.split(|byte| *byte == 0).next())
+        .map(|name| String::from_utf8_lossy(name).into_owned())
+        .filter(|name| !name.is_empty());
+    let has_debug_info = section(".debug_info").or_else(|| section(".zdebug_info")).is_some();
+
+    let candidates = candidates(object, build_id.as_deref(), debuglink.as_deref(), debug_dirs);
+    let found = candidates.iter().find(|candidate| candidate.is_file()).cloned();
+    Ok(DebugFiles {
+        object: object.to_path_buf(),
+        build_id,
+        debuglink,
+        has_debug_info,
+        candidates,
+        found,
+    })
+}
+
+/// Paths GDB looks at for the debug file: by build ID in the debug
+/// directories, then by debug link next to the object, in its `.debug`
+/// directory and under the debug directories
+fn candidates(
+    object: &Path,
+    build_id: Option<&str>,
+    debuglink: Option<&str>,
+    debug_dirs: &[PathBuf],
+) -> Vec<PathBuf> {
+    let mut candidates = vec![];
+    if let Some(build_id) = build_id.filter(|build_id| build_id.len() > 2) {
+        for dir in debug_dirs {
+            let path = format!(".build-id/{}/{}.debug", &build_id[..2], &build_id[2..]);
+            candidates.push(dir.join(path));
+        }
+    }
+    if let Some(debuglink) = debuglink {
+        let object_dir = object.parent().unwrap_or(Path::new("/"));
+        candidates.push(object_dir.join(debuglink));
+        candidates.push(object_dir.join(".debug").join(debuglink));
+        for dir in debug_dirs {
+            let relative = object_dir.strip_prefix("/").unwrap_or(object_dir);
+            candidates.push(dir.join(relative).join(debuglink));
+        }
+    }
+    candidates
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_candidates() {
+        let candidates = candidates(
+            Path::new("/opt/app/bin/server"),
+            Some("ab12cd"),
+            Some("server.debug"),
+            &[PathBuf::from(DEBUG_DIR)],
+        );
+        assert_eq!(
+            candidates,
+            [
+                PathBuf::from("/usr/lib/debug/.build-id/ab/12cd.debug"),
+                PathBuf::from("/opt/app/bin/server.debug"),
+                PathBuf::from("/opt/app/bin/.debug/server.debug"),
+                PathBuf::from("/usr/lib/debug/opt/app/bin/server.debug"),
+            ]
+        );
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_debug_files() {
+        // the tests are built with debug information
+        let test_binary = std::env::current_exe().unwrap();
+        assert!(debug_files(&test_binary, &[]).unwrap().has_debug_info);
+    }
+}
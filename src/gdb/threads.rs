@@ -0,0 +1,156 @@
+//! Threads of a session as announced by the thread notifications of GDB
+
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::Serialize;
+use serde_json::Value;
+
+use super::GDBManager;
+use crate::error::{AppError, AppResult};
+use crate::mi::output::ThreadEvent;
+
+/// Number of exited threads kept per session
+const MAX_EXITED: usize = 1000;
+
+/// Threads and thread groups, i.e. processes, created so far
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ThreadTable {
+    /// By creation
+    pub threads: Vec<ThreadEntry>,
+    pub groups: Vec<ThreadGroup>,
+    pub selected: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ThreadEntry {
+    pub id: String,
+    pub group_id: Option<String>,
+    pub alive: bool,
+    /// Milliseconds since the Unix epoch
+    pub created_at: u64,
+    pub exited_at: Option<u64>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ThreadGroup {
+    pub id: String,
+    pub pid: Option<u64>,
+    pub alive: bool,
+    pub exit_code: Option<String>,
+}
+
+impl ThreadTable {
+    /// Update the table with a thread notification
+    pub fn apply(&mut self, event: ThreadEvent, results: &Value) {
+        let field = |key| results.get(key).and_then(Value::as_str).map(str::to_string);
+        let Some(id) = field("id") else {
+            return;
+        };
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_millis() as u64;
+        match event {
+            ThreadEvent::Created => {
+                // IDs are not reused, but a new run numbers its threads from 1 again
+                self.threads.retain(|thread| thread.id != id);
+                self.threads.push(ThreadEntry {
+                    id,
+                    group_id: field("group-id"),
+                    alive: true,
+                    created_at: now,
+                    exited_at: None,
+                });
+            }
+            ThreadEvent::Exited => {
+                if let Some(thread) =
+                    self.threads.iter_mut().find(|thread| thread.id == id && thread.alive)
+                {
+                    thread.alive = false;
+                    thread.exited_at = Some(now);
+                }
+                if self.selected.as_ref() == Some(&id) {
+                    self.selected = None;
+                }
+                let exited = self.threads.iter().filter(|thread| !thread.alive).count();
+                if exited > MAX_EXITED {
+                    let mut excess = exited - MAX_EXITED;
+                    self.threads.retain(|thread| {
+                        let drop = !thread.alive && excess > 0;
+                        excess -= drop as usize;
+                        !drop
+                    });
+                }
+            }
+            ThreadEvent::GroupStarted => {
+                self.groups.retain(|group| group.id != id);
+                self.groups.push(ThreadGroup {
+                    id,
+                    pid: field("pid").and_then(|pid| pid.parse().ok()),
+                    alive: true,
+                    exit_code: None,
+                });
+            }
+            ThreadEvent::GroupExited => {
+                for thread in &mut self.threads {
+                    if thread.alive && thread.group_id.as_ref() == Some(&id) {
+                        thread.alive = false;
+                        thread.exited_at = Some(now);
+                    }
+                }
+                if let Some(group) = self.groups.iter_mut().find(|group| group.id == id) {
+                    group.alive = false;
+                    group.exit_code = field("exit-code");
+                }
+            }
+            ThreadEvent::Selected => self.selected = Some(id),
+        }
+    }
+
+    /// Number of threads which did not exit
+    pub fn live_threads(&self) -> usize {
+        self.threads.iter().filter(|thread| thread.alive).count()
+    }
+
+    /// Whether a program is being debugged, one of its processes started and
+    /// did not exit yet
+    pub fn is_program_alive(&self) -> bool {
+        self.groups.iter().any(|group| group.alive) || self.live_threads() > 0
+    }
+}
+
+impl GDBManager {
+    /// The threads created in a session, alive or exited, without querying GDB
+    pub async fn get_thread_table(&self, session_id: &str) -> AppResult<ThreadTable> {
+        let sessions = self.sessions.lock().await;
+        let handle = sessions
+            .get(session_id)
+            .ok_or_else(|| AppError::NotFound(format!("Session {} does not exist", session_id)))?;
+        Ok(handle.threads.lock().unwrap().clone())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn test_thread_table() {
+        let mut table = ThreadTable::default();
+        table.apply(ThreadEvent::GroupStarted, &json!({"id": "i1", "pid": "6550"}));
+        table.apply(ThreadEvent::Created, &json!({"id": "1", "group-id": "i1"}));
+        table.apply(ThreadEvent::Created, &json!({"id": "2", "group-id": "i1"}));
+        table.apply(ThreadEvent::Selected, &json!({"id": "2"}));
+        table.apply(ThreadEvent::Exited, &json!({"id": "2", "group-id": "i1"}));
+        assert_eq!(table.groups[0].pid, Some(6550));
+        assert!(table.threads[0].alive);
+        assert!(!table.threads[1].alive && table.threads[1].exited_at.is_some());
+        assert_eq!(table.selected, None);
+        assert_eq!(table.live_threads(), 1);
+        assert!(table.is_program_alive());
+
+        table.apply(ThreadEvent::GroupExited, &json!({"id": "i1", "exit-code": "0"}));
+        assert!(table.threads.iter().all(|thread| !thread.alive));
+        assert_eq!(table.groups[0].exit_code.as_deref(), Some("0"));
+        assert!(!table.is_program_alive());
+    }
+}
@@ -0,0 +1,218 @@
+use std::collections::HashMap;
+use std::time::Duration;
+
+use serde::Serialize;
+use serde_json::Value;
+use tokio::time::Instant;
+use tracing::warn;
+
+use super::{GDBManager, Resumed};
+use crate::error::AppResult;
+use crate::mi::commands::MiCommand;
+use crate::models::GDBSessionStatus;
+
+/// A call of a traced function
+#[derive(Debug, Clone, Serialize)]
+pub struct CallRecord {
+    pub function: String,
+    /// Number of traced calls in progress when this one was made
+    pub depth: usize,
+    /// Arguments as reported by GDB, name and value pairs
+    pub args: Value,
+    /// Not set if the program stopped before the function returned
+    pub return_value: Option<String>,
+}
+
+/// Outcome of `trace_calls`
+#[derive(Debug, Clone, Serialize)]
+pub struct CallTrace {
+    pub calls: Vec<CallRecord>,
+    /// Why tracing ended: exited, max_calls, timeout or the stop reason
+    pub ended: String,
+    /// Results of the last `*stopped` record
+    pub stopped: Value,
+}
+
+impl GDBManager {
+    /// Run or continue the program recording the arguments and the return
+    /// value of every call to `functions`, until the program exits, stops for
+    /// another reason, `max_calls` calls are recorded or `timeout` elapses.
+    ///
+    /// Return values of nested traced calls are matched by nesting order, a
+    /// traced function called through an untraced one may get the return
+    /// value of the latter
+    pub async fn trace_calls(
+        &self,
+        session_id: &str,
+        functions: &[String],
+        max_calls: usize,
+        timeout: Duration,
+    ) -> AppResult<CallTrace> {
+        let mut breakpoints = HashMap::new();
+        let mut numbers = vec![];
+        let inserted: AppResult<()> = async {
+            for function in functions {
+                let number = self.insert_breakpoint_at(session_id, function, None, false).await?;
+                breakpoints.insert(number.to_string(), function.clone());
+                numbers.push(number);
+            }
+            Ok(())
+        }
+        .await;
+
+        let result = match inserted {
+            Ok(()) => self.record_calls(session_id, &breakpoints, max_calls, timeout).await,
+            Err(e) => Err(e),
+        };
+
+        // the ones inserted before one failed too
+        if !numbers.is_empty()
+            && let Err(e) = self
+                .send_command_with_timeout(session_id, &MiCommand::delete_breakpoints(numbers))
+                .await
+        {
+            warn!("Failed to delete the tracing breakpoints: {}", e);
+        }
+        result
+    }
+
+    async fn record_calls(
+        &self,
+        session_id: &str,
+        breakpoints: &HashMap<String, String>,
+        max_calls: usize,
+        timeout: Duration,
+    ) -> AppResult<CallTrace> {
+        let deadline = Instant::now() + timeout;
+        let mut calls = Vec::<CallRecord>::new();
+        // traced calls which did not return yet, innermost last
+        let mut pending = Vec::<usize>::new();
+
+        let started = self.get_session(session_id).await?.status != GDBSessionStatus::Created;
+        let mut command = if started { MiCommand::exec_continue() } else { MiCommand::exec_run() };
+        loop {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            let stopped = match self.resume_and_wait(session_id, &command, remaining).await? {
+                Resumed::Stopped(stopped) => stopped,
+                Resumed::Interrupted(stopped) => {
+                    return Ok(CallTrace { calls, ended: "timeout".to_string(), stopped });
+                }
+            };
+
+            let reason = stopped.get("reason").and_then(|r| r.as_str()).unwrap_or_default();
+            let function = stopped
+                .get("bkptno")
+                .and_then(|n| n.as_str())
+                .and_then(|number| breakpoints.get(number));
+            match (reason, function) {
+                ("breakpoint-hit", Some(function)) => {
+                    if calls.len() == max_calls {
+                        return Ok(CallTrace { calls, ended: "max_calls".to_string(), stopped });
+                    }
+                    let args = stopped
+                        .get("frame")
+                        .and_then(|frame| frame.get("args"))
+                        .cloned()
+                        .unwrap_or(Value::Array(vec![]));
+                    calls.push(CallRecord {
+                        function: function.clone(),
+                        depth: pending.len(),
+                        args,
+                        return_value: None,
+                    });
+                    pending.push(calls.len() - 1);
+                    command = MiCommand::exec_finish();
+                }
+                ("function-finished", _) => {
+                    if let Some(call) = pending.pop() {
+                        calls[call].return_value = stopped
+                            .get("return-value")
+                            .and_then(|value| value.as_str())
+                            .map(|value| value.to_string());
+                    }
+                    command = if pending.is_empty() {
+                        MiCommand::exec_continue()
+                    } else {
+                        MiCommand::exec_finish()
+                    };
+                }
+                _ => {
+                    let ended = if reason.starts_with("exited") { "exited" } else { reason };
+                    return Ok(CallTrace { calls, ended: ended.to_string(), stopped });
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::error::AppError;
+
+    #[tokio::test]
+    async fn test_trace_calls() {
+        let manager = GDBManager::default();
+        let session_id = manager
+            .create_mock_session(
+                r#"
+                -> -break-insert area
+                <- ^done,bkpt={number="1",type="breakpoint",disp="keep",enabled="y",addr="0x1000",func="area",times="0"}
+                -> -break-insert mul
+                <- ^done,bkpt={number="2",type="breakpoint",disp="keep",enabled="y",addr="0x1100",func="mul",times="0"}
+                -> -exec-run
+                <- ^running
+                <- *running,thread-id="all"
+                <- *stopped,reason="breakpoint-hit",disp="keep",bkptno="1",frame={addr="0x1000",func="area",args=[{name="w",value="2"},{name="h",value="3"}]},thread-id="1"
+                -> -exec-finish
+                <- ^running
+                <- *running,thread-id="all"
+                <- *stopped,reason="breakpoint-hit",disp="keep",bkptno="2",frame={addr="0x1100",func="mul",args=[{name="a",value="2"},{name="b",value="3"}]},thread-id="1"
+                -> -exec-finish
+                <- ^running
+                <- *running,thread-id="all"
+                <- *stopped,reason="function-finished",frame={addr="0x1010",func="area",args=[]},gdb-result-var="$1",return-value="6",thread-id="1"
+                -> -exec-finish
+                <- ^running
+                <- *running,thread-id="all"
+                <- *stopped,reason="function-finished",frame={addr="0x1200",func="main",args=[]},gdb-result-var="$2",return-value="7",thread-id="1"
+                -> -exec-continue
+                <- ^running
+                <- *running,thread-id="all"
+                <- *stopped,reason="exited-normally"
+                -> -break-delete 1 2
+                <- ^done
+                -> -break-insert area
+                <- ^done,bkpt={number="3",type="breakpoint",disp="keep",enabled="y",addr="0x1000",func="area",times="0"}
+                -> -break-insert no_such
+                <- ^error,msg="Function \"no_such\" not defined."
+                -> -break-delete 3
+                <- ^done
+                "#,
+            )
+            .await
+            .unwrap();
+
+        let functions = ["area".to_string(), "mul".to_string()];
+        let trace =
+            manager.trace_calls(&session_id, &functions, 10, Duration::from_secs(5)).await.unwrap();
+        assert_eq!(trace.ended, "exited");
+        // the innermost call finishes first
+        let calls = trace
+            .calls
+            .iter()
+            .map(|call| (call.function.as_str(), call.depth, call.return_value.as_deref()))
+            .collect::<Vec<_>>();
+        assert_eq!(calls, [("area", 0, Some("7")), ("mul", 1, Some("6"))]);
+        assert_eq!(trace.calls[1].args[1]["value"], "3");
+
+        // the breakpoints inserted before the failure are deleted
+        let functions = ["area".to_string(), "no_such".to_string()];
+        assert!(matches!(
+            manager.trace_calls(&session_id, &functions, 10, Duration::from_secs(5)).await,
+            Err(AppError::GDBCommandError { msg, .. }) if msg.contains("not defined")
+        ));
+        assert!(matches!(manager.list_threads(&session_id).await,
+            Err(AppError::GDBCommandError { msg, .. }) if msg.contains("expecting none")));
+    }
+}
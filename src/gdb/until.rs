@@ -0,0 +1,345 @@
+use std::time::Duration;
+
+use serde::Serialize;
+use serde_json::Value;
+use tokio::time::Instant;
+use tracing::warn;
+
+use super::{GDBManager, Resumed};
+use crate::error::{AppError, AppResult};
+use crate::mi::commands::{BreakPointNumber, MiCommand};
+
+/// Outcome of `continue_until`
+#[derive(Debug, Clone, Serialize)]
+pub struct ContinueUntil {
+    /// The expression held when the program stopped
+    pub reached: bool,
+    /// Number of steps taken, 0 when a conditional breakpoint was used
+    pub iterations: usize,
+    /// Results of the last `*stopped` record
+    pub stopped: Value,
+}
+
+/// Outcome of `finish`
+#[derive(Debug, Clone, Serialize)]
+pub struct FunctionFinished {
+    /// The function returned, the program may stop earlier at a breakpoint
+    pub finished: bool,
+    /// The function which was finished
+    pub function: Option<String>,
+    /// Value history variable holding the return value, e.g. `$1`
+    pub result_var: Option<String>,
+    /// Not set for a void function
+    pub return_value: Option<String>,
+    /// Results of the last `*stopped` record
+    pub stopped: Value,
+}
+
+/// Outcome of `until_return_of`
+#[derive(Debug, Clone, Serialize)]
+pub struct ReturnedFrom {
+    /// The function of the frame returned, the program may stop earlier at a
+    /// breakpoint
+    pub returned: bool,
+    /// The functions finished on the way, innermost first
+    pub finished: Vec<FunctionFinished>,
+}
+
+/// Outcome of `start_program`
+#[derive(Debug, Clone, Serialize)]
+pub struct ProgramStarted {
+    /// The program stopped at the start symbol, it may stop earlier, e.g. at
+    /// another breakpoint, or exit
+    pub reached: bool,
+    /// Results of the `*stopped` record with the context of the stop
+    pub stopped: Value,
+}
+
+impl GDBManager {
+    /// Evaluate an expression in the selected frame
+    pub async fn evaluate_expression(
+        &self,
+        session_id: &str,
+        expression: &str,
+    ) -> AppResult<String> {
+        let command = MiCommand::data_evaluate_expression(expression.to_string());
+        let response = self.send_command_with_timeout(session_id, &command).await?;
+        response
+            .results
+            .get("value")
+            .and_then(|value| value.as_str())
+            .map(|value| value.to_string())
+            .ok_or(AppError::NotFound("value not found".to_string()))
+    }
+
+    /// Resume the program until `condition` holds. With a `location`, a
+    /// temporary breakpoint conditioned on it is inserted there and the
+    /// program continues, otherwise the program is stepped line by line and
+    /// the condition evaluated after each step, at most `max_iterations` times
+    pub async fn continue_until(
+        &self,
+        session_id: &str,
+        condition: &str,
+        location: Option<&str>,
+        max_iterations: usize,
+        timeout: Duration,
+    ) -> AppResult<ContinueUntil> {
+        match location {
+            Some(location) => {
+                self.continue_to_condition(session_id, condition, location, timeout).await
+            }
+            None => self.step_until(session_id, condition, max_iterations, timeout).await,
+        }
+    }
+
+    async fn continue_to_condition(
+        &self,
+        session_id: &str,
+        condition: &str,
+        location: &str,
+        timeout: Duration,
+    ) -> AppResult<ContinueUntil> {
+        let number = self.insert_breakpoint_at(session_id, location, Some(condition), true).await?;
+        let stopped =
+            match self.resume_and_wait(session_id, &MiCommand::exec_continue(), timeout).await? {
+                Resumed::Stopped(stopped) => stopped,
+                Resumed::Interrupted(stopped) => {
+                    self.delete_temporary_breakpoint(session_id, number).await;
+                    return Ok(ContinueUntil { reached: false, iterations: 0, stopped });
+                }
+            };
+
+        // a temporary breakpoint is gone once hit, but not when the program
+        // stopped elsewhere
+        let reached =
+            stopped.get("bkptno").and_then(|n| n.as_str()) == Some(number.to_string().as_str());
+        if !reached {
+            self.delete_temporary_breakpoint(session_id, number).await;
+        }
+        Ok(ContinueUntil { reached, iterations: 0, stopped })
+    }
+
+    /// Run the program to a temporary breakpoint at `symbol`, `main` by
+    /// default, as the `start` command of GDB does
+    pub async fn start_program(
+        &self,
+        session_id: &str,
+        symbol: Option<&str>,
+        timeout: Duration,
+    ) -> AppResult<ProgramStarted> {
+        let number =
+            self.insert_breakpoint_at(session_id, symbol.unwrap_or("main"), None, true).await?;
+        let mut stopped =
+            match self.resume_and_wait(session_id, &MiCommand::exec_run(), timeout).await? {
+                Resumed::Stopped(stopped) | Resumed::Interrupted(stopped) => stopped,
+            };
+        let reached =
+            stopped.get("bkptno").and_then(|n| n.as_str()) == Some(number.to_string().as_str());
+        if !reached {
+            self.delete_temporary_breakpoint(session_id, number).await;
+        }
+        // added to the stops only when enabled in the configuration
+        if stopped.get("context").is_none() {
+            let context = self.stop_context(session_id, &self.config.stop_context).await;
+            if let Some(results) = stopped.as_object_mut() {
+                results.insert("context".to_string(), serde_json::to_value(context)?);
+            }
+        }
+        Ok(ProgramStarted { reached, stopped })
+    }
+
+    async fn delete_temporary_breakpoint(&self, session_id: &str, number: BreakPointNumber) {
+        let command = MiCommand::delete_breakpoints(vec![number]);
+        if let Err(e) = self.send_command_with_timeout(session_id, &command).await {
+            warn!("Failed to delete the temporary breakpoint {}: {}", number, e);
+        }
+    }
+
+    async fn step_until(
+        &self,
+        session_id: &str,
+        condition: &str,
+        max_iterations: usize,
+        timeout: Duration,
+    ) -> AppResult<ContinueUntil> {
+        let deadline = Instant::now() + timeout;
+        let mut stopped = Value::Null;
+        for iteration in 1..=max_iterations {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            if remaining.is_zero() {
+                return Ok(ContinueUntil { reached: false, iterations: iteration - 1, stopped });
+            }
+
+            let stops = self.stop_count(session_id).await?;
+            self.next_execution(session_id).await?;
+            stopped = self.wait_stopped(session_id, stops, remaining).await?;
+            let exited = stopped
+                .get("reason")
+                .and_then(|reason| reason.as_str())
+                .is_some_and(|reason| reason.starts_with("exited"));
+            if exited {
+                return Ok(ContinueUntil { reached: false, iterations: iteration, stopped });
+            }
+
+            let value = self.evaluate_expression(session_id, condition).await?;
+            if is_true(&value)? {
+                return Ok(ContinueUntil { reached: true, iterations: iteration, stopped });
+            }
+        }
+        Ok(ContinueUntil { reached: false, iterations: max_iterations, stopped })
+    }
+
+    /// Resume the program until the function of the selected frame returns
+    /// and get its return value
+    pub async fn finish(&self, session_id: &str, timeout: Duration) -> AppResult<FunctionFinished> {
+        let response =
+            self.send_command_with_timeout(session_id, &MiCommand::stack_info_frame(None)).await?;
+        let function = response
+            .results
+            .get("frame")
+            .and_then(|frame| frame.get("func"))
+            .and_then(|func| func.as_str())
+            .map(|func| func.to_string());
+
+        let stopped =
+            match self.resume_and_wait(session_id, &MiCommand::exec_finish(), timeout).await? {
+                Resumed::Stopped(stopped) | Resumed::Interrupted(stopped) => stopped,
+            };
+        let field = |key| stopped.get(key).and_then(|value| value.as_str()).map(str::to_string);
+        Ok(FunctionFinished {
+            finished: field("reason").as_deref() == Some("function-finished"),
+            function,
+            result_var: field("gdb-result-var"),
+            return_value: field("return-value"),
+            stopped,
+        })
+    }
+
+    /// Finish the functions of the frames from 0 to `frame_id` one by one,
+    /// until the function of `frame_id` returned, and get their return values.
+    /// Each finish returns to the caller of the frame it finished, so the
+    /// stack is only measured once, up to the caller of `frame_id`
+    pub async fn until_return_of(
+        &self,
+        session_id: &str,
+        frame_id: usize,
+        timeout: Duration,
+    ) -> AppResult<ReturnedFrom> {
+        let deadline = Instant::now() + timeout;
+        let depth = self.stack_depth(session_id, Some(frame_id + 2)).await?;
+        if frame_id + 1 >= depth {
+            return Err(AppError::InvalidArgument(format!(
+                "frame {} has no caller to return to, the stack has {} frames",
+                frame_id, depth
+            )));
+        }
+        // the function of the selected frame is finished
+        self.send_command_with_timeout(session_id, &MiCommand::select_frame(0)).await?;
+
+        let mut finished = vec![];
+        for _ in 0..=frame_id {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            if remaining.is_zero() {
+                return Ok(ReturnedFrom { returned: false, finished });
+            }
+            let result = self.finish(session_id, remaining).await?;
+            let stopped_early = !result.finished;
+            finished.push(result);
+            if stopped_early {
+                return Ok(ReturnedFrom { returned: false, finished });
+            }
+        }
+        Ok(ReturnedFrom { returned: true, finished })
+    }
+}
+
+/// Truth of a value printed by GDB, e.g. `1`, `false` or `0 '\000'`
+fn is_true(value: &str) -> AppResult<bool> {
+    let word = value.split_whitespace().next().unwrap_or_default();
+    match word {
+        "true" => Ok(true),
+        "false" => Ok(false),
+        _ => {
+            let number = match word.strip_prefix("0x") {
+                Some(hex) => i128::from_str_radix(hex, 16).ok(),
+                None => word.parse::<i128>().ok(),
+            };
+            number.map(|n| n != 0).ok_or_else(|| {
+                AppError::InvalidArgument(format!("condition is not a boolean, got {}", value))
+            })
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const SCRIPT: &str = r#"
+        -> -stack-info-depth 3
+        <- ^done,depth="3"
+        -> -stack-select-frame 0
+        <- ^done
+        -> -stack-info-frame
+        <- ^done,frame={level="0",addr="0x1000",func="leaf"}
+        -> -exec-finish
+        <- ^running
+        <- *running,thread-id="all"
+        <- *stopped,reason="function-finished",frame={level="0",addr="0x1010",func="middle"},gdb-result-var="$1",return-value="1"
+        -> -stack-info-frame
+        <- ^done,frame={level="0",addr="0x1010",func="middle"}
+        -> -exec-finish
+        <- ^running
+        <- *running,thread-id="all"
+        <- *stopped,reason="function-finished",frame={level="0",addr="0x1020",func="main"},gdb-result-var="$2",return-value="2"
+    "#;
+
+    #[tokio::test]
+    async fn test_until_return_of() {
+        let manager = GDBManager::default();
+        let session_id = manager.create_mock_session(SCRIPT).await.unwrap();
+        let timeout = Duration::from_secs(5);
+
+        let returned = manager.until_return_of(&session_id, 1, timeout).await.unwrap();
+        assert!(returned.returned);
+        assert_eq!(
+            returned.finished.iter().map(|f| f.function.as_deref().unwrap()).collect::<Vec<_>>(),
+            ["leaf", "middle"]
+        );
+        assert_eq!(returned.finished[1].return_value.as_deref(), Some("2"));
+    }
+
+    #[tokio::test]
+    async fn test_start_program() {
+        let manager = GDBManager::default();
+        let session_id = manager
+            .create_mock_session(
+                r#"
+                -> -break-insert -t main
+                <- ^done,bkpt={number="1",type="breakpoint",disp="del",enabled="y",addr="0x1000",func="main",times="0"}
+                -> -exec-run
+                <- ^running
+                <- *running,thread-id="all"
+                <- *stopped,reason="breakpoint-hit",disp="del",bkptno="1",frame={addr="0x1000",func="main",args=[]},thread-id="1"
+                "#,
+            )
+            .await
+            .unwrap();
+
+        let started =
+            manager.start_program(&session_id, None, Duration::from_secs(5)).await.unwrap();
+        assert!(started.reached);
+        assert_eq!(started.stopped["frame"]["func"], "main");
+        assert!(started.stopped["context"].is_object());
+    }
+
+    #[test]
+    fn test_is_true() {
+        assert!(is_true("1").unwrap());
+        assert!(is_true("true").unwrap());
+        assert!(is_true("0x10").unwrap());
+        assert!(!is_true("0").unwrap());
+        assert!(!is_true("0 '\\000'").unwrap());
+        assert!(is_true("{a = 1}").is_err());
+    }
+}
@@ -0,0 +1,285 @@
+use std::hash::{DefaultHasher, Hash, Hasher};
+
+use serde::{Deserialize, Serialize};
+use serde_with::{DisplayFromStr, serde_as};
+
+use super::GDBManager;
+use crate::error::{AppError, AppResult};
+use crate::mi::commands::MiCommand;
+use crate::mi::output::ResultRecord;
+use crate::models::Memory;
+
+/// Largest region `watch_memory_region` hashes on every stop
+const MAX_REGION_BYTES: usize = 64 * 1024;
+
+/// An expression re-evaluated on every stop
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WatchExpression {
+    /// Name of the variable object, used to remove the watch
+    pub name: String,
+    #[serde(default)]
+    pub expression: String,
+    pub value: Option<String>,
+    #[serde(rename = "type")]
+    pub type_: Option<String>,
+}
+
+/// A watch expression whose value changed since the last stop
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WatchChange {
+    pub name: String,
+    #[serde(default)]
+    pub expression: String,
+    pub value: Option<String>,
+    /// "true", "false" when the expression can't be evaluated in the current
+    /// frame, or "invalid"
+    pub in_scope: String,
+}
+
+/// The current value of a watch expression, or of one of its children such as
+/// the fields of a struct
+#[serde_as]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WatchValue {
+    /// Name of the variable object
+    pub name: String,
+    /// The expression of a watch, the name of a child, e.g. a field
+    #[serde(default, rename = "exp")]
+    pub expression: String,
+    /// None when the expression can't be evaluated in the current frame
+    pub value: Option<String>,
+    #[serde(rename = "type")]
+    pub type_: Option<String>,
+    #[serde_as(as = "DisplayFromStr")]
+    #[serde(default)]
+    pub numchild: u32,
+}
+
+/// A region of memory hashed on every stop, a watchpoint emulated in
+/// software when the hardware ones are unavailable or all used
+#[derive(Debug, Clone, Serialize)]
+pub struct MemoryWatch {
+    /// Used to stop watching the region
+    pub id: u64,
+    /// The expression the address was resolved from, when it was registered
+    pub expression: String,
+    pub address: String,
+    pub length: usize,
+    #[serde(skip)]
+    hash: u64,
+}
+
+impl GDBManager {
+    /// Register an expression to evaluate on every stop
+    pub async fn add_watch_expression(
+        &self,
+        session_id: &str,
+        expression: &str,
+    ) -> AppResult<WatchExpression> {
+        let command = MiCommand::var_create_floating(None, expression);
+        let response = self.send_command_with_timeout(session_id, &command).await?;
+        let mut watch: WatchExpression = serde_json::from_value(response.results)?;
+        watch.expression = expression.to_string();
+
+        let mut sessions = self.sessions.lock().await;
+        if let Some(handle) = sessions.get_mut(session_id) {
+            handle.watches.insert(watch.name.clone(), watch.expression.clone());
+        }
+        Ok(watch)
+    }
+
+    /// Remove a watch expression by the name returned when adding it
+    pub async fn remove_watch_expression(&self, session_id: &str, name: &str) -> AppResult<()> {
+        {
+            let sessions = self.sessions.lock().await;
+            let handle = sessions.get(session_id).ok_or_else(|| {
+                AppError::NotFound(format!("Session {} does not exist", session_id))
+            })?;
+            if !handle.watches.contains_key(name) {
+                return Err(AppError::NotFound(format!(
+                    "Watch expression {} does not exist",
+                    name
+                )));
+            }
+        }
+
+        self.send_command_with_timeout(session_id, &MiCommand::var_delete(name, true)).await?;
+
+        let mut sessions = self.sessions.lock().await;
+        if let Some(handle) = sessions.get_mut(session_id) {
+            handle.watches.remove(name);
+        }
+        Ok(())
+    }
+
+    /// The values of the watch expressions
+    pub async fn list_watches(&self, session_id: &str) -> AppResult<Vec<WatchValue>> {
+        let watches = {
+            let sessions = self.sessions.lock().await;
+            let handle = sessions.get(session_id).ok_or_else(|| {
+                AppError::NotFound(format!("Session {} does not exist", session_id))
+            })?;
+            handle.watches.clone()
+        };
+
+        let mut values = vec![];
+        for (name, expression) in watches {
+            let field = |response: AppResult<ResultRecord>, key: &str| {
+                response.ok()?.results.get(key)?.as_str().map(str::to_string)
+            };
+            let value = self
+                .send_command_with_timeout(session_id, &MiCommand::var_evaluate_expression(&name))
+                .await;
+            let numchild = self
+                .send_command_with_timeout(session_id, &MiCommand::var_info_num_children(&name))
+                .await;
+            let type_ =
+                self.send_command_with_timeout(session_id, &MiCommand::var_info_type(&name)).await;
+            values.push(WatchValue {
+                value: field(value, "value"),
+                numchild: field(numchild, "numchild")
+                    .and_then(|numchild| numchild.parse().ok())
+                    .unwrap_or_default(),
+                type_: field(type_, "type"),
+                name,
+                expression,
+            });
+        }
+        Ok(values)
+    }
+
+    /// The children of a watch expression or of one of its children
+    pub async fn list_watch_children(
+        &self,
+        session_id: &str,
+        name: &str,
+    ) -> AppResult<Vec<WatchValue>> {
+        let command = MiCommand::var_list_children(name, true, None);
+        let response = self.send_command_with_timeout(session_id, &command).await?;
+        match response.results.get("children") {
+            Some(children) => Ok(serde_json::from_value(children.to_owned())?),
+            None => Ok(vec![]),
+        }
+    }
+
+    /// Re-evaluate the watch expressions, returns those which changed
+    pub async fn update_watches(&self, session_id: &str) -> AppResult<Vec<WatchChange>> {
+        let watches = {
+            let sessions = self.sessions.lock().await;
+            let handle = sessions.get(session_id).ok_or_else(|| {
+                AppError::NotFound(format!("Session {} does not exist", session_id))
+            })?;
+            handle.watches.clone()
+        };
+        if watches.is_empty() {
+            return Ok(vec![]);
+        }
+
+        let response =
+            self.send_command_with_timeout(session_id, &MiCommand::var_update("*", true)).await?;
+        let changes: Vec<WatchChange> = serde_json::from_value(
+            response
+                .results
+                .get("changelist")
+                .ok_or(AppError::NotFound("changelist not found".to_string()))?
+                .to_owned(),
+        )?;
+        Ok(changes
+            .into_iter()
+            .filter_map(|mut change| {
+                change.expression = watches.get(&change.name)?.clone();
+                Some(change)
+            })
+            .collect())
+    }
+
+    /// Watch `length` bytes from the address an expression evaluates to, the
+    /// address is not evaluated again
+    pub async fn watch_memory_region(
+        &self,
+        session_id: &str,
+        expression: &str,
+        length: usize,
+    ) -> AppResult<MemoryWatch> {
+        if length == 0 || length > MAX_REGION_BYTES {
+            return Err(AppError::InvalidArgument(format!(
+                "length must be between 1 and {} bytes",
+                MAX_REGION_BYTES
+            )));
+        }
+        let address = format!("0x{:x}", self.resolve_address(session_id, expression).await?);
+        let memory = self.read_memory(session_id, None, address.clone(), length).await?;
+
+        let mut sessions = self.sessions.lock().await;
+        let handle = sessions
+            .get_mut(session_id)
+            .ok_or_else(|| AppError::NotFound(format!("Session {} does not exist", session_id)))?;
+        let id = handle.memory_watches.last_key_value().map_or(1, |(id, _)| id + 1);
+        let watch = MemoryWatch {
+            id,
+            expression: expression.to_string(),
+            address,
+            length,
+            hash: hash_memory(&memory),
+        };
+        handle.memory_watches.insert(id, watch.clone());
+        Ok(watch)
+    }
+
+    /// Stop watching a region by the ID returned when adding it
+    pub async fn unwatch_memory_region(&self, session_id: &str, id: u64) -> AppResult<()> {
+        let mut sessions = self.sessions.lock().await;
+        let handle = sessions
+            .get_mut(session_id)
+            .ok_or_else(|| AppError::NotFound(format!("Session {} does not exist", session_id)))?;
+        handle
+            .memory_watches
+            .remove(&id)
+            .map(|_| ())
+            .ok_or_else(|| AppError::NotFound(format!("Memory watch {} does not exist", id)))
+    }
+
+    /// Read the watched regions again, returns those whose contents changed
+    pub async fn update_memory_watches(&self, session_id: &str) -> AppResult<Vec<MemoryWatch>> {
+        let watches = {
+            let sessions = self.sessions.lock().await;
+            let handle = sessions.get(session_id).ok_or_else(|| {
+                AppError::NotFound(format!("Session {} does not exist", session_id))
+            })?;
+            handle.memory_watches.values().cloned().collect::<Vec<_>>()
+        };
+
+        let mut changes = vec![];
+        for mut watch in watches {
+            let memory =
+                self.read_memory(session_id, None, watch.address.clone(), watch.length).await?;
+            let hash = hash_memory(&memory);
+            if hash != watch.hash {
+                watch.hash = hash;
+                changes.push(watch);
+            }
+        }
+
+        let mut sessions = self.sessions.lock().await;
+        if let Some(handle) = sessions.get_mut(session_id) {
+            for change in &changes {
+                // it may have been removed in the meantime
+                if let Some(watch) = handle.memory_watches.get_mut(&change.id) {
+                    watch.hash = change.hash;
+                }
+            }
+        }
+        Ok(changes)
+    }
+}
+
+/// Hash of the readable blocks of a region, a block becoming readable or
+/// unreadable is a change too
+fn hash_memory(memory: &[Memory]) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    for block in memory {
+        block.begin.hash(&mut hasher);
+        block.contents.hash(&mut hasher);
+    }
+    hasher.finish()
+}
@@ -0,0 +1,135 @@
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use tracing::Level;
+use tracing_appender::rolling::{RollingFileAppender, Rotation};
+
+use crate::config::{LogConfig, LogRotation};
+use crate::error::{AppError, AppResult};
+
+const LOG_FILE_PREFIX: &str = "mcp-gdb";
+const LOG_FILE_SUFFIX: &str = "log";
+/// Only the end of the log file is read when tailing
+const TAIL_MAX_BYTES: u64 = 4 * 1024 * 1024;
+
+/// Rolling log files named `mcp-gdb.<date>.log`
+pub fn file_appender(log: &LogConfig) -> AppResult<RollingFileAppender> {
+    let rotation = match log.rotation {
+        LogRotation::Minutely => Rotation::MINUTELY,
+        LogRotation::Hourly => Rotation::HOURLY,
+        LogRotation::Daily => Rotation::DAILY,
+        LogRotation::Never => Rotation::NEVER,
+    };
+    let mut builder = RollingFileAppender::builder()
+        .rotation(rotation)
+        .filename_prefix(LOG_FILE_PREFIX)
+        .filename_suffix(LOG_FILE_SUFFIX);
+    if let Some(max_files) = log.max_files {
+        builder = builder.max_log_files(max_files);
+    }
+    builder.build(&log.dir).map_err(|e| {
+        AppError::InvalidArgument(format!(
+            "failed to create log file in {}: {}",
+            log.dir.display(),
+            e
+        ))
+    })
+}
+
+/// The log file written last in `dir`
+pub fn latest_file(dir: &Path) -> Option<PathBuf> {
+    std::fs::read_dir(dir)
+        .ok()?
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| {
+            let name = entry.file_name();
+            let name = name.to_string_lossy();
+            name.starts_with(LOG_FILE_PREFIX) && name.ends_with(LOG_FILE_SUFFIX)
+        })
+        .filter_map(|entry| Some((entry.metadata().ok()?.modified().ok()?, entry.path())))
+        .max()
+        .map(|(_, path)| path)
+}
+
+/// Last `count` lines of the log file at or above `level`
+pub fn tail(path: &Path, count: usize, level: Option<Level>) -> AppResult<Vec<String>> {
+    let mut file = File::open(path)?;
+    let len = file.metadata()?.len();
+    let start = len.saturating_sub(TAIL_MAX_BYTES);
+    file.seek(SeekFrom::Start(start))?;
+    let mut content = Vec::new();
+    file.read_to_end(&mut content)?;
+    let content = String::from_utf8_lossy(&content);
+
+    let mut lines = content.lines();
+    if start > 0 {
+        // drop the partially read line
+        lines.next();
+    }
+    Ok(filter_lines(lines, count, level))
+}
+
+fn filter_lines<'a>(
+    lines: impl Iterator<Item = &'a str>,
+    count: usize,
+    level: Option<Level>,
+) -> Vec<String> {
+    let mut kept = Vec::new();
+    // continuation lines of multi-line records take the level of the record
+    let mut current = None;
+    for line in lines {
+        if let Some(line_level) = line_level(line) {
+            current = Some(line_level);
+        }
+        // Level orders more verbose as greater
+        let keep = match (level, current) {
+            (None, _) => true,
+            (Some(level), Some(current)) => current <= level,
+            (Some(_), None) => false,
+        };
+        if keep {
+            kept.push(line.to_string());
+        }
+    }
+    let skip = kept.len().saturating_sub(count);
+    kept.split_off(skip)
+}
+
+/// Level of a log line in any of the supported formats, None for continuation
+/// lines
+fn line_level(line: &str) -> Option<Level> {
+    if line.starts_with('{') {
+        let record: serde_json::Value = serde_json::from_str(line).ok()?;
+        return Level::from_str(record.get("level")?.as_str()?).ok();
+    }
+    // the level follows the timestamp in the text formats
+    line.split_whitespace().take(2).find_map(|word| match word {
+        "TRACE" | "DEBUG" | "INFO" | "WARN" | "ERROR" => Level::from_str(word).ok(),
+        _ => None,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_filter_lines() {
+        let log = "2025-01-01T00:00:00.000000Z  INFO mcp_server_gdb: Starting\n\
+                   2025-01-01T00:00:01.000000Z DEBUG mcp_server_gdb::mi: -break-insert main\n\
+                   2025-01-01T00:00:02.000000Z ERROR mcp_server_gdb::gdb: No symbol table\n\
+                   \x20   at src/gdb.rs:120\n\
+                   {\"timestamp\":\"2025-01-01T00:00:03Z\",\"level\":\"WARN\",\"fields\":{}}\n";
+        let lines = filter_lines(log.lines(), 10, Some(Level::WARN));
+        assert_eq!(lines.len(), 3);
+        assert!(lines[0].contains("No symbol table"));
+        assert!(lines[1].contains("src/gdb.rs"));
+        assert!(lines[2].contains("WARN"));
+
+        let lines = filter_lines(log.lines(), 2, None);
+        assert_eq!(lines.len(), 2);
+        assert!(lines[0].contains("src/gdb.rs"));
+    }
+}
@@ -1,28 +1,39 @@
 mod config;
 mod error;
 mod gdb;
+mod logs;
+mod markdown;
+mod metrics;
 mod mi;
 mod models;
+mod resources;
+mod server;
 mod tools;
 mod ui;
 
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, HashSet};
 use std::env;
+use std::io::IsTerminal;
 use std::path::{Path, PathBuf};
 use std::str::FromStr;
 use std::sync::{Arc, LazyLock};
-use std::time::Duration;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
 use anyhow::Result;
-use clap::{Parser, ValueEnum};
+use clap::error::ErrorKind;
+use clap::{CommandFactory, Parser, ValueEnum};
+use config::LogFormat;
 use crossterm::event::EventStream;
 use error::{AppError, AppResult};
 use futures::StreamExt;
-use gdb::GDBManager;
-use mcp_core::server::{Server, ServerProtocolBuilder};
-use mcp_core::transport::{ServerSseTransport, ServerStdioTransport, Transport};
-use mcp_core::types::ServerCapabilities;
-use models::{ASM, BT, MemoryMapping, MemoryType, ResolveSymbol, TrackedRegister};
+use gdb::{Activity, StopState};
+use mcp_core::transport::{ServerSseTransport, Transport};
+use mcp_core::types::{ResourceCapabilities, ServerCapabilities};
+use mi::commands::BreakPointNumber;
+use models::{
+    ASM, BT, BreakPointSet, Endian, MemoryMapping, MemoryType, RegisterRaw, ResolveSymbol,
+    TrackedRegister, Variable,
+};
 use ratatui::Terminal;
 use ratatui::crossterm::event::{DisableMouseCapture, Event, KeyCode};
 use ratatui::crossterm::execute;
@@ -31,15 +42,29 @@ use ratatui::crossterm::terminal::{
 };
 use ratatui::prelude::Backend;
 use ratatui::widgets::ScrollbarState;
+use resources::MAX_HEXDUMP_BYTES;
 use serde_json::json;
-use tokio::sync::{Mutex, mpsc, oneshot};
+use server::{ServerBuilder, StdioTransport};
+use tokio::sync::{Mutex, Notify, mpsc, oneshot, watch};
 use tools::GDB_MANAGER;
 use tracing::{debug, error, info, warn};
-use tracing_appender::rolling::{RollingFileAppender, Rotation};
-use tracing_subscriber::EnvFilter;
 use tracing_subscriber::layer::SubscriberExt;
 use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::{EnvFilter, Layer};
+use ui::SAVED_STACK;
+use ui::breakpoints::sorted_breakpoints;
 use ui::hexdump::HEXDUMP_WIDTH;
+use ui::input::InputBar;
+use ui::locals::WatchRow;
+use ui::search::Search;
+
+/// Bytes read when jumping to an address in the hexdump
+const HEXDUMP_READ_BYTES: usize = 4096;
+/// Columns scrolled by h and l in the views with long lines
+const HORIZONTAL_STEP: usize = 8;
+/// Interval of the redraws and of the checks for a new session when nothing
+/// is notified
+const TICK: Duration = Duration::from_millis(250);
 
 #[derive(Copy, Clone, PartialEq, Eq, ValueEnum, Debug)]
 enum TransportType {
@@ -87,7 +112,9 @@ struct Args {
         required_if_eq("enable_tui", "true"),
         value_parser = clap::builder::ValueParser::new(|s: &str| -> Result<TransportType, String> {
             let t = s.parse::<TransportType>()?;
-            if t == TransportType::Stdio && std::env::args().any(|arg| arg == "--enable-tui") {
+            if t == TransportType::Stdio
+                && std::env::args().any(|arg| arg == "--enable-tui" || arg == "--tui")
+            {
                 Err("When TUI is enabled, transport must be SSE".to_string())
             } else {
                 Ok(t)
@@ -97,9 +124,36 @@ struct Args {
     )]
     transport: TransportType,
 
-    /// Enable TUI
-    #[arg(long)]
+    /// Enable TUI, only usable with the SSE transport and a terminal
+    #[arg(long, visible_alias = "tui")]
     enable_tui: bool,
+
+    /// Path to the configuration file, defaults to
+    /// ~/.config/mcp-gdb/config.toml
+    #[arg(long)]
+    config: Option<PathBuf>,
+
+    /// Address the SSE transport binds to
+    #[arg(long)]
+    host: Option<String>,
+
+    /// Port the SSE transport listens on
+    #[arg(long)]
+    port: Option<u16>,
+
+    /// Port `/healthz` and `/metrics` are served on with the SSE transport,
+    /// defaults to the one after `--port`
+    #[arg(long)]
+    metrics_port: Option<u16>,
+
+    /// Directory of the log files
+    #[arg(long)]
+    log_dir: Option<PathBuf>,
+
+    /// Write logs to stderr instead of log files, not available with the stdio
+    /// transport
+    #[arg(long, conflicts_with = "enable_tui")]
+    log_to_stderr: bool,
 }
 
 #[derive(Copy, Clone, Default, PartialEq)]
@@ -112,6 +166,9 @@ enum Mode {
     OnlyOutput,
     OnlyMapping,
     OnlyHexdump,
+    OnlyBreakpoints,
+    OnlyLocals,
+    OnlyBacktrace,
 }
 
 impl Mode {
@@ -123,35 +180,113 @@ impl Mode {
             Mode::OnlyInstructions => Mode::OnlyOutput,
             Mode::OnlyOutput => Mode::OnlyMapping,
             Mode::OnlyMapping => Mode::OnlyHexdump,
-            Mode::OnlyHexdump => Mode::All,
+            Mode::OnlyHexdump => Mode::OnlyBreakpoints,
+            Mode::OnlyBreakpoints => Mode::OnlyLocals,
+            Mode::OnlyLocals => Mode::OnlyBacktrace,
+            Mode::OnlyBacktrace => Mode::All,
         }
     }
 }
 
-/// An endian
-#[derive(Debug, Copy, Clone, Eq, PartialEq)]
-pub enum Endian {
-    /// Little endian
-    Little,
-    /// Big endian
-    Big,
+/// The data `sync_target` reads, the one of the visible views
+#[derive(Debug, Clone, Copy)]
+struct Panes {
+    registers: bool,
+    frames: bool,
+    disassembly: bool,
+    memory_map: bool,
+    stack: bool,
+    hexdump: bool,
+    breakpoints: bool,
+    locals: bool,
+}
+
+impl Mode {
+    /// The data the views shown in this mode need
+    fn panes(self) -> Panes {
+        Panes {
+            // the hexdump jumps to the registers holding a pointer
+            registers: matches!(self, Mode::All | Mode::OnlyRegister | Mode::OnlyHexdump),
+            // the backtrace is above the views but the output
+            frames: self != Mode::OnlyOutput,
+            disassembly: matches!(self, Mode::All | Mode::OnlyInstructions),
+            // the values are colored by the mapping they point to
+            memory_map: matches!(
+                self,
+                Mode::All
+                    | Mode::OnlyRegister
+                    | Mode::OnlyStack
+                    | Mode::OnlyInstructions
+                    | Mode::OnlyMapping
+                    | Mode::OnlyHexdump
+            ),
+            stack: matches!(self, Mode::All | Mode::OnlyStack),
+            hexdump: self == Mode::OnlyHexdump,
+            breakpoints: self == Mode::OnlyBreakpoints,
+            locals: self == Mode::OnlyLocals,
+        }
+    }
+}
+
+/// What the text typed in a popup is for
+#[derive(Copy, Clone, PartialEq)]
+enum Popup {
+    /// An address to show in the hexdump
+    GotoAddress,
+    /// A pattern to search in the output or the hexdump
+    Search,
 }
 
 #[derive(Default)]
 pub struct MyScrollState {
     pub scroll: usize,
     pub state: ScrollbarState,
+    /// The last page was shown, it keeps following the new lines
+    pub follow: bool,
+    /// Columns scrolled out on the left of the long lines
+    pub x: usize,
+}
+
+impl MyScrollState {
+    fn top(&mut self) {
+        self.scroll = 0;
+        self.follow = false;
+        self.state = self.state.position(0);
+    }
+
+    fn bottom(&mut self, len: usize) {
+        self.scroll = len.saturating_sub(1);
+        self.follow = true;
+        self.state = self.state.position(self.scroll);
+    }
+
+    /// First line of a page of `height` lines out of `len`. The scroll is
+    /// kept within the last page, and stays on it as lines are added if the
+    /// user was there
+    pub fn page(&mut self, len: usize, height: usize) -> usize {
+        let last_page = len.saturating_sub(height);
+        if self.follow {
+            self.scroll = last_page;
+        }
+        self.scroll = self.scroll.min(last_page);
+        self.follow = self.scroll == last_page;
+        self.state = self.state.content_length(last_page + 1).position(self.scroll);
+        self.scroll
+    }
 }
 
 #[derive(Default)]
 struct App {
-    gdb: GDBManager,
     /// -32 bit mode
     bit32: bool,
     /// Current filepath of .text
     filepath: Option<PathBuf>,
     /// Current endian
     endian: Option<Endian>,
+    /// Word width and endian chosen by the user, they are detected from the
+    /// session otherwise
+    bit32_override: Option<bool>,
+    endian_override: Option<Endian>,
     /// Current display mode
     mode: Mode,
     /// Memory map TUI
@@ -162,8 +297,7 @@ struct App {
     /// All output from gdb
     output: Vec<String>,
     output_scroll: MyScrollState,
-    /// Saved output such as (gdb) or > from gdb
-    stream_output_prompt: String,
+    output_search: Option<Search>,
     /// Register TUI
     register_changed: Vec<u8>,
     registers: Vec<TrackedRegister>,
@@ -171,14 +305,49 @@ struct App {
     stack: BTreeMap<u64, ResolveSymbol>,
     /// Saved ASM
     asm: Vec<ASM>,
+    /// Only scrolled horizontally, the view follows $pc
+    asm_scroll: MyScrollState,
     /// Hexdump
     hexdump: Option<(u64, Vec<u8>)>,
+    /// Bytes of the hexdump at the previous stop, the changed ones are
+    /// highlighted
+    hexdump_previous: Option<Vec<u8>>,
     hexdump_scroll: MyScrollState,
+    hexdump_search: Option<Search>,
+    /// Text typed in a popup
+    popup: Option<(Popup, InputBar)>,
+    /// Index of the register jumped to in the hexdump, among those holding a
+    /// pointer
+    hexdump_register: usize,
     /// Right side of status in TUI
     async_result: String,
     /// Left side of status in TUI
     status: String,
+    /// Backtrace TUI
     bt: Vec<BT>,
+    bt_selected: usize,
+    /// Level of the frame the locals are listed for
+    locals_frame: usize,
+    /// Breakpoints TUI
+    breakpoints: BreakPointSet,
+    breakpoint_selected: usize,
+    /// Locals TUI
+    locals: Vec<Variable>,
+    /// Watch expressions with the children which are expanded
+    watches: Vec<WatchRow>,
+    watch_expanded: HashSet<String>,
+    watch_selected: usize,
+    /// GDB command line
+    input: InputBar,
+    /// Session the typed commands are sent to, the latest one
+    session_id: Option<String>,
+    /// Status bar
+    transport: Option<TransportType>,
+    /// Connected MCP clients, unknown if the connections cannot be listed
+    clients: Option<usize>,
+    activity: Activity,
+    /// Wakes up the draw loop after a change
+    redraw: Arc<Notify>,
     /// Exit the app
     _exit: bool,
 }
@@ -192,6 +361,128 @@ impl App {
         self.filepath = Some(filepath);
     }
 
+    /// Draw the views again, the notifications until the next frame are
+    /// batched into one redraw
+    fn redraw(&self) {
+        self.redraw.notify_one();
+    }
+
+    /// Keys are typed in the command line or in a popup
+    fn is_typing(&self) -> bool {
+        self.input.active || self.popup.is_some()
+    }
+
+    /// Search the output or the hexdump view and scroll to the first match
+    fn search(&mut self, pattern: &str) {
+        let search = match self.mode {
+            Mode::OnlyOutput => Search::lines(&self.output, pattern),
+            Mode::OnlyHexdump => match self.hexdump.as_ref() {
+                Some((_, bytes)) => Search::bytes(bytes, pattern),
+                None => return,
+            },
+            _ => return,
+        };
+        match search {
+            Ok(search) => {
+                self.async_result = format!("{} matches of {}", search.matches.len(), pattern);
+                let first = search.current();
+                match self.mode {
+                    Mode::OnlyOutput => self.output_search = Some(search),
+                    _ => self.hexdump_search = Some(search),
+                }
+                if let Some(position) = first {
+                    self.scroll_to_match(position);
+                }
+            }
+            Err(e) => self.async_result = e.to_string(),
+        }
+    }
+
+    /// Scroll to the next or the previous match of the search
+    fn search_next(&mut self, forward: bool) {
+        let search = match self.mode {
+            Mode::OnlyOutput => self.output_search.as_mut(),
+            Mode::OnlyHexdump => self.hexdump_search.as_mut(),
+            _ => None,
+        };
+        let position =
+            search.and_then(|search| if forward { search.next() } else { search.previous() });
+        if let Some(position) = position {
+            self.scroll_to_match(position);
+        }
+    }
+
+    fn scroll_to_match(&mut self, position: usize) {
+        let (scroll, line) = match self.mode {
+            Mode::OnlyOutput => (&mut self.output_scroll, position),
+            _ => (&mut self.hexdump_scroll, position / HEXDUMP_WIDTH),
+        };
+        scroll.scroll = line;
+        scroll.follow = false;
+        scroll.state = scroll.state.position(line);
+    }
+
+    /// The scroll state of the view with long lines and the width of the
+    /// longest one, these views can be scrolled horizontally
+    fn wide_view(&mut self) -> Option<(&mut MyScrollState, usize)> {
+        let longest = match self.mode {
+            Mode::OnlyOutput => {
+                self.output.iter().map(|line| line.replace('\t', "    ").chars().count()).max()
+            }
+            Mode::OnlyMapping => self.memory_map.as_ref().and_then(|memory_map| {
+                memory_map
+                    .iter()
+                    .filter_map(|m| m.path.as_ref())
+                    .map(|path| path.to_string_lossy().chars().count())
+                    .max()
+            }),
+            Mode::OnlyInstructions => self.asm.iter().map(|asm| asm.inst.chars().count()).max(),
+            _ => return None,
+        }
+        .unwrap_or_default();
+        let scroll = match self.mode {
+            Mode::OnlyOutput => &mut self.output_scroll,
+            Mode::OnlyMapping => &mut self.memory_map_scroll,
+            _ => &mut self.asm_scroll,
+        };
+        Some((scroll, longest))
+    }
+
+    /// Registers whose value points into the mapped memory, or all those which
+    /// are not null when the mappings are unknown
+    fn pointer_registers(&self) -> Vec<(String, u64)> {
+        self.registers
+            .iter()
+            .filter_map(|tracked| {
+                let register = tracked.register.as_ref()?;
+                let value = match register.value.as_ref()? {
+                    RegisterRaw::U32(value) => u64::from(value.0),
+                    RegisterRaw::U64(value) => value.0,
+                    _ => return None,
+                };
+                Some((register.name.clone()?, value))
+            })
+            .filter(|(_, value)| match self.memory_map.as_ref() {
+                Some(memory_map) => memory_map.iter().any(|mapping| mapping.contains(*value)),
+                None => *value != 0,
+            })
+            .collect()
+    }
+
+    /// Write the bytes of the hexdump view to a new file of the dump
+    /// directory, None if the view is empty
+    fn save_hexdump(&self) -> std::io::Result<Option<PathBuf>> {
+        let Some((address, bytes)) = self.hexdump.as_ref() else {
+            return Ok(None);
+        };
+        let dir = &config::get().tui.dump_dir;
+        std::fs::create_dir_all(dir)?;
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default();
+        let path = dir.join(format!("hexdump-{}-0x{:x}.bin", now.as_millis(), address));
+        std::fs::write(&path, bytes)?;
+        Ok(Some(path))
+    }
+
     pub async fn find_first_heap(&self) -> Option<MemoryMapping> {
         self.memory_map.as_ref()?.iter().find(|a| a.is_heap()).cloned()
     }
@@ -201,28 +492,7 @@ impl App {
     }
 
     pub fn classify_val(&self, val: u64, filepath: &Path) -> MemoryType {
-        if val != 0 {
-            // look through, add see if the value is part of the stack
-            // trace!("{:02x?}", memory_map);
-            if let Some(memory_map) = self.memory_map.as_ref() {
-                for r in memory_map {
-                    if r.contains(val) {
-                        if r.is_stack() {
-                            return MemoryType::Stack;
-                        }
-                        if r.is_heap() {
-                            return MemoryType::Heap;
-                        }
-                        if r.is_path(filepath) || r.is_exec() {
-                            // TODO(23): This could be expanded to all segments loaded in
-                            // as executable
-                            return MemoryType::Exec;
-                        }
-                    }
-                }
-            }
-        }
-        MemoryType::Unknown
+        MemoryType::classify(self.memory_map.as_deref().unwrap_or_default(), val, Some(filepath))
     }
 }
 
@@ -231,29 +501,79 @@ async fn main() -> Result<(), AppError> {
     dotenv::dotenv().ok();
 
     let args = Args::parse();
+    if args.log_to_stderr && args.transport == TransportType::Stdio {
+        Args::command()
+            .error(ErrorKind::ArgumentConflict, "--log-to-stderr requires the SSE transport")
+            .exit();
+    }
 
-    let file_appender = RollingFileAppender::new(Rotation::DAILY, "logs", "mcp-gdb.log");
-    let (non_blocking, _guard) = tracing_appender::non_blocking(file_appender);
+    // Get configuration, CLI flags take precedence
+    let mut config = config::Config::load(args.config.as_deref())?;
+    if args.enable_tui {
+        config.tui.enabled = true;
+    }
+    if let Some(host) = args.host {
+        config.server_ip = host;
+    }
+    if let Some(port) = args.port {
+        config.server_port = port;
+    }
+    if let Some(port) = args.metrics_port {
+        config.metrics_port = Some(port);
+    }
+    if let Some(log_dir) = args.log_dir {
+        config.log.dir = log_dir;
+    }
+    config.log.stderr = args.log_to_stderr;
+    if args.log_to_stderr && config.tui.enabled {
+        eprintln!("Logging to stderr would garble the TUI, disabling it");
+        config.tui.enabled = false;
+    }
+    if config.tui.enabled && args.transport == TransportType::Stdio {
+        eprintln!("TUI requires the SSE transport, disabling it");
+        config.tui.enabled = false;
+    }
+    // e.g. started by a MCP client or a service manager
+    if config.tui.enabled && !std::io::stdout().is_terminal() {
+        eprintln!("TUI requires a terminal, disabling it");
+        config.tui.enabled = false;
+    }
+    config::init(config.clone());
+
+    let (non_blocking, _guard) = if args.log_to_stderr {
+        tracing_appender::non_blocking(std::io::stderr())
+    } else {
+        tracing_appender::non_blocking(logs::file_appender(&config.log)?)
+    };
+    let fmt_layer =
+        tracing_subscriber::fmt::layer().with_writer(non_blocking).with_ansi(args.log_to_stderr);
+    let fmt_layer = match config.log.format {
+        LogFormat::Full => fmt_layer.boxed(),
+        LogFormat::Pretty => fmt_layer.pretty().boxed(),
+        LogFormat::Json => fmt_layer.json().boxed(),
+    };
 
     // Initialize logging
     tracing_subscriber::registry()
         .with(EnvFilter::try_from_default_env().unwrap_or_else(|_| {
             EnvFilter::try_new(&args.log_level).unwrap_or_else(|_| EnvFilter::new("info"))
         }))
-        // needs to go to file due to stdio transport
-        .with(tracing_subscriber::fmt::layer().with_writer(non_blocking))
+        // never goes to stdout which is taken by the stdio transport
+        .with(fmt_layer)
         .init();
 
-    // Get configuration
-    let config = config::Config::default();
     debug!("config: {:?}", config);
 
     info!("Starting MCP GDB Server on port {}", config.server_port);
 
-    let app = Arc::new(Mutex::new(Default::default()));
+    let app = Arc::new(Mutex::new(App {
+        bit32_override: config.tui.pointer_bits.map(|bits| bits == 32),
+        endian_override: config.tui.endian,
+        ..Default::default()
+    }));
 
     // Initialize terminal
-    let ui_handle = if args.enable_tui {
+    let ui_handle = if config.tui.enabled {
         // TODO: add panic hook to restore terminal
         enable_raw_mode()?;
         execute!(std::io::stdout(), EnterAlternateScreen)?;
@@ -263,8 +583,9 @@ async fn main() -> Result<(), AppError> {
                 let (quit_sender, quit_receiver) = oneshot::channel();
                 let app_clone = app.clone();
                 let terminal_for_tui = terminal.clone();
+                let refresh = Duration::from_millis(config.tui.refresh_ms);
                 let tui_handle = tokio::spawn(async move {
-                    if let Err(e) = run_app(terminal_for_tui, app_clone).await {
+                    if let Err(e) = run_app(terminal_for_tui, app_clone, refresh).await {
                         error!("failed to run app: {}", e);
                     } else {
                         quit_sender.send(()).unwrap();
@@ -285,21 +606,30 @@ async fn main() -> Result<(), AppError> {
     tools::init_gdb_manager();
 
     let server_protocol =
-        Server::builder("MCP Server GDB".to_string(), env!("CARGO_PKG_VERSION").to_string())
+        ServerBuilder::new("MCP Server GDB".to_string(), env!("CARGO_PKG_VERSION").to_string())
             .capabilities(ServerCapabilities {
+                // e.g. the peripheral tools are listed once an SVD file is loaded
                 tools: Some(json!({
-                    "listChanged": false,
+                    "listChanged": true,
                 })),
+                resources: Some(ResourceCapabilities {
+                    subscribe: Some(false),
+                    list_changed: Some(false),
+                }),
                 ..Default::default()
             });
 
     let server_protocol = register_tools(server_protocol).build();
 
+    {
+        let mut app = app.lock().await;
+        app.transport = Some(args.transport);
+    }
+
     let transport = match args.transport {
         TransportType::Stdio => {
-            let transport = Arc::new(
-                Box::new(ServerStdioTransport::new(server_protocol)) as Box<dyn Transport>
-            );
+            let transport =
+                Arc::new(Box::new(StdioTransport::new(server_protocol)) as Box<dyn Transport>);
             {
                 let mut transport_guard = TRANSPORT.lock().await;
                 *transport_guard = Some(transport.clone());
@@ -307,6 +637,12 @@ async fn main() -> Result<(), AppError> {
             transport
         }
         TransportType::Sse => {
+            let (host, port) = (config.server_ip.clone(), config.metrics_port());
+            tokio::spawn(async move {
+                if let Err(e) = metrics::serve(&host, port).await {
+                    error!("failed to serve the metrics on {}:{}: {}", host, port, e);
+                }
+            });
             let transport = Arc::new(Box::new(ServerSseTransport::new(
                 config.server_ip,
                 config.server_port,
@@ -320,6 +656,8 @@ async fn main() -> Result<(), AppError> {
         }
     };
 
+    tokio::spawn(server::notify_tools_changed(transport.clone()));
+
     // Start transport in a separate task
     let transport_clone = transport.clone();
     let transport_handle = tokio::spawn(async move {
@@ -365,19 +703,27 @@ async fn main() -> Result<(), AppError> {
         }
     }
 
-    // TODO: transport is still running due to a sync call (reader.read_line) in the
-    // dependency
+    // TODO: transport is still running due to a blocking read of stdin
     std::process::exit(0);
 }
 
 fn scroll_down(n: usize, scroll: &mut MyScrollState, len: usize) {
     if scroll.scroll < len.saturating_sub(1) {
-        scroll.scroll += n;
+        scroll.scroll = (scroll.scroll + n).min(len - 1);
         scroll.state = scroll.state.position(scroll.scroll);
     }
 }
 
+fn scroll_right(n: usize, scroll: &mut MyScrollState, longest: usize) {
+    scroll.x = (scroll.x + n).min(longest.saturating_sub(1));
+}
+
+fn scroll_left(n: usize, scroll: &mut MyScrollState) {
+    scroll.x = scroll.x.saturating_sub(n);
+}
+
 fn scroll_up(n: usize, scroll: &mut MyScrollState) {
+    scroll.follow = false;
     if scroll.scroll > n {
         scroll.scroll -= n;
     } else {
@@ -389,18 +735,78 @@ fn scroll_up(n: usize, scroll: &mut MyScrollState) {
 async fn run_app<B: Backend + Send + 'static>(
     terminal: Arc<Mutex<Terminal<B>>>,
     app: Arc<Mutex<App>>,
+    refresh: Duration,
 ) -> AppResult<()> {
     let app_clone1 = app.clone();
     let app_clone2 = app.clone();
+    let app_clone3 = app.clone();
+    let app_clone4 = app.clone();
+    let app_clone5 = app.clone();
     let mut reader = EventStream::new();
     let (tx, mut rx) = mpsc::channel(100);
+    let redraw = app.lock().await.redraw.clone();
 
     let event_loop = tokio::spawn(async move {
         while let Some(event) = rx.recv().await {
             if let Event::Key(key) = event {
                 debug!("key >>> {:?}", key);
                 let mut app = app_clone1.lock().await;
+                // drawn once the key is handled and the lock released
+                app.redraw();
+                let previous_mode = app.mode;
+                if let Some((popup, input)) = app.popup.as_mut() {
+                    match key.code {
+                        KeyCode::Esc => app.popup = None,
+                        KeyCode::Enter => {
+                            let (popup, text) = (*popup, input.submit());
+                            app.popup = None;
+                            match (popup, text) {
+                                (Popup::GotoAddress, Some(address)) => show_memory(
+                                    app_clone1.clone(),
+                                    app.session_id.clone(),
+                                    address,
+                                    HEXDUMP_READ_BYTES,
+                                ),
+                                (Popup::Search, Some(pattern)) => app.search(&pattern),
+                                (_, None) => (),
+                            }
+                        }
+                        KeyCode::Char(c) => input.insert(c),
+                        KeyCode::Backspace => input.backspace(),
+                        KeyCode::Delete => input.delete(),
+                        KeyCode::Left => input.left(),
+                        KeyCode::Right => input.right(),
+                        KeyCode::Home => input.home(),
+                        KeyCode::End => input.end(),
+                        _ => (),
+                    }
+                    continue;
+                }
+                if app.input.active {
+                    match key.code {
+                        KeyCode::Esc => app.input.active = false,
+                        KeyCode::Enter => {
+                            if let Some(command) = app.input.submit() {
+                                run_command(app_clone1.clone(), app.session_id.clone(), command);
+                            }
+                        }
+                        KeyCode::Char(c) => app.input.insert(c),
+                        KeyCode::Backspace => app.input.backspace(),
+                        KeyCode::Delete => app.input.delete(),
+                        KeyCode::Left => app.input.left(),
+                        KeyCode::Right => app.input.right(),
+                        KeyCode::Home => app.input.home(),
+                        KeyCode::End => app.input.end(),
+                        KeyCode::Up => app.input.previous(),
+                        KeyCode::Down => app.input.next(),
+                        _ => (),
+                    }
+                    continue;
+                }
                 match key.code {
+                    KeyCode::Char(':') => {
+                        app.input.active = true;
+                    }
                     KeyCode::Tab => {
                         app.mode = app.mode.next();
                     }
@@ -425,15 +831,48 @@ async fn run_app<B: Backend + Send + 'static>(
                     KeyCode::F(7) => {
                         app.mode = Mode::OnlyHexdump;
                     }
+                    KeyCode::F(8) => {
+                        app.mode = Mode::OnlyBreakpoints;
+                    }
+                    KeyCode::F(9) => {
+                        app.mode = Mode::OnlyLocals;
+                    }
+                    KeyCode::F(10) => {
+                        app.mode = Mode::OnlyBacktrace;
+                    }
+                    // word width and endian, the stack is read again with them
+                    KeyCode::Char('W') => {
+                        app.bit32 = !app.bit32;
+                        app.bit32_override = Some(app.bit32);
+                        spawn_sync_target(app_clone1.clone(), app.session_id.clone());
+                    }
+                    KeyCode::Char('E') => {
+                        let endian = match app.endian {
+                            Some(Endian::Big) => Endian::Little,
+                            _ => Endian::Big,
+                        };
+                        app.endian = Some(endian);
+                        app.endian_override = Some(endian);
+                        spawn_sync_target(app_clone1.clone(), app.session_id.clone());
+                    }
+                    // long lines of the output, mapping and instructions views
+                    KeyCode::Char('l') => {
+                        if let Some((scroll, longest)) = app.wide_view() {
+                            scroll_right(HORIZONTAL_STEP, scroll, longest);
+                        }
+                    }
+                    KeyCode::Char('h') => {
+                        if let Some((scroll, _)) = app.wide_view() {
+                            scroll_left(HORIZONTAL_STEP, scroll);
+                        }
+                    }
                     // output
                     KeyCode::Char('g') if app.mode == Mode::OnlyOutput => {
-                        app.output_scroll.scroll = 0;
-                        app.output_scroll.state = app.output_scroll.state.position(0);
+                        app.output_scroll.top();
                     }
                     KeyCode::Char('G') if app.mode == Mode::OnlyOutput => {
                         let len = app.output.len();
-                        app.output_scroll.scroll = len;
-                        app.output_scroll.state.last();
+                        app.output_scroll.bottom(len);
                     }
                     KeyCode::Char('j') if app.mode == Mode::OnlyOutput => {
                         let len = app.output.len();
@@ -449,22 +888,19 @@ async fn run_app<B: Backend + Send + 'static>(
                     KeyCode::Char('K') if app.mode == Mode::OnlyOutput => {
                         scroll_up(50, &mut app.output_scroll);
                     }
-                    // memory mapping
+                    // memory mapping, the scroll position is the selected mapping
                     KeyCode::Char('g') if app.mode == Mode::OnlyMapping => {
-                        app.memory_map_scroll.scroll = 0;
-                        app.memory_map_scroll.state = app.memory_map_scroll.state.position(0);
+                        app.memory_map_scroll.top();
                     }
                     KeyCode::Char('G') if app.mode == Mode::OnlyMapping => {
                         if let Some(memory) = app.memory_map.as_ref() {
                             let len = memory.len();
-                            let memory_map_scroll = &mut app.memory_map_scroll;
-                            memory_map_scroll.scroll = len;
-                            memory_map_scroll.state.last();
+                            app.memory_map_scroll.bottom(len);
                         }
                     }
                     KeyCode::Char('j') if app.mode == Mode::OnlyMapping => {
                         if let Some(memory) = app.memory_map.as_ref() {
-                            let len = memory.len() / HEXDUMP_WIDTH;
+                            let len = memory.len();
                             scroll_down(1, &mut app.memory_map_scroll, len);
                         }
                     }
@@ -473,63 +909,106 @@ async fn run_app<B: Backend + Send + 'static>(
                     }
                     KeyCode::Char('J') if app.mode == Mode::OnlyMapping => {
                         if let Some(memory) = app.memory_map.as_ref() {
-                            let len = memory.len() / HEXDUMP_WIDTH;
+                            let len = memory.len();
                             scroll_down(50, &mut app.memory_map_scroll, len);
                         }
                     }
                     KeyCode::Char('K') if app.mode == Mode::OnlyMapping => {
                         scroll_up(50, &mut app.memory_map_scroll);
                     }
+                    KeyCode::Enter if app.mode == Mode::OnlyMapping => {
+                        let selected = app
+                            .memory_map
+                            .as_ref()
+                            .and_then(|memory| memory.get(app.memory_map_scroll.scroll))
+                            .cloned();
+                        if let Some(mapping) = selected {
+                            show_memory(
+                                app_clone1.clone(),
+                                app.session_id.clone(),
+                                format!("0x{:x}", mapping.start_address),
+                                mapping.size.min(MAX_HEXDUMP_BYTES) as usize,
+                            );
+                            app.mode = Mode::OnlyHexdump;
+                        }
+                    }
                     // hexdump
                     KeyCode::Char('g') if app.mode == Mode::OnlyHexdump => {
-                        app.hexdump_scroll.scroll = 0;
-                        app.hexdump_scroll.state = app.hexdump_scroll.state.position(0);
+                        app.hexdump_scroll.top();
                     }
                     KeyCode::Char('G') if app.mode == Mode::OnlyHexdump => {
                         if let Some(hexdump) = app.hexdump.as_ref() {
-                            let len = hexdump.1.len() / HEXDUMP_WIDTH;
-                            let hexdump_scroll = &mut app.hexdump_scroll;
-                            hexdump_scroll.scroll = len;
-                            hexdump_scroll.state.last();
+                            let len = hexdump.1.len().div_ceil(HEXDUMP_WIDTH);
+                            app.hexdump_scroll.bottom(len);
                         }
                     }
                     KeyCode::Char('H') if app.mode == Mode::OnlyHexdump => {
                         if let Some(find_heap) = app.find_first_heap().await {
-                            let memory = GDB_MANAGER
-                                .read_memory(
-                                    "",
-                                    Some(find_heap.start_address as isize),
-                                    "0".to_string(),
-                                    find_heap.size as usize,
-                                )
-                                .await?;
-                            // TODO: print memory
-
-                            // reset position
-                            app.hexdump_scroll.scroll = 0;
-                            app.hexdump_scroll.state = app.hexdump_scroll.state.position(0);
+                            show_memory(
+                                app_clone1.clone(),
+                                app.session_id.clone(),
+                                format!("0x{:x}", find_heap.start_address),
+                                find_heap.size.min(MAX_HEXDUMP_BYTES) as usize,
+                            );
                         }
                     }
                     KeyCode::Char('T') if app.mode == Mode::OnlyHexdump => {
+                        // from the top of the stack to its base
                         if let Some(find_stack) = app.find_first_stack().await {
-                            let memory = GDB_MANAGER
-                                .read_memory(
-                                    "",
-                                    Some(find_stack.start_address as isize),
-                                    "0".to_string(),
-                                    find_stack.size as usize,
-                                )
-                                .await?;
-                            // TODO: print memory
-
-                            // reset position
-                            app.hexdump_scroll.scroll = 0;
-                            app.hexdump_scroll.state = app.hexdump_scroll.state.position(0);
+                            show_memory(
+                                app_clone1.clone(),
+                                app.session_id.clone(),
+                                "$sp".to_string(),
+                                find_stack.size.min(MAX_HEXDUMP_BYTES) as usize,
+                            );
+                        }
+                    }
+                    KeyCode::Char('S') if app.mode == Mode::OnlyHexdump => {
+                        app.async_result = match app.save_hexdump() {
+                            Ok(Some(path)) => format!("Saved to {}", path.display()),
+                            Ok(None) => "Nothing to save".to_string(),
+                            Err(e) => format!("Failed to save: {}", e),
+                        };
+                    }
+                    KeyCode::Char('a') if app.mode == Mode::OnlyHexdump => {
+                        app.popup = Some((Popup::GotoAddress, InputBar::default()));
+                    }
+                    // search
+                    KeyCode::Char('/')
+                        if matches!(app.mode, Mode::OnlyOutput | Mode::OnlyHexdump) =>
+                    {
+                        app.popup = Some((Popup::Search, InputBar::default()));
+                    }
+                    KeyCode::Char('n')
+                        if matches!(app.mode, Mode::OnlyOutput | Mode::OnlyHexdump) =>
+                    {
+                        app.search_next(true);
+                    }
+                    KeyCode::Char('N')
+                        if matches!(app.mode, Mode::OnlyOutput | Mode::OnlyHexdump) =>
+                    {
+                        app.search_next(false);
+                    }
+                    KeyCode::Char(c @ ('r' | 'R')) if app.mode == Mode::OnlyHexdump => {
+                        let registers = app.pointer_registers();
+                        if !registers.is_empty() {
+                            let len = registers.len();
+                            app.hexdump_register = match c {
+                                'r' => (app.hexdump_register + 1) % len,
+                                _ => (app.hexdump_register + len - 1) % len,
+                            };
+                            let (_, address) = &registers[app.hexdump_register];
+                            show_memory(
+                                app_clone1.clone(),
+                                app.session_id.clone(),
+                                format!("0x{:x}", address),
+                                HEXDUMP_READ_BYTES,
+                            );
                         }
                     }
                     KeyCode::Char('j') if app.mode == Mode::OnlyHexdump => {
                         if let Some(hexdump) = app.hexdump.as_ref() {
-                            let len = hexdump.1.len() / HEXDUMP_WIDTH;
+                            let len = hexdump.1.len().div_ceil(HEXDUMP_WIDTH);
                             scroll_down(1, &mut app.hexdump_scroll, len);
                         }
                     }
@@ -538,29 +1017,217 @@ async fn run_app<B: Backend + Send + 'static>(
                     }
                     KeyCode::Char('J') if app.mode == Mode::OnlyHexdump => {
                         if let Some(hexdump) = app.hexdump.as_ref() {
-                            let len = hexdump.1.len() / HEXDUMP_WIDTH;
+                            let len = hexdump.1.len().div_ceil(HEXDUMP_WIDTH);
                             scroll_down(50, &mut app.hexdump_scroll, len);
                         }
                     }
                     KeyCode::Char('K') if app.mode == Mode::OnlyHexdump => {
-                        scroll_up(1, &mut app.hexdump_scroll);
+                        scroll_up(50, &mut app.hexdump_scroll);
+                    }
+                    // breakpoints
+                    KeyCode::Char('j') if app.mode == Mode::OnlyBreakpoints => {
+                        let len = app.breakpoints.len();
+                        app.breakpoint_selected =
+                            (app.breakpoint_selected + 1).min(len.saturating_sub(1));
+                    }
+                    KeyCode::Char('k') if app.mode == Mode::OnlyBreakpoints => {
+                        app.breakpoint_selected = app.breakpoint_selected.saturating_sub(1);
+                    }
+                    KeyCode::Char(c @ ('e' | 'd' | 'x')) if app.mode == Mode::OnlyBreakpoints => {
+                        let selected = sorted_breakpoints(&app)
+                            .get(app.breakpoint_selected)
+                            .map(|bp| bp.number);
+                        if let Some(number) = selected {
+                            let action = match c {
+                                'e' => BreakpointAction::Enable,
+                                'd' => BreakpointAction::Disable,
+                                _ => BreakpointAction::Delete,
+                            };
+                            update_breakpoints(
+                                app_clone1.clone(),
+                                app.session_id.clone(),
+                                Some((number, action)),
+                            );
+                        }
+                    }
+                    KeyCode::Char('r') if app.mode == Mode::OnlyBreakpoints => {
+                        update_breakpoints(app_clone1.clone(), app.session_id.clone(), None);
+                    }
+                    // locals and watches
+                    KeyCode::Char('j') if app.mode == Mode::OnlyLocals => {
+                        let len = app.watches.len();
+                        app.watch_selected = (app.watch_selected + 1).min(len.saturating_sub(1));
                     }
-                    _ => {
-                        // app.input.handle_event(&Event::Key(key));
+                    KeyCode::Char('k') if app.mode == Mode::OnlyLocals => {
+                        app.watch_selected = app.watch_selected.saturating_sub(1);
+                    }
+                    KeyCode::Enter if app.mode == Mode::OnlyLocals => {
+                        let selected = app
+                            .watches
+                            .get(app.watch_selected)
+                            .filter(|row| row.watch.numchild > 0)
+                            .map(|row| row.watch.name.clone());
+                        if let Some(name) = selected {
+                            if !app.watch_expanded.remove(&name) {
+                                app.watch_expanded.insert(name);
+                            }
+                            spawn_refresh_locals(app_clone1.clone(), app.session_id.clone());
+                        }
+                    }
+                    KeyCode::Char('r') if app.mode == Mode::OnlyLocals => {
+                        spawn_refresh_locals(app_clone1.clone(), app.session_id.clone());
+                    }
+                    // backtrace
+                    KeyCode::Char('j') if app.mode == Mode::OnlyBacktrace => {
+                        let len = app.bt.len();
+                        app.bt_selected = (app.bt_selected + 1).min(len.saturating_sub(1));
+                    }
+                    KeyCode::Char('k') if app.mode == Mode::OnlyBacktrace => {
+                        app.bt_selected = app.bt_selected.saturating_sub(1);
+                    }
+                    KeyCode::Enter if app.mode == Mode::OnlyBacktrace => {
+                        // the locals are refreshed when entering their view
+                        app.locals_frame = app.bt_selected;
+                        app.mode = Mode::OnlyLocals;
+                    }
+                    _ => (),
+                }
+                if app.mode != previous_mode {
+                    match app.mode {
+                        Mode::OnlyBreakpoints => {
+                            update_breakpoints(app_clone1.clone(), app.session_id.clone(), None)
+                        }
+                        Mode::OnlyLocals => {
+                            spawn_refresh_locals(app_clone1.clone(), app.session_id.clone())
+                        }
+                        // the views hidden so far were not refreshed
+                        _ => spawn_sync_target(app_clone1.clone(), app.session_id.clone()),
                     }
                 }
             }
         }
         let mut app = app.lock().await;
         app._exit = true;
+        app.redraw();
         Ok::<(), AppError>(())
     });
 
-    let draw_loop = tokio::task::spawn_blocking(move || {
+    // follow the latest session and refresh the views when its program stops
+    let target_loop = tokio::spawn(async move {
+        let mut sessions_changed = GDB_MANAGER.subscribe_sessions();
+        let mut followed: Option<(String, watch::Receiver<StopState>)> = None;
         loop {
+            // the changes made from now on wake up the loop again
+            sessions_changed.borrow_and_update();
+            let sessions = GDB_MANAGER.get_all_sessions().await.unwrap_or_default();
+            let session = sessions.into_iter().max_by_key(|session| session.created_at);
+            {
+                let mut app = app_clone3.lock().await;
+                let status = match &session {
+                    Some(session) => format!("Session {} {:?}", session.id, session.status),
+                    None => "No session".to_string(),
+                };
+                if app.status != status {
+                    app.status = status;
+                    app.redraw();
+                }
+                if let Some(session) = &session {
+                    app.bit32 = app.bit32_override.unwrap_or(session.arch.pointer_bits == Some(32));
+                    app.endian = app.endian_override.or(session.arch.endian);
+                    app.session_id = Some(session.id.clone());
+                }
+            }
+            let Some(session) = session else {
+                followed = None;
+                let _ = sessions_changed.changed().await;
+                continue;
+            };
+
+            if followed.as_ref().is_none_or(|(id, _)| *id != session.id) {
+                followed = GDB_MANAGER.subscribe_stops(&session.id).await.ok().map(|mut stops| {
+                    // show where the program already stopped
+                    if stops.borrow().count > 0 {
+                        stops.mark_changed();
+                    }
+                    (session.id.clone(), stops)
+                });
+            }
+            let Some((_, stops)) = followed.as_mut() else {
+                let _ = sessions_changed.changed().await;
+                continue;
+            };
+            tokio::select! {
+                changed = stops.changed() => {
+                    if changed.is_err() {
+                        // GDB quit or the session is restarted or closing,
+                        // followed again once it changes
+                        followed = None;
+                        let _ = sessions_changed.changed().await;
+                        continue;
+                    }
+                }
+                _ = sessions_changed.changed() => continue,
+            }
+            let reason = stops
+                .borrow_and_update()
+                .last
+                .as_ref()
+                .and_then(|stopped| stopped.get("reason"))
+                .and_then(|reason| reason.as_str())
+                .map(str::to_string);
             {
-                let mut terminal = terminal.blocking_lock();
-                let mut app = app_clone2.blocking_lock();
+                let mut app = app_clone3.lock().await;
+                app.async_result = reason.unwrap_or_default();
+                // the frames are new, back to the innermost one
+                app.bt_selected = 0;
+                app.locals_frame = 0;
+                app.redraw();
+            }
+            sync_target(&app_clone3, &session.id).await;
+        }
+    });
+
+    // the last command of GDB and the clients connected, for the status bar
+    let status_loop = tokio::spawn(async move {
+        let mut tick = tokio::time::interval(TICK);
+        loop {
+            tick.tick().await;
+            let transport = app_clone5.lock().await.transport;
+            let clients = match transport {
+                // the process which spawned the server is the only client
+                Some(TransportType::Stdio) => Some(1),
+                Some(TransportType::Sse) => {
+                    let port = config::get().server_port;
+                    tokio::task::spawn_blocking(move || metrics::connected_clients(port))
+                        .await
+                        .ok()
+                        .flatten()
+                }
+                None => None,
+            };
+            let activity = GDB_MANAGER.activity();
+            let mut app = app_clone5.lock().await;
+            if app.activity != activity || app.clients != clients {
+                app.activity = activity;
+                app.clients = clients;
+                app.redraw();
+            }
+        }
+    });
+
+    // draw when a key, a stop or a background task changed something, or on a
+    // tick, at most once per refresh interval
+    let draw_redraw = redraw.clone();
+    let draw_loop = tokio::spawn(async move {
+        let mut tick = tokio::time::interval(TICK);
+        loop {
+            tokio::select! {
+                _ = draw_redraw.notified() => {}
+                _ = tick.tick() => {}
+            }
+            {
+                let mut terminal = terminal.lock().await;
+                let mut app = app_clone2.lock().await;
                 if app._exit {
                     break;
                 }
@@ -570,15 +1237,19 @@ async fn run_app<B: Backend + Send + 'static>(
                     error!("failed to draw: {}", e);
                 }
             }
-            std::thread::sleep(Duration::from_millis(10));
+            // the changes notified meanwhile are drawn together in the next frame
+            tokio::time::sleep(refresh).await;
         }
     });
 
     // Event collection task
     while let Some(Ok(event)) = reader.next().await {
         debug!("event <<< {:?}", event);
+        if let Event::Resize(..) = event {
+            redraw.notify_one();
+        }
         if let Event::Key(key) = event {
-            if key.code == KeyCode::Char('q') {
+            if key.code == KeyCode::Char('q') && !app_clone4.lock().await.is_typing() {
                 drop(tx);
                 break;
             }
@@ -589,6 +1260,9 @@ async fn run_app<B: Backend + Send + 'static>(
         }
     }
 
+    target_loop.abort();
+    status_loop.abort();
+
     // Wait for processor to finish
     if let Err(e) = event_loop.await {
         error!("event processor error: {}", e);
@@ -602,24 +1276,472 @@ async fn run_app<B: Backend + Send + 'static>(
     Ok(())
 }
 
+/// Refresh the views in the background, e.g. after the word width changed
+fn spawn_sync_target(app: Arc<Mutex<App>>, session_id: Option<String>) {
+    tokio::spawn(async move {
+        if let Some(session_id) = session_id {
+            sync_target(&app, &session_id).await;
+        }
+    });
+}
+
+/// Refresh the visible views from the program of a session which stopped.
+/// What cannot be read, e.g. the mappings of a remote target, is left empty
+async fn sync_target(app: &Mutex<App>, session_id: &str) {
+    let (panes, bit32, endian, hexdump_region) = {
+        let app = app.lock().await;
+        let hexdump_region = app.hexdump.as_ref().map(|(begin, bytes)| (*begin, bytes.len()));
+        (app.mode.panes(), app.bit32, app.endian, hexdump_region)
+    };
+    // a client resumed the program, the next stop refreshes the views
+    if GDB_MANAGER.is_running(session_id).await {
+        return;
+    }
+
+    let registers = if panes.registers {
+        let registers = GDB_MANAGER.get_registers(session_id, None, None).await;
+        Some(registers.unwrap_or_else(|e| {
+            debug!("failed to get registers: {}", e);
+            vec![]
+        }))
+    } else {
+        None
+    };
+    let frames = if panes.frames {
+        Some(GDB_MANAGER.get_stack_frames(session_id).await.unwrap_or_default())
+    } else {
+        None
+    };
+    let disassembly = if panes.disassembly {
+        Some(GDB_MANAGER.disassemble_current_function(session_id).await.ok())
+    } else {
+        None
+    };
+    let memory_map = if panes.memory_map {
+        Some(GDB_MANAGER.memory_mappings(session_id).await.ok())
+    } else {
+        None
+    };
+    let word = if bit32 { 4 } else { 8 };
+    let stack = if panes.stack {
+        let count = SAVED_STACK as usize * word;
+        Some(
+            GDB_MANAGER
+                .read_memory(session_id, None, "$sp".to_string(), count)
+                .await
+                .unwrap_or_default(),
+        )
+    } else {
+        None
+    };
+    // the same region, to compare it with the bytes before the stop
+    let hexdump = match hexdump_region.filter(|_| panes.hexdump) {
+        Some((begin, len)) => read_hexdump(session_id, format!("0x{:x}", begin), len)
+            .await
+            .inspect_err(|e| debug!("failed to read the hexdump again: {}", e))
+            .ok()
+            .filter(|(address, _)| *address == begin),
+        None => None,
+    };
+    // hit counts changed
+    if panes.breakpoints
+        && let Err(e) = refresh_breakpoints(app, session_id).await
+    {
+        debug!("failed to get breakpoints: {}", e);
+    }
+    if panes.locals
+        && let Err(e) = refresh_locals(app, session_id).await
+    {
+        debug!("failed to get locals: {}", e);
+    }
+    // what was read after the program resumed is not shown
+    if GDB_MANAGER.is_running(session_id).await {
+        return;
+    }
+
+    let mut app = app.lock().await;
+    if let Some(registers) = registers {
+        app.register_changed = registers
+            .iter()
+            .enumerate()
+            .filter(|(i, register)| {
+                app.registers
+                    .get(*i)
+                    .and_then(|tracked| tracked.register.as_ref())
+                    .is_some_and(|previous| previous.value != register.value)
+            })
+            .map(|(i, _)| i as u8)
+            .collect();
+        app.registers = registers
+            .into_iter()
+            .map(|register| TrackedRegister::new(Some(register), ResolveSymbol::default()))
+            .collect();
+    }
+    if let Some(frames) = frames {
+        app.bt_selected = app.bt_selected.min(frames.len().saturating_sub(1));
+        app.bt = frames
+            .iter()
+            .map(|frame| BT {
+                location: frame.address.map(|address| address.0).unwrap_or_default(),
+                function: Some(frame.function.clone()),
+            })
+            .collect();
+    }
+    if let Some(disassembly) = disassembly {
+        app.asm = vec![];
+        if let Some(disassembly) = disassembly {
+            app.current_pc = disassembly.pc.0;
+            app.asm = disassembly
+                .blocks
+                .into_iter()
+                .flat_map(|block| block.instructions)
+                .map(|instruction| ASM {
+                    address: instruction.address.0,
+                    inst: instruction.inst,
+                    offset: instruction.offset.unwrap_or_default(),
+                    func_name: instruction.function,
+                })
+                .collect();
+        }
+    }
+    if let Some(memory_map) = memory_map {
+        app.memory_map = memory_map;
+    }
+    // unless another region was opened meanwhile
+    let same_region = |(begin, _): &(u64, Vec<u8>)| {
+        app.hexdump.as_ref().is_some_and(|(current, _)| current == begin)
+    };
+    if let Some(hexdump) = hexdump.filter(same_region) {
+        app.hexdump_previous = app.hexdump.replace(hexdump).map(|(_, bytes)| bytes);
+    }
+    if let Some(stack) = stack {
+        app.stack.clear();
+        for block in stack {
+            let (Ok(bytes), Ok(begin)) = (
+                resources::decode_hex(&block.contents),
+                u64::from_str_radix(block.begin.trim_start_matches("0x"), 16),
+            ) else {
+                continue;
+            };
+            for (i, chunk) in bytes.chunks_exact(word).enumerate() {
+                let mut value = [0; 8];
+                let value = if endian == Some(Endian::Big) {
+                    value[8 - word..].copy_from_slice(chunk);
+                    u64::from_be_bytes(value)
+                } else {
+                    value[..word].copy_from_slice(chunk);
+                    u64::from_le_bytes(value)
+                };
+                let mut resolve = ResolveSymbol::default();
+                resolve.try_push(value);
+                app.stack.insert(begin + (i * word) as u64, resolve);
+            }
+        }
+    }
+    app.redraw();
+}
+
+/// What a key of the breakpoints view does to the selected breakpoint
+#[derive(Clone, Copy)]
+enum BreakpointAction {
+    Enable,
+    Disable,
+    Delete,
+}
+
+/// Apply an action to a breakpoint, if any, and reload the breakpoints view.
+/// Failures are shown in the output view
+fn update_breakpoints(
+    app: Arc<Mutex<App>>,
+    session_id: Option<String>,
+    action: Option<(BreakPointNumber, BreakpointAction)>,
+) {
+    tokio::spawn(async move {
+        let Some(session_id) = session_id else {
+            return;
+        };
+        let result = match action {
+            Some((number, BreakpointAction::Enable)) => {
+                GDB_MANAGER.enable_breakpoints(&session_id, vec![number], true).await
+            }
+            Some((number, BreakpointAction::Disable)) => {
+                GDB_MANAGER.enable_breakpoints(&session_id, vec![number], false).await
+            }
+            Some((number, BreakpointAction::Delete)) => {
+                GDB_MANAGER.delete_breakpoint(&session_id, vec![number.to_string()]).await
+            }
+            None => Ok(()),
+        };
+        let result = match result {
+            Ok(()) => refresh_breakpoints(&app, &session_id).await,
+            Err(e) => Err(e),
+        };
+        if let Err(e) = result {
+            let mut app = app.lock().await;
+            app.output.push(format!("error: {}", e));
+            app.redraw();
+        }
+    });
+}
+
+/// Reload the breakpoints of a session into the breakpoints view
+async fn refresh_breakpoints(app: &Mutex<App>, session_id: &str) -> AppResult<()> {
+    let breakpoints = GDB_MANAGER.get_breakpoints(session_id).await?;
+    let mut app = app.lock().await;
+    app.breakpoints = BreakPointSet::default();
+    for bp in breakpoints {
+        app.breakpoints.update_breakpoint(bp);
+    }
+    app.breakpoint_selected = app.breakpoint_selected.min(app.breakpoints.len().saturating_sub(1));
+    app.redraw();
+    Ok(())
+}
+
+/// Reload the locals view in the background, failures are shown in the output
+/// view
+fn spawn_refresh_locals(app: Arc<Mutex<App>>, session_id: Option<String>) {
+    tokio::spawn(async move {
+        let Some(session_id) = session_id else {
+            return;
+        };
+        if let Err(e) = refresh_locals(&app, &session_id).await {
+            let mut app = app.lock().await;
+            app.output.push(format!("error: {}", e));
+            app.redraw();
+        }
+    });
+}
+
+/// Reload the locals of the frame selected in the backtrace and the watch
+/// expressions, with the children of the expanded ones, into the locals view
+async fn refresh_locals(app: &Mutex<App>, session_id: &str) -> AppResult<()> {
+    let (expanded, frame) = {
+        let app = app.lock().await;
+        (app.watch_expanded.clone(), app.locals_frame)
+    };
+    let locals = GDB_MANAGER.get_local_variables(session_id, Some(frame), None).await?;
+
+    let mut watches = vec![];
+    let mut pending = GDB_MANAGER
+        .list_watches(session_id)
+        .await?
+        .into_iter()
+        .rev()
+        .map(|watch| WatchRow { depth: 0, watch })
+        .collect::<Vec<_>>();
+    // depth first, the children follow their parent
+    while let Some(row) = pending.pop() {
+        if row.watch.numchild > 0 && expanded.contains(&row.watch.name) {
+            let children = GDB_MANAGER.list_watch_children(session_id, &row.watch.name).await?;
+            pending.extend(
+                children.into_iter().rev().map(|watch| WatchRow { depth: row.depth + 1, watch }),
+            );
+        }
+        watches.push(row);
+    }
+
+    let mut app = app.lock().await;
+    app.locals = locals;
+    app.watch_selected = app.watch_selected.min(watches.len().saturating_sub(1));
+    app.watches = watches;
+    app.redraw();
+    Ok(())
+}
+
+/// Read memory from the address, an expression GDB evaluates, into the
+/// hexdump view. Failures are shown in the output view
+fn show_memory(app: Arc<Mutex<App>>, session_id: Option<String>, address: String, count: usize) {
+    tokio::spawn(async move {
+        let result = match session_id {
+            Some(session_id) => read_hexdump(&session_id, address, count).await,
+            None => Err(AppError::NotFound("no GDB session".to_string())),
+        };
+
+        let mut app = app.lock().await;
+        match result {
+            Ok(hexdump) => {
+                app.hexdump = Some(hexdump);
+                app.hexdump_previous = None;
+                app.hexdump_search = None;
+                // reset position
+                app.hexdump_scroll.top();
+            }
+            Err(e) => app.output.push(format!("error: {}", e)),
+        }
+        app.redraw();
+    });
+}
+
+/// Read the first readable block of memory from the address, an expression
+/// GDB evaluates, with the address it begins at
+async fn read_hexdump(
+    session_id: &str,
+    address: String,
+    count: usize,
+) -> AppResult<(u64, Vec<u8>)> {
+    let blocks = GDB_MANAGER.read_memory(session_id, None, address.clone(), count).await?;
+    let block = blocks
+        .first()
+        .ok_or_else(|| AppError::NotFound(format!("no readable memory at {}", address)))?;
+    let begin = u64::from_str_radix(block.begin.trim_start_matches("0x"), 16)
+        .map_err(|_| AppError::ParseError(format!("invalid address {}", block.begin)))?;
+    Ok((begin, resources::decode_hex(&block.contents)?))
+}
+
+/// Run a command typed in the TUI and append it with its output to the output
+/// view. The command waits in the session queue like the ones of the tools
+fn run_command(app: Arc<Mutex<App>>, session_id: Option<String>, command: String) {
+    tokio::spawn(async move {
+        let result = match session_id {
+            Some(session_id) => GDB_MANAGER.execute_cli(&session_id, &command).await,
+            None => Err(AppError::NotFound("no GDB session".to_string())),
+        };
+        let mut app = app.lock().await;
+        app.output.push(format!("(gdb) {}", command));
+        match result {
+            Ok(output) => app.output.extend(output.lines().map(str::to_string)),
+            Err(e) => app.output.push(format!("error: {}", e)),
+        }
+        app.redraw();
+    });
+}
+
 /// Register all debugging tools to the server
-fn register_tools(builder: ServerProtocolBuilder) -> ServerProtocolBuilder {
+fn register_tools(builder: ServerBuilder) -> ServerBuilder {
     builder
         .register_tool(tools::CreateSessionTool::tool(), tools::CreateSessionTool::call())
+        .register_tool(
+            tools::CreateEmbeddedSessionTool::tool(),
+            tools::CreateEmbeddedSessionTool::call(),
+        )
+        .register_tool(tools::CreateQemuSessionTool::tool(), tools::CreateQemuSessionTool::call())
+        .register_tool(tools::AttachToContainerTool::tool(), tools::AttachToContainerTool::call())
+        .register_tool(tools::SetSysrootTool::tool(), tools::SetSysrootTool::call())
+        .register_tool(tools::SetGdbParameterTool::tool(), tools::SetGdbParameterTool::call())
+        .register_tool(tools::ShowGdbParameterTool::tool(), tools::ShowGdbParameterTool::call())
+        .register_tool(
+            tools::ListAvailableDebuggersTool::tool(),
+            tools::ListAvailableDebuggersTool::call(),
+        )
+        .register_tool(tools::GetServerLogsTool::tool(), tools::GetServerLogsTool::call())
         .register_tool(tools::GetSessionTool::tool(), tools::GetSessionTool::call())
+        .register_tool(tools::PingSessionTool::tool(), tools::PingSessionTool::call())
         .register_tool(tools::GetAllSessionsTool::tool(), tools::GetAllSessionsTool::call())
         .register_tool(tools::CloseSessionTool::tool(), tools::CloseSessionTool::call())
+        .register_tool(tools::RestartSessionTool::tool(), tools::RestartSessionTool::call())
+        .register_tool(tools::GetConsoleOutputTool::tool(), tools::GetConsoleOutputTool::call())
+        .register_tool(tools::GetSessionEventsTool::tool(), tools::GetSessionEventsTool::call())
+        .register_tool(tools::GetOutputStatsTool::tool(), tools::GetOutputStatsTool::call())
+        .register_tool(tools::ExportTranscriptTool::tool(), tools::ExportTranscriptTool::call())
         .register_tool(tools::StartDebuggingTool::tool(), tools::StartDebuggingTool::call())
+        .register_tool(tools::StartProgramTool::tool(), tools::StartProgramTool::call())
         .register_tool(tools::StopDebuggingTool::tool(), tools::StopDebuggingTool::call())
         .register_tool(tools::GetBreakpointsTool::tool(), tools::GetBreakpointsTool::call())
         .register_tool(tools::SetBreakpointTool::tool(), tools::SetBreakpointTool::call())
+        .register_tool(tools::GetBreakpointHitsTool::tool(), tools::GetBreakpointHitsTool::call())
+        .register_tool(tools::RunToHitCountTool::tool(), tools::RunToHitCountTool::call())
         .register_tool(tools::DeleteBreakpointTool::tool(), tools::DeleteBreakpointTool::call())
         .register_tool(tools::GetStackFramesTool::tool(), tools::GetStackFramesTool::call())
+        .register_tool(tools::DemangleTool::tool(), tools::DemangleTool::call())
+        .register_tool(tools::AddSymbolFileTool::tool(), tools::AddSymbolFileTool::call())
+        .register_tool(tools::FindDebugFilesTool::tool(), tools::FindDebugFilesTool::call())
+        .register_tool(tools::GetLoadedModulesTool::tool(), tools::GetLoadedModulesTool::call())
+        .register_tool(tools::RebaseAddressTool::tool(), tools::RebaseAddressTool::call())
+        .register_tool(tools::ImportAnnotationsTool::tool(), tools::ImportAnnotationsTool::call())
+        .register_tool(tools::ExportAnnotationsTool::tool(), tools::ExportAnnotationsTool::call())
+        .register_tool(tools::GetProcessInfoTool::tool(), tools::GetProcessInfoTool::call())
+        .register_tool(tools::ListProcessesTool::tool(), tools::ListProcessesTool::call())
+        .register_tool(tools::ListThreadsTool::tool(), tools::ListThreadsTool::call())
+        .register_tool(tools::GetThreadTableTool::tool(), tools::GetThreadTableTool::call())
+        .register_tool(tools::AnalyzeDeadlockTool::tool(), tools::AnalyzeDeadlockTool::call())
+        .register_tool(tools::SetRtosTool::tool(), tools::SetRtosTool::call())
         .register_tool(tools::GetLocalVariablesTool::tool(), tools::GetLocalVariablesTool::call())
+        .register_tool(
+            tools::GetFunctionArgumentsTool::tool(),
+            tools::GetFunctionArgumentsTool::call(),
+        )
+        .register_tool(tools::GetFrameContextTool::tool(), tools::GetFrameContextTool::call())
+        .register_tool(tools::GetFrameDetailsTool::tool(), tools::GetFrameDetailsTool::call())
         .register_tool(tools::ContinueExecutionTool::tool(), tools::ContinueExecutionTool::call())
+        .register_tool(
+            tools::SetSchedulerLockingTool::tool(),
+            tools::SetSchedulerLockingTool::call(),
+        )
+        .register_tool(tools::AddSkipTool::tool(), tools::AddSkipTool::call())
+        .register_tool(tools::ListSkipsTool::tool(), tools::ListSkipsTool::call())
+        .register_tool(tools::DeleteSkipTool::tool(), tools::DeleteSkipTool::call())
         .register_tool(tools::StepExecutionTool::tool(), tools::StepExecutionTool::call())
         .register_tool(tools::NextExecutionTool::tool(), tools::NextExecutionTool::call())
+        .register_tool(tools::WaitForStopTool::tool(), tools::WaitForStopTool::call())
+        .register_tool(tools::ContinueUntilTool::tool(), tools::ContinueUntilTool::call())
+        .register_tool(
+            tools::FinishAndGetReturnValueTool::tool(),
+            tools::FinishAndGetReturnValueTool::call(),
+        )
+        .register_tool(tools::UntilReturnOfTool::tool(), tools::UntilReturnOfTool::call())
+        .register_tool(tools::CallFunctionTool::tool(), tools::CallFunctionTool::call())
+        .register_tool(tools::RunPythonTool::tool(), tools::RunPythonTool::call())
+        .register_tool(tools::TraceCallsTool::tool(), tools::TraceCallsTool::call())
+        .register_tool(tools::CollectCoverageTool::tool(), tools::CollectCoverageTool::call())
+        .register_tool(tools::ContinueAndSampleTool::tool(), tools::ContinueAndSampleTool::call())
+        .register_tool(tools::StartRecordingTool::tool(), tools::StartRecordingTool::call())
+        .register_tool_when(
+            tools::StopRecordingTool::tool(),
+            tools::StopRecordingTool::call(),
+            |features| features.recording,
+        )
+        .register_tool_when(
+            tools::GetFunctionCallHistoryTool::tool(),
+            tools::GetFunctionCallHistoryTool::call(),
+            |features| features.recording,
+        )
+        .register_tool_when(
+            tools::GetInstructionHistoryTool::tool(),
+            tools::GetInstructionHistoryTool::call(),
+            |features| features.recording,
+        )
+        .register_tool(tools::RunDebugPlanTool::tool(), tools::RunDebugPlanTool::call())
+        .register_tool(tools::AddWatchExpressionTool::tool(), tools::AddWatchExpressionTool::call())
+        .register_tool(
+            tools::RemoveWatchExpressionTool::tool(),
+            tools::RemoveWatchExpressionTool::call(),
+        )
+        .register_tool(tools::WatchMemoryRegionTool::tool(), tools::WatchMemoryRegionTool::call())
+        .register_tool(
+            tools::UnwatchMemoryRegionTool::tool(),
+            tools::UnwatchMemoryRegionTool::call(),
+        )
         .register_tool(tools::GetRegistersTool::tool(), tools::GetRegistersTool::call())
         .register_tool(tools::GetRegisterNamesTool::tool(), tools::GetRegisterNamesTool::call())
+        .register_tool(tools::GetRegisterChangesTool::tool(), tools::GetRegisterChangesTool::call())
+        .register_tool(tools::SetArchitectureTool::tool(), tools::SetArchitectureTool::call())
+        .register_tool(tools::SetEndianTool::tool(), tools::SetEndianTool::call())
+        .register_tool(tools::LoadSvdTool::tool(), tools::LoadSvdTool::call())
+        .register_tool_when(
+            tools::ListPeripheralsTool::tool(),
+            tools::ListPeripheralsTool::call(),
+            |features| features.svd,
+        )
+        .register_tool_when(
+            tools::ReadPeripheralRegisterTool::tool(),
+            tools::ReadPeripheralRegisterTool::call(),
+            |features| features.svd,
+        )
+        .register_tool(
+            tools::DisassembleCurrentFunctionTool::tool(),
+            tools::DisassembleCurrentFunctionTool::call(),
+        )
+        .register_tool(tools::GetCurrentLocationTool::tool(), tools::GetCurrentLocationTool::call())
         .register_tool(tools::ReadMemoryTool::tool(), tools::ReadMemoryTool::call())
+        .register_tool(tools::DumpMemoryTool::tool(), tools::DumpMemoryTool::call())
+        .register_tool(tools::ReadTypedMemoryTool::tool(), tools::ReadTypedMemoryTool::call())
+        .register_tool(tools::ReadStringTool::tool(), tools::ReadStringTool::call())
+        .register_tool(tools::FindStringsTool::tool(), tools::FindStringsTool::call())
+        .register_tool(tools::GetStackMemoryTool::tool(), tools::GetStackMemoryTool::call())
+        .register_tool(
+            tools::AnalyzeStackCorruptionTool::tool(),
+            tools::AnalyzeStackCorruptionTool::call(),
+        )
+        .register_tool(tools::DumpGotTool::tool(), tools::DumpGotTool::call())
+        .register_tool(tools::FindGadgetsTool::tool(), tools::FindGadgetsTool::call())
 }
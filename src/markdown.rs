@@ -0,0 +1,180 @@
+//! Markdown rendering of the output of the inspection tools, for the answers
+//! shown to humans
+
+use std::fmt::Write;
+use std::str::FromStr;
+
+use crate::error::AppError;
+use crate::models::{BreakPoint, Register, RegisterRaw, StackFrame};
+
+/// Format of the output of an inspection tool
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum OutputFormat {
+    #[default]
+    Json,
+    /// Aligned Markdown tables
+    Markdown,
+}
+
+impl FromStr for OutputFormat {
+    type Err = AppError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "json" => Ok(Self::Json),
+            "markdown" | "md" => Ok(Self::Markdown),
+            _ => Err(AppError::InvalidArgument(format!(
+                "unknown output format {}, expecting json or markdown",
+                s
+            ))),
+        }
+    }
+}
+
+/// A table whose columns are padded to the widest cell
+struct Table {
+    header: Vec<&'static str>,
+    rows: Vec<Vec<String>>,
+}
+
+impl Table {
+    fn new(header: &[&'static str]) -> Self {
+        Self { header: header.to_vec(), rows: vec![] }
+    }
+
+    fn push(&mut self, row: Vec<String>) {
+        self.rows.push(row.iter().map(|cell| escape(cell)).collect());
+    }
+
+    fn render(&self) -> String {
+        let mut widths = self.header.iter().map(|title| title.chars().count()).collect::<Vec<_>>();
+        for row in &self.rows {
+            for (width, cell) in widths.iter_mut().zip(row) {
+                *width = (*width).max(cell.chars().count());
+            }
+        }
+        // the delimiter row needs 3 dashes
+        widths.iter_mut().for_each(|width| *width = (*width).max(3));
+
+        let mut text = String::new();
+        let mut line = |cells: &mut dyn Iterator<Item = &str>| {
+            text.push('|');
+            for (cell, width) in cells.zip(&widths) {
+                let _ = write!(text, " {:<width$} |", cell, width = width);
+            }
+            text.push('\n');
+        };
+        line(&mut self.header.iter().copied());
+        let dashes = widths.iter().map(|width| "-".repeat(*width)).collect::<Vec<_>>();
+        line(&mut dashes.iter().map(String::as_str));
+        for row in &self.rows {
+            line(&mut row.iter().map(String::as_str));
+        }
+        text
+    }
+}
+
+/// Escape the characters breaking a table cell
+fn escape(cell: &str) -> String {
+    cell.replace('|', "\\|").replace('\n', " ")
+}
+
+pub fn breakpoints(breakpoints: &[BreakPoint]) -> String {
+    let mut table = Table::new(&["Num", "Type", "Disp", "Enb", "Address", "Location", "Hits"]);
+    for bp in breakpoints {
+        table.push(vec![
+            bp.number.to_string(),
+            bp.r#type.clone(),
+            bp.display.clone(),
+            if bp.enabled.0 { "y" } else { "n" }.to_string(),
+            bp.address.map(String::from).unwrap_or_default(),
+            bp.src_pos
+                .as_ref()
+                .map(|pos| format!("{}:{}", pos.fullname.display(), pos.line))
+                .unwrap_or_default(),
+            bp.hits.map(|hits| hits.to_string()).unwrap_or_default(),
+        ]);
+    }
+    table.render()
+}
+
+pub fn stack_frames(frames: &[StackFrame]) -> String {
+    let mut table = Table::new(&["Level", "Address", "Function", "Location"]);
+    for frame in frames {
+        let location = match (&frame.file, frame.line) {
+            (Some(file), Some(line)) => format!("{}:{}", file, line),
+            (Some(file), None) => file.clone(),
+            _ => String::new(),
+        };
+        let function = match &frame.annotation {
+            Some(annotation) => format!("{} <{}>", frame.function, annotation),
+            None => frame.function.clone(),
+        };
+        table.push(vec![
+            frame.level.to_string(),
+            frame.address.map(String::from).unwrap_or_default(),
+            function,
+            location,
+        ]);
+    }
+    table.render()
+}
+
+pub fn registers(registers: &[Register]) -> String {
+    let mut table = Table::new(&["Number", "Name", "Value", "Details"]);
+    for register in registers {
+        let value = match (&register.value, &register.error) {
+            (Some(raw), _) => register_value(raw),
+            (None, Some(error)) => format!("<{}>", error),
+            (None, None) => String::new(),
+        };
+        let details = if let Some(flags) = &register.flags {
+            let mut details =
+                flags.iter().filter(|(_, set)| **set).map(|(flag, _)| *flag).collect::<Vec<_>>();
+            let exception = register.exception.map(|number| format!("exception {}", number));
+            details.extend(exception.as_deref());
+            details.join(" ")
+        } else if let Some(lanes) = &register.lanes {
+            lanes.iter().map(|lane| lane.to_string()).collect::<Vec<_>>().join(", ")
+        } else {
+            String::new()
+        };
+        table.push(vec![
+            register.number.to_string(),
+            register.name.clone().unwrap_or_default(),
+            value,
+            details,
+        ]);
+    }
+    table.render()
+}
+
+fn register_value(raw: &RegisterRaw) -> String {
+    match raw {
+        RegisterRaw::U32(value) => String::from(*value),
+        RegisterRaw::U64(value) => String::from(*value),
+        RegisterRaw::U128(value) => String::from(*value),
+        RegisterRaw::U256(low, high) => format!("0x{:x}{:032x}", high.0, low.0),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_table() {
+        let mut table = Table::new(&["Num", "Function"]);
+        table.push(vec!["1".to_string(), "operator|".to_string()]);
+        table.push(vec!["12".to_string(), "main".to_string()]);
+        assert_eq!(
+            table.render(),
+            "| Num | Function   |\n\
+             | --- | ---------- |\n\
+             | 1   | operator\\| |\n\
+             | 12  | main       |\n"
+        );
+        assert_eq!("md".parse::<OutputFormat>().unwrap(), OutputFormat::Markdown);
+        assert!("xml".parse::<OutputFormat>().is_err());
+    }
+}
@@ -0,0 +1,316 @@
+//! Counters of the server, served in the Prometheus text format along with a
+//! health check when it runs with the SSE transport, so it can be operated as
+//! a long-running service
+
+use std::fmt::Write;
+use std::sync::atomic::{AtomicI64, AtomicU64, Ordering};
+use std::time::Duration;
+
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::net::{TcpListener, TcpStream};
+use tracing::{debug, info};
+
+use crate::error::AppResult;
+use crate::mi::output::StreamKind;
+
+/// Upper bounds of the buckets of the command latency in seconds, the last
+/// bucket is unbounded
+const LATENCY_BUCKETS: [f64; 9] = [0.001, 0.005, 0.01, 0.05, 0.1, 0.5, 1.0, 5.0, 10.0];
+/// Bytes read of a request, its body is ignored
+const MAX_REQUEST_BYTES: u64 = 8192;
+/// Time given to a client to send its request
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+
+pub static METRICS: Metrics = Metrics::new();
+
+pub struct Metrics {
+    sessions_active: AtomicI64,
+    sessions_created: AtomicU64,
+    commands: AtomicU64,
+    /// Commands by latency bucket, not cumulative
+    latency_buckets: [AtomicU64; LATENCY_BUCKETS.len() + 1],
+    latency_sum_us: AtomicU64,
+    timeouts: AtomicU64,
+    parse_errors: AtomicU64,
+    /// Console, target and log records
+    dropped_records: [AtomicU64; 3],
+}
+
+impl Metrics {
+    const fn new() -> Self {
+        Metrics {
+            sessions_active: AtomicI64::new(0),
+            sessions_created: AtomicU64::new(0),
+            commands: AtomicU64::new(0),
+            latency_buckets: [const { AtomicU64::new(0) }; LATENCY_BUCKETS.len() + 1],
+            latency_sum_us: AtomicU64::new(0),
+            timeouts: AtomicU64::new(0),
+            parse_errors: AtomicU64::new(0),
+            dropped_records: [const { AtomicU64::new(0) }; 3],
+        }
+    }
+
+    pub fn session_opened(&self) {
+        self.sessions_created.fetch_add(1, Ordering::Relaxed);
+        self.sessions_active.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn session_closed(&self) {
+        self.sessions_active.fetch_sub(1, Ordering::Relaxed);
+    }
+
+    /// A command answered by GDB after `latency`
+    pub fn command_answered(&self, latency: Duration) {
+        self.commands.fetch_add(1, Ordering::Relaxed);
+        let seconds = latency.as_secs_f64();
+        let bucket = LATENCY_BUCKETS
+            .iter()
+            .position(|bound| seconds <= *bound)
+            .unwrap_or(LATENCY_BUCKETS.len());
+        self.latency_buckets[bucket].fetch_add(1, Ordering::Relaxed);
+        self.latency_sum_us.fetch_add(latency.as_micros() as u64, Ordering::Relaxed);
+    }
+
+    /// A command GDB did not answer in time
+    pub fn command_timed_out(&self) {
+        self.timeouts.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// A line of the output of GDB which could not be parsed
+    pub fn parse_error(&self) {
+        self.parse_errors.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// A stream record dropped because it was not read in time
+    pub fn record_dropped(&self, kind: StreamKind) {
+        let stream = match kind {
+            StreamKind::Console => 0,
+            StreamKind::Target => 1,
+            StreamKind::Log => 2,
+        };
+        self.dropped_records[stream].fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// The counters in the Prometheus text format
+    pub fn render(&self) -> String {
+        let load = |counter: &AtomicU64| counter.load(Ordering::Relaxed);
+        let single = |value: String| vec![(String::new(), value)];
+        let mut text = String::new();
+        let mut metric = |name: &str, kind: &str, help: &str, samples: Vec<(String, String)>| {
+            let _ = writeln!(text, "# HELP mcp_gdb_{} {}", name, help);
+            let _ = writeln!(text, "# TYPE mcp_gdb_{} {}", name, kind);
+            for (suffix, value) in samples {
+                let _ = writeln!(text, "mcp_gdb_{}{} {}", name, suffix, value);
+            }
+        };
+
+        metric(
+            "sessions_active",
+            "gauge",
+            "GDB sessions open",
+            single(self.sessions_active.load(Ordering::Relaxed).to_string()),
+        );
+        metric(
+            "sessions_created_total",
+            "counter",
+            "GDB sessions created",
+            single(load(&self.sessions_created).to_string()),
+        );
+        metric(
+            "commands_total",
+            "counter",
+            "MI commands answered by GDB",
+            single(load(&self.commands).to_string()),
+        );
+
+        let mut latency = vec![];
+        let mut cumulative = 0;
+        for (i, bucket) in self.latency_buckets.iter().enumerate() {
+            cumulative += load(bucket);
+            let bound = LATENCY_BUCKETS.get(i).map_or("+Inf".to_string(), f64::to_string);
+            latency.push((format!("_bucket{{le=\"{}\"}}", bound), cumulative.to_string()));
+        }
+        latency.push(("_sum".to_string(), (load(&self.latency_sum_us) as f64 / 1e6).to_string()));
+        latency.push(("_count".to_string(), cumulative.to_string()));
+        metric(
+            "command_duration_seconds",
+            "histogram",
+            "Time GDB took to answer the MI commands",
+            latency,
+        );
+
+        metric(
+            "command_timeouts_total",
+            "counter",
+            "MI commands GDB did not answer in time",
+            single(load(&self.timeouts).to_string()),
+        );
+        metric(
+            "parse_errors_total",
+            "counter",
+            "Lines of the GDB output which could not be parsed",
+            single(load(&self.parse_errors).to_string()),
+        );
+        metric(
+            "dropped_records_total",
+            "counter",
+            "Stream records of GDB dropped because they were not read in time",
+            ["console", "target", "log"]
+                .iter()
+                .zip(&self.dropped_records)
+                .map(|(stream, count)| {
+                    (format!("{{stream=\"{}\"}}", stream), load(count).to_string())
+                })
+                .collect(),
+        );
+        text
+    }
+}
+
+/// Serve `/healthz` and `/metrics` on `host:port` until the server exits
+pub async fn serve(host: &str, port: u16) -> AppResult<()> {
+    let listener = TcpListener::bind((host, port)).await?;
+    info!("Serving /healthz and /metrics on {}:{}", host, port);
+    accept(listener).await
+}
+
+async fn accept(listener: TcpListener) -> AppResult<()> {
+    loop {
+        let (stream, peer) = listener.accept().await?;
+        tokio::spawn(async move {
+            if let Err(e) = respond(stream).await {
+                debug!("Failed to answer {}: {}", peer, e);
+            }
+        });
+    }
+}
+
+/// Clients connected to the server listening on `port`, the established TCP
+/// connections accepted on it, None where the connections cannot be listed
+pub fn connected_clients(port: u16) -> Option<usize> {
+    if !cfg!(target_os = "linux") {
+        return None;
+    }
+    let mut clients = 0;
+    for table in ["/proc/net/tcp", "/proc/net/tcp6"] {
+        // no IPv6 on the host
+        if let Ok(table) = std::fs::read_to_string(table) {
+            clients += established_on(&table, port);
+        }
+    }
+    Some(clients)
+}
+
+/// Connections of a `/proc/net/tcp` table established on the local `port`
+fn established_on(table: &str, port: u16) -> usize {
+    // the addresses are `address:port` in hexadecimal, state 01 is ESTABLISHED
+    table
+        .lines()
+        .skip(1)
+        .filter(|line| {
+            let mut fields = line.split_whitespace().skip(1);
+            let local_port = fields
+                .next()
+                .and_then(|local| local.rsplit_once(':'))
+                .and_then(|(_, local_port)| u16::from_str_radix(local_port, 16).ok());
+            local_port == Some(port) && fields.nth(1) == Some("01")
+        })
+        .count()
+}
+
+/// Answer a HTTP request and close the connection
+async fn respond(mut stream: TcpStream) -> std::io::Result<()> {
+    let (reader, mut writer) = stream.split();
+    let mut reader = BufReader::new(reader.take(MAX_REQUEST_BYTES));
+    let mut request = String::new();
+    let read_head = async {
+        reader.read_line(&mut request).await?;
+        // the headers end with an empty line
+        let mut header = String::new();
+        while reader.read_line(&mut header).await? > 0 && !header.trim().is_empty() {
+            header.clear();
+        }
+        std::io::Result::Ok(())
+    };
+    tokio::time::timeout(REQUEST_TIMEOUT, read_head).await??;
+
+    let mut words = request.split_whitespace();
+    let (status, content_type, body) = match (words.next(), words.next()) {
+        (Some("GET"), Some("/healthz")) => ("200 OK", "text/plain", "ok\n".to_string()),
+        (Some("GET"), Some("/metrics")) => {
+            ("200 OK", "text/plain; version=0.0.4", METRICS.render())
+        }
+        (Some("GET"), _) => ("404 Not Found", "text/plain", "not found\n".to_string()),
+        _ => ("405 Method Not Allowed", "text/plain", "method not allowed\n".to_string()),
+    };
+    let response = format!(
+        "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        content_type,
+        body.len(),
+        body
+    );
+    writer.write_all(response.as_bytes()).await?;
+    writer.shutdown().await
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    async fn get(port: u16, path: &str) -> String {
+        let mut stream = TcpStream::connect(("127.0.0.1", port)).await.unwrap();
+        let request = format!("GET {} HTTP/1.1\r\nHost: localhost\r\n\r\n", path);
+        stream.write_all(request.as_bytes()).await.unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await.unwrap();
+        response
+    }
+
+    #[test]
+    fn test_established_on() {
+        let table = "  sl  local_address rem_address   st tx_queue rx_queue tr tm->when retrnsmt   uid  timeout inode
+   0: 0100007F:1F90 00000000:0000 0A 00000000:00000000 00:00000000 00000000  1000        0 1 1 0000000000000000 100 0 0 10 0
+   1: 0100007F:1F90 0100007F:C350 01 00000000:00000000 00:00000000 00000000  1000        0 2 1 0000000000000000 20 4 30 10 -1
+   2: 0100007F:C350 0100007F:1F90 01 00000000:00000000 00:00000000 00000000  1000        0 3 1 0000000000000000 20 4 30 10 -1
+   3: 0100007F:1F90 0100007F:C351 06 00000000:00000000 03:00001770 00000000     0        0 0 3 0000000000000000
+";
+        // the listening socket, the client side and the closed connection
+        // are left out
+        assert_eq!(established_on(table, 8080), 1);
+        assert_eq!(established_on(table, 9000), 0);
+    }
+
+    #[tokio::test]
+    async fn test_serve_metrics() {
+        let listener = TcpListener::bind(("127.0.0.1", 0)).await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        tokio::spawn(accept(listener));
+
+        METRICS.command_answered(Duration::from_millis(3));
+        METRICS.record_dropped(StreamKind::Target);
+
+        let health = get(port, "/healthz").await;
+        assert!(health.starts_with("HTTP/1.1 200 OK\r\n") && health.ends_with("\r\n\r\nok\n"));
+
+        let metrics = get(port, "/metrics").await;
+        assert!(metrics.contains("# TYPE mcp_gdb_command_duration_seconds histogram\n"));
+        let value = |name: &str| {
+            metrics
+                .lines()
+                .find_map(|line| line.strip_prefix(name)?.strip_prefix(' '))
+                .and_then(|value| value.parse::<f64>().ok())
+                .unwrap()
+        };
+        // other tests count too
+        assert!(value("mcp_gdb_commands_total") >= 1.0);
+        assert!(value("mcp_gdb_command_duration_seconds_bucket{le=\"0.005\"}") >= 1.0);
+        assert_eq!(
+            value("mcp_gdb_command_duration_seconds_bucket{le=\"+Inf\"}"),
+            value("mcp_gdb_command_duration_seconds_count")
+        );
+        assert!(value("mcp_gdb_dropped_records_total{stream=\"target\"}") >= 1.0);
+
+        assert!(get(port, "/nope").await.starts_with("HTTP/1.1 404 Not Found\r\n"));
+    }
+}
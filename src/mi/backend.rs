@@ -0,0 +1,73 @@
+//! The debugger the MI commands are sent to, a GDB process or a script
+//! replaying a transcript in the tests
+
+use futures::future::BoxFuture;
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio::process::Child;
+
+use crate::error::{AppError, AppResult};
+
+/// Where the commands are written
+pub type BackendInput = Box<dyn AsyncWrite + Send + Sync + Unpin>;
+/// Where the records are read from
+pub type BackendOutput = Box<dyn AsyncRead + Send + Sync + Unpin>;
+
+/// A debugger speaking GDB/MI
+pub trait DebuggerBackend: Send {
+    /// Input of the debugger, taken once when the session starts
+    fn take_input(&mut self) -> Option<BackendInput>;
+    /// Output of the debugger, taken once when the session starts
+    fn take_output(&mut self) -> Option<BackendOutput>;
+    /// Interrupt the running program as Ctrl-C does
+    fn interrupt(&self) -> AppResult<()>;
+    /// Whether the debugger is still running, without talking to it
+    fn is_alive(&mut self) -> bool;
+    /// Terminate the debugger
+    fn kill(&mut self) -> BoxFuture<'_, ()>;
+}
+
+/// A GDB process started with `--interpreter=mi`
+pub struct ProcessBackend {
+    child: Child,
+}
+
+impl ProcessBackend {
+    pub fn new(child: Child) -> Self {
+        ProcessBackend { child }
+    }
+}
+
+impl DebuggerBackend for ProcessBackend {
+    fn take_input(&mut self) -> Option<BackendInput> {
+        self.child.stdin.take().map(|stdin| Box::new(stdin) as BackendInput)
+    }
+
+    fn take_output(&mut self) -> Option<BackendOutput> {
+        self.child.stdout.take().map(|stdout| Box::new(stdout) as BackendOutput)
+    }
+
+    #[cfg(unix)]
+    fn interrupt(&self) -> AppResult<()> {
+        use nix::sys::signal;
+        use nix::unistd::Pid;
+        let pid = self.child.id().ok_or(AppError::GDBQuit)?;
+        signal::kill(Pid::from_raw(pid as i32), signal::SIGINT)
+            .map_err(|e| AppError::GDBError(e.to_string()))
+    }
+
+    #[cfg(windows)]
+    fn interrupt(&self) -> AppResult<()> {
+        Ok(())
+    }
+
+    fn is_alive(&mut self) -> bool {
+        matches!(self.child.try_wait(), Ok(None))
+    }
+
+    fn kill(&mut self) -> BoxFuture<'_, ()> {
+        Box::pin(async move {
+            // it may have exited already
+            let _ = self.child.kill().await;
+        })
+    }
+}
@@ -18,12 +18,8 @@ pub struct MiCommand {
 }
 
 pub enum DisassembleMode {
-    DisassemblyOnly = 0,
     DisassemblyWithRawOpcodes = 2,
-    MixedSourceAndDisassembly = 1, /* deprecated and 4 would be preferred, but might not be
-                                    * available in older gdb(mi) versions */
-    MixedSourceAndDisassemblyWithRawOpcodes = 3, /* deprecated and 5 would be preferred, same
-                                                  * as above */
+    SourceAndDisassemblyWithRawOpcodes = 5,
 }
 
 pub enum WatchMode {
@@ -89,16 +85,22 @@ impl<'de> Deserialize<'de> for BreakPointNumber {
         D: serde::Deserializer<'de>,
     {
         let s: String = serde::Deserialize::deserialize(deserializer)?;
+        s.parse().map_err(de::Error::custom)
+    }
+}
+
+impl FromStr for BreakPointNumber {
+    type Err = std::num::ParseIntError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
         if let Some(dot_pos) = s.find('.') {
             Ok(BreakPointNumber {
-                major: s[..dot_pos].parse::<usize>().map_err(de::Error::custom)?,
-                minor: Some(s[dot_pos + 1..].parse::<usize>().map_err(de::Error::custom)?),
+                major: s[..dot_pos].parse::<usize>()?,
+                minor: Some(s[dot_pos + 1..].parse::<usize>()?),
             })
         } else {
-            Ok(BreakPointNumber {
-                major: s.parse::<usize>().map_err(de::Error::custom)?,
-                minor: None,
-            })
+            Ok(BreakPointNumber { major: s.parse::<usize>()?, minor: None })
         }
     }
 }
@@ -113,7 +115,8 @@ impl fmt::Display for BreakPointNumber {
     }
 }
 
-fn escape_command(input: &str) -> String {
+/// Quote `input` as a C string, e.g. a file name with spaces
+pub fn escape_command(input: &str) -> String {
     let mut output = '\"'.to_string();
     for c in input.chars() {
         match c {
@@ -134,10 +137,29 @@ impl MiCommand {
         sink: &mut S,
         token: u64,
     ) -> Result<(), Error> {
-        // use std::os::unix::ffi::OsStrExt;
+        let command = self.to_interpreter_string(token);
+        info!("Writing GDB command: {}", String::from_utf8_lossy(command.as_encoded_bytes()));
+
+        sink.write_all(command.as_encoded_bytes()).await?;
+        Ok(())
+    }
+
+    /// The command line sent to GDB, prefixed by `token`
+    pub fn to_interpreter_string(&self, token: u64) -> OsString {
+        let mut command = OsString::new();
+        if !self.operation.is_empty() {
+            command.push(token.to_string());
+        }
+        command.push(self.command_line());
+        command.push("\n");
+        command
+    }
+
+    /// The command without its token and newline, as shown to users
+    pub fn command_line(&self) -> OsString {
         let mut command = OsString::new();
         if !self.operation.is_empty() {
-            command.push(format!("{}-{}", token, self.operation));
+            command.push(format!("-{}", self.operation));
         }
 
         if let Some(options) = &self.options {
@@ -155,11 +177,7 @@ impl MiCommand {
                 command.push(parameter);
             }
         }
-        command.push("\n");
-        info!("Writing GDB command: {}", String::from_utf8_lossy(command.as_encoded_bytes()));
-
-        sink.write_all(command.as_encoded_bytes()).await?;
-        Ok(())
+        command
     }
 
     pub fn interpreter_exec<S1: Into<OsString>, S2: Into<OsString>>(
@@ -214,6 +232,15 @@ impl MiCommand {
         }
     }
 
+    /// Disassemble the whole function around `address`
+    pub fn data_disassemble_function(address: u64, mode: DisassembleMode) -> MiCommand {
+        MiCommand {
+            operation: "data-disassemble",
+            options: Some(vec![OsString::from("-a"), OsString::from(format!("0x{:x}", address))]),
+            parameters: Some(vec![OsString::from((mode as u8).to_string())]),
+        }
+    }
+
     pub fn data_evaluate_expression(expression: String) -> MiCommand {
         MiCommand {
             operation: "data-evaluate-expression",
@@ -262,6 +289,25 @@ impl MiCommand {
         }
     }
 
+    /// Insert a breakpoint at a location in any form GDB accepts, e.g.
+    /// `file.c:42`, `function` or `*0x401000`
+    pub fn insert_breakpoint_at(
+        location: &str,
+        condition: Option<&str>,
+        temporary: bool,
+    ) -> MiCommand {
+        let mut options = vec![];
+        if temporary {
+            options.push("-t".into());
+        }
+        if let Some(condition) = condition {
+            options.push("-c".into());
+            options.push(escape_command(condition).into());
+        }
+        options.push(location.into());
+        MiCommand { operation: "break-insert", options: Some(options), parameters: None }
+    }
+
     pub fn delete_breakpoints(breakpoint_numbers: Vec<BreakPointNumber>) -> MiCommand {
         //GDB is broken: see http://sourceware-org.1504.n7.nabble.com/Bug-breakpoints-20133-New-unable-to-delete-a-sub-breakpoint-td396197.html
         let mut options = breakpoint_numbers;
@@ -274,6 +320,31 @@ impl MiCommand {
         }
     }
 
+    pub fn enable_breakpoints(breakpoint_numbers: Vec<BreakPointNumber>) -> MiCommand {
+        MiCommand {
+            operation: "break-enable",
+            options: Some(breakpoint_numbers.iter().map(|n| n.to_string().into()).collect()),
+            parameters: None,
+        }
+    }
+
+    pub fn disable_breakpoints(breakpoint_numbers: Vec<BreakPointNumber>) -> MiCommand {
+        MiCommand {
+            operation: "break-disable",
+            options: Some(breakpoint_numbers.iter().map(|n| n.to_string().into()).collect()),
+            parameters: None,
+        }
+    }
+
+    /// Ignore the next `count` hits of a breakpoint
+    pub fn break_after(breakpoint_number: BreakPointNumber, count: u32) -> MiCommand {
+        MiCommand {
+            operation: "break-after",
+            options: Some(vec![breakpoint_number.to_string().into(), count.to_string().into()]),
+            parameters: None,
+        }
+    }
+
     pub fn breakpoints_list() -> MiCommand {
         MiCommand { operation: "break-list", ..Default::default() }
     }
@@ -313,6 +384,10 @@ impl MiCommand {
         MiCommand { operation: "exec-next", ..Default::default() }
     }
 
+    pub fn exec_finish() -> MiCommand {
+        MiCommand { operation: "exec-finish", ..Default::default() }
+    }
+
     // Warning: This cannot be used to pass special characters like \n to gdb
     // because (unlike it is said in the spec) there is apparently no way to
     // pass \n unescaped to gdb, and for "exec-arguments" gdb somehow does not
@@ -321,10 +396,54 @@ impl MiCommand {
         MiCommand { operation: "exec-arguments", options: Some(args), parameters: None }
     }
 
+    pub fn target_select(transport: &str, parameters: &str) -> MiCommand {
+        MiCommand {
+            operation: "target-select",
+            options: None,
+            parameters: Some(vec![transport.into(), parameters.into()]),
+        }
+    }
+
+    /// Whether the command may write to the memory without GDB notifying it,
+    /// e.g. a CLI command like `monitor reset` or a load
+    pub fn may_write_memory(&self) -> bool {
+        matches!(self.operation, "interpreter-exec" | "target-download")
+    }
+
+    /// Whether the command inserts a breakpoint, which GDB answers with the
+    /// breakpoint rather than notifying it
+    pub fn inserts_breakpoint(&self) -> bool {
+        matches!(self.operation, "break-insert" | "dprintf-insert")
+    }
+
+    pub fn target_download() -> MiCommand {
+        MiCommand { operation: "target-download", ..Default::default() }
+    }
+
+    pub fn gdb_set(variable: &str, value: &str) -> MiCommand {
+        MiCommand {
+            operation: "gdb-set",
+            options: None,
+            parameters: Some(vec![variable.into(), value.into()]),
+        }
+    }
+
+    pub fn gdb_show(variable: &str) -> MiCommand {
+        MiCommand { operation: "gdb-show", options: None, parameters: Some(vec![variable.into()]) }
+    }
+
     pub fn exit() -> MiCommand {
         MiCommand { operation: "gdb-exit", ..Default::default() }
     }
 
+    pub fn thread_select(thread_id: &str) -> MiCommand {
+        MiCommand {
+            operation: "thread-select",
+            options: Some(vec![thread_id.into()]),
+            parameters: None,
+        }
+    }
+
     pub fn select_frame(frame_number: u64) -> MiCommand {
         MiCommand {
             operation: "stack-select-frame",
@@ -336,17 +455,19 @@ impl MiCommand {
     pub fn stack_info_frame(frame_number: Option<u64>) -> MiCommand {
         MiCommand {
             operation: "stack-info-frame",
-            options: if let Some(frame_number) = frame_number {
-                Some(vec![frame_number.to_string().into()])
-            } else {
-                None
-            },
+            // the command takes no argument, the frame is selected by the global option
+            options: frame_number.map(|n| vec!["--frame".into(), n.to_string().into()]),
             parameters: None,
         }
     }
 
-    pub fn stack_info_depth() -> MiCommand {
-        MiCommand { operation: "stack-info-depth", ..Default::default() }
+    /// Count the frames, at most `max_depth` of them
+    pub fn stack_info_depth(max_depth: Option<usize>) -> MiCommand {
+        MiCommand {
+            operation: "stack-info-depth",
+            options: None,
+            parameters: max_depth.map(|max_depth| vec![max_depth.to_string().into()]),
+        }
     }
 
     pub fn stack_list_variables(
@@ -363,14 +484,23 @@ impl MiCommand {
             parameters.push("--frame".into());
             parameters.push(frame_number.to_string().into());
         }
-        if let Some(values) = print_values {
-            parameters.push(values.to_string().into());
-        } else {
-            parameters.push("--simple-values".into());
-        }
+        parameters.push(print_values.unwrap_or_default().to_string().into());
         MiCommand { operation: "stack-list-variables", options: None, parameters: Some(parameters) }
     }
 
+    pub fn stack_list_arguments(
+        print_values: Option<PrintValue>,
+        low_frame: usize,
+        high_frame: usize,
+    ) -> MiCommand {
+        let parameters = vec![
+            print_values.unwrap_or_default().to_string().into(),
+            low_frame.to_string().into(),
+            high_frame.to_string().into(),
+        ];
+        MiCommand { operation: "stack-list-arguments", options: None, parameters: Some(parameters) }
+    }
+
     pub fn stack_list_frames(low_frame: Option<usize>, high_frame: Option<usize>) -> MiCommand {
         let options = if let Some(low) = low_frame {
             if let Some(high) = high_frame {
@@ -478,6 +608,76 @@ impl MiCommand {
         cmd
     }
 
+    pub fn var_evaluate_expression(name: impl Into<OsString>) -> MiCommand {
+        MiCommand {
+            operation: "var-evaluate-expression",
+            options: None,
+            parameters: Some(vec![name.into()]),
+        }
+    }
+
+    pub fn var_info_num_children(name: impl Into<OsString>) -> MiCommand {
+        MiCommand {
+            operation: "var-info-num-children",
+            options: None,
+            parameters: Some(vec![name.into()]),
+        }
+    }
+
+    pub fn var_info_type(name: impl Into<OsString>) -> MiCommand {
+        MiCommand { operation: "var-info-type", options: None, parameters: Some(vec![name.into()]) }
+    }
+
+    /// Create a variable object which is evaluated in the selected frame on
+    /// every update
+    pub fn var_create_floating(name: Option<OsString>, expression: &str) -> MiCommand {
+        MiCommand {
+            operation: "var-create",
+            options: None,
+            parameters: Some(vec![
+                name.unwrap_or_else(|| "-".into()),
+                "@".into(),
+                escape_command(expression).into(),
+            ]),
+        }
+    }
+
+    /// List the variable objects whose value changed since the last update,
+    /// `*` updates all of them
+    pub fn var_update(name: impl Into<OsString>, print_values: bool) -> MiCommand {
+        MiCommand {
+            operation: "var-update",
+            options: None,
+            parameters: Some(vec![
+                if print_values { "--all-values" } else { "--no-values" }.into(),
+                name.into(),
+            ]),
+        }
+    }
+
+    /// List the functions, optionally only those whose name matches
+    /// `name_regexp`
+    pub fn symbol_info_functions(name_regexp: Option<&str>, include_nondebug: bool) -> MiCommand {
+        let mut options = vec![];
+        if include_nondebug {
+            options.push("--include-nondebug".into());
+        }
+        if let Some(name_regexp) = name_regexp {
+            options.push("--name".into());
+            options.push(escape_command(name_regexp).into());
+        }
+        MiCommand { operation: "symbol-info-functions", options: Some(options), parameters: None }
+    }
+
+    /// List the lines of a source file which have code
+    pub fn symbol_list_lines(file: &str) -> MiCommand {
+        MiCommand {
+            operation: "symbol-list-lines",
+            options: None,
+            parameters: Some(vec![file.into()]),
+        }
+    }
+
     pub fn data_list_register_names(reg_list: Option<Vec<usize>>) -> MiCommand {
         MiCommand {
             operation: "data-list-register-names",
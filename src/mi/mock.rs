@@ -0,0 +1,172 @@
+//! A scripted debugger replaying an MI transcript, to test without GDB.
+//!
+//! A script lists the commands expected from the server, each followed by
+//! what the debugger outputs in response:
+//!
+//! ```text
+//! <- =thread-group-added,id="i1"
+//! -> -break-insert main
+//! <- ^done,bkpt={number="1"}
+//! -> <interrupt>
+//! <- *stopped,reason="signal-received"
+//! ```
+//!
+//! The lines before the first command are output at startup, the token of a
+//! command is added to its result record and `<interrupt>` stands for an
+//! interrupt. A command which is not the next one expected gets an `^error`
+//! and the output ends at `<- <eof>`, as if GDB died
+
+use std::collections::VecDeque;
+
+use futures::future::BoxFuture;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader, DuplexStream};
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+
+use super::backend::{BackendInput, BackendOutput, DebuggerBackend};
+use crate::error::AppResult;
+
+/// Size of the pipes to and from the script
+const PIPE_BYTES: usize = 64 * 1024;
+/// Command of the script standing for an interrupt
+const INTERRUPT: &str = "<interrupt>";
+/// Record of the script closing the output
+const EOF: &str = "<eof>";
+
+pub struct MockBackend {
+    input: Option<DuplexStream>,
+    output: Option<DuplexStream>,
+    interrupts: mpsc::UnboundedSender<()>,
+    task: JoinHandle<()>,
+}
+
+/// A command expected by the script and the records output in response
+#[derive(Debug, PartialEq)]
+struct Step {
+    command: String,
+    output: Vec<String>,
+}
+
+impl MockBackend {
+    pub fn new(script: &str) -> Self {
+        let (startup, steps) = parse_script(script);
+        let (input, script_input) = tokio::io::duplex(PIPE_BYTES);
+        let (mut script_output, output) = tokio::io::duplex(PIPE_BYTES);
+        let (interrupts, mut interrupted) = mpsc::unbounded_channel();
+        let task = tokio::spawn(async move {
+            let mut steps = steps;
+            let mut lines = BufReader::new(script_input).lines();
+            let mut records = startup;
+            loop {
+                for record in records {
+                    if record == EOF {
+                        return;
+                    }
+                    if script_output.write_all(format!("{}\n", record).as_bytes()).await.is_err() {
+                        return;
+                    }
+                }
+                let (token, command) = tokio::select! {
+                    line = lines.next_line() => match line {
+                        Ok(Some(line)) => split_token(&line),
+                        _ => return,
+                    },
+                    Some(()) = interrupted.recv() => (String::new(), INTERRUPT.to_string()),
+                };
+                records = match steps.front() {
+                    Some(step) if step.command == command => steps.pop_front().unwrap().output,
+                    _ => vec![format!(
+                        "^error,msg=\"unexpected command {}, expecting {}\"",
+                        command.replace('"', "'"),
+                        steps.front().map_or("none", |step| step.command.as_str())
+                    )],
+                };
+                for record in &mut records {
+                    if record.starts_with('^') {
+                        record.insert_str(0, &token);
+                    }
+                }
+            }
+        });
+        MockBackend { input: Some(input), output: Some(output), interrupts, task }
+    }
+}
+
+impl Drop for MockBackend {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+impl DebuggerBackend for MockBackend {
+    fn take_input(&mut self) -> Option<BackendInput> {
+        self.input.take().map(|input| Box::new(input) as BackendInput)
+    }
+
+    fn take_output(&mut self) -> Option<BackendOutput> {
+        self.output.take().map(|output| Box::new(output) as BackendOutput)
+    }
+
+    fn interrupt(&self) -> AppResult<()> {
+        let _ = self.interrupts.send(());
+        Ok(())
+    }
+
+    fn is_alive(&mut self) -> bool {
+        !self.task.is_finished()
+    }
+
+    fn kill(&mut self) -> BoxFuture<'_, ()> {
+        self.task.abort();
+        Box::pin(async {})
+    }
+}
+
+/// The records output at startup and the steps of a script
+fn parse_script(script: &str) -> (Vec<String>, VecDeque<Step>) {
+    let mut startup = vec![];
+    let mut steps = VecDeque::<Step>::new();
+    for line in script.lines().map(str::trim).filter(|line| !line.is_empty()) {
+        if let Some(command) = line.strip_prefix("->") {
+            steps.push_back(Step { command: command.trim().to_string(), output: vec![] });
+        } else if let Some(record) = line.strip_prefix("<-") {
+            let record = record.trim().to_string();
+            match steps.back_mut() {
+                Some(step) => step.output.push(record),
+                None => startup.push(record),
+            }
+        }
+    }
+    (startup, steps)
+}
+
+/// The token and the command of a line written by the server
+fn split_token(line: &str) -> (String, String) {
+    let start = line.find(|c: char| !c.is_ascii_digit()).unwrap_or(line.len());
+    (line[..start].to_string(), line[start..].trim().to_string())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_script() {
+        let (startup, steps) = parse_script(
+            "<- =thread-group-added,id=\"i1\"\n\n-> -break-insert main\n<- ^done\n-> <interrupt>\n",
+        );
+        assert_eq!(startup, ["=thread-group-added,id=\"i1\""]);
+        assert_eq!(
+            steps,
+            [
+                Step {
+                    command: "-break-insert main".to_string(),
+                    output: vec!["^done".to_string()]
+                },
+                Step { command: INTERRUPT.to_string(), output: vec![] },
+            ]
+        );
+        assert_eq!(split_token("12-exec-run"), ("12".to_string(), "-exec-run".to_string()));
+        assert_eq!(split_token(""), (String::new(), String::new()));
+    }
+}
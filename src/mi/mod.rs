@@ -1,30 +1,48 @@
+pub mod backend;
 pub mod commands;
+#[cfg(test)]
+pub mod mock;
 pub mod output;
+pub mod queue;
+pub mod transcript;
 
 use std::ffi::OsString;
+use std::fs::File;
+use std::io::ErrorKind;
 use std::path::{Path, PathBuf};
 use std::process::Stdio;
 use std::sync::Arc;
 use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::time::Duration;
 
-use anyhow::Result;
+use backend::{BackendInput, DebuggerBackend, ProcessBackend};
 use output::process_output;
-use tokio::io::BufReader;
-use tokio::process::{Child, Command};
-use tokio::sync::Mutex;
-use tokio::sync::mpsc::{self, Sender};
+use queue::OutOfBandSender;
+use tokio::io::{AsyncWriteExt, BufReader};
+use tokio::process::Command;
+use tokio::sync::{Mutex, mpsc};
 use tracing::debug;
+use transcript::{EntryKind, Transcript};
 
 use crate::error::{AppError, AppResult};
 
 #[allow(clippy::upper_case_acronyms)]
 pub struct GDB {
-    pub process: Arc<Mutex<Child>>,
+    backend: Mutex<Box<dyn DebuggerBackend>>,
+    /// Held by the command in progress, the other methods do not wait for it
+    commands: Mutex<Commands>,
     is_running: Arc<AtomicBool>,
-    result_output: mpsc::Receiver<output::ResultRecord>,
     current_command_token: AtomicU64,
     binary_path: PathBuf,
     init_options: Vec<OsString>,
+    transcript: Transcript,
+    failure: output::Failure,
+}
+
+/// The input of the debugger and the results of the commands written to it
+struct Commands {
+    input: BackendInput,
+    results: mpsc::Receiver<output::ResultRecord>,
 }
 
 #[derive(Clone, Debug, PartialEq)]
@@ -36,6 +54,7 @@ pub enum ExecuteError {
 /// A builder struct for configuring and launching GDB with various command line
 /// options. This struct provides a fluent interface for setting up GDB with
 /// different parameters before spawning the debugger process.
+#[derive(Clone)]
 pub struct GDBBuilder {
     /// Path to the GDB executable
     pub gdb_path: PathBuf,
@@ -87,7 +106,51 @@ impl GDBBuilder {
         }
     }
 
-    pub fn try_spawn(self, oob_sink: Sender<output::OutOfBandRecord>) -> AppResult<GDB> {
+    /// Resolve a path the way GDB will, relative paths are looked up in the
+    /// `--cd` directory if there is one
+    fn resolve_path(&self, path: &Path) -> PathBuf {
+        match &self.opt_cd {
+            Some(cd) if path.is_relative() => cd.join(path),
+            _ => path.to_path_buf(),
+        }
+    }
+
+    /// Check the files handed to GDB up front, otherwise mistakes only show up
+    /// later as stream output of an already running session
+    fn validate(&self) -> AppResult<()> {
+        if let Some(cd) = &self.opt_cd
+            && !cd.is_dir()
+        {
+            return Err(AppError::InvalidArgument(format!(
+                "cd {} is not an existing directory",
+                cd.display()
+            )));
+        }
+        if let Some(program) = &self.opt_program {
+            let mut resolved = self.resolve_path(program);
+            // like GDB itself, bare program names are also searched in PATH
+            if !resolved.exists() && program.parent() == Some(Path::new("")) {
+                resolved = find_in_path(program).unwrap_or(resolved);
+            }
+            let program = resolved;
+            check_file("program", &program)?;
+            check_executable(&program)?;
+        }
+        if let Some(symbol_file) = &self.opt_symbol_file {
+            check_file("symbol_file", &self.resolve_path(symbol_file))?;
+        }
+        if let Some(core_file) = &self.opt_core_file {
+            check_file("core_file", &self.resolve_path(core_file))?;
+        }
+        if let Some(command) = &self.opt_command {
+            check_file("command", &self.resolve_path(command))?;
+        }
+        Ok(())
+    }
+
+    pub fn try_spawn(self, oob_sink: OutOfBandSender) -> AppResult<GDB> {
+        self.validate()?;
+
         let mut gdb_args = Vec::<OsString>::new();
         let mut init_options = Vec::<OsString>::new();
         if self.opt_nh {
@@ -154,42 +217,173 @@ impl GDBBuilder {
 
         debug!("Starting GDB process with command: {:?}", command);
 
-        let mut child = command
+        let child = command
             .stdin(Stdio::piped())
             .stdout(Stdio::piped())
             .stderr(Stdio::piped())
             .spawn()
-            .map_err(|e| AppError::GDBError(format!("Failed to start GDB process: {}", e)))?;
+            .map_err(|e| AppError::GDBUnusable {
+                path: self.gdb_path.display().to_string(),
+                reason: format!("failed to start the GDB process: {}", e),
+            })?;
+
+        GDB::with_backend(
+            Box::new(ProcessBackend::new(child)),
+            oob_sink,
+            self.gdb_path,
+            init_options,
+        )
+    }
+}
+
+/// Check that the GDB binary at `path` runs and understands the MI interpreter,
+/// returns the version line reported by `--version`
+pub async fn probe_gdb(path: &Path, timeout: Duration) -> AppResult<String> {
+    let unusable =
+        |reason: String| AppError::GDBUnusable { path: path.display().to_string(), reason };
+
+    let output = tokio::time::timeout(timeout, Command::new(path).arg("--version").output())
+        .await
+        .map_err(|_| unusable("timed out running --version".to_string()))?
+        .map_err(|e| {
+            unusable(match e.kind() {
+                ErrorKind::NotFound => "executable not found, is it on PATH?".to_string(),
+                ErrorKind::PermissionDenied => "permission denied when executing it".to_string(),
+                _ => format!("failed to execute it: {}", e),
+            })
+        })?;
+    if !output.status.success() {
+        return Err(unusable(format!("--version exited with {}", output.status)));
+    }
+    let version = String::from_utf8_lossy(&output.stdout).lines().next().unwrap_or("").to_string();
+    if !version.contains("gdb") && !version.contains("GDB") {
+        return Err(unusable(format!("unexpected --version output: {:?}", version)));
+    }
+
+    let mut child = Command::new(path)
+        .args(["--interpreter=mi", "--nx", "--quiet"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()
+        .map_err(|e| unusable(format!("failed to start the MI interpreter: {}", e)))?;
+    if let Some(mut stdin) = child.stdin.take() {
+        // a failed write shows up as a missing ^done below
+        let _ = stdin.write_all(b"-list-features\n-gdb-exit\n").await;
+    }
+    let output = tokio::time::timeout(timeout, child.wait_with_output())
+        .await
+        .map_err(|_| unusable("timed out waiting for the MI handshake".to_string()))?
+        .map_err(|e| unusable(format!("MI handshake failed: {}", e)))?;
+    if !String::from_utf8_lossy(&output.stdout).lines().any(|line| line.starts_with("^done")) {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(unusable(format!(
+            "no GDB/MI support ({})",
+            stderr.lines().next().unwrap_or("no response to the MI handshake")
+        )));
+    }
+
+    Ok(version)
+}
+
+/// Look up a bare executable name in the directories of `PATH`
+pub fn find_in_path(name: &Path) -> Option<PathBuf> {
+    let paths = std::env::var_os("PATH")?;
+    std::env::split_paths(&paths).map(|dir| dir.join(name)).find(|path| path.is_file())
+}
 
-        let stdout = BufReader::new(child.stdout.take().unwrap());
+/// Make sure `path` is an existing, readable regular file, `name` is the
+/// parameter reported in the error message
+fn check_file(name: &str, path: &Path) -> AppResult<()> {
+    let metadata = std::fs::metadata(path).map_err(|e| {
+        AppError::InvalidArgument(match e.kind() {
+            ErrorKind::NotFound if path.is_relative() => format!(
+                "{} {} does not exist, relative paths are resolved against the server's working \
+                 directory, pass an absolute path or set cd",
+                name,
+                path.display()
+            ),
+            ErrorKind::NotFound => format!("{} {} does not exist", name, path.display()),
+            _ => format!("{} {} is not accessible: {}", name, path.display(), e),
+        })
+    })?;
+    if !metadata.is_file() {
+        return Err(AppError::InvalidArgument(format!(
+            "{} {} is not a regular file",
+            name,
+            path.display()
+        )));
+    }
+    File::open(path).map_err(|e| {
+        AppError::InvalidArgument(format!("{} {} is not readable: {}", name, path.display(), e))
+    })?;
+    Ok(())
+}
+
+#[cfg(unix)]
+fn check_executable(path: &Path) -> AppResult<()> {
+    use std::os::unix::fs::PermissionsExt;
+
+    if std::fs::metadata(path)?.permissions().mode() & 0o111 == 0 {
+        return Err(AppError::InvalidArgument(format!(
+            "program {} is not executable, add the execute permission (chmod +x)",
+            path.display()
+        )));
+    }
+    Ok(())
+}
+
+#[cfg(windows)]
+fn check_executable(_path: &Path) -> AppResult<()> {
+    Ok(())
+}
+
+impl GDB {
+    /// Talk to a debugger, the records it outputs are parsed by a spawned
+    /// task. `binary_path` and `init_options` are only reported
+    pub fn with_backend(
+        mut backend: Box<dyn DebuggerBackend>,
+        oob_sink: OutOfBandSender,
+        binary_path: PathBuf,
+        init_options: Vec<OsString>,
+    ) -> AppResult<GDB> {
+        let missing = |what: &str| AppError::GDBError(format!("no {} to the debugger", what));
+        let input = backend.take_input().ok_or_else(|| missing("input"))?;
+        let output = BufReader::new(backend.take_output().ok_or_else(|| missing("output"))?);
         let is_running = Arc::new(AtomicBool::new(false));
         let is_running_clone = is_running.clone();
         let (result_input, result_output) = mpsc::channel(100);
-        tokio::spawn(process_output(stdout, result_input, oob_sink, is_running_clone));
+        let transcript = Transcript::default();
+        let failure = output::Failure::default();
+        tokio::spawn(process_output(
+            output,
+            result_input,
+            oob_sink,
+            is_running_clone,
+            transcript.clone(),
+            failure.clone(),
+        ));
 
-        let gdb = GDB {
-            process: Arc::new(Mutex::new(child)),
+        Ok(GDB {
+            backend: Mutex::new(backend),
+            commands: Mutex::new(Commands { input, results: result_output }),
             is_running,
             current_command_token: AtomicU64::new(0),
-            binary_path: self.gdb_path,
+            binary_path,
             init_options,
-            result_output,
-        };
-        Ok(gdb)
+            transcript,
+            failure,
+        })
     }
-}
 
-impl GDB {
-    #[cfg(unix)]
-    pub async fn interrupt_execution(&self) -> Result<(), nix::Error> {
-        use nix::sys::signal;
-        use nix::unistd::Pid;
-        signal::kill(Pid::from_raw(self.process.lock().await.id().unwrap() as i32), signal::SIGINT)
+    pub async fn interrupt_execution(&self) -> AppResult<()> {
+        self.backend.lock().await.interrupt()
     }
 
-    #[cfg(windows)]
-    pub async fn interrupt_execution(&self) -> Result<()> {
-        Ok(())
+    /// Terminate the debugger
+    pub async fn kill(&self) {
+        self.backend.lock().await.kill().await
     }
 
     pub fn binary_path(&self) -> &Path {
@@ -200,40 +394,44 @@ impl GDB {
         &self.init_options
     }
 
+    pub fn transcript(&self) -> &Transcript {
+        &self.transcript
+    }
+
+    /// Why GDB stopped answering, if it did other than by exiting as asked
+    pub fn failure(&self) -> &output::Failure {
+        &self.failure
+    }
+
+    /// Whether GDB is still running, checked without sending it a command,
+    /// which would fail while the program runs
+    pub async fn is_alive(&self) -> bool {
+        self.failure.get().is_none() && self.backend.lock().await.is_alive()
+    }
+
     pub fn is_running(&self) -> bool {
         self.is_running.load(Ordering::SeqCst)
     }
 
-    pub fn new_token(&mut self) -> u64 {
+    pub fn new_token(&self) -> u64 {
         self.current_command_token.fetch_add(1, Ordering::SeqCst)
     }
 
     pub async fn execute<C: std::borrow::Borrow<commands::MiCommand>>(
-        &mut self,
+        &self,
         command: C,
     ) -> AppResult<output::ResultRecord> {
+        let mut commands = self.commands.lock().await;
         if self.is_running() {
             return Err(AppError::GDBBusy);
         }
 
         let command_token = self.new_token();
+        self.record_command(command.borrow(), command_token);
 
-        command
-            .borrow()
-            .write_interpreter_string(
-                &mut self
-                    .process
-                    .lock()
-                    .await
-                    .stdin
-                    .as_mut()
-                    .ok_or_else(|| AppError::GDBError("Failed to get stdin".to_string()))?,
-                command_token,
-            )
-            .await
-            .expect("write interpreter command");
+        self.write_command(&mut commands.input, command.borrow(), command_token).await?;
 
-        match self.result_output.recv().await {
+        match commands.results.recv().await {
             Some(record) => match record.token {
                 Some(token) => {
                     if token == command_token {
@@ -251,33 +449,52 @@ impl GDB {
                     command_token
                 ))),
             },
-            None => Err(AppError::GDBError("no result, expecting {}".to_string())),
+            None => Err(self.failure.get().map_or(AppError::GDBQuit, AppError::GDBError)),
         }
     }
 
-    pub async fn execute_later<C: std::borrow::Borrow<commands::MiCommand>>(&mut self, command: C) {
-        let command_token = self.new_token();
-        command
-            .borrow()
-            .write_interpreter_string(
-                &mut self.process.lock().await.stdin.as_mut().unwrap(),
-                command_token,
+    async fn write_command(
+        &self,
+        input: &mut BackendInput,
+        command: &commands::MiCommand,
+        token: u64,
+    ) -> AppResult<()> {
+        command.write_interpreter_string(input, token).await.map_err(|e| {
+            self.failure.get().map_or_else(
+                || AppError::GDBError(format!("Failed to write to GDB: {}", e)),
+                AppError::GDBError,
             )
-            .await
-            .expect("write interpreter command");
-        let _ = self.result_output.recv().await;
+        })
     }
 
-    pub async fn is_session_active(&mut self) -> AppResult<bool> {
-        let res = self.execute(commands::MiCommand::thread_info(None)).await?;
-        if let Some(threads) = res.results.get("threads") {
-            if let Some(threads) = threads.as_array() {
-                Ok(!threads.is_empty())
-            } else {
-                Err(AppError::GDBError("threads is not an array".to_string()))
-            }
-        } else {
-            Err(AppError::GDBError("threads is not found".to_string()))
-        }
+    fn record_command(&self, command: &commands::MiCommand, token: u64) {
+        let text = command.to_interpreter_string(token);
+        self.transcript.record(EntryKind::Command, &text.to_string_lossy());
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_validate() {
+        let test_binary = std::env::current_exe().unwrap();
+        let mut builder = GDBBuilder::new(PathBuf::from("gdb"));
+        builder.opt_program = Some(test_binary.clone());
+        assert!(builder.validate().is_ok());
+
+        // relative paths are looked up in the cd directory
+        builder.opt_cd = test_binary.parent().map(Path::to_path_buf);
+        builder.opt_program = test_binary.file_name().map(PathBuf::from);
+        assert!(builder.validate().is_ok());
+
+        builder.opt_core_file = Some(PathBuf::from("no-such-core"));
+        assert!(matches!(builder.validate(), Err(AppError::InvalidArgument(msg))
+            if msg.starts_with("core_file") && msg.contains("does not exist")));
+
+        builder.opt_cd = Some(test_binary.clone());
+        assert!(matches!(builder.validate(), Err(AppError::InvalidArgument(msg))
+            if msg.contains("is not an existing directory")));
     }
 }
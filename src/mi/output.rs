@@ -1,19 +1,26 @@
 // use std::io::{BufRead, BufReader, Read};
-use std::sync::Arc;
+use std::borrow::Cow;
 use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
 
 use anyhow::Result;
 use nom::branch::alt;
-use nom::bytes::complete::{is_not, tag, take_while_m_n};
+use nom::bytes::complete::{is_not, tag, take_till, take_while_m_n};
 use nom::character::complete::{char, digit1, line_ending, multispace1};
 use nom::combinator::{map, map_opt, map_res, opt, value, verify};
 use nom::error::{FromExternalError, ParseError};
 use nom::multi::{fold, many0, separated_list0};
 use nom::sequence::{delimited, preceded, separated_pair};
 use nom::{IResult, Parser};
+use serde::Serialize;
 use serde_json::{Map, Value};
-use tokio::io::{AsyncBufReadExt, AsyncRead, BufReader};
-use tracing::{debug, error, info};
+use tokio::io::{AsyncBufRead, AsyncBufReadExt, AsyncRead, AsyncReadExt, BufReader};
+use tracing::{debug, error, info, warn};
+
+use crate::error::AppError;
+use crate::metrics::METRICS;
+use crate::mi::queue::OutOfBandSender;
+use crate::mi::transcript::{EntryKind, Transcript};
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum ResultClass {
@@ -51,14 +58,37 @@ pub enum AsyncClass {
     Other(String), //?
 }
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+impl AsyncClass {
+    /// The class as written in the record, e.g. `library-loaded`
+    pub fn as_str(&self) -> &str {
+        match self {
+            AsyncClass::Running => "running",
+            AsyncClass::Stopped => "stopped",
+            AsyncClass::CmdParamChanged => "cmd-param-changed",
+            AsyncClass::LibraryLoaded => "library-loaded",
+            AsyncClass::Thread(ThreadEvent::Created) => "thread-created",
+            AsyncClass::Thread(ThreadEvent::GroupStarted) => "thread-group-started",
+            AsyncClass::Thread(ThreadEvent::Exited) => "thread-exited",
+            AsyncClass::Thread(ThreadEvent::GroupExited) => "thread-group-exited",
+            AsyncClass::Thread(ThreadEvent::Selected) => "thread-selected",
+            AsyncClass::BreakPoint(BreakPointEvent::Created) => "breakpoint-created",
+            AsyncClass::BreakPoint(BreakPointEvent::Deleted) => "breakpoint-deleted",
+            AsyncClass::BreakPoint(BreakPointEvent::Modified) => "breakpoint-modified",
+            AsyncClass::Other(class) => class,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
 pub enum AsyncKind {
     Exec,
     Status,
     Notify,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
 pub enum StreamKind {
     Console,
     Target,
@@ -70,6 +100,25 @@ pub struct ResultRecord {
     pub(crate) token: Option<u64>,
     pub class: ResultClass,
     pub results: Value,
+    /// What GDB printed on the console since the previous result record, the
+    /// output of a CLI command
+    pub console: String,
+}
+
+impl ResultRecord {
+    /// Convert an `^error` record into [`AppError::GDBCommandError`] with the
+    /// message and optional error code reported by GDB, `None` for other
+    /// classes
+    pub fn error(&self) -> Option<AppError> {
+        if self.class != ResultClass::Error {
+            return None;
+        }
+        let field = |name: &str| self.results.get(name).and_then(Value::as_str).map(str::to_string);
+        Some(AppError::GDBCommandError {
+            msg: field("msg").unwrap_or_else(|| self.results.to_string()),
+            code: field("code"),
+        })
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -86,6 +135,22 @@ enum Output {
     SomethingElse(String), /* Debug */
 }
 
+/// Why the output of GDB stopped before it was asked to exit, shared by
+/// [`process_output`] and the session
+#[derive(Debug, Clone, Default)]
+pub struct Failure(Arc<Mutex<Option<String>>>);
+
+impl Failure {
+    pub fn get(&self) -> Option<String> {
+        self.0.lock().unwrap().clone()
+    }
+
+    fn set(&self, reason: String) {
+        error!("{}", reason);
+        *self.0.lock().unwrap() = Some(reason);
+    }
+}
+
 // use crate::mi::OutOfBandRecordSink;
 
 use tokio::sync::mpsc::Sender;
@@ -93,70 +158,243 @@ use tokio::sync::mpsc::Sender;
 pub async fn process_output<T: AsyncRead + Unpin>(
     output: T,
     result_pipe: Sender<ResultRecord>,
-    out_of_band_pipe: Sender<OutOfBandRecord>,
+    out_of_band_pipe: OutOfBandSender,
     is_running: Arc<AtomicBool>,
+    transcript: Transcript,
+    failure: Failure,
 ) {
     let mut reader = BufReader::new(output);
+    let mut lines = LineAssembler::default();
+    // GDB answers ^exit before exiting as asked
+    let mut exiting = false;
+    // the console output of the command in progress, GDB prints it before
+    // the result record
+    let mut console = String::new();
 
     loop {
-        let mut buffer = String::new();
-        match reader.read_line(&mut buffer).await {
+        let mut bytes = Vec::new();
+        match read_record(&mut reader, &mut bytes, MAX_RECORD_BYTES).await {
             Ok(0) => {
+                if !exiting {
+                    failure.set("GDB exited unexpectedly".to_string());
+                }
                 return;
             }
+            Ok(length) if length > bytes.len() => {
+                warn!("Skipped a record of {} bytes from GDB, it is too large", length);
+                if let Some(mut record) = oversized_result(&bytes, length) {
+                    record.console = std::mem::take(&mut console);
+                    if result_pipe.send(record).await.is_err() {
+                        return;
+                    }
+                }
+            }
             Ok(_) => {
-                info!("{}", buffer.trim_end());
+                // GDB passes through whatever the program prints
+                let buffer = String::from_utf8_lossy(&bytes);
+                info!("{}", abbreviate(buffer.trim_end()));
+                transcript.record(EntryKind::Output, &abbreviate(&buffer));
 
-                let parse_result = match Output::parse(&buffer) {
-                    Ok(r) => r,
-                    Err(e) => {
-                        error!("PARSING ERROR: {}", e);
-                        continue;
-                    }
-                };
-                debug!("{:?}", &parse_result);
-                match parse_result {
-                    Output::Result(record) => {
-                        match record.class {
-                            ResultClass::Running => is_running.store(true, Ordering::SeqCst),
-                            //Apparently sometimes gdb first claims to be running, only to then
-                            // stop again (without notifying the user)...
-                            ResultClass::Error => is_running.store(false, Ordering::SeqCst),
-                            _ => {}
+                for line in lines.push(&buffer) {
+                    let line = format!("{}\n", line);
+                    // a large record takes a while to parse, which would stall
+                    // the other tasks of the thread
+                    let parsed = if line.len() > LARGE_RECORD_BYTES {
+                        tokio::task::spawn_blocking(move || Output::parse(&line))
+                            .await
+                            .unwrap_or_else(|e| Err(e.to_string()))
+                    } else {
+                        Output::parse(&line)
+                    };
+                    let parse_result = match parsed {
+                        Ok(r) => r,
+                        Err(e) => {
+                            error!("PARSING ERROR: {}", e);
+                            METRICS.parse_error();
+                            continue;
                         }
-                        result_pipe.send(record).await.expect("send result to pipe");
-                    }
-                    Output::OutOfBand(record) => {
-                        if let OutOfBandRecord::AsyncRecord { class: AsyncClass::Stopped, .. } =
-                            record
-                        {
-                            is_running.store(false, Ordering::SeqCst);
+                    };
+                    debug!("{:?}", &parse_result);
+                    match parse_result {
+                        Output::Result(mut record) => {
+                            record.console = std::mem::take(&mut console);
+                            match record.class {
+                                ResultClass::Running => is_running.store(true, Ordering::SeqCst),
+                                //Apparently sometimes gdb first claims to be running, only to
+                                // then stop again (without notifying the user)...
+                                ResultClass::Error => is_running.store(false, Ordering::SeqCst),
+                                ResultClass::Exit => exiting = true,
+                                _ => {}
+                            }
+                            if result_pipe.send(record).await.is_err() {
+                                return;
+                            }
                         }
-                        out_of_band_pipe
-                            .send(record)
-                            .await
-                            .expect("send out of band record to pipe");
-                    }
-                    Output::GDBLine => {}
-                    //Output::SomethingElse(_) => { /*println!("SOMETHING ELSE: {}", str);*/ }
-                    Output::SomethingElse(text) => {
-                        out_of_band_pipe
-                            .send(OutOfBandRecord::StreamRecord {
+                        Output::OutOfBand(record) => {
+                            match &record {
+                                OutOfBandRecord::AsyncRecord {
+                                    kind: AsyncKind::Exec,
+                                    class,
+                                    ..
+                                } => {
+                                    if *class == AsyncClass::Stopped {
+                                        is_running.store(false, Ordering::SeqCst);
+                                    }
+                                    // what GDB printed about the program, e.g.
+                                    // the breakpoint hit, is not the output
+                                    // of the next command
+                                    console.clear();
+                                }
+                                OutOfBandRecord::StreamRecord {
+                                    kind: StreamKind::Console,
+                                    data,
+                                } => console.push_str(data),
+                                _ => {}
+                            }
+                            if out_of_band_pipe.send(record).await.is_err() {
+                                return;
+                            }
+                        }
+                        Output::GDBLine => {}
+                        Output::SomethingElse(text) => {
+                            let record = OutOfBandRecord::StreamRecord {
                                 kind: StreamKind::Target,
                                 data: text,
-                            })
-                            .await
-                            .expect("send out of band record to pipe");
+                            };
+                            if out_of_band_pipe.send(record).await.is_err() {
+                                return;
+                            }
+                        }
                     }
                 }
             }
             Err(e) => {
-                panic!("{}", e);
+                failure.set(format!("Failed to read the output of GDB: {}", e));
+                return;
             }
         }
     }
 }
 
+/// Largest record read, the commands reading more at once fail
+const MAX_RECORD_BYTES: usize = 64 * 1024 * 1024;
+/// Records parsed on the blocking threads from this size
+const LARGE_RECORD_BYTES: usize = 1024 * 1024;
+/// Bytes of a record logged and kept in the transcript
+const LOGGED_BYTES: usize = 4096;
+
+/// Read a line of at most `limit` bytes into `bytes`, skipping the rest of a
+/// longer one, and return the length of the whole line
+async fn read_record<R: AsyncBufRead + Unpin>(
+    reader: &mut R,
+    bytes: &mut Vec<u8>,
+    limit: usize,
+) -> std::io::Result<usize> {
+    let mut length = (&mut *reader).take(limit as u64).read_until(b'\n', bytes).await?;
+    if length < limit || bytes.ends_with(b"\n") {
+        return Ok(length);
+    }
+    let mut rest = Vec::new();
+    loop {
+        rest.clear();
+        let read = (&mut *reader).take(limit as u64).read_until(b'\n', &mut rest).await?;
+        length += read;
+        if read == 0 || rest.ends_with(b"\n") {
+            return Ok(length);
+        }
+    }
+}
+
+/// The error answering the command whose result record of `length` bytes,
+/// starting with `start`, was skipped, None for other records
+fn oversized_result(start: &[u8], length: usize) -> Option<ResultRecord> {
+    let digits = start.iter().take_while(|b| b.is_ascii_digit()).count();
+    if start.get(digits) != Some(&b'^') {
+        return None;
+    }
+    Some(ResultRecord {
+        token: std::str::from_utf8(&start[..digits]).ok().and_then(|token| token.parse().ok()),
+        class: ResultClass::Error,
+        results: serde_json::json!({
+            "msg": format!(
+                "The result of {} bytes is larger than the {} bytes allowed, ask for less at once",
+                length, MAX_RECORD_BYTES
+            )
+        }),
+        console: String::new(),
+    })
+}
+
+/// The start of a long text, for the logs
+fn abbreviate(text: &str) -> Cow<'_, str> {
+    if text.len() <= LOGGED_BYTES {
+        return Cow::Borrowed(text);
+    }
+    let mut end = LOGGED_BYTES;
+    while !text.is_char_boundary(end) {
+        end -= 1;
+    }
+    Cow::Owned(format!("{}... ({} bytes)", &text[..end], text.len()))
+}
+
+/// Lines of a stream record joined at most, a string left open longer is
+/// taken for the output of the program
+const MAX_CONTINUATION_LINES: usize = 64;
+
+/// Split the output of GDB into records: CR LF and lone CR, which a program
+/// writing to the same terminal as GDB may print to redraw a line, end a
+/// record like LF, except in a stream record whose string continues on the
+/// next lines, which are joined
+#[derive(Debug, Default)]
+struct LineAssembler {
+    /// Stream record whose string is not terminated yet, with its lines
+    pending: Option<(String, usize)>,
+}
+
+impl LineAssembler {
+    /// The records completed by a line read from GDB, without line ending
+    fn push(&mut self, line: &str) -> Vec<String> {
+        let line = line.strip_suffix('\n').unwrap_or(line);
+        let line = line.strip_suffix('\r').unwrap_or(line);
+        if let Some((mut record, count)) = self.pending.take() {
+            record.push('\n');
+            record.push_str(line);
+            if is_unterminated_stream(&record) && count < MAX_CONTINUATION_LINES {
+                self.pending = Some((record, count + 1));
+                return vec![];
+            }
+            return vec![record];
+        }
+        if is_unterminated_stream(line) {
+            self.pending = Some((line.to_string(), 1));
+            return vec![];
+        }
+        // a CR in the string of a stream record is not escaped as it should
+        if stream_kind(line).is_ok_and(|(rest, _)| rest.starts_with('"')) {
+            return vec![line.to_string()];
+        }
+        line.split('\r').filter(|record| !record.is_empty()).map(str::to_string).collect()
+    }
+}
+
+/// Whether the string of a stream record is not closed on its line
+fn is_unterminated_stream(record: &str) -> bool {
+    let Some(string) = stream_kind(record).ok().and_then(|(rest, _)| rest.strip_prefix('"')) else {
+        return false;
+    };
+    let mut chars = string.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => {
+                chars.next();
+            }
+            '"' => return false,
+            _ => {}
+        }
+    }
+    true
+}
+
 impl Output {
     fn parse(line: &str) -> Result<Self, String> {
         match output(line) {
@@ -170,6 +408,15 @@ impl Output {
     }
 }
 
+/// Parse a result record captured from GDB, to test the models
+#[cfg(test)]
+pub fn parse_result_record(line: &str) -> ResultRecord {
+    match Output::parse(line) {
+        Ok(Output::Result(record)) => record,
+        other => panic!("not a result record: {:?}", other),
+    }
+}
+
 /// parse the result class by looking for the corresponding tag, which is
 /// one of: done, running, connected, error, exit
 fn result_class(input: &str) -> IResult<&str, ResultClass> {
@@ -199,6 +446,14 @@ where
     map_opt(parse_u32, std::char::from_u32).parse(input)
 }
 
+/// Parse an escaped byte in octal, \ooo, which GDB writes for the bytes it
+/// can't print, like the parts of a multibyte character
+fn escaped_byte(input: &str) -> IResult<&str, u8> {
+    let parse_octal = take_while_m_n(1, 3, |c: char| c.is_digit(8));
+
+    preceded(char('\\'), map_res(parse_octal, |octal| u8::from_str_radix(octal, 8))).parse(input)
+}
+
 /// Parse an escaped character: \n, \t, \r, \u{00AC}, etc.
 fn escaped_char(input: &str) -> IResult<&str, char> {
     preceded(
@@ -208,6 +463,9 @@ fn escaped_char(input: &str) -> IResult<&str, char> {
             value('\n', char('n')),
             value('\r', char('r')),
             value('\t', char('t')),
+            value('\u{07}', char('a')),
+            value('\u{0B}', char('v')),
+            value('\u{1B}', char('e')),
             value('\u{08}', char('b')),
             value('\u{0C}', char('f')),
             value('\\', char('\\')),
@@ -233,11 +491,12 @@ fn literal(input: &str) -> IResult<&str, &str> {
 
 /// A string fragment contains a fragment of a string being parsed: either
 /// a non-empty Literal (a series of non-escaped characters), a single
-/// parsed escaped character, or a block of escaped whitespace.
+/// parsed escaped character or byte, or a block of escaped whitespace.
 #[derive(Debug, PartialEq, Eq, Clone)]
 enum StringFragment<'a> {
     Literal(&'a str),
     EscapedChar(char),
+    EscapedByte(u8),
     EscapedWS,
 }
 
@@ -246,6 +505,7 @@ enum StringFragment<'a> {
 fn parse_fragment(input: &str) -> IResult<&str, StringFragment> {
     alt((
         map(literal, |s| StringFragment::Literal(s)),
+        map(escaped_byte, StringFragment::EscapedByte),
         map(escaped_char, |c| StringFragment::EscapedChar(c)),
         value(StringFragment::EscapedWS, escaped_whitespace),
     ))
@@ -253,18 +513,25 @@ fn parse_fragment(input: &str) -> IResult<&str, StringFragment> {
 }
 
 /// Parse a string. Use a loop of parse_fragment and push all of the fragments
-/// into an output string.
+/// into an output string. The escaped bytes which are not valid UTF-8 are
+/// replaced by U+FFFD
 fn string(input: &str) -> IResult<&str, String> {
-    let build_string = fold(0.., parse_fragment, String::new, |mut string, fragment| {
+    let build_string = fold(0.., parse_fragment, Vec::new, |mut bytes, fragment| {
         match fragment {
-            StringFragment::Literal(s) => string.push_str(s.as_ref()),
-            StringFragment::EscapedChar(c) => string.push(c),
+            StringFragment::Literal(s) => bytes.extend_from_slice(s.as_bytes()),
+            StringFragment::EscapedChar(c) => {
+                bytes.extend_from_slice(c.encode_utf8(&mut [0; 4]).as_bytes())
+            }
+            StringFragment::EscapedByte(b) => bytes.push(b),
             StringFragment::EscapedWS => {}
         }
-        string
+        bytes
     });
 
-    delimited(char('"'), build_string, char('"')).parse(input)
+    map(delimited(char('"'), build_string, char('"')), |bytes| {
+        String::from_utf8_lossy(&bytes).into_owned()
+    })
+    .parse(input)
 }
 
 fn to_map(v: Vec<(String, Value)>) -> Map<String, Value> {
@@ -315,7 +582,7 @@ fn key_value(input: &str) -> IResult<&str, (String, Value)> {
 }
 
 fn token(input: &str) -> IResult<&str, u64> {
-    map(digit1, |values: &str| values.parse::<u64>().unwrap()).parse(input)
+    map_res(digit1, |values: &str| values.parse::<u64>()).parse(input)
 }
 
 /// \[token\] "^" result-class ( "," result )* nl,
@@ -329,6 +596,7 @@ fn result_record(input: &str) -> IResult<&str, Output> {
                 token: t,
                 class: c,
                 results: Value::Object(to_map(results)),
+                console: String::new(),
             })
         },
     )
@@ -408,7 +676,7 @@ fn prompt(input: &str) -> IResult<&str, Output> {
 }
 
 fn debug_line(input: &str) -> IResult<&str, Output> {
-    Ok(("", Output::SomethingElse(input.to_string())))
+    map(take_till(|c| c == '\n'), |line: &str| Output::SomethingElse(line.to_string())).parse(input)
 }
 
 fn output(input: &str) -> IResult<&str, Output> {
@@ -421,8 +689,315 @@ fn output(input: &str) -> IResult<&str, Output> {
 
 #[cfg(test)]
 mod test {
+    use proptest::prelude::*;
+    use tokio::sync::mpsc;
+
     use super::*;
 
+    /// Quote a string as GDB does, in octal the bytes of the non-ASCII
+    /// characters too if `octal` is set
+    fn quote(text: &str, octal: bool) -> String {
+        let mut quoted = String::from("\"");
+        for c in text.chars() {
+            match c {
+                '"' => quoted.push_str("\\\""),
+                '\\' => quoted.push_str("\\\\"),
+                '\n' => quoted.push_str("\\n"),
+                '\r' => quoted.push_str("\\r"),
+                '\t' => quoted.push_str("\\t"),
+                c if c.is_ascii_control() || (octal && !c.is_ascii()) => {
+                    for b in c.encode_utf8(&mut [0; 4]).bytes() {
+                        quoted.push_str(&format!("\\{:03o}", b));
+                    }
+                }
+                c => quoted.push(c),
+            }
+        }
+        quoted.push('"');
+        quoted
+    }
+
+    fn write_value(value: &Value, octal: bool) -> String {
+        match value {
+            Value::String(text) => quote(text, octal),
+            Value::Array(values) => format!(
+                "[{}]",
+                values.iter().map(|value| write_value(value, octal)).collect::<Vec<_>>().join(",")
+            ),
+            Value::Object(map) => format!("{{{}}}", write_results(map, octal, false).join(",")),
+            _ => unreachable!("GDB only outputs strings, lists and tuples"),
+        }
+    }
+
+    /// `key=value` of each result, the lists of several values written
+    /// without brackets as GDB sometimes does if `buggy` is set
+    fn write_results(results: &Map<String, Value>, octal: bool, buggy: bool) -> Vec<String> {
+        results
+            .iter()
+            .map(|(key, value)| match value {
+                Value::Array(values) if buggy && values.len() > 1 => format!(
+                    "{}={}",
+                    key,
+                    values
+                        .iter()
+                        .map(|value| write_value(value, octal))
+                        .collect::<Vec<_>>()
+                        .join(",")
+                ),
+                _ => format!("{}={}", key, write_value(value, octal)),
+            })
+            .collect()
+    }
+
+    fn mi_value() -> impl Strategy<Value = Value> {
+        any::<String>().prop_map(Value::String).prop_recursive(4, 32, 4, |inner| {
+            prop_oneof![
+                prop::collection::vec(inner.clone(), 0..4).prop_map(Value::Array),
+                prop::collection::btree_map("[a-z][a-z0-9_-]{0,8}", inner, 0..4)
+                    .prop_map(|map| Value::Object(map.into_iter().collect())),
+            ]
+        })
+    }
+
+    fn mi_results() -> impl Strategy<Value = Map<String, Value>> {
+        prop::collection::btree_map("[a-z][a-z0-9_-]{0,8}", mi_value(), 0..5)
+            .prop_map(|map| map.into_iter().collect())
+    }
+
+    /// Feed `bytes` to [`process_output`] and collect the records it sends
+    async fn run_output(bytes: Vec<u8>) -> (Vec<ResultRecord>, Vec<OutOfBandRecord>) {
+        let (result_input, mut result_output) = mpsc::channel(100);
+        let (oob_input, mut oob_output) = crate::mi::queue::channel();
+        let task = tokio::spawn(process_output(
+            std::io::Cursor::new(bytes),
+            result_input,
+            oob_input,
+            Arc::new(AtomicBool::new(false)),
+            Transcript::default(),
+            Failure::default(),
+        ));
+        let (mut results, mut records) = (vec![], vec![]);
+        loop {
+            tokio::select! {
+                Some(record) = result_output.recv() => results.push(record),
+                Some(record) = oob_output.recv() => records.push(record),
+                else => break,
+            }
+        }
+        task.await.expect("the output task panicked");
+        (results, records)
+    }
+
+    proptest! {
+        #[test]
+        fn test_result_round_trip(
+            token in proptest::option::of(any::<u64>()),
+            results in mi_results(),
+            octal in any::<bool>(),
+            buggy in any::<bool>(),
+        ) {
+            let line = format!(
+                "{}^done{}\n",
+                token.map(|token| token.to_string()).unwrap_or_default(),
+                write_results(&results, octal, buggy)
+                    .iter()
+                    .map(|result| format!(",{}", result))
+                    .collect::<String>()
+            );
+            match Output::parse(&line) {
+                Ok(Output::Result(record)) => {
+                    prop_assert_eq!(record.token, token);
+                    prop_assert_eq!(record.class, ResultClass::Done);
+                    prop_assert_eq!(record.results, Value::Object(results));
+                }
+                other => prop_assert!(false, "{:?} parsed as {:?}", line, other),
+            }
+        }
+
+        #[test]
+        fn test_console_round_trip(
+            lines in prop::collection::vec(any::<String>(), 1..4),
+            octal in any::<bool>(),
+        ) {
+            let text = lines.join("\n");
+            let line = format!("~{}\n", quote(&text, octal));
+            match Output::parse(&line) {
+                Ok(Output::OutOfBand(OutOfBandRecord::StreamRecord { kind, data })) => {
+                    prop_assert_eq!(kind, StreamKind::Console);
+                    prop_assert_eq!(data, text);
+                }
+                other => prop_assert!(false, "{:?} parsed as {:?}", line, other),
+            }
+        }
+
+        #[test]
+        fn test_process_output_never_panics(
+            lines in prop::collection::vec(
+                prop_oneof![
+                    prop::collection::vec(any::<u8>(), 0..64),
+                    "[0-9]{0,22}[~@&*+=^][a-z-]{0,12}(,[a-z]{1,4}=(\"[^\"]{0,8}\"?|\\[|\\]|\\{|\\}|,))*"
+                        .prop_map(String::into_bytes),
+                ],
+                0..16,
+            ),
+        ) {
+            let runtime = tokio::runtime::Builder::new_current_thread().build().unwrap();
+            runtime.block_on(run_output(lines.join(&b'\n')));
+        }
+    }
+
+    #[test]
+    fn test_buggy_gdb_list_in_result() {
+        let output = Output::parse(
+            "^done,bkpt={number=\"1\",addr=\"<MULTIPLE>\"},{number=\"1.1\"},{number=\"1.2\"}\n",
+        );
+        if let Ok(Output::Result(result)) = output {
+            let numbers = result.results["bkpt"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|bkpt| bkpt["number"].as_str().unwrap())
+                .collect::<Vec<_>>();
+            assert_eq!(numbers, ["1", "1.1", "1.2"]);
+        } else {
+            panic!("output is not a result record: {:?}", output);
+        }
+    }
+
+    #[test]
+    fn test_token_overflow() {
+        assert!(matches!(
+            Output::parse("99999999999999999999^done\n"),
+            Ok(Output::SomethingElse(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_non_utf8_output() {
+        let (results, records) =
+            run_output(b"~\"caf\\303\\251\"\n\xff\xfe\n~\"\\377\"\n^done\n".to_vec()).await;
+        assert_eq!(
+            streams(&records),
+            [
+                (StreamKind::Console, "caf\u{e9}"),
+                (StreamKind::Target, "\u{fffd}\u{fffd}"),
+                (StreamKind::Console, "\u{fffd}")
+            ]
+        );
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].class, ResultClass::Done);
+    }
+
+    fn streams(records: &[OutOfBandRecord]) -> Vec<(StreamKind, &str)> {
+        records
+            .iter()
+            .filter_map(|record| match record {
+                OutOfBandRecord::StreamRecord { kind, data } => Some((*kind, data.as_str())),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_read_record() {
+        let mut reader =
+            BufReader::new(&b"^done\n12^done,memory=[{contents=\"00ff\"}]\n~\"a\""[..]);
+        let mut bytes = Vec::new();
+        assert_eq!(read_record(&mut reader, &mut bytes, 8).await.unwrap(), 6);
+        assert_eq!(bytes, b"^done\n");
+
+        bytes.clear();
+        assert_eq!(read_record(&mut reader, &mut bytes, 8).await.unwrap(), 35);
+        assert_eq!(bytes, b"12^done,");
+        let record = oversized_result(&bytes, 35).unwrap();
+        assert_eq!(record.token, Some(12));
+        assert!(matches!(record.error(), Some(AppError::GDBCommandError { .. })));
+
+        bytes.clear();
+        assert_eq!(read_record(&mut reader, &mut bytes, 8).await.unwrap(), 4);
+        assert_eq!(bytes, b"~\"a\"");
+        assert!(oversized_result(&bytes, 4).is_none());
+        assert_eq!(abbreviate(&"x".repeat(LOGGED_BYTES + 1)).len(), LOGGED_BYTES + 16);
+    }
+
+    #[test]
+    fn test_line_assembler() {
+        let mut lines = LineAssembler::default();
+        assert_eq!(lines.push("^done\r\n"), ["^done"]);
+        assert_eq!(lines.push("10%\r20%\r*stopped\n"), ["10%", "20%", "*stopped"]);
+        assert_eq!(lines.push("\n"), Vec::<String>::new());
+        assert_eq!(lines.push("@\"a\rb\"\n"), ["@\"a\rb\""]);
+        assert!(lines.push("~\"one \\\"quoted\\\"\n").is_empty());
+        assert!(lines.push("two\r\n").is_empty());
+        assert_eq!(lines.push("three\"\n"), ["~\"one \\\"quoted\\\"\ntwo\nthree\""]);
+        assert_eq!(lines.push("(gdb) \n"), ["(gdb) "]);
+
+        // a string never closed is not waited for forever
+        assert!(lines.push("~\"open\n").is_empty());
+        let records =
+            (0..MAX_CONTINUATION_LINES).flat_map(|_| lines.push("more\n")).collect::<Vec<_>>();
+        assert_eq!(records.len(), 1);
+        assert_eq!(lines.push("^done\n"), ["^done"]);
+    }
+
+    /// Output of GDB debugging a program which shares its terminal and
+    /// redraws a progress bar
+    #[tokio::test]
+    async fn test_program_output_with_cr() {
+        let (results, records) = run_output(
+            b"=thread-group-started,id=\"i1\",pid=\"4242\"\n\
+              ^running\n\
+              *running,thread-id=\"all\"\n\
+              (gdb) \n\
+              [##   ] 40%\r[#### ] 80%\r[#####] 100%\n\
+              done\r\n\
+              *stopped,reason=\"exited-normally\"\n"
+                .to_vec(),
+        )
+        .await;
+        assert_eq!(results.len(), 1);
+        assert_eq!(
+            streams(&records),
+            [
+                (StreamKind::Target, "[##   ] 40%"),
+                (StreamKind::Target, "[#### ] 80%"),
+                (StreamKind::Target, "[#####] 100%"),
+                (StreamKind::Target, "done")
+            ]
+        );
+        assert!(matches!(
+            records.last(),
+            Some(OutOfBandRecord::AsyncRecord { class: AsyncClass::Stopped, .. })
+        ));
+    }
+
+    /// Output of GDB for Windows, ending the lines with CR LF, and of a
+    /// pretty printer printing a raw newline in a console string
+    #[tokio::test]
+    async fn test_crlf_and_continued_console_output() {
+        let (results, records) = run_output(
+            b"~\"Breakpoint 1 at 0x140001591: file main.c, line 5.\\n\"\r\n\
+              ^done,bkpt={number=\"1\",line=\"5\"}\r\n\
+              (gdb) \r\n\
+              ~\"$1 = {first = 1,\n\
+              \x20 second = 2}\"\n\
+              ~\"\\n\"\n\
+              ^done\n"
+                .to_vec(),
+        )
+        .await;
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[0].results["bkpt"]["line"], "5");
+        assert_eq!(
+            streams(&records),
+            [
+                (StreamKind::Console, "Breakpoint 1 at 0x140001591: file main.c, line 5.\n"),
+                (StreamKind::Console, "$1 = {first = 1,\n  second = 2}"),
+                (StreamKind::Console, "\n")
+            ]
+        );
+    }
+
     #[test]
     fn test_output() {
         let output = match Output::parse("=library-loaded,ranges=[{}]\n") {
@@ -568,4 +1143,87 @@ mod test {
             panic!("output is not a result record");
         }
     }
+
+    #[test]
+    fn test_error_record_no_symbol_table() {
+        let output = match Output::parse(
+            "^error,msg=\"No symbol table is loaded.  Use the \\\"file\\\" command.\"\n",
+        ) {
+            Ok(output) => output,
+            Err(e) => {
+                panic!("parse output failed: {}", e);
+            }
+        };
+        if let Output::Result(result) = output {
+            assert_eq!(result.class, ResultClass::Error);
+            match result.error() {
+                Some(AppError::GDBCommandError { msg, code }) => {
+                    assert_eq!(msg, "No symbol table is loaded.  Use the \"file\" command.");
+                    assert_eq!(code, None);
+                }
+                other => panic!("unexpected error: {:?}", other),
+            }
+        } else {
+            panic!("output is not a result record");
+        }
+    }
+
+    #[test]
+    fn test_error_record_invalid_location() {
+        let output = match Output::parse("7^error,msg=\"No source file named foo.c.\"\n") {
+            Ok(output) => output,
+            Err(e) => {
+                panic!("parse output failed: {}", e);
+            }
+        };
+        if let Output::Result(result) = output {
+            assert_eq!(result.token, Some(7));
+            match result.error() {
+                Some(AppError::GDBCommandError { msg, .. }) => {
+                    assert_eq!(msg, "No source file named foo.c.");
+                }
+                other => panic!("unexpected error: {:?}", other),
+            }
+        } else {
+            panic!("output is not a result record");
+        }
+    }
+
+    #[test]
+    fn test_error_record_with_code() {
+        let output = match Output::parse(
+            "^error,msg=\"Undefined MI command: foo\",code=\"undefined-command\"\n",
+        ) {
+            Ok(output) => output,
+            Err(e) => {
+                panic!("parse output failed: {}", e);
+            }
+        };
+        if let Output::Result(result) = output {
+            match result.error() {
+                Some(AppError::GDBCommandError { msg, code }) => {
+                    assert_eq!(msg, "Undefined MI command: foo");
+                    assert_eq!(code.as_deref(), Some("undefined-command"));
+                }
+                other => panic!("unexpected error: {:?}", other),
+            }
+        } else {
+            panic!("output is not a result record");
+        }
+    }
+
+    #[test]
+    fn test_done_record_is_not_error() {
+        let output = match Output::parse("^done\n") {
+            Ok(output) => output,
+            Err(e) => {
+                panic!("parse output failed: {}", e);
+            }
+        };
+        if let Output::Result(result) = output {
+            assert!(result.error().is_none());
+        } else {
+            panic!("output is not a result record");
+        }
+    }
 }
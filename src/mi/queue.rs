@@ -0,0 +1,285 @@
+//! Out of band records on their way from the reader of the GDB output to the
+//! session, in bounded queues per kind of record so a flood of output can't
+//! stall the reader. Stream records are dropped, oldest first, when their
+//! queue is full while the reader waits for room for async records, which
+//! carry the state of the program. The records are received in the order
+//! they were read
+
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+
+use serde::Serialize;
+use tokio::sync::Notify;
+use tracing::warn;
+
+use super::output::{OutOfBandRecord, StreamKind};
+use crate::metrics::METRICS;
+
+/// Async records queued before the reader waits
+const ASYNC_CAPACITY: usize = 1000;
+/// Records queued per stream before the oldest are dropped
+const STREAM_CAPACITY: usize = 5000;
+
+/// Counters of a queue
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct QueueStats {
+    /// Async records waiting to be handled
+    pub queued_async: usize,
+    /// Stream records waiting to be handled
+    pub queued_stream: usize,
+    /// Most records ever waiting at once
+    pub max_queued: usize,
+    /// Async records the reader had to wait for room for
+    pub reader_waits: u64,
+    /// Stream records dropped because their queue was full
+    pub dropped: DroppedRecords,
+}
+
+/// Stream records dropped, by stream
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct DroppedRecords {
+    pub console: u64,
+    pub target: u64,
+    pub log: u64,
+}
+
+#[derive(Default)]
+struct Queues {
+    /// Sequence number of the next record
+    next: u64,
+    async_records: VecDeque<(u64, OutOfBandRecord)>,
+    /// Console, target and log records
+    streams: [VecDeque<(u64, OutOfBandRecord)>; 3],
+    sender_closed: bool,
+    receiver_closed: bool,
+    max_queued: usize,
+}
+
+impl Queues {
+    fn len(&self) -> usize {
+        self.async_records.len() + self.streams.iter().map(VecDeque::len).sum::<usize>()
+    }
+
+    /// The queue holding the oldest record
+    fn oldest(&mut self) -> Option<&mut VecDeque<(u64, OutOfBandRecord)>> {
+        std::iter::once(&mut self.async_records)
+            .chain(self.streams.iter_mut())
+            .filter(|queue| !queue.is_empty())
+            .min_by_key(|queue| queue[0].0)
+    }
+}
+
+#[derive(Default)]
+struct Shared {
+    queues: Mutex<Queues>,
+    /// Woken when a record is queued or the sender is dropped
+    readable: Notify,
+    /// Woken when an async record is taken or the receiver is dropped
+    writable: Notify,
+    reader_waits: AtomicU64,
+    dropped: [AtomicU64; 3],
+}
+
+/// Queue records, used by the reader of the GDB output
+pub struct OutOfBandSender(Arc<Shared>);
+
+/// Take the queued records, used by the session
+pub struct OutOfBandReceiver(Arc<Shared>);
+
+/// Read the counters of a queue while the records flow
+#[derive(Clone)]
+pub struct QueueMonitor(Arc<Shared>);
+
+/// The receiver was dropped, the session is closing
+#[derive(Debug)]
+pub struct Closed;
+
+pub fn channel() -> (OutOfBandSender, OutOfBandReceiver) {
+    let shared = Arc::new(Shared::default());
+    (OutOfBandSender(shared.clone()), OutOfBandReceiver(shared))
+}
+
+fn stream_index(kind: StreamKind) -> usize {
+    match kind {
+        StreamKind::Console => 0,
+        StreamKind::Target => 1,
+        StreamKind::Log => 2,
+    }
+}
+
+impl OutOfBandSender {
+    /// Queue a record, waiting for room if it is an async record
+    pub async fn send(&self, record: OutOfBandRecord) -> Result<(), Closed> {
+        let stream = match &record {
+            OutOfBandRecord::StreamRecord { kind, .. } => Some(*kind),
+            OutOfBandRecord::AsyncRecord { .. } => None,
+        };
+        let mut waited = false;
+        loop {
+            {
+                let mut queues = self.0.queues.lock().unwrap();
+                if queues.receiver_closed {
+                    return Err(Closed);
+                }
+                if stream.is_some() || queues.async_records.len() < ASYNC_CAPACITY {
+                    let sequence = queues.next;
+                    queues.next += 1;
+                    match stream {
+                        Some(kind) => {
+                            let queue = &mut queues.streams[stream_index(kind)];
+                            if queue.len() >= STREAM_CAPACITY {
+                                queue.pop_front();
+                                self.count_dropped(kind);
+                            }
+                            queue.push_back((sequence, record));
+                        }
+                        None => queues.async_records.push_back((sequence, record)),
+                    }
+                    queues.max_queued = queues.max_queued.max(queues.len());
+                    self.0.readable.notify_one();
+                    return Ok(());
+                }
+            }
+            if !waited {
+                waited = true;
+                self.0.reader_waits.fetch_add(1, Ordering::Relaxed);
+            }
+            self.0.writable.notified().await;
+        }
+    }
+
+    fn count_dropped(&self, kind: StreamKind) {
+        METRICS.record_dropped(kind);
+        let count = self.0.dropped[stream_index(kind)].fetch_add(1, Ordering::Relaxed) + 1;
+        if count.is_power_of_two() {
+            warn!("Dropped {} records of a GDB stream, it is read too slowly", count);
+        }
+    }
+}
+
+impl Drop for OutOfBandSender {
+    fn drop(&mut self) {
+        self.0.queues.lock().unwrap().sender_closed = true;
+        self.0.readable.notify_one();
+    }
+}
+
+impl OutOfBandReceiver {
+    /// The oldest record, None once the sender is dropped and all the
+    /// records are taken
+    pub async fn recv(&mut self) -> Option<OutOfBandRecord> {
+        loop {
+            {
+                let mut queues = self.0.queues.lock().unwrap();
+                if let Some(queue) = queues.oldest() {
+                    let (_, record) = queue.pop_front().unwrap();
+                    if let OutOfBandRecord::AsyncRecord { .. } = record {
+                        self.0.writable.notify_one();
+                    }
+                    return Some(record);
+                }
+                if queues.sender_closed {
+                    return None;
+                }
+            }
+            self.0.readable.notified().await;
+        }
+    }
+
+    pub fn monitor(&self) -> QueueMonitor {
+        QueueMonitor(self.0.clone())
+    }
+}
+
+impl Drop for OutOfBandReceiver {
+    fn drop(&mut self) {
+        self.0.queues.lock().unwrap().receiver_closed = true;
+        self.0.writable.notify_one();
+    }
+}
+
+impl QueueMonitor {
+    pub fn stats(&self) -> QueueStats {
+        let queues = self.0.queues.lock().unwrap();
+        let dropped = |index: usize| self.0.dropped[index].load(Ordering::Relaxed);
+        QueueStats {
+            queued_async: queues.async_records.len(),
+            queued_stream: queues.streams.iter().map(VecDeque::len).sum(),
+            max_queued: queues.max_queued,
+            reader_waits: self.0.reader_waits.load(Ordering::Relaxed),
+            dropped: DroppedRecords { console: dropped(0), target: dropped(1), log: dropped(2) },
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use futures::FutureExt;
+    use serde_json::json;
+
+    use super::*;
+    use crate::mi::output::{AsyncClass, AsyncKind};
+
+    fn console(text: &str) -> OutOfBandRecord {
+        OutOfBandRecord::StreamRecord { kind: StreamKind::Console, data: text.to_string() }
+    }
+
+    fn stopped() -> OutOfBandRecord {
+        OutOfBandRecord::AsyncRecord {
+            token: None,
+            kind: AsyncKind::Exec,
+            class: AsyncClass::Stopped,
+            results: json!({}),
+        }
+    }
+
+    fn text(record: Option<OutOfBandRecord>) -> String {
+        match record {
+            Some(OutOfBandRecord::StreamRecord { data, .. }) => data,
+            Some(OutOfBandRecord::AsyncRecord { class, .. }) => class.as_str().to_string(),
+            None => "closed".to_string(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_queue() {
+        let (sender, mut receiver) = channel();
+        let monitor = receiver.monitor();
+
+        // the order is kept across the queues
+        sender.send(console("a")).await.unwrap();
+        sender.send(stopped()).await.unwrap();
+        sender.send(console("b")).await.unwrap();
+        assert_eq!(text(receiver.recv().await), "a");
+        assert_eq!(text(receiver.recv().await), "stopped");
+        assert_eq!(text(receiver.recv().await), "b");
+
+        // the oldest stream records are dropped
+        for i in 0..STREAM_CAPACITY + 2 {
+            sender.send(console(&i.to_string())).await.unwrap();
+        }
+        assert_eq!(monitor.stats().dropped.console, 2);
+        assert_eq!(monitor.stats().queued_stream, STREAM_CAPACITY);
+        assert_eq!(text(receiver.recv().await), "2");
+        while monitor.stats().queued_stream > 0 {
+            receiver.recv().await;
+        }
+
+        // the reader waits for room for the async records
+        for _ in 0..ASYNC_CAPACITY {
+            sender.send(stopped()).await.unwrap();
+        }
+        assert!(sender.send(stopped()).now_or_never().is_none());
+        receiver.recv().await;
+        assert!(sender.send(stopped()).now_or_never().is_some());
+        assert_eq!(monitor.stats().reader_waits, 1);
+        assert_eq!(monitor.stats().dropped.console, 2);
+
+        drop(sender);
+        for _ in 0..ASYNC_CAPACITY {
+            assert_eq!(text(receiver.recv().await), "stopped");
+        }
+        assert_eq!(text(receiver.recv().await), "closed");
+    }
+}
@@ -0,0 +1,201 @@
+use std::collections::VecDeque;
+use std::fmt::Write;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::error::AppError;
+
+/// Oldest entries are dropped beyond this
+const MAX_ENTRIES: usize = 50_000;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum EntryKind {
+    /// MI command sent to GDB
+    Command,
+    /// Line received from GDB
+    Output,
+}
+
+#[derive(Debug, Clone)]
+pub struct Entry {
+    /// Time since the session started
+    pub elapsed: Duration,
+    pub kind: EntryKind,
+    pub text: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TranscriptFormat {
+    /// MI commands which can be fed to `gdb --interpreter=mi` again
+    GdbScript,
+    /// Commands with their output annotated
+    Markdown,
+}
+
+impl FromStr for TranscriptFormat {
+    type Err = AppError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "gdb" | "script" => Ok(Self::GdbScript),
+            "markdown" | "md" => Ok(Self::Markdown),
+            _ => Err(AppError::InvalidArgument(format!(
+                "unknown transcript format {}, expecting gdb or markdown",
+                s
+            ))),
+        }
+    }
+}
+
+/// Every MI command sent and every line received in a session, shared between
+/// the GDB handle and its output reader
+#[derive(Debug, Clone)]
+pub struct Transcript {
+    started: Instant,
+    entries: Arc<Mutex<VecDeque<Entry>>>,
+    dropped: Arc<Mutex<usize>>,
+}
+
+impl Default for Transcript {
+    fn default() -> Self {
+        Self { started: Instant::now(), entries: Default::default(), dropped: Default::default() }
+    }
+}
+
+impl Transcript {
+    pub fn record(&self, kind: EntryKind, text: &str) {
+        let text = text.trim_end();
+        if text.is_empty() {
+            return;
+        }
+        let mut entries = self.entries.lock().unwrap();
+        if entries.len() == MAX_ENTRIES {
+            entries.pop_front();
+            *self.dropped.lock().unwrap() += 1;
+        }
+        entries.push_back(Entry { elapsed: self.started.elapsed(), kind, text: text.to_string() });
+    }
+
+    pub fn entries(&self) -> Vec<Entry> {
+        self.entries.lock().unwrap().iter().cloned().collect()
+    }
+
+    /// Render the transcript, `title` names the session and `gdb` is the
+    /// command line GDB was started with
+    pub fn export(&self, format: TranscriptFormat, title: &str, gdb: &str) -> String {
+        let entries = self.entries();
+        let dropped = *self.dropped.lock().unwrap();
+        match format {
+            TranscriptFormat::GdbScript => to_gdb_script(&entries, dropped, title, gdb),
+            TranscriptFormat::Markdown => to_markdown(&entries, dropped, title, gdb),
+        }
+    }
+}
+
+fn to_gdb_script(entries: &[Entry], dropped: usize, title: &str, gdb: &str) -> String {
+    let mut script = format!("# Transcript of {}\n# Replay with: {} < <this file>\n", title, gdb);
+    if dropped > 0 {
+        let _ = writeln!(script, "# {} earlier entries were dropped", dropped);
+    }
+    for entry in entries.iter().filter(|entry| entry.kind == EntryKind::Command) {
+        // tokens are assigned per process, leave them out
+        script.push_str(entry.text.trim_start_matches(|c: char| c.is_ascii_digit()));
+        script.push('\n');
+    }
+    script
+}
+
+fn to_markdown(entries: &[Entry], dropped: usize, title: &str, gdb: &str) -> String {
+    let mut report = format!("# {}\n\nGDB: `{}`\n", title, gdb);
+    if dropped > 0 {
+        let _ = writeln!(report, "\n_{} earlier entries were dropped_", dropped);
+    }
+
+    let mut step = 0;
+    let mut in_block = false;
+    let mut startup = false;
+    for entry in entries {
+        match entry.kind {
+            EntryKind::Command => {
+                if in_block {
+                    report.push_str("```\n");
+                    in_block = false;
+                }
+                step += 1;
+                let _ = writeln!(
+                    report,
+                    "\n## {}. `{}` (+{:.3}s)\n",
+                    step,
+                    entry.text.trim_start_matches(|c: char| c.is_ascii_digit()),
+                    entry.elapsed.as_secs_f64()
+                );
+            }
+            EntryKind::Output => {
+                if step == 0 && !startup {
+                    report.push_str("\n## Startup\n\n");
+                    startup = true;
+                }
+                if let Some(note) = annotate(&entry.text) {
+                    if in_block {
+                        report.push_str("```\n");
+                        in_block = false;
+                    }
+                    let _ = writeln!(report, "> {}\n", note);
+                }
+                if !in_block {
+                    report.push_str("```text\n");
+                    in_block = true;
+                }
+                report.push_str(&entry.text);
+                report.push('\n');
+            }
+        }
+    }
+    if in_block {
+        report.push_str("```\n");
+    }
+    report
+}
+
+/// Short note for records worth highlighting in the report
+fn annotate(line: &str) -> Option<String> {
+    let record = line.trim_start_matches(|c: char| c.is_ascii_digit());
+    if let Some(error) = record.strip_prefix("^error,") {
+        return Some(format!("**Error** {}", error));
+    }
+    if let Some(stopped) = record.strip_prefix("*stopped") {
+        return Some(format!("**Stopped** {}", stopped.trim_start_matches(',')));
+    }
+    if record.starts_with("=thread-group-exited") || record.starts_with("=exited") {
+        return Some("**Program exited**".to_string());
+    }
+    None
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_export() {
+        let transcript = Transcript::default();
+        transcript.record(EntryKind::Output, "=thread-group-added,id=\"i1\"\n");
+        transcript.record(EntryKind::Command, "1-break-insert main\n");
+        transcript.record(EntryKind::Output, "1^error,msg=\"No symbol table is loaded.\"\n");
+        transcript.record(EntryKind::Command, "\n");
+        transcript.record(EntryKind::Command, "2-exec-run\n");
+        transcript.record(EntryKind::Output, "2^running\n");
+        transcript.record(EntryKind::Output, "*stopped,reason=\"exited-normally\"\n");
+
+        let script = transcript.export(TranscriptFormat::GdbScript, "session", "gdb");
+        assert!(script.ends_with("\n-break-insert main\n-exec-run\n"));
+
+        let report = transcript.export(TranscriptFormat::Markdown, "session", "gdb");
+        assert!(report.contains("## Startup"));
+        assert!(report.contains("## 1. `-break-insert main`"));
+        assert!(report.contains("> **Error** msg=\"No symbol table is loaded.\""));
+        assert!(report.contains("> **Stopped** reason=\"exited-normally\""));
+        assert_eq!(report.matches("```text").count(), report.matches("```\n").count());
+    }
+}
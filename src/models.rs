@@ -1,5 +1,5 @@
 use core::fmt;
-use std::collections::{HashMap, VecDeque};
+use std::collections::{BTreeMap, HashMap, VecDeque};
 use std::fmt::Display;
 use std::ops::{Add, Sub};
 use std::path::{Path, PathBuf};
@@ -12,7 +12,7 @@ use nom::character::complete::{alphanumeric1, space0};
 use nom::combinator::map;
 use nom::sequence::{delimited, preceded, separated_pair};
 use nom::{IResult, Parser};
-use serde::{Deserialize, Serialize, de};
+use serde::{Deserialize, Deserializer, Serialize, de};
 use serde_with::{DisplayFromStr, serde_as, skip_serializing_none};
 use tracing::debug;
 
@@ -28,6 +28,93 @@ pub struct GDBSession {
     pub status: GDBSessionStatus,
     /// Creation time
     pub created_at: u64,
+    /// Target architecture, as far as it is known
+    #[serde(default)]
+    pub arch: TargetArch,
+    /// GDB parameters set in the session, applied again when it is restarted
+    #[serde(default)]
+    pub parameters: BTreeMap<String, String>,
+    /// Why the session failed, restart it to debug again
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Architecture of the target of a session
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct TargetArch {
+    /// Architecture name as reported by GDB, e.g. `i386:x86-64`, or as read
+    /// from the ELF header of the program, e.g. `x86_64`
+    pub name: Option<String>,
+    /// Width of a pointer in bits
+    pub pointer_bits: Option<u32>,
+    pub endian: Option<Endian>,
+}
+
+/// An endian
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Endian {
+    /// Little endian
+    Little,
+    /// Big endian
+    Big,
+}
+
+impl FromStr for Endian {
+    type Err = AppError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "little" => Ok(Endian::Little),
+            "big" => Ok(Endian::Big),
+            _ => Err(AppError::InvalidArgument(format!(
+                "unknown endian {}, expecting little or big",
+                s
+            ))),
+        }
+    }
+}
+
+/// Which threads may run while one is resumed, `set scheduler-locking`
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SchedulerLocking {
+    /// All threads run
+    Off,
+    /// Only the current thread runs
+    On,
+    /// Only the current thread runs while stepping
+    Step,
+    /// Like `on` when replaying a recording, `off` otherwise
+    Replay,
+}
+
+impl SchedulerLocking {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SchedulerLocking::Off => "off",
+            SchedulerLocking::On => "on",
+            SchedulerLocking::Step => "step",
+            SchedulerLocking::Replay => "replay",
+        }
+    }
+}
+
+impl FromStr for SchedulerLocking {
+    type Err = AppError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "off" => Ok(SchedulerLocking::Off),
+            "on" => Ok(SchedulerLocking::On),
+            "step" => Ok(SchedulerLocking::Step),
+            "replay" => Ok(SchedulerLocking::Replay),
+            _ => Err(AppError::InvalidArgument(format!(
+                "unknown scheduler locking mode {}, expecting on, step, off or replay",
+                s
+            ))),
+        }
+    }
 }
 
 /// GDB session status
@@ -41,6 +128,8 @@ pub enum GDBSessionStatus {
     Stopped,
     /// Session terminated
     Terminated,
+    /// GDB crashed or its output could not be read
+    Errored,
 }
 
 /// GDB command request
@@ -128,7 +217,7 @@ impl Address128 {
 }
 
 #[derive(Debug, Clone, Serialize)]
-pub struct Enabled(bool);
+pub struct Enabled(pub bool);
 
 impl<'de> Deserialize<'de> for Enabled {
     fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
@@ -140,6 +229,7 @@ impl<'de> Deserialize<'de> for Enabled {
     }
 }
 
+#[serde_as]
 #[skip_serializing_none]
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct BreakPoint {
@@ -152,6 +242,13 @@ pub struct BreakPoint {
     pub r#type: String,
     #[serde(rename = "disp")]
     pub display: String,
+    /// Times the breakpoint was hit
+    #[serde(rename = "times")]
+    #[serde_as(as = "Option<DisplayFromStr>")]
+    pub hits: Option<u32>,
+    /// Hits left to ignore before the program stops
+    #[serde_as(as = "Option<DisplayFromStr>")]
+    pub ignore: Option<u32>,
 }
 
 pub struct BreakPointSet {
@@ -175,14 +272,6 @@ impl BreakPointSet {
         //debug_assert!(res.is_some(), "Modified non-existent breakpoint");
         self.notify_change();
     }
-
-    pub fn remove_breakpoint(&mut self, bp_num: BreakPointNumber) {
-        self.map.remove(&bp_num);
-        if bp_num.minor.is_none() {
-            //TODO: ensure removal of child breakpoints
-        }
-        self.notify_change();
-    }
 }
 
 impl std::ops::Deref for BreakPointSet {
@@ -216,8 +305,44 @@ pub struct StackFrame {
     pub address: Option<Address64>,
     /// Arch
     pub arch: Option<String>,
+    /// Name or comment imported for the address
+    #[serde(default)]
+    pub annotation: Option<String>,
 }
 
+/// Location of the program after a step
+#[serde_as]
+#[skip_serializing_none]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TracePoint {
+    /// Address
+    #[serde(rename = "addr")]
+    pub address: Option<Address64>,
+    /// Function name
+    #[serde(rename = "func")]
+    pub function: Option<String>,
+    /// File name
+    pub file: Option<String>,
+    /// Line number
+    #[serde_as(as = "Option<DisplayFromStr>")]
+    pub line: Option<u32>,
+}
+
+/// Result of stepping several times
+#[skip_serializing_none]
+#[derive(Debug, Clone, Serialize)]
+pub struct StepTrace {
+    /// Number of steps taken, less than requested if the program stopped for
+    /// another reason, e.g. a breakpoint or its exit
+    pub steps: usize,
+    /// Location after each step, only if collected
+    pub trace: Option<Vec<TracePoint>>,
+    /// Results of the last `*stopped` record
+    pub stopped: serde_json::Value,
+}
+
+/// What `-stack-list-variables` prints of the variables
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
 pub enum PrintValue {
     /// print only the names of the variables, equivalent to "--no-values"
     NoValues,
@@ -225,25 +350,34 @@ pub enum PrintValue {
     AllValues,
     /// print the name, type and value for simple data types, and the name and
     /// type for arrays, structures and unions, equivalent to "--simple-values"
+    #[default]
     SimpleValues,
 }
 
 impl FromStr for PrintValue {
     type Err = AppError;
 
+    /// Parse the MI flag, with or without the dashes, or its number
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        match s.parse::<usize>()? {
-            0 => Ok(PrintValue::NoValues),
-            1 => Ok(PrintValue::AllValues),
-            2 => Ok(PrintValue::SimpleValues),
-            _ => Err(AppError::InvalidArgument("only 0,1,2 are valid".to_string())),
+        match s.trim_start_matches("--") {
+            "0" | "no-values" => Ok(PrintValue::NoValues),
+            "1" | "all-values" => Ok(PrintValue::AllValues),
+            "2" | "simple-values" => Ok(PrintValue::SimpleValues),
+            _ => Err(AppError::InvalidArgument(format!(
+                "unknown print values {}, expecting no-values, all-values or simple-values",
+                s
+            ))),
         }
     }
 }
 
 impl Display for PrintValue {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "{}", self)
+        f.write_str(match self {
+            PrintValue::NoValues => "--no-values",
+            PrintValue::AllValues => "--all-values",
+            PrintValue::SimpleValues => "--simple-values",
+        })
     }
 }
 
@@ -253,10 +387,45 @@ impl Display for PrintValue {
 pub struct Variable {
     /// Variable name
     pub name: String,
-    /// Variable type, only present if --all-values or --simple-values
+    /// Variable type, only present with --simple-values
     pub r#type: Option<String>,
-    /// Variable value, only present if --simple-values
+    /// Variable value, absent with --no-values and for the arrays, structures
+    /// and unions with --simple-values
     pub value: Option<String>,
+    /// `1` for the arguments of the function
+    pub arg: Option<String>,
+}
+
+/// Arguments of a frame of `-stack-list-arguments`
+#[serde_as]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FrameArguments {
+    /// Frame level
+    #[serde_as(as = "DisplayFromStr")]
+    pub level: u32,
+    /// Only the names with `--no-values`
+    #[serde(deserialize_with = "names_or_variables")]
+    pub args: Vec<Variable>,
+}
+
+fn names_or_variables<'de, D>(deserializer: D) -> Result<Vec<Variable>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Argument {
+        Name(String),
+        Variable(Variable),
+    }
+
+    Ok(Vec::<Argument>::deserialize(deserializer)?
+        .into_iter()
+        .map(|argument| match argument {
+            Argument::Name(name) => Variable { name, r#type: None, value: None, arg: None },
+            Argument::Variable(variable) => variable,
+        })
+        .collect())
 }
 
 #[derive(Debug, Clone, Serialize, PartialEq, Eq)]
@@ -270,7 +439,7 @@ pub enum RegisterRaw {
 // Define Register struct to hold register data
 #[serde_as]
 #[skip_serializing_none]
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct Register {
     // Not exist in the register value output but can be amended afterwards
     pub name: Option<String>,
@@ -284,6 +453,285 @@ pub struct Register {
     pub v16_int8: Option<String>,
     pub v4_int32: Option<String>,
     pub error: Option<String>,
+    /// Individual flags of a status register
+    #[serde(skip_deserializing)]
+    pub flags: Option<BTreeMap<&'static str, bool>>,
+    /// Exception being handled by an ARM M-profile core, from its xPSR, 0 in
+    /// thread mode
+    #[serde(skip_deserializing)]
+    pub exception: Option<u32>,
+    /// Lanes of a vector register in the requested interpretation
+    #[serde(skip_deserializing)]
+    pub lanes: Option<Vec<serde_json::Value>>,
+}
+
+/// Interpretation of the lanes of a vector register
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum VectorFormat {
+    Bytes,
+    U32,
+    U64,
+    Float,
+    Double,
+}
+
+impl FromStr for VectorFormat {
+    type Err = AppError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "bytes" => Ok(VectorFormat::Bytes),
+            "u32" => Ok(VectorFormat::U32),
+            "u64" => Ok(VectorFormat::U64),
+            "float" => Ok(VectorFormat::Float),
+            "double" => Ok(VectorFormat::Double),
+            _ => Err(AppError::InvalidArgument(format!(
+                "unknown vector format {}, expecting bytes, u32, u64, float or double",
+                s
+            ))),
+        }
+    }
+}
+
+/// Lanes of the field of a vector register value whose name ends with
+/// `suffix`, the value looks like `{v4_int32 = {0x1, 0x0, 0x0, 0x0}, ...}` or
+/// `builtin_type_vec128i {v4_int32: [0x1, 0x0, 0x0, 0x0], ...}`
+fn vector_field(raw: &str, suffix: &str) -> Option<Vec<String>> {
+    let mut rest = raw;
+    while let Some(pos) = rest.find(suffix) {
+        let after = rest[pos + suffix.len()..].trim_start();
+        if let Some(body) = after.strip_prefix("= {").or_else(|| after.strip_prefix(": [")) {
+            let body = &body[..body.find(['}', ']'])?];
+            let mut lanes = vec![];
+            for lane in body.split(',').map(str::trim) {
+                // e.g. 0x0 <repeats 18 times>
+                match lane.split_once(" <repeats ") {
+                    Some((value, repeats)) => {
+                        let count = repeats.trim_end_matches(" times>").parse().ok()?;
+                        lanes.extend(std::iter::repeat_n(value.to_string(), count));
+                    }
+                    None => lanes.push(lane.to_string()),
+                }
+            }
+            return Some(lanes);
+        }
+        rest = &rest[pos + suffix.len()..];
+    }
+    None
+}
+
+/// A value printed by GDB: a number, an array like `{0x1, 0x0}` or a union of
+/// named fields like `{u8 = {0x1, 0x0}, u64 = 0x1}`
+#[derive(Debug, Clone, PartialEq)]
+enum PrintedValue {
+    Scalar(String),
+    Array(Vec<String>),
+    Fields(Vec<(String, PrintedValue)>),
+}
+
+impl PrintedValue {
+    fn parse(raw: &str) -> Option<Self> {
+        let (value, rest) = Self::parse_next(raw)?;
+        rest.trim().is_empty().then_some(value)
+    }
+
+    /// The value at the beginning of `input` and what follows it
+    fn parse_next(input: &str) -> Option<(Self, &str)> {
+        let input = input.trim_start();
+        let Some(mut rest) = input.strip_prefix('{') else {
+            let end = input.find([',', '}']).unwrap_or(input.len());
+            return Some((Self::Scalar(input[..end].trim().to_string()), &input[end..]));
+        };
+        let is_union = rest.split_once(" = ").is_some_and(|(name, _)| {
+            name.trim().chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+        });
+        let (mut fields, mut lanes) = (vec![], vec![]);
+        loop {
+            rest = rest.trim_start();
+            if let Some(after) = rest.strip_prefix('}') {
+                let value = if is_union { Self::Fields(fields) } else { Self::Array(lanes) };
+                return Some((value, after));
+            }
+            if is_union {
+                let (name, value) = rest.split_once(" = ")?;
+                let (value, after) = Self::parse_next(value)?;
+                fields.push((name.trim().to_string(), value));
+                rest = after;
+            } else {
+                let end = rest.find([',', '}'])?;
+                // e.g. 0x0 <repeats 15 times>
+                match rest[..end].trim().split_once(" <repeats ") {
+                    Some((lane, repeats)) => {
+                        let count = repeats.trim_end_matches(" times>").parse().ok()?;
+                        lanes.extend(std::iter::repeat_n(lane.to_string(), count));
+                    }
+                    None => lanes.push(rest[..end].trim().to_string()),
+                }
+                rest = &rest[end..];
+            }
+            rest = rest.trim_start();
+            rest = rest.strip_prefix(',').unwrap_or(rest);
+        }
+    }
+
+    /// The lanes of the field at `path`, a number is a single lane
+    fn lanes(&self, path: &[&str]) -> Option<Vec<String>> {
+        match (self, path.split_first()) {
+            (Self::Fields(fields), Some((name, path))) => {
+                fields.iter().find(|(field, _)| field == name)?.1.lanes(path)
+            }
+            (Self::Scalar(value), None) => Some(vec![value.clone()]),
+            (Self::Array(lanes), None) => Some(lanes.clone()),
+            _ => None,
+        }
+    }
+}
+
+/// Lanes of a NEON register value, a union of the lane types like `{u8 =
+/// {...}, u64 = 0x0, ...}` for the d and q registers of AArch32, or of the
+/// lane sizes like `{d = {f = {...}, u = {...}}, s = ...}` for the v
+/// registers of AArch64, whose d registers are `{f = 0, u = 0x0, s = 0x0}`
+fn neon_lanes(raw: &str, format: VectorFormat) -> Option<Vec<String>> {
+    let paths: &[&[&str]] = match format {
+        VectorFormat::Bytes => &[&["u8"], &["b", "u"]],
+        VectorFormat::U32 => &[&["u32"], &["s", "u"]],
+        VectorFormat::U64 => &[&["u64"], &["d", "u"], &["u"]],
+        VectorFormat::Float => &[&["f32"], &["s", "f"]],
+        VectorFormat::Double => &[&["f64"], &["d", "f"], &["f"]],
+    };
+    let value = PrintedValue::parse(raw)?;
+    paths.iter().find_map(|path| value.lanes(path))
+}
+
+fn parse_lane(lane: &str) -> Option<u64> {
+    match lane.strip_prefix("0x") {
+        Some(hex) => u64::from_str_radix(hex, 16).ok(),
+        None => lane.parse::<i64>().ok().map(|n| n as u64),
+    }
+}
+
+fn float_value(value: f64) -> serde_json::Value {
+    serde_json::Number::from_f64(value)
+        .map_or_else(|| serde_json::Value::String(value.to_string()), serde_json::Value::Number)
+}
+
+/// Lanes of a vector register value in the given interpretation, None if it
+/// is not a vector register
+pub fn vector_lanes(raw: &str, format: VectorFormat) -> Option<Vec<serde_json::Value>> {
+    if raw.starts_with("0x") {
+        return None;
+    }
+    let suffix = match format {
+        VectorFormat::Bytes => "_int8",
+        VectorFormat::U32 => "_int32",
+        VectorFormat::U64 => "_int64",
+        VectorFormat::Float => "_float",
+        VectorFormat::Double => "_double",
+    };
+    let lanes = vector_field(raw, suffix).or_else(|| neon_lanes(raw, format))?;
+    lanes
+        .iter()
+        .map(|lane| {
+            Some(match format {
+                VectorFormat::Bytes | VectorFormat::U32 | VectorFormat::U64 => {
+                    serde_json::Value::from(parse_lane(lane)?)
+                }
+                // lanes printed in hex are the raw bits of the floats
+                VectorFormat::Float => match parse_lane(lane) {
+                    Some(bits) if lane.starts_with("0x") => {
+                        float_value(f32::from_bits(bits as u32) as f64)
+                    }
+                    _ => float_value(lane.parse().ok()?),
+                },
+                VectorFormat::Double => match parse_lane(lane) {
+                    Some(bits) if lane.starts_with("0x") => float_value(f64::from_bits(bits)),
+                    _ => float_value(lane.parse().ok()?),
+                },
+            })
+        })
+        .collect()
+}
+
+/// Flags of the x86 EFLAGS register and their bit
+const X86_FLAGS: &[(&str, u32)] = &[
+    ("CF", 0),
+    ("PF", 2),
+    ("AF", 4),
+    ("ZF", 6),
+    ("SF", 7),
+    ("TF", 8),
+    ("IF", 9),
+    ("DF", 10),
+    ("OF", 11),
+];
+/// Flags of the ARM CPSR register and their bit
+const ARM_FLAGS: &[(&str, u32)] = &[
+    ("N", 31),
+    ("Z", 30),
+    ("C", 29),
+    ("V", 28),
+    ("Q", 27),
+    ("A", 8),
+    ("I", 7),
+    ("F", 6),
+    ("T", 5),
+];
+/// Flags of the xPSR register of the ARM M-profile and their bit, the GE bits
+/// are only in the DSP extension
+const XPSR_FLAGS: &[(&str, u32)] = &[
+    ("N", 31),
+    ("Z", 30),
+    ("C", 29),
+    ("V", 28),
+    ("Q", 27),
+    ("T", 24),
+    ("GE3", 19),
+    ("GE2", 18),
+    ("GE1", 17),
+    ("GE0", 16),
+];
+/// Mask of the exception number in the IPSR bits of the xPSR register
+const XPSR_EXCEPTION_MASK: u64 = 0x1ff;
+/// Flags of the RISC-V mstatus register and their bit
+const RISCV_MSTATUS_FLAGS: &[(&str, u32)] = &[
+    ("SIE", 1),
+    ("MIE", 3),
+    ("SPIE", 5),
+    ("MPIE", 7),
+    ("SPP", 8),
+    ("MPRV", 17),
+    ("SUM", 18),
+    ("MXR", 19),
+    ("TVM", 20),
+    ("TW", 21),
+    ("TSR", 22),
+];
+
+/// Flags and their bit of the status register named `name`, in bit order
+pub fn flag_layout(name: &str) -> Option<&'static [(&'static str, u32)]> {
+    match name {
+        "eflags" | "rflags" => Some(X86_FLAGS),
+        "cpsr" => Some(ARM_FLAGS),
+        "xpsr" => Some(XPSR_FLAGS),
+        "mstatus" => Some(RISCV_MSTATUS_FLAGS),
+        _ => None,
+    }
+}
+
+impl Register {
+    /// Split the value of a status register into its flags
+    pub fn decode_flags(&mut self) {
+        let (Some(name), Some(RegisterRaw::U64(value))) = (&self.name, &self.value) else {
+            return;
+        };
+        if let Some(layout) = flag_layout(name) {
+            self.flags =
+                Some(layout.iter().map(|(flag, bit)| (*flag, value.0 >> bit & 1 == 1)).collect());
+        }
+        if name == "xpsr" {
+            self.exception = Some((value.0 & XPSR_EXCEPTION_MASK) as u32);
+        }
+    }
 }
 
 // impl Register {
@@ -329,12 +777,109 @@ impl<'de> Deserialize<'de> for RegisterRaw {
         let s: String = serde::Deserialize::deserialize(deserializer)?;
         if s.starts_with("0x") {
             Ok(RegisterRaw::U64(Address64::from(s[2..].to_owned())))
+        } else if let Ok((_, raw)) = register_data(&s) {
+            Ok(raw)
+        } else if let Some([low, high]) = vector_field(&s, "v2_int128").as_deref() {
+            Ok(RegisterRaw::U256(Address128::from(low.clone()), Address128::from(high.clone())))
+        } else if let Some([low, high]) = vector_field(&s, "v2_int64").as_deref() {
+            Ok(RegisterRaw::U128(Address128::new(
+                Address64::from(low.clone()),
+                Address64::from(high.clone()),
+            )))
+        } else if let Some(lanes) = neon_lanes(&s, VectorFormat::U64) {
+            match lanes.as_slice() {
+                // the q registers of AArch64 are a single 128-bit lane
+                [value] => Ok(match parse_lane(value) {
+                    Some(value) => RegisterRaw::U64(Address(value)),
+                    None => RegisterRaw::U128(Address128::from(value.clone())),
+                }),
+                [low, high] => Ok(RegisterRaw::U128(Address128::new(
+                    Address64::from(low.clone()),
+                    Address64::from(high.clone()),
+                ))),
+                _ => Err(de::Error::custom(format!("unknown register value {}", s))),
+            }
         } else {
-            register_data(&s).map(|(_, o)| o).map_err(|e| de::Error::custom(e.to_string()))
+            Err(de::Error::custom(format!("unknown register value {}", s)))
         }
     }
 }
 
+/// Raw value of a register, an entry of the `register-values` of
+/// `-data-list-register-values`
+#[serde_as]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct RegisterValue {
+    #[serde_as(as = "DisplayFromStr")]
+    pub number: usize,
+    /// Value as printed by GDB, a number or the fields of a vector register
+    pub value: String,
+}
+
+/// A thread of `-thread-info`
+#[serde_as]
+#[skip_serializing_none]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ThreadInfo {
+    /// Global thread ID
+    #[serde_as(as = "DisplayFromStr")]
+    pub id: u32,
+    /// Target specific name of the thread, e.g. `Thread 0x7ffff7d8a740 (LWP
+    /// 42)`
+    #[serde(rename = "target-id")]
+    pub target_id: String,
+    /// Name of the thread, if known
+    pub name: Option<String>,
+    /// `running` or `stopped`
+    pub state: Option<String>,
+    /// Core the thread was last seen on
+    #[serde_as(as = "Option<DisplayFromStr>")]
+    pub core: Option<u32>,
+    /// Top frame, only when the thread is stopped
+    pub frame: Option<StackFrame>,
+    /// Additional information from the target
+    pub details: Option<String>,
+}
+
+/// An instruction of `-data-disassemble`
+#[serde_as]
+#[skip_serializing_none]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DisassembledInstruction {
+    pub address: Address64,
+    /// Function containing the instruction
+    #[serde(rename = "func-name")]
+    pub function: Option<String>,
+    /// Offset from the start of the function
+    #[serde_as(as = "Option<DisplayFromStr>")]
+    pub offset: Option<u64>,
+    /// Raw bytes in hex, only with the opcodes modes
+    pub opcodes: Option<String>,
+    pub inst: String,
+}
+
+/// A source line of `-data-disassemble` with the instructions generated from
+/// it, in the source modes
+#[serde_as]
+#[skip_serializing_none]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DisassembledLine {
+    #[serde_as(as = "Option<DisplayFromStr>")]
+    pub line: Option<u32>,
+    pub file: Option<String>,
+    pub fullname: Option<String>,
+    #[serde(rename = "line_asm_insn")]
+    pub instructions: Vec<DisassembledInstruction>,
+}
+
+/// An entry of the `asm_insns` of `-data-disassemble`, depending on the mode
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum DisassembledItem {
+    Line(DisassembledLine),
+    Instruction(DisassembledInstruction),
+}
+
 #[derive(Debug, Default, Serialize, Deserialize)]
 pub struct BT {
     pub location: u64,
@@ -361,6 +906,9 @@ impl TrackedRegister {
     }
 }
 
+/// What a value points into
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
 pub enum MemoryType {
     Unknown,
     Stack,
@@ -368,6 +916,30 @@ pub enum MemoryType {
     Exec,
 }
 
+impl MemoryType {
+    /// Classify a value with the memory mappings, the mappings of `filepath`
+    /// count as code like the executable ones
+    pub fn classify(memory_map: &[MemoryMapping], val: u64, filepath: Option<&Path>) -> Self {
+        if val == 0 {
+            return MemoryType::Unknown;
+        }
+        for r in memory_map.iter().filter(|r| r.contains(val)) {
+            if r.is_stack() {
+                return MemoryType::Stack;
+            }
+            if r.is_heap() {
+                return MemoryType::Heap;
+            }
+            if filepath.is_some_and(|filepath| r.is_path(filepath)) || r.is_exec() {
+                // TODO(23): This could be expanded to all segments loaded in
+                // as executable
+                return MemoryType::Exec;
+            }
+        }
+        MemoryType::Unknown
+    }
+}
+
 // Define memory output layout
 #[skip_serializing_none]
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -428,7 +1000,10 @@ impl MemoryMapping {
     /// Parse from `MEMORY_MAP_START_STR_NEW`
     fn from_str_new(line: &str) -> Result<Self, String> {
         let parts: Vec<&str> = line.split_whitespace().collect();
-        if let Some([start_address, end_address, size, offset, permissions]) = parts.first_chunk() {
+        // a chunk of 5 matches lines with a path too, try the longer one first
+        if let Some([start_address, end_address, size, offset, permissions, path]) =
+            parts.first_chunk()
+        {
             Ok(MemoryMapping {
                 start_address: u64::from_str_radix(&start_address[2..], 16)
                     .map_err(|_| "Invalid start address")?,
@@ -437,9 +1012,9 @@ impl MemoryMapping {
                 size: u64::from_str_radix(&size[2..], 16).map_err(|_| "Invalid size")?,
                 offset: u64::from_str_radix(&offset[2..], 16).map_err(|_| "Invalid offset")?,
                 permissions: Some(permissions.to_string()),
-                path: None,
+                path: Some(PathBuf::from(path)),
             })
-        } else if let Some([start_address, end_address, size, offset, permissions, path]) =
+        } else if let Some([start_address, end_address, size, offset, permissions]) =
             parts.first_chunk()
         {
             Ok(MemoryMapping {
@@ -450,7 +1025,7 @@ impl MemoryMapping {
                 size: u64::from_str_radix(&size[2..], 16).map_err(|_| "Invalid size")?,
                 offset: u64::from_str_radix(&offset[2..], 16).map_err(|_| "Invalid offset")?,
                 permissions: Some(permissions.to_string()),
-                path: Some(PathBuf::from(path)),
+                path: None,
             })
         } else {
             return Err(format!("Invalid line format: {}", line));
@@ -487,7 +1062,7 @@ pub fn parse_memory_mappings_old(input: &str) -> Vec<MemoryMapping> {
     input.lines().skip(1).filter_map(|line| MemoryMapping::from_str_old(line).ok()).collect()
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default)]
 pub struct ResolveSymbol {
     pub map: VecDeque<u64>,
     pub repeated_pattern: bool,
@@ -547,6 +1122,8 @@ impl ResolveSymbol {
 #[cfg(test)]
 mod test {
     use super::*;
+    use crate::mi::commands::MiCommand;
+    use crate::mi::output::parse_result_record;
 
     #[test]
     fn test_address() {
@@ -589,4 +1166,191 @@ mod test {
             ))
         );
     }
+
+    #[test]
+    fn test_decode_flags() {
+        let mut reg: Register =
+            serde_json::from_str("{\"number\": \"17\", \"value\": \"0x246\"}").unwrap();
+        reg.name = Some("eflags".to_string());
+        reg.decode_flags();
+        let flags = reg.flags.unwrap();
+        assert!(flags["ZF"] && flags["PF"] && flags["IF"]);
+        assert!(!flags["CF"] && !flags["SF"]);
+        assert_eq!(reg.exception, None);
+
+        // Z, C and Thumb set in the SysTick handler
+        let mut reg: Register =
+            serde_json::from_str("{\"number\": \"16\", \"value\": \"0x6100000f\"}").unwrap();
+        reg.name = Some("xpsr".to_string());
+        reg.decode_flags();
+        let flags = reg.flags.unwrap();
+        assert!(flags["Z"] && flags["C"] && flags["T"]);
+        assert!(!flags["N"] && !flags["GE0"]);
+        assert_eq!(reg.exception, Some(15));
+    }
+
+    #[test]
+    fn test_vector_lanes() {
+        let raw = "{v8_bfloat16 = {0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0}, v4_float = \
+                   {0x3fc00000, 0x0, 0x0, 0x0}, v2_double = {0x0, 0x0}, v16_int8 = {0x1, 0x0 \
+                   <repeats 15 times>}, v4_int32 = {0x1, 0x0, 0x0, 0x2}, v2_int64 = {0x1, 0x2}, \
+                   uint128 = 0x20000000000000001}";
+        let lanes = vector_lanes(raw, VectorFormat::Bytes).unwrap();
+        assert_eq!(lanes.len(), 16);
+        assert_eq!(lanes[0], 1);
+        assert_eq!(vector_lanes(raw, VectorFormat::U32).unwrap()[3], 2);
+        assert_eq!(vector_lanes(raw, VectorFormat::Float).unwrap()[0], 1.5);
+        assert!(vector_lanes("0x1234", VectorFormat::U32).is_none());
+
+        let reg: Register =
+            serde_json::from_value(serde_json::json!({"number": "40", "value": raw})).unwrap();
+        assert_eq!(reg.value, Some(RegisterRaw::U128(Address128::new(Address(1), Address(2)))));
+    }
+
+    #[test]
+    fn test_neon_lanes() {
+        // q0 of AArch32
+        let q0 = "{u8 = {0x1, 0x0 <repeats 15 times>}, u16 = {0x1, 0x0, 0x0, 0x0, 0x0, 0x0, \
+                  0x0, 0x0}, u32 = {0x1, 0x0, 0x0, 0x3fc00000}, u64 = {0x1, 0x3fc0000000000000}, \
+                  f32 = {1.40129846e-45, 0, 0, 1.5}, f64 = {4.9406564584124654e-324, 0.125}}";
+        assert_eq!(vector_lanes(q0, VectorFormat::Bytes).unwrap().len(), 16);
+        assert_eq!(vector_lanes(q0, VectorFormat::Float).unwrap()[3], 1.5);
+        assert_eq!(vector_lanes(q0, VectorFormat::Double).unwrap()[1], 0.125);
+        let reg: Register =
+            serde_json::from_value(serde_json::json!({"number": "50", "value": q0})).unwrap();
+        assert_eq!(
+            reg.value,
+            Some(RegisterRaw::U128(Address128::new(Address(1), Address(0x3fc0000000000000))))
+        );
+
+        // d0 of AArch32
+        let d0 = "{u8 = {0x2, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0}, u16 = {0x2, 0x0, 0x0, 0x0}, \
+                  u32 = {0x2, 0x0}, u64 = 0x2, f32 = {2.80259693e-45, 0}, f64 = \
+                  9.8813129168249309e-324}";
+        assert_eq!(vector_lanes(d0, VectorFormat::U32).unwrap(), [2, 0]);
+        let reg: Register =
+            serde_json::from_value(serde_json::json!({"number": "26", "value": d0})).unwrap();
+        assert_eq!(reg.value, Some(RegisterRaw::U64(Address(2))));
+
+        // v0 of AArch64
+        let v0 = "{d = {f = {0.5, 0}, u = {0x3fe0000000000000, 0x0}, s = {0x3fe0000000000000, \
+                  0x0}}, s = {f = {0, 1.75, 0, 0}, u = {0x0, 0x3fe00000, 0x0, 0x0}, s = {0x0, \
+                  0x3fe00000, 0x0, 0x0}}, h = {bf = {0, 0, 0, 1.75, 0, 0, 0, 0}, f = {0, 0, 0, \
+                  1.9922, 0, 0, 0, 0}, u = {0x0, 0x0, 0x0, 0x3fe0, 0x0, 0x0, 0x0, 0x0}, s = {0x0, \
+                  0x0, 0x0, 0x3fe0, 0x0, 0x0, 0x0, 0x0}}, b = {u = {0x0, 0x0, 0x0, 0x0, 0x0, 0x0, \
+                  0xe0, 0x3f, 0x0 <repeats 8 times>}, s = {0x0, 0x0, 0x0, 0x0, 0x0, 0x0, -0x20, \
+                  0x3f, 0x0 <repeats 8 times>}}, q = {u = {0x3fe0000000000000}, s = \
+                  {0x3fe0000000000000}}}";
+        assert_eq!(vector_lanes(v0, VectorFormat::Bytes).unwrap()[6], 0xe0);
+        assert_eq!(vector_lanes(v0, VectorFormat::U32).unwrap()[1], 0x3fe00000);
+        assert_eq!(vector_lanes(v0, VectorFormat::Double).unwrap()[0], 0.5);
+        let reg: Register =
+            serde_json::from_value(serde_json::json!({"number": "34", "value": v0})).unwrap();
+        assert_eq!(
+            reg.value,
+            Some(RegisterRaw::U128(Address128::new(Address(0x3fe0000000000000), Address(0))))
+        );
+
+        // d0 of AArch64
+        let d0 = "{f = 0.5, u = 0x3fe0000000000000, s = 0x3fe0000000000000}";
+        assert_eq!(vector_lanes(d0, VectorFormat::Double).unwrap(), [0.5]);
+        let reg: Register =
+            serde_json::from_value(serde_json::json!({"number": "66", "value": d0})).unwrap();
+        assert_eq!(reg.value, Some(RegisterRaw::U64(Address(0x3fe0000000000000))));
+
+        // q0 of AArch64
+        let q0 = "{u = {0x3fe00000000000000000000000000001}, s = \
+                  {0x3fe00000000000000000000000000001}}";
+        let reg: Register =
+            serde_json::from_value(serde_json::json!({"number": "98", "value": q0})).unwrap();
+        assert_eq!(reg.value, Some(RegisterRaw::U128(Address(0x3fe00000000000000000000000000001))));
+    }
+
+    #[test]
+    fn test_print_value() {
+        assert_eq!("no-values".parse::<PrintValue>().unwrap(), PrintValue::NoValues);
+        assert_eq!("--all-values".parse::<PrintValue>().unwrap(), PrintValue::AllValues);
+        assert_eq!("2".parse::<PrintValue>().unwrap(), PrintValue::SimpleValues);
+        assert!("3".parse::<PrintValue>().is_err());
+        assert_eq!(PrintValue::NoValues.to_string(), "--no-values");
+        let command = MiCommand::stack_list_variables(None, Some(1), Some(PrintValue::AllValues));
+        assert_eq!(
+            command.to_interpreter_string(7).to_string_lossy(),
+            "7-stack-list-variables --frame 1 --all-values\n"
+        );
+    }
+
+    #[test]
+    fn test_thread_info() {
+        let record = parse_result_record(
+            "^done,threads=[{id=\"2\",target-id=\"Thread 0x7ffff7a3f6c0 (LWP 4242)\",\
+             name=\"worker\",state=\"running\",core=\"3\"},{id=\"1\",target-id=\"Thread \
+             0x7ffff7d8a740 (LWP 4239)\",name=\"test_app\",frame={level=\"0\",\
+             addr=\"0x0000555555559e2a\",func=\"test_app::compute\",args=[{name=\"value\",\
+             value=\"20\"}],file=\"src/bin/test_app.rs\",fullname=\"/root/crate/src/bin/\
+             test_app.rs\",line=\"13\",arch=\"i386:x86-64\"},state=\"stopped\",core=\"1\"}],\
+             current-thread-id=\"1\"\n",
+        );
+        let threads: Vec<ThreadInfo> =
+            serde_json::from_value(record.results["threads"].clone()).unwrap();
+        assert_eq!(threads.len(), 2);
+        assert_eq!(threads[0].id, 2);
+        assert_eq!(threads[0].state.as_deref(), Some("running"));
+        assert!(threads[0].frame.is_none());
+        let frame = threads[1].frame.as_ref().unwrap();
+        assert_eq!(threads[1].target_id, "Thread 0x7ffff7d8a740 (LWP 4239)");
+        assert_eq!(threads[1].core, Some(1));
+        assert_eq!(frame.function, "test_app::compute");
+        assert_eq!(frame.line, Some(13));
+        assert_eq!(frame.address, Some(Address(0x555555559e2a)));
+    }
+
+    #[test]
+    fn test_disassembled_items() {
+        let record = parse_result_record(
+            "^done,asm_insns=[src_and_asm_line={line=\"12\",file=\"src/bin/test_app.rs\",\
+             fullname=\"/root/crate/src/bin/test_app.rs\",line_asm_insn=[{address=\
+             \"0x0000555555559e20\",func-name=\"test_app::compute\",offset=\"0\",\
+             opcodes=\"48 83 ec 18\",inst=\"sub    $0x18,%rsp\"}]},src_and_asm_line={line=\"13\",\
+             file=\"src/bin/test_app.rs\",fullname=\"/root/crate/src/bin/test_app.rs\",\
+             line_asm_insn=[]}]\n",
+        );
+        let items: Vec<DisassembledItem> =
+            serde_json::from_value(record.results["asm_insns"].clone()).unwrap();
+        let DisassembledItem::Line(line) = &items[0] else {
+            panic!("expecting a source line, got {:?}", items[0]);
+        };
+        assert_eq!(line.line, Some(12));
+        assert_eq!(line.instructions[0].address, Address(0x555555559e20));
+        assert_eq!(line.instructions[0].offset, Some(0));
+        assert_eq!(line.instructions[0].opcodes.as_deref(), Some("48 83 ec 18"));
+        assert!(matches!(&items[1], DisassembledItem::Line(line) if line.instructions.is_empty()));
+
+        let record = parse_result_record(
+            "^done,asm_insns=[{address=\"0x0000000000401000\",inst=\"endbr64\"},\
+             {address=\"0x0000000000401004\",inst=\"sub    $0x8,%rsp\"}]\n",
+        );
+        let items: Vec<DisassembledItem> =
+            serde_json::from_value(record.results["asm_insns"].clone()).unwrap();
+        let DisassembledItem::Instruction(insn) = &items[1] else {
+            panic!("expecting an instruction, got {:?}", items[1]);
+        };
+        assert_eq!(insn.address, Address(0x401004));
+        assert!(insn.function.is_none() && insn.offset.is_none());
+        assert_eq!(insn.inst, "sub    $0x8,%rsp");
+    }
+
+    #[test]
+    fn test_register_values() {
+        let record = parse_result_record(
+            "^done,register-values=[{number=\"0\",value=\"0x1c\"},{number=\"17\",\
+             value=\"0x246\"},{number=\"40\",value=\"{v8_bfloat16 = {0x0, 0x0, 0x0, 0x0, 0x0, \
+             0x0, 0x0, 0x0}, v2_int64 = {0x1, 0x2}, uint128 = 0x20000000000000001}\"}]\n",
+        );
+        let values: Vec<RegisterValue> =
+            serde_json::from_value(record.results["register-values"].clone()).unwrap();
+        assert_eq!(values[0], RegisterValue { number: 0, value: "0x1c".to_string() });
+        assert_eq!(values[1].number, 17);
+        assert!(values[2].value.starts_with("{v8_bfloat16"));
+    }
 }
@@ -0,0 +1,260 @@
+//! Resources addressing the memory of the debugged programs by URI, so it is
+//! read lazily instead of inflating tool responses:
+//!
+//! - `gdb://{session}/memory` lists the memory mappings
+//! - `gdb://{session}/memory/{start}-{end}` is a hexdump of `[start, end)`
+
+use std::fmt::Write;
+
+use serde::Serialize;
+use serde_with::skip_serializing_none;
+
+use crate::error::{AppError, AppResult};
+use crate::gdb::GDBManager;
+use crate::models::MemoryMapping;
+
+const SCHEME: &str = "gdb://";
+/// Largest range a hexdump resource covers
+pub const MAX_HEXDUMP_BYTES: u64 = 64 * 1024;
+const HEXDUMP_WIDTH: usize = 16;
+
+#[skip_serializing_none]
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Resource {
+    pub uri: String,
+    pub name: String,
+    pub description: Option<String>,
+    pub mime_type: Option<String>,
+}
+
+#[skip_serializing_none]
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ResourceTemplate {
+    pub uri_template: String,
+    pub name: String,
+    pub description: Option<String>,
+    pub mime_type: Option<String>,
+}
+
+#[skip_serializing_none]
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ResourceContents {
+    pub uri: String,
+    pub mime_type: Option<String>,
+    pub text: String,
+}
+
+/// What a resource URI designates
+#[derive(Debug, Clone, PartialEq)]
+enum ResourceUri {
+    Mappings { session_id: String },
+    Memory { session_id: String, start: u64, end: u64 },
+}
+
+impl ResourceUri {
+    fn parse(uri: &str) -> AppResult<Self> {
+        let invalid = || AppError::InvalidArgument(format!("unknown resource {}", uri));
+        let path = uri.strip_prefix(SCHEME).ok_or_else(invalid)?;
+        let mut parts = path.split('/');
+        let (Some(session_id), Some("memory")) = (parts.next(), parts.next()) else {
+            return Err(invalid());
+        };
+        let session_id = session_id.to_string();
+        match (parts.next(), parts.next()) {
+            (None, _) => Ok(ResourceUri::Mappings { session_id }),
+            (Some(range), None) => {
+                let (start, end) = range.split_once('-').ok_or_else(invalid)?;
+                let (start, end) = (parse_address(start)?, parse_address(end)?);
+                if start >= end {
+                    return Err(AppError::InvalidArgument(format!("empty range in {}", uri)));
+                }
+                Ok(ResourceUri::Memory { session_id, start, end })
+            }
+            _ => Err(invalid()),
+        }
+    }
+}
+
+/// Templates of the resources
+pub fn resource_templates() -> Vec<ResourceTemplate> {
+    vec![
+        ResourceTemplate {
+            uri_template: format!("{}{{session}}/memory", SCHEME),
+            name: "Memory mappings".to_string(),
+            description: Some("Memory mappings of the debugged process".to_string()),
+            mime_type: Some("text/plain".to_string()),
+        },
+        ResourceTemplate {
+            uri_template: format!("{}{{session}}/memory/{{start}}-{{end}}", SCHEME),
+            name: "Memory hexdump".to_string(),
+            description: Some(format!(
+                "Hexdump of the memory from start to end, at most {} bytes",
+                MAX_HEXDUMP_BYTES
+            )),
+            mime_type: Some("text/plain".to_string()),
+        },
+    ]
+}
+
+/// The mappings of every session and their memory regions
+pub async fn list_resources(manager: &GDBManager) -> AppResult<Vec<Resource>> {
+    let mut resources = vec![];
+    for session in manager.get_all_sessions().await? {
+        resources.push(Resource {
+            uri: format!("{}{}/memory", SCHEME, session.id),
+            name: format!("Memory mappings of session {}", session.id),
+            description: None,
+            mime_type: Some("text/plain".to_string()),
+        });
+        // no process, or a target without mappings
+        let Ok(mappings) = manager.memory_mappings(&session.id).await else {
+            continue;
+        };
+        resources.extend(mappings.iter().map(|mapping| Resource {
+            uri: memory_uri(&session.id, mapping.start_address, mapping.end_address),
+            name: mapping_name(mapping),
+            description: Some(format!(
+                "{} bytes{}",
+                mapping.size,
+                mapping.permissions.as_deref().map(|p| format!(", {}", p)).unwrap_or_default()
+            )),
+            mime_type: Some("text/plain".to_string()),
+        }));
+    }
+    Ok(resources)
+}
+
+/// Read a resource
+pub async fn read_resource(manager: &GDBManager, uri: &str) -> AppResult<ResourceContents> {
+    let text = match ResourceUri::parse(uri)? {
+        ResourceUri::Mappings { session_id } => {
+            let mappings = manager.memory_mappings(&session_id).await?;
+            let mut text = String::new();
+            for mapping in &mappings {
+                let _ = writeln!(
+                    text,
+                    "{} {}",
+                    memory_uri(&session_id, mapping.start_address, mapping.end_address),
+                    mapping_name(mapping)
+                );
+            }
+            text
+        }
+        ResourceUri::Memory { session_id, start, end } => {
+            if end - start > MAX_HEXDUMP_BYTES {
+                return Err(AppError::InvalidArgument(format!(
+                    "{} bytes requested, at most {} can be read at once, e.g. {}",
+                    end - start,
+                    MAX_HEXDUMP_BYTES,
+                    memory_uri(&session_id, start, start + MAX_HEXDUMP_BYTES)
+                )));
+            }
+            let blocks = manager
+                .read_memory(&session_id, None, format!("0x{:x}", start), (end - start) as usize)
+                .await?;
+            let mut text = String::new();
+            for block in blocks {
+                let begin = parse_address(&block.begin)?;
+                text.push_str(&hexdump(begin, &decode_hex(&block.contents)?));
+            }
+            text
+        }
+    };
+    Ok(ResourceContents { uri: uri.to_string(), mime_type: Some("text/plain".to_string()), text })
+}
+
+fn memory_uri(session_id: &str, start: u64, end: u64) -> String {
+    format!("{}{}/memory/0x{:x}-0x{:x}", SCHEME, session_id, start, end)
+}
+
+fn mapping_name(mapping: &MemoryMapping) -> String {
+    let path = mapping.path.as_ref().map(|path| path.display().to_string());
+    format!(
+        "0x{:x}-0x{:x} {} {}",
+        mapping.start_address,
+        mapping.end_address,
+        mapping.permissions.as_deref().unwrap_or("----"),
+        path.as_deref().unwrap_or("[anon]")
+    )
+}
+
+/// An address in hex with the `0x` prefix, or in decimal
+fn parse_address(address: &str) -> AppResult<u64> {
+    match address.strip_prefix("0x") {
+        Some(hex) => u64::from_str_radix(hex, 16),
+        None => address.parse(),
+    }
+    .map_err(|_| AppError::InvalidArgument(format!("invalid address {}", address)))
+}
+
+/// Bytes of the hex contents of a memory block
+pub fn decode_hex(contents: &str) -> AppResult<Vec<u8>> {
+    (0..contents.len())
+        .step_by(2)
+        .map(|i| {
+            contents.get(i..i + 2).and_then(|byte| u8::from_str_radix(byte, 16).ok()).ok_or_else(
+                || AppError::ParseError(format!("invalid memory contents {}", contents)),
+            )
+        })
+        .collect()
+}
+
+/// Lines of the address, the bytes in hex and in ASCII
+fn hexdump(address: u64, bytes: &[u8]) -> String {
+    let mut text = String::new();
+    for (i, chunk) in bytes.chunks(HEXDUMP_WIDTH).enumerate() {
+        let _ = write!(text, "{:016x} ", address + (i * HEXDUMP_WIDTH) as u64);
+        for j in 0..HEXDUMP_WIDTH {
+            if j % 8 == 0 {
+                text.push(' ');
+            }
+            match chunk.get(j) {
+                Some(byte) => {
+                    let _ = write!(text, "{:02x} ", byte);
+                }
+                None => text.push_str("   "),
+            }
+        }
+        text.push_str(" |");
+        text.extend(
+            chunk.iter().map(|&byte| if byte.is_ascii_graphic() { byte as char } else { '.' }),
+        );
+        text.push_str("|\n");
+    }
+    text
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_resource_uri() {
+        assert_eq!(
+            ResourceUri::parse("gdb://abc/memory/0x1000-0x1010").unwrap(),
+            ResourceUri::Memory { session_id: "abc".to_string(), start: 0x1000, end: 0x1010 }
+        );
+        assert_eq!(
+            ResourceUri::parse("gdb://abc/memory").unwrap(),
+            ResourceUri::Mappings { session_id: "abc".to_string() }
+        );
+        assert!(ResourceUri::parse("gdb://abc/memory/0x1010-0x1000").is_err());
+        assert!(ResourceUri::parse("file:///etc/passwd").is_err());
+    }
+
+    #[test]
+    fn test_hexdump() {
+        let dump = hexdump(0x1000, b"\x7fELF\x02\x01\x01\x00\x00\x00\x00\x00\x00\x00\x00\x00AB");
+        let lines = dump.lines().collect::<Vec<_>>();
+        assert_eq!(
+            lines[0],
+            "0000000000001000  7f 45 4c 46 02 01 01 00  00 00 00 00 00 00 00 00  |.ELF............|"
+        );
+        assert!(lines[1].starts_with("0000000000001010  41 42 "));
+        assert!(lines[1].ends_with("|AB|"));
+        assert_eq!(decode_hex("7f45").unwrap(), vec![0x7f, 0x45]);
+    }
+}
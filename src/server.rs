@@ -0,0 +1,603 @@
+//! The protocol of the MCP server and its stdio transport. The builder of
+//! mcp-core only answers the tools, this one also answers the resources of the
+//! sessions and lists only the tools they can use, and the transport of
+//! mcp-core reads nothing while it handles a request, this one keeps reading so
+//! the requests can be cancelled
+
+use std::collections::HashMap;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use anyhow::{Result, anyhow};
+use async_trait::async_trait;
+use mcp_core::protocol::{Protocol, RequestOptions};
+use mcp_core::tools::ToolHandlerFn;
+use mcp_core::transport::{
+    JsonRpcError, JsonRpcNotification, JsonRpcRequest, JsonRpcResponse, Message, RequestId,
+    Transport,
+};
+use mcp_core::types::{
+    CallToolRequest, Implementation, InitializeRequest, InitializeResponse,
+    LATEST_PROTOCOL_VERSION, ListRequest, ServerCapabilities, Tool, ToolsListResponse,
+};
+use serde::{Deserialize, Serialize};
+use tokio::io::{AsyncBufRead, AsyncBufReadExt, AsyncRead, AsyncWrite, AsyncWriteExt, BufReader};
+use tokio::sync::{Mutex, mpsc};
+use tracing::{debug, error, warn};
+
+use crate::gdb::Features;
+use crate::resources::{self, Resource, ResourceContents, ResourceTemplate};
+use crate::tools::GDB_MANAGER;
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ResourcesList {
+    resources: Vec<Resource>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ResourceTemplatesList {
+    resource_templates: Vec<ResourceTemplate>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct ReadResourceRequest {
+    uri: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct ReadResourceResult {
+    contents: Vec<ResourceContents>,
+}
+
+/// Whether a tool is listed, from the features of the sessions
+type Available = fn(&Features) -> bool;
+
+/// Builds the protocol answering the requests of the client
+pub struct ServerBuilder {
+    info: Implementation,
+    capabilities: ServerCapabilities,
+    /// In the order they are registered, with the features they need to be
+    /// listed
+    tools: Vec<(Tool, ToolHandlerFn, Available)>,
+}
+
+impl ServerBuilder {
+    pub fn new(name: String, version: String) -> Self {
+        Self {
+            info: Implementation { name, version },
+            capabilities: ServerCapabilities::default(),
+            tools: vec![],
+        }
+    }
+
+    pub fn capabilities(mut self, capabilities: ServerCapabilities) -> Self {
+        self.capabilities = capabilities;
+        self
+    }
+
+    pub fn register_tool(self, tool: Tool, f: ToolHandlerFn) -> Self {
+        self.register_tool_when(tool, f, |_| true)
+    }
+
+    /// Register a tool listed only while the sessions have the features it
+    /// needs, it can still be called otherwise
+    pub fn register_tool_when(
+        mut self,
+        tool: Tool,
+        f: ToolHandlerFn,
+        available: Available,
+    ) -> Self {
+        self.tools.push((tool, f, available));
+        self
+    }
+
+    pub fn build(self) -> Protocol {
+        let Self { info, capabilities, tools } = self;
+        let tools = Arc::new(tools);
+        let initialized = Arc::new(AtomicBool::new(false));
+        let (tools_list, tools_call) = (tools.clone(), tools);
+        let on_initialized = initialized.clone();
+        let on_list = initialized.clone();
+        let on_call = initialized.clone();
+        let on_resources = initialized.clone();
+        let on_templates = initialized.clone();
+        let on_read = initialized;
+
+        Protocol::builder()
+            .request_handler("initialize", move |_request: InitializeRequest| {
+                let response = InitializeResponse {
+                    protocol_version: LATEST_PROTOCOL_VERSION.to_string(),
+                    capabilities: capabilities.clone(),
+                    server_info: info.clone(),
+                };
+                Box::pin(async move { Ok(response) })
+            })
+            .notification_handler("notifications/initialized", move |_: ()| {
+                on_initialized.store(true, Ordering::SeqCst);
+                Box::pin(async { Ok(()) })
+            })
+            .request_handler("tools/list", move |_request: ListRequest| {
+                let tools = tools_list.clone();
+                let initialized = on_list.clone();
+                Box::pin(async move {
+                    check_initialized(&initialized, "tools/list")?;
+                    let features = GDB_MANAGER.features().await;
+                    Ok(ToolsListResponse {
+                        tools: tools
+                            .iter()
+                            .filter(|(_, _, available)| available(&features))
+                            .map(|(tool, _, _)| tool.clone())
+                            .collect(),
+                        next_cursor: None,
+                        meta: None,
+                    })
+                })
+            })
+            .request_handler("tools/call", move |request: CallToolRequest| {
+                let tools = tools_call.clone();
+                let initialized = on_call.clone();
+                Box::pin(async move {
+                    check_initialized(&initialized, "tools/call")?;
+                    let (_, f, _) = tools
+                        .iter()
+                        .find(|(tool, _, _)| tool.name == request.name)
+                        .ok_or_else(|| anyhow!("Tool not found: {}", request.name))?;
+                    Ok(f(request).await)
+                })
+            })
+            .request_handler("resources/list", move |_request: ListRequest| {
+                let initialized = on_resources.clone();
+                Box::pin(async move {
+                    check_initialized(&initialized, "resources/list")?;
+                    let resources = resources::list_resources(&GDB_MANAGER).await?;
+                    Ok(ResourcesList { resources })
+                })
+            })
+            .request_handler("resources/templates/list", move |_request: ListRequest| {
+                let initialized = on_templates.clone();
+                Box::pin(async move {
+                    check_initialized(&initialized, "resources/templates/list")?;
+                    Ok(ResourceTemplatesList {
+                        resource_templates: resources::resource_templates(),
+                    })
+                })
+            })
+            .request_handler("resources/read", move |request: ReadResourceRequest| {
+                let initialized = on_read.clone();
+                Box::pin(async move {
+                    check_initialized(&initialized, "resources/read")?;
+                    let contents = resources::read_resource(&GDB_MANAGER, &request.uri).await?;
+                    Ok(ReadResourceResult { contents: vec![contents] })
+                })
+            })
+            .build()
+    }
+}
+
+/// Params of `notifications/cancelled`
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Cancelled {
+    request_id: RequestId,
+    reason: Option<String>,
+}
+
+/// A request read, handled one at a time in order
+#[derive(Debug, Clone, Default)]
+struct Call {
+    /// The session of a tool, from its `session_id` argument
+    session_id: Option<String>,
+    started: bool,
+    cancelled: bool,
+}
+
+/// Transport over stdin and stdout. A cancelled request is dropped if it did
+/// not start, otherwise the waits of its session are cancelled, and it is not
+/// answered
+#[derive(Clone)]
+pub struct StdioTransport {
+    protocol: Protocol,
+    input: Arc<Mutex<Box<dyn AsyncBufRead + Send + Unpin>>>,
+    /// Lines written in order by a task
+    output: mpsc::UnboundedSender<String>,
+    calls: Arc<std::sync::Mutex<HashMap<RequestId, Call>>>,
+}
+
+impl StdioTransport {
+    pub fn new(protocol: Protocol) -> Self {
+        Self::with_io(protocol, tokio::io::stdin(), tokio::io::stdout())
+    }
+
+    fn with_io(
+        protocol: Protocol,
+        input: impl AsyncRead + Send + Unpin + 'static,
+        mut output: impl AsyncWrite + Send + Unpin + 'static,
+    ) -> Self {
+        let (lines, mut receiver) = mpsc::unbounded_channel::<String>();
+        tokio::spawn(async move {
+            while let Some(line) = receiver.recv().await {
+                let written = async {
+                    output.write_all(line.as_bytes()).await?;
+                    output.write_all(b"\n").await?;
+                    output.flush().await
+                };
+                if let Err(e) = written.await {
+                    error!("Failed to write to the client: {}", e);
+                    break;
+                }
+            }
+        });
+        Self {
+            protocol,
+            input: Arc::new(Mutex::new(Box::new(BufReader::new(input)))),
+            output: lines,
+            calls: Default::default(),
+        }
+    }
+
+    fn write(&self, message: &impl Serialize) -> Result<()> {
+        let line = serde_json::to_string(message)?;
+        debug!("Sending: {}", line);
+        self.output.send(line).map_err(|_| anyhow!("The output to the client is closed"))
+    }
+
+    /// Queue a request, or handle a notification or a response right away
+    async fn receive(&self, message: Message, queue: &mpsc::UnboundedSender<JsonRpcRequest>) {
+        match message {
+            Message::Request(request) => {
+                let session_id =
+                    (request.method == "tools/call").then(|| request_session(&request)).flatten();
+                let call = Call { session_id, ..Default::default() };
+                self.calls.lock().unwrap().insert(request.id, call);
+                let _ = queue.send(request);
+            }
+            Message::Notification(notification)
+                if notification.method == "notifications/cancelled" =>
+            {
+                match serde_json::from_value::<Cancelled>(notification.params.unwrap_or_default()) {
+                    Ok(cancelled) => self.cancel(cancelled).await,
+                    Err(e) => warn!("Invalid cancellation: {}", e),
+                }
+            }
+            Message::Notification(notification) => {
+                self.protocol.handle_notification(notification).await
+            }
+            Message::Response(response) => self.protocol.handle_response(response).await,
+        }
+    }
+
+    async fn cancel(&self, cancelled: Cancelled) {
+        let Cancelled { request_id, reason } = cancelled;
+        debug!("Request {} cancelled: {}", request_id, reason.as_deref().unwrap_or("no reason"));
+        let session_id = {
+            let mut calls = self.calls.lock().unwrap();
+            let Some(call) = calls.get_mut(&request_id) else {
+                // answered already
+                return;
+            };
+            call.cancelled = true;
+            call.session_id.clone().filter(|_| call.started)
+        };
+        if let Some(session_id) = session_id
+            && let Err(e) = GDB_MANAGER.cancel(&session_id).await
+        {
+            warn!("Failed to cancel request {}: {}", request_id, e);
+        }
+    }
+
+    /// Handle the requests in the order they were read
+    async fn serve(self, mut queue: mpsc::UnboundedReceiver<JsonRpcRequest>) {
+        while let Some(request) = queue.recv().await {
+            let id = request.id;
+            let cancelled = {
+                let mut calls = self.calls.lock().unwrap();
+                let call = calls.entry(id).or_default();
+                call.started = true;
+                call.cancelled
+            };
+            let response =
+                if cancelled { None } else { Some(self.protocol.handle_request(request).await) };
+            let call = self.calls.lock().unwrap().remove(&id);
+            // the client ignores the response of a cancelled request
+            if let Some(response) = response
+                && !call.is_some_and(|call| call.cancelled)
+                && let Err(e) = self.send_response(id, response.result, response.error).await
+            {
+                error!("Failed to answer request {}: {}", id, e);
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl Transport for StdioTransport {
+    async fn open(&self) -> Result<()> {
+        let (queue, requests) = mpsc::unbounded_channel();
+        let server = tokio::spawn(self.clone().serve(requests));
+        loop {
+            match self.poll_message().await {
+                Ok(Some(message)) => self.receive(message, &queue).await,
+                Ok(None) => break,
+                Err(e) => error!("Error receiving message: {:?}", e),
+            }
+        }
+        // answer the requests read
+        drop(queue);
+        server.await?;
+        Ok(())
+    }
+
+    async fn close(&self) -> Result<()> {
+        Ok(())
+    }
+
+    async fn poll_message(&self) -> Result<Option<Message>> {
+        let mut line = String::new();
+        if self.input.lock().await.read_line(&mut line).await? == 0 {
+            return Ok(None);
+        }
+        debug!("Received: {}", line);
+        Ok(Some(serde_json::from_str(&line)?))
+    }
+
+    fn request(
+        &self,
+        method: &str,
+        params: Option<serde_json::Value>,
+        options: RequestOptions,
+    ) -> Pin<Box<dyn Future<Output = Result<JsonRpcResponse>> + Send + Sync>> {
+        let transport = self.clone();
+        let method = method.to_string();
+        Box::pin(async move {
+            let (id, response) = transport.protocol.create_request().await;
+            transport.write(&JsonRpcRequest { id, method, params, jsonrpc: Default::default() })?;
+            match tokio::time::timeout(options.timeout, response).await {
+                Ok(Ok(response)) => Ok(response),
+                _ => {
+                    transport.protocol.cancel_response(id).await;
+                    Err(anyhow!("Request {} was not answered", id))
+                }
+            }
+        })
+    }
+
+    async fn send_notification(
+        &self,
+        method: &str,
+        params: Option<serde_json::Value>,
+    ) -> Result<()> {
+        self.write(&JsonRpcNotification {
+            method: method.to_string(),
+            params,
+            jsonrpc: Default::default(),
+        })
+    }
+
+    async fn send_response(
+        &self,
+        id: RequestId,
+        result: Option<serde_json::Value>,
+        error: Option<JsonRpcError>,
+    ) -> Result<()> {
+        self.write(&JsonRpcResponse { id, result, error, jsonrpc: Default::default() })
+    }
+}
+
+/// Tell the client to list the tools again when the features of the
+/// sessions change
+pub async fn notify_tools_changed(transport: Arc<Box<dyn Transport>>) {
+    let mut sessions_changed = GDB_MANAGER.subscribe_sessions();
+    let mut features = GDB_MANAGER.features().await;
+    while sessions_changed.changed().await.is_ok() {
+        let changed = GDB_MANAGER.features().await;
+        if changed == features {
+            continue;
+        }
+        features = changed;
+        if let Err(e) = transport.send_notification("notifications/tools/list_changed", None).await
+        {
+            error!("Failed to send the notifications/tools/list_changed notification: {:?}", e);
+        }
+    }
+}
+
+/// The session a tool is called on
+fn request_session(request: &JsonRpcRequest) -> Option<String> {
+    let arguments = request.params.as_ref()?.get("arguments")?;
+    arguments.get("session_id")?.as_str().map(str::to_string)
+}
+
+fn check_initialized(initialized: &AtomicBool, method: &str) -> Result<()> {
+    if initialized.load(Ordering::SeqCst) {
+        Ok(())
+    } else {
+        Err(anyhow!("Client must be initialized before using {}", method))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use serde_json::{Value, json};
+
+    use super::*;
+    use crate::gdb::record::RecordMethod;
+    use crate::tools;
+
+    async fn request(protocol: &Protocol, method: &str, params: Value) -> JsonRpcResponse {
+        let request = JsonRpcRequest {
+            id: 1,
+            method: method.to_string(),
+            params: Some(params),
+            jsonrpc: Default::default(),
+        };
+        protocol.handle_request(request).await
+    }
+
+    #[tokio::test]
+    async fn test_protocol() {
+        let protocol = ServerBuilder::new("test".to_string(), "0.1".to_string())
+            .register_tool(tools::DemangleTool::tool(), tools::DemangleTool::call())
+            .build();
+
+        let response = request(&protocol, "initialize", json!({})).await;
+        assert_eq!(response.result.unwrap()["serverInfo"]["name"], "test");
+        let response = request(&protocol, "tools/list", json!({})).await;
+        assert!(response.error.unwrap().message.contains("initialized"));
+        protocol
+            .handle_notification(JsonRpcNotification {
+                method: "notifications/initialized".to_string(),
+                params: None,
+                jsonrpc: Default::default(),
+            })
+            .await;
+
+        let response = request(&protocol, "tools/list", json!({})).await;
+        assert_eq!(response.result.unwrap()["tools"][0]["name"], "demangle");
+        let params = json!({"name": "demangle", "arguments": {"names": ["_ZN3foo3barE"]}});
+        let response = request(&protocol, "tools/call", params).await;
+        let text = response.result.unwrap()["content"][0]["text"].as_str().unwrap().to_string();
+        assert!(text.contains("foo::bar"));
+
+        let response = request(&protocol, "resources/templates/list", json!({})).await;
+        let templates = response.result.unwrap()["resourceTemplates"].clone();
+        assert_eq!(templates[1]["uriTemplate"], "gdb://{session}/memory/{start}-{end}");
+        let response =
+            request(&protocol, "resources/read", json!({"uri": "file:///etc/passwd"})).await;
+        assert!(response.error.unwrap().message.contains("unknown resource"));
+    }
+
+    #[tokio::test]
+    async fn test_cancelled() {
+        let session_id = GDB_MANAGER
+            .create_mock_session(
+                r#"
+                -> -exec-continue
+                <- ^running
+                <- *running,thread-id="all"
+                -> <interrupt>
+                <- *stopped,reason="signal-received"
+                "#,
+            )
+            .await
+            .unwrap();
+        GDB_MANAGER.continue_execution(&session_id).await.unwrap();
+
+        let protocol = ServerBuilder::new("test".to_string(), "0.1".to_string())
+            .register_tool(tools::WaitForStopTool::tool(), tools::WaitForStopTool::call())
+            .register_tool(tools::DemangleTool::tool(), tools::DemangleTool::call())
+            .build();
+        let (mut client, input) = tokio::io::duplex(1 << 16);
+        let (output, responses) = tokio::io::duplex(1 << 16);
+        let transport = StdioTransport::with_io(protocol, input, output);
+        let server = tokio::spawn({
+            let transport = transport.clone();
+            async move { transport.open().await }
+        });
+
+        let messages = [
+            json!({"jsonrpc": "2.0", "id": 1, "method": "initialize", "params": {}}),
+            json!({"jsonrpc": "2.0", "method": "notifications/initialized"}),
+            json!({"jsonrpc": "2.0", "id": 7, "method": "tools/call",
+                   "params": {"name": "wait_for_stop", "arguments": {"session_id": session_id}}}),
+            json!({"jsonrpc": "2.0", "id": 10, "method": "tools/call",
+                   "params": {"name": "demangle", "arguments": {"names": ["_ZN3foo3barE"]}}}),
+            json!({"jsonrpc": "2.0", "id": 8, "method": "tools/list", "params": {}}),
+            // queued behind the wait, never handled
+            json!({"jsonrpc": "2.0", "method": "notifications/cancelled",
+                   "params": {"requestId": 10}}),
+        ];
+        for message in messages {
+            client.write_all(format!("{}\n", message).as_bytes()).await.unwrap();
+        }
+        tokio::time::timeout(Duration::from_secs(5), async {
+            while !transport.calls.lock().unwrap().get(&7).is_some_and(|call| call.started) {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .unwrap();
+        // the wait is cancelled and the program interrupted
+        let cancelled = json!({"jsonrpc": "2.0", "method": "notifications/cancelled",
+                               "params": {"requestId": 7, "reason": "timeout"}});
+        client.write_all(format!("{}\n", cancelled).as_bytes()).await.unwrap();
+        drop(client);
+        tokio::time::timeout(Duration::from_secs(5), server).await.unwrap().unwrap().unwrap();
+        drop(transport);
+
+        let mut lines = BufReader::new(responses).lines();
+        let mut ids = vec![];
+        while let Some(line) = lines.next_line().await.unwrap() {
+            let response: Value = serde_json::from_str(&line).unwrap();
+            ids.push(response["id"].as_u64().unwrap());
+        }
+        assert_eq!(ids, [1, 8]);
+        let stopped =
+            GDB_MANAGER.wait_for_stop(&session_id, 0, Duration::from_secs(5)).await.unwrap();
+        assert_eq!(stopped["reason"], "signal-received");
+        GDB_MANAGER.close_session(&session_id).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_tools_changed() {
+        let protocol = ServerBuilder::new("test".to_string(), "0.1".to_string())
+            .register_tool(tools::DemangleTool::tool(), tools::DemangleTool::call())
+            .register_tool_when(
+                tools::GetInstructionHistoryTool::tool(),
+                tools::GetInstructionHistoryTool::call(),
+                |features| features.recording,
+            )
+            .build();
+        let (_client, input) = tokio::io::duplex(1 << 16);
+        let (output, notifications) = tokio::io::duplex(1 << 16);
+        let transport = StdioTransport::with_io(protocol.clone(), input, output);
+        tokio::spawn(notify_tools_changed(Arc::new(Box::new(transport))));
+        // subscribed to the sessions
+        tokio::task::yield_now().await;
+        protocol
+            .handle_notification(JsonRpcNotification {
+                method: "notifications/initialized".to_string(),
+                params: None,
+                jsonrpc: Default::default(),
+            })
+            .await;
+        let names = || async {
+            let response = request(&protocol, "tools/list", json!({})).await;
+            let tools = response.result.unwrap()["tools"].as_array().unwrap().clone();
+            tools.iter().map(|tool| tool["name"].as_str().unwrap().to_string()).collect::<Vec<_>>()
+        };
+        assert_eq!(names().await, ["demangle"]);
+
+        let session_id = GDB_MANAGER
+            .create_mock_session(
+                r#"
+                -> -interpreter-exec console "record full"
+                <- ^done
+                -> -interpreter-exec console "info record"
+                <- ~"Active record target: record-full\n"
+                <- ~"Log contains 0 instructions.\n"
+                <- ^done
+                "#,
+            )
+            .await
+            .unwrap();
+        GDB_MANAGER.start_recording(&session_id, RecordMethod::Full).await.unwrap();
+        assert_eq!(names().await, ["demangle", "get_instruction_history"]);
+        let mut lines = BufReader::new(notifications).lines();
+        let line = tokio::time::timeout(Duration::from_secs(5), lines.next_line())
+            .await
+            .unwrap()
+            .unwrap()
+            .unwrap();
+        let notification: Value = serde_json::from_str(&line).unwrap();
+        assert_eq!(notification["method"], "notifications/tools/list_changed");
+
+        GDB_MANAGER.close_session(&session_id).await.unwrap();
+        assert_eq!(names().await, ["demangle"]);
+    }
+}
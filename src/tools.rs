@@ -1,14 +1,39 @@
 use std::ffi::OsString;
+use std::marker::PhantomData;
 use std::path::PathBuf;
 use std::sync::{Arc, LazyLock};
+use std::time::Duration;
 
 use anyhow::Result;
 use mcp_core::tool_text_content;
 use mcp_core::types::ToolResponseContent;
 use mcp_core_macros::tool;
+use schemars::schema::Schema;
+use schemars::{JsonSchema, SchemaGenerator};
+use serde::{Deserialize, Deserializer};
+use serde_json::Value;
+use tracing::Level;
 
-use crate::gdb::GDBManager;
+use crate::error::{AppError, AppResult};
+use crate::gdb::annotations::Annotation;
+use crate::gdb::coverage::CoverageMode;
+use crate::gdb::demangle::demangle_or_keep;
+use crate::gdb::embedded::{EmbeddedOptions, GdbServer};
+use crate::gdb::memory::ScalarType;
+use crate::gdb::modules::AddressKind;
+use crate::gdb::plan::PlanStep;
+use crate::gdb::qemu::QemuOptions;
+use crate::gdb::record::RecordMethod;
+use crate::gdb::registers::RegisterGroup;
+use crate::gdb::rtos::{RtosKind, RtosState};
+use crate::gdb::skip::{SkipPreset, SkipTarget};
+use crate::gdb::{GDBManager, SessionOptions, discovery};
+use crate::markdown::OutputFormat;
 use crate::mi::GDB;
+use crate::mi::commands::BreakPointNumber;
+use crate::mi::transcript::TranscriptFormat;
+use crate::models::{Endian, PrintValue, SchedulerLocking, VectorFormat};
+use crate::{config, logs, markdown};
 
 pub static GDB_MANAGER: LazyLock<Arc<GDBManager>> =
     LazyLock::new(|| Arc::new(GDBManager::default()));
@@ -17,6 +42,155 @@ pub fn init_gdb_manager() {
     LazyLock::force(&GDB_MANAGER);
 }
 
+/// Loosely typed conversion from a JSON tool argument
+///
+/// MCP clients frequently send strings where numbers, booleans or paths are
+/// expected, so the conversion accepts every reasonable representation.
+pub trait Coerce: Sized {
+    /// Description of the expected value, used in error messages
+    const EXPECTED: &'static str;
+
+    fn coerce(value: &Value) -> Option<Self>;
+}
+
+impl Coerce for bool {
+    const EXPECTED: &'static str = "a boolean or \"true\"/\"false\"";
+
+    fn coerce(value: &Value) -> Option<Self> {
+        match value {
+            Value::Bool(b) => Some(*b),
+            Value::Number(n) => match n.as_u64()? {
+                0 => Some(false),
+                1 => Some(true),
+                _ => None,
+            },
+            Value::String(s) => match s.trim().to_ascii_lowercase().as_str() {
+                "true" | "yes" | "1" => Some(true),
+                "false" | "no" | "0" => Some(false),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+}
+
+macro_rules! coerce_integer {
+    ($($t:ty),*) => {
+        $(
+            impl Coerce for $t {
+                const EXPECTED: &'static str = "an integer or a numeric string";
+
+                fn coerce(value: &Value) -> Option<Self> {
+                    match value {
+                        Value::Number(n) => n
+                            .as_i64()
+                            .and_then(|n| n.try_into().ok())
+                            .or_else(|| n.as_u64().and_then(|n| n.try_into().ok())),
+                        Value::String(s) => {
+                            let s = s.trim();
+                            match s.strip_prefix("0x").or_else(|| s.strip_prefix("0X")) {
+                                Some(hex) => <$t>::from_str_radix(hex, 16).ok(),
+                                None => s.parse().ok(),
+                            }
+                        }
+                        _ => None,
+                    }
+                }
+            }
+        )*
+    };
+}
+
+coerce_integer!(u16, u32, u64, usize, isize);
+
+impl Coerce for String {
+    const EXPECTED: &'static str = "a string";
+
+    fn coerce(value: &Value) -> Option<Self> {
+        match value {
+            Value::String(s) => Some(s.clone()),
+            Value::Number(n) => Some(n.to_string()),
+            Value::Bool(b) => Some(b.to_string()),
+            _ => None,
+        }
+    }
+}
+
+impl Coerce for PathBuf {
+    const EXPECTED: &'static str = "a path string";
+
+    fn coerce(value: &Value) -> Option<Self> {
+        match value {
+            Value::String(s) => Some(PathBuf::from(s)),
+            _ => None,
+        }
+    }
+}
+
+impl Coerce for Vec<String> {
+    const EXPECTED: &'static str = "an array of strings or a whitespace separated string";
+
+    fn coerce(value: &Value) -> Option<Self> {
+        match value {
+            Value::Array(values) => values.iter().map(String::coerce).collect(),
+            Value::String(s) => Some(s.split_whitespace().map(str::to_string).collect()),
+            Value::Number(n) => Some(vec![n.to_string()]),
+            _ => None,
+        }
+    }
+}
+
+/// A tool argument kept as raw JSON and coerced into `T` on use, so a
+/// mistyped argument yields an error naming the offending parameter instead of
+/// a generic invalid type error
+#[derive(Debug, Clone)]
+pub struct Arg<T> {
+    value: Value,
+    _marker: PhantomData<T>,
+}
+
+impl<'de, T> Deserialize<'de> for Arg<T> {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        Value::deserialize(deserializer).map(|value| Arg { value, _marker: PhantomData })
+    }
+}
+
+impl<T: JsonSchema> JsonSchema for Arg<T> {
+    fn schema_name() -> String {
+        T::schema_name()
+    }
+
+    fn json_schema(generator: &mut SchemaGenerator) -> Schema {
+        T::json_schema(generator)
+    }
+
+    fn is_referenceable() -> bool {
+        T::is_referenceable()
+    }
+}
+
+impl<T: Coerce> Arg<T> {
+    /// Coerce the argument, `name` is the parameter name reported on failure
+    pub fn get(self, name: &str) -> AppResult<T> {
+        T::coerce(&self.value).ok_or_else(|| {
+            AppError::InvalidArgument(format!(
+                "parameter '{}' expects {}, got {}",
+                name,
+                T::EXPECTED,
+                self.value
+            ))
+        })
+    }
+}
+
+/// Coerce an optional tool argument, see [`Arg::get`]
+fn opt_arg<T: Coerce>(arg: Option<Arg<T>>, name: &str) -> AppResult<Option<T>> {
+    arg.map(|arg| arg.get(name)).transpose()
+}
+
 #[tool(
     name = "create_session",
     description = "Create a new GDB debugging session with optional parameters,\
@@ -35,176 +209,1437 @@ pub fn init_gdb_manager() {
         source_dir = "if provided, search for source files in DIR",
         args = "if provided, arguments to be passed to the inferior program",
         tty = "if provided, use TTY for input/output by the program being debugged",
-        gdb_path = "if provided, path to the GDB executable",
+        gdb_path = "if provided, path to the GDB executable, otherwise a GDB matching \
+            the architecture of the program is picked from PATH",
+        sysroot = "if provided, local copy of the root filesystem of a remote target, shared \
+            libraries are read from it instead of over the remote protocol",
+        init_commands = "if provided, GDB commands run one after the other once GDB is started, \
+            what each one printed or why it failed is returned",
+        python_script = "if provided, Python file sourced once GDB is started, before \
+            init_commands, e.g. gef.py",
+    )
+)]
+#[allow(clippy::too_many_arguments)]
+pub async fn create_session_tool(
+    program: Option<Arg<PathBuf>>,
+    nh: Option<Arg<bool>>,
+    nx: Option<Arg<bool>>,
+    quiet: Option<Arg<bool>>,
+    cd: Option<Arg<PathBuf>>,
+    bps: Option<Arg<u32>>,
+    symbol_file: Option<Arg<PathBuf>>,
+    core_file: Option<Arg<PathBuf>>,
+    proc_id: Option<Arg<u32>>,
+    command: Option<Arg<PathBuf>>,
+    source_dir: Option<Arg<PathBuf>>,
+    args: Option<Arg<Vec<String>>>,
+    tty: Option<Arg<PathBuf>>,
+    gdb_path: Option<Arg<PathBuf>>,
+    sysroot: Option<Arg<PathBuf>>,
+    init_commands: Option<Arg<Vec<String>>>,
+    python_script: Option<Arg<PathBuf>>,
+) -> Result<ToolResponseContent> {
+    let args = opt_arg(args, "args")?.map(|args| args.into_iter().map(OsString::from).collect());
+    let session = GDB_MANAGER
+        .create_session(SessionOptions {
+            program: opt_arg(program, "program")?,
+            nh: opt_arg(nh, "nh")?,
+            nx: opt_arg(nx, "nx")?,
+            quiet: opt_arg(quiet, "quiet")?,
+            cd: opt_arg(cd, "cd")?,
+            bps: opt_arg(bps, "bps")?,
+            symbol_file: opt_arg(symbol_file, "symbol_file")?,
+            core_file: opt_arg(core_file, "core_file")?,
+            proc_id: opt_arg(proc_id, "proc_id")?,
+            command: opt_arg(command, "command")?,
+            source_dir: opt_arg(source_dir, "source_dir")?,
+            args,
+            tty: opt_arg(tty, "tty")?,
+            gdb_path: opt_arg(gdb_path, "gdb_path")?,
+            sysroot: opt_arg(sysroot, "sysroot")?,
+        })
+        .await?;
+    let mut init_commands = opt_arg(init_commands, "init_commands")?.unwrap_or_default();
+    if let Some(script) = opt_arg(python_script, "python_script")? {
+        init_commands.insert(0, format!("source {}", script.display()));
+    }
+    if init_commands.is_empty() {
+        return Ok(tool_text_content!(format!("Created GDB session: {}", session)));
+    }
+    let results = GDB_MANAGER.run_init_commands(&session, &init_commands).await;
+    Ok(tool_text_content!(format!(
+        "Created GDB session: {}\nInit commands: {}",
+        session,
+        serde_json::to_string(&results)?
+    )))
+}
+
+#[tool(
+    name = "set_sysroot",
+    description = "Set the local copy of the root filesystem of a remote target, so shared \
+                   libraries are read from it instead of over the remote protocol",
+    params(session_id = "The ID of the GDB session", sysroot = "Path to the root filesystem copy")
+)]
+pub async fn set_sysroot_tool(
+    session_id: String,
+    sysroot: Arg<PathBuf>,
+) -> Result<ToolResponseContent> {
+    let sysroot = sysroot.get("sysroot")?;
+    GDB_MANAGER.set_sysroot(&session_id, &sysroot).await?;
+    Ok(tool_text_content!(format!("Sysroot set to {}", sysroot.display())))
+}
+
+#[tool(
+    name = "set_gdb_parameter",
+    description = "Set a GDB parameter as with the set command, e.g. name print elements and \
+                   value 0, or name pagination and value off. The parameters set are reported by \
+                   get_session and set again when the session is restarted",
+    params(
+        session_id = "The ID of the GDB session",
+        name = "the parameter, e.g. print pretty",
+        value = "the value, e.g. on"
+    )
+)]
+pub async fn set_gdb_parameter_tool(
+    session_id: String,
+    name: String,
+    value: String,
+) -> Result<ToolResponseContent> {
+    GDB_MANAGER.set_parameter(&session_id, &name, &value).await?;
+    Ok(tool_text_content!(format!("{} set to {}", name, value)))
+}
+
+#[tool(
+    name = "show_gdb_parameter",
+    description = "Get the value of a GDB parameter as with the show command, e.g. print elements",
+    params(session_id = "The ID of the GDB session", name = "the parameter, e.g. print elements")
+)]
+pub async fn show_gdb_parameter_tool(
+    session_id: String,
+    name: String,
+) -> Result<ToolResponseContent> {
+    let value = GDB_MANAGER.show_setting(&session_id, &name).await?;
+    Ok(tool_text_content!(format!("{}: {}", name, value)))
+}
+
+#[tool(
+    name = "create_embedded_session",
+    description = "Create a GDB session debugging a microcontroller: launch OpenOCD or the J-Link \
+                   GDB server, or use one already running, connect GDB to it with \
+                   extended-remote, optionally reset, halt and flash the target. The server is \
+                   killed when the session is closed. Returns a session ID (UUID)",
+    params(
+        program = "if provided, path to the ELF file of the firmware",
+        gdb_path = "if provided, path to the GDB executable, e.g. arm-none-eabi-gdb, otherwise \
+            a GDB matching the architecture of the program is picked from PATH",
+        server = "GDB server to launch: openocd or jlink, omit it to connect to a running server",
+        server_path = "if provided, path to the GDB server executable",
+        config_files = "OpenOCD configuration files, e.g. [\"interface/stlink.cfg\", \
+            \"target/stm32f4x.cfg\"]",
+        device = "J-Link device name, e.g. STM32F407VG",
+        interface = "J-Link target interface, defaults to SWD",
+        address = "host:port of the GDB server, defaults to localhost:3333 for OpenOCD and \
+            localhost:2331 for J-Link",
+        reset = "reset and halt the target once connected, defaults to true",
+        load = "flash the program into the target, defaults to false",
+    )
+)]
+#[allow(clippy::too_many_arguments)]
+pub async fn create_embedded_session_tool(
+    program: Option<Arg<PathBuf>>,
+    gdb_path: Option<Arg<PathBuf>>,
+    server: Option<String>,
+    server_path: Option<Arg<PathBuf>>,
+    config_files: Option<Arg<Vec<String>>>,
+    device: Option<String>,
+    interface: Option<String>,
+    address: Option<String>,
+    reset: Option<Arg<bool>>,
+    load: Option<Arg<bool>>,
+) -> Result<ToolResponseContent> {
+    let options = EmbeddedOptions {
+        server: server.map(|server| server.parse::<GdbServer>()).transpose()?,
+        server_path: opt_arg(server_path, "server_path")?,
+        config_files: opt_arg(config_files, "config_files")?.unwrap_or_default(),
+        device,
+        interface,
+        address,
+        reset: opt_arg(reset, "reset")?.unwrap_or(true),
+        load: opt_arg(load, "load")?.unwrap_or(false),
+    };
+    let session = GDB_MANAGER
+        .create_embedded_session(
+            opt_arg(program, "program")?,
+            opt_arg(gdb_path, "gdb_path")?,
+            options,
+        )
+        .await?;
+    Ok(tool_text_content!(format!("Created GDB session: {}", session)))
+}
+
+#[tool(
+    name = "create_qemu_session",
+    description = "Launch QEMU frozen at startup with its gdbstub enabled (-s -S) and create a GDB \
+                   session connected to it. QEMU is killed when the session is closed, its \
+                   serial console goes to the server log. Returns a session ID (UUID)",
+    params(
+        kernel = "if provided, kernel image to boot",
+        program = "if provided, file to read the symbols from, defaults to the kernel",
+        gdb_path = "if provided, path to the GDB executable, otherwise a GDB matching the \
+            architecture of the program is picked from PATH",
+        qemu_path = "if provided, path to the QEMU executable, defaults to \
+            qemu-system-<arch of the kernel>",
+        machine = "if provided, machine type, e.g. virt",
+        cpu = "if provided, CPU model",
+        memory = "if provided, RAM size, e.g. 512M",
+        append = "if provided, kernel command line",
+        args = "if provided, extra arguments passed to QEMU as is",
+        port = "port of the gdbstub, defaults to 1234",
+    )
+)]
+#[allow(clippy::too_many_arguments)]
+pub async fn create_qemu_session_tool(
+    kernel: Option<Arg<PathBuf>>,
+    program: Option<Arg<PathBuf>>,
+    gdb_path: Option<Arg<PathBuf>>,
+    qemu_path: Option<Arg<PathBuf>>,
+    machine: Option<String>,
+    cpu: Option<String>,
+    memory: Option<String>,
+    append: Option<String>,
+    args: Option<Arg<Vec<String>>>,
+    port: Option<Arg<u16>>,
+) -> Result<ToolResponseContent> {
+    let options = QemuOptions {
+        qemu_path: opt_arg(qemu_path, "qemu_path")?,
+        machine,
+        cpu,
+        memory,
+        kernel: opt_arg(kernel, "kernel")?,
+        append,
+        args: opt_arg(args, "args")?.unwrap_or_default(),
+        port: opt_arg(port, "port")?,
+    };
+    let session = GDB_MANAGER
+        .create_qemu_session(opt_arg(program, "program")?, opt_arg(gdb_path, "gdb_path")?, options)
+        .await?;
+    Ok(tool_text_content!(format!("Created GDB session: {}", session)))
+}
+
+#[tool(
+    name = "attach_to_container",
+    description = "Create a GDB session attached to a process running in a local Docker or \
+                   Podman container, found by the container name or ID and a part of its \
+                   command line. Shared libraries are read from the filesystem of the container. \
+                   Attaching needs the permission to ptrace the process, usually root. Returns \
+                   the session ID (UUID) and the process",
+    params(
+        container = "name or ID of the container",
+        process = "part of the command line of the process, it must match only one process",
+        runtime = "if provided, docker or podman, otherwise both are tried",
+        source_dir = "if provided, directory of the sources in the container, they are read \
+                      from the filesystem of the container",
+        gdb_path = "if provided, path to the GDB executable"
+    )
+)]
+pub async fn attach_to_container_tool(
+    container: String,
+    process: String,
+    runtime: Option<String>,
+    source_dir: Option<String>,
+    gdb_path: Option<Arg<PathBuf>>,
+) -> Result<ToolResponseContent> {
+    let (session, process) = GDB_MANAGER
+        .create_container_session(
+            &container,
+            &process,
+            runtime.as_deref(),
+            source_dir.as_deref(),
+            opt_arg(gdb_path, "gdb_path")?,
+        )
+        .await?;
+    Ok(tool_text_content!(format!(
+        "Created GDB session: {}\nProcess: {}",
+        session,
+        serde_json::to_string(&process)?
+    )))
+}
+
+#[tool(
+    name = "list_available_debuggers",
+    description = "List the GDB binaries found in PATH, including gdb-multiarch and cross \
+                   debuggers such as arm-none-eabi-gdb, with the target they are built for",
+    params(
+        program = "if provided, also report which debugger would be picked for this executable"
+    )
+)]
+pub async fn list_available_debuggers_tool(
+    program: Option<Arg<PathBuf>>,
+) -> Result<ToolResponseContent> {
+    let debuggers = discovery::find_debuggers();
+    let mut text = format!("Debuggers: {}", serde_json::to_string(&debuggers)?);
+    if let Some(program) = opt_arg(program, "program")? {
+        let selected = discovery::select_for_program(&program)
+            .map_or("none".to_string(), |path| path.display().to_string());
+        text.push_str(&format!("\nSelected for {}: {}", program.display(), selected));
+    }
+    Ok(tool_text_content!(text))
+}
+
+#[tool(
+    name = "get_server_logs",
+    description = "Get the tail of the server's own log, useful to find out why a GDB command \
+                   failed",
+    params(
+        lines = "number of lines to return, defaults to 100",
+        level = "minimum level of the lines to return: error, warn, info, debug or trace"
+    )
+)]
+pub async fn get_server_logs_tool(
+    lines: Option<Arg<usize>>,
+    level: Option<String>,
+) -> Result<ToolResponseContent> {
+    let log = &config::get().log;
+    if log.stderr {
+        return Err(AppError::NotFound("logs are written to stderr".to_string()).into());
+    }
+    let level = level
+        .map(|level| {
+            level.parse::<Level>().map_err(|_| {
+                AppError::InvalidArgument(format!(
+                    "parameter 'level' expects one of error, warn, info, debug, trace, got {}",
+                    level
+                ))
+            })
+        })
+        .transpose()?;
+    let path = logs::latest_file(&log.dir)
+        .ok_or_else(|| AppError::NotFound(format!("no log file in {}", log.dir.display())))?;
+    let lines = logs::tail(&path, opt_arg(lines, "lines")?.unwrap_or(100), level)?;
+    Ok(tool_text_content!(format!("Server logs ({}):\n{}", path.display(), lines.join("\n"))))
+}
+
+#[tool(
+    name = "get_session",
+    description = "Get a GDB debugging session by ID, including the detected target architecture, \
+                   pointer width and endianness",
+    params(session_id = "The ID of the GDB session")
+)]
+pub async fn get_session_tool(session_id: String) -> Result<ToolResponseContent> {
+    let session = GDB_MANAGER.get_session(&session_id).await?;
+    Ok(tool_text_content!(format!("Session: {}", serde_json::to_string(&session)?)))
+}
+
+#[tool(
+    name = "ping_session",
+    description = "Check that a GDB session is alive without sending a command to GDB, so it \
+                   answers while the program runs: whether GDB and the program are alive, the \
+                   program is running and the number of live threads",
+    params(session_id = "The ID of the GDB session")
+)]
+pub async fn ping_session_tool(session_id: String) -> Result<ToolResponseContent> {
+    let ping = GDB_MANAGER.ping_session(&session_id).await?;
+    Ok(tool_text_content!(format!("Liveness: {}", serde_json::to_string(&ping)?)))
+}
+
+#[tool(name = "get_all_sessions", description = "Get all GDB debugging sessions", params())]
+pub async fn get_all_sessions_tool() -> Result<ToolResponseContent> {
+    let sessions = GDB_MANAGER.get_all_sessions().await?;
+    Ok(tool_text_content!(format!("Sessions: {}", serde_json::to_string(&sessions)?)))
+}
+
+#[tool(
+    name = "close_session",
+    description = "Close a GDB debugging session",
+    params(session_id = "The ID of the GDB session")
+)]
+pub async fn close_session_tool(session_id: String) -> Result<ToolResponseContent> {
+    GDB_MANAGER.close_session(&session_id).await?;
+    Ok(tool_text_content!("Closed GDB session".to_string()))
+}
+
+#[tool(
+    name = "restart_session",
+    description = "Restart GDB of a session with the same command line and set the GDB parameters \
+                   set so far again. The program has to be started again, breakpoints and \
+                   watches are lost",
+    params(session_id = "The ID of the GDB session")
+)]
+pub async fn restart_session_tool(session_id: String) -> Result<ToolResponseContent> {
+    let session = GDB_MANAGER.restart_session(&session_id).await?;
+    Ok(tool_text_content!(format!("Session: {}", serde_json::to_string(&session)?)))
+}
+
+#[tool(
+    name = "get_console_output",
+    description = "Get the messages GDB printed in a session, such as warnings, symbol loading \
+                   and the output of the program, from a ring buffer of the latest records",
+    params(
+        session_id = "The ID of the GDB session",
+        since_cursor = "the cursor returned by the previous call to only get newer output"
+    )
+)]
+pub async fn get_console_output_tool(
+    session_id: String,
+    since_cursor: Option<Arg<u64>>,
+) -> Result<ToolResponseContent> {
+    let since_cursor = opt_arg(since_cursor, "since_cursor")?;
+    let output = GDB_MANAGER.get_console_output(&session_id, since_cursor).await?;
+    Ok(tool_text_content!(format!("Console output: {}", serde_json::to_string(&output)?)))
+}
+
+#[tool(
+    name = "get_session_events",
+    description = "Get the async and stream records received from GDB in a session with the \
+                   time they were received: stops, library loads, thread creations and exits, \
+                   breakpoint changes and the console output. Pass the returned cursor as since \
+                   to only get the events received after the previous call",
+    params(
+        session_id = "The ID of the GDB session",
+        since = "if provided, cursor returned by a previous call, otherwise all logged events"
+    )
+)]
+pub async fn get_session_events_tool(
+    session_id: String,
+    since: Option<Arg<u64>>,
+) -> Result<ToolResponseContent> {
+    let events = GDB_MANAGER.get_session_events(&session_id, opt_arg(since, "since")?).await?;
+    Ok(tool_text_content!(format!("Events: {}", serde_json::to_string(&events)?)))
+}
+
+#[tool(
+    name = "get_output_stats",
+    description = "Get the number of out of band records of a session waiting to be handled and \
+                   the number of console, target and log records dropped because GDB output \
+                   them faster than they were handled",
+    params(session_id = "The ID of the GDB session")
+)]
+pub async fn get_output_stats_tool(session_id: String) -> Result<ToolResponseContent> {
+    let stats = GDB_MANAGER.get_output_stats(&session_id).await?;
+    Ok(tool_text_content!(format!("Output stats: {}", serde_json::to_string(&stats)?)))
+}
+
+#[tool(
+    name = "export_transcript",
+    description = "Export every MI command sent and record received in a session, as a \
+                   replayable GDB script or an annotated Markdown report",
+    params(
+        session_id = "The ID of the GDB session",
+        format = "gdb for a script of MI commands, markdown (default) for a report",
+        path = "if provided, write the transcript to this file instead of returning it"
+    )
+)]
+pub async fn export_transcript_tool(
+    session_id: String,
+    format: Option<String>,
+    path: Option<Arg<PathBuf>>,
+) -> Result<ToolResponseContent> {
+    let format = format.as_deref().unwrap_or("markdown").parse::<TranscriptFormat>()?;
+    let transcript = GDB_MANAGER.export_transcript(&session_id, format).await?;
+    match opt_arg(path, "path")? {
+        Some(path) => {
+            tokio::fs::write(&path, transcript).await?;
+            Ok(tool_text_content!(format!("Transcript written to {}", path.display())))
+        }
+        None => Ok(tool_text_content!(format!("Transcript:\n{}", transcript))),
+    }
+}
+
+#[tool(
+    name = "continue_until",
+    description = "Resume the program until a boolean expression holds and return the stop \
+                   state. With a location, a temporary breakpoint conditioned on the expression \
+                   is set there and the program continues, otherwise the program is stepped \
+                   line by line and the expression evaluated after each step",
+    params(
+        session_id = "The ID of the GDB session",
+        condition = "the expression to wait for, e.g. i == 42",
+        location = "where to check the condition, e.g. file.c:42, a function or *0x401000",
+        max_iterations = "maximum number of steps without a location, defaults to 100",
+        timeout_secs = "maximum time to wait, defaults to the command timeout"
+    )
+)]
+pub async fn continue_until_tool(
+    session_id: String,
+    condition: String,
+    location: Option<String>,
+    max_iterations: Option<Arg<usize>>,
+    timeout_secs: Option<Arg<u64>>,
+) -> Result<ToolResponseContent> {
+    let max_iterations = opt_arg(max_iterations, "max_iterations")?.unwrap_or(100);
+    let timeout = opt_arg(timeout_secs, "timeout_secs")?
+        .map_or(Duration::from_secs(config::get().command_timeout), Duration::from_secs);
+    let result = GDB_MANAGER
+        .continue_until(&session_id, &condition, location.as_deref(), max_iterations, timeout)
+        .await?;
+    Ok(tool_text_content!(format!("Continue until: {}", serde_json::to_string(&result)?)))
+}
+
+#[tool(
+    name = "finish_and_get_return_value",
+    description = "Resume the program until the function of the selected frame returns, and get \
+                   the value it returned along with the value history variable holding it, \
+                   e.g. $1. finished is false if the program stopped before, e.g. at a breakpoint",
+    params(
+        session_id = "The ID of the GDB session",
+        timeout_secs = "maximum time to wait, defaults to the command timeout"
+    )
+)]
+pub async fn finish_and_get_return_value_tool(
+    session_id: String,
+    timeout_secs: Option<Arg<u64>>,
+) -> Result<ToolResponseContent> {
+    let timeout = opt_arg(timeout_secs, "timeout_secs")?
+        .map_or(Duration::from_secs(config::get().command_timeout), Duration::from_secs);
+    let result = GDB_MANAGER.finish(&session_id, timeout).await?;
+    Ok(tool_text_content!(format!("Finished: {}", serde_json::to_string(&result)?)))
+}
+
+#[tool(
+    name = "until_return_of",
+    description = "Resume the program until the function of a stack frame returns, finishing the \
+                   functions of the inner frames one by one, and get the value each of them \
+                   returned. returned is false if the program stopped before, e.g. at a \
+                   breakpoint",
+    params(
+        session_id = "The ID of the GDB session",
+        frame_id = "The ID of the stack frame to return from, 0 is the topest frame",
+        timeout_secs = "maximum time to wait in total, defaults to the command timeout"
+    )
+)]
+pub async fn until_return_of_tool(
+    session_id: String,
+    frame_id: Arg<usize>,
+    timeout_secs: Option<Arg<u64>>,
+) -> Result<ToolResponseContent> {
+    let frame_id = frame_id.get("frame_id")?;
+    let timeout = opt_arg(timeout_secs, "timeout_secs")?
+        .map_or(Duration::from_secs(config::get().command_timeout), Duration::from_secs);
+    let result = GDB_MANAGER.until_return_of(&session_id, frame_id, timeout).await?;
+    Ok(tool_text_content!(format!("Returned: {}", serde_json::to_string(&result)?)))
+}
+
+#[tool(
+    name = "call_function",
+    description = "Call a function of the program, e.g. a debug dump helper, and return the value \
+                   it returned. The call runs code in the program which may change its state, so \
+                   allow_side_effects must be set. The frame is restored if the call crashes",
+    params(
+        session_id = "The ID of the GDB session",
+        function = "the function to call",
+        args = "argument expressions, e.g. [\"list\", \"0\"]",
+        allow_side_effects = "must be true, acknowledges that the call may change the program state",
+        timeout_secs = "abandon the call after this time, defaults to the command timeout"
+    )
+)]
+pub async fn call_function_tool(
+    session_id: String,
+    function: String,
+    args: Option<Arg<Vec<String>>>,
+    allow_side_effects: Arg<bool>,
+    timeout_secs: Option<Arg<u64>>,
+) -> Result<ToolResponseContent> {
+    if !allow_side_effects.get("allow_side_effects")? {
+        return Err(AppError::InvalidArgument(
+            "calling a function may change the program state, set allow_side_effects to call it"
+                .to_string(),
+        )
+        .into());
+    }
+    let args = opt_arg(args, "args")?.unwrap_or_default();
+    let timeout = opt_arg(timeout_secs, "timeout_secs")?
+        .map_or(Duration::from_secs(config::get().command_timeout), Duration::from_secs);
+    let value = GDB_MANAGER.call_function(&session_id, &function, &args, timeout).await?;
+    Ok(tool_text_content!(format!("Returned: {}", value)))
+}
+
+#[tool(
+    name = "run_python",
+    description = "Run Python code in the GDB embedded interpreter and return what it printed, \
+                   e.g. to use the gdb module or the helpers of GEF or pwndbg. Variables are kept \
+                   between runs, an uncaught exception prints its traceback",
+    params(session_id = "The ID of the GDB session", code = "the Python code, may span lines")
+)]
+pub async fn run_python_tool(session_id: String, code: String) -> Result<ToolResponseContent> {
+    let output = GDB_MANAGER.run_python(&session_id, &code).await?;
+    Ok(tool_text_content!(format!("Output: {}", output)))
+}
+
+#[tool(
+    name = "trace_calls",
+    description = "Run or continue the program and record the arguments and return value of \
+                   every call to the given functions, like ltrace, until the program exits or \
+                   stops for another reason",
+    params(
+        session_id = "The ID of the GDB session",
+        functions = "names of the functions to trace",
+        max_calls = "stop tracing after this many calls, defaults to 100",
+        timeout_secs = "maximum time to trace, defaults to the command timeout"
+    )
+)]
+pub async fn trace_calls_tool(
+    session_id: String,
+    functions: Arg<Vec<String>>,
+    max_calls: Option<Arg<usize>>,
+    timeout_secs: Option<Arg<u64>>,
+) -> Result<ToolResponseContent> {
+    let functions = functions.get("functions")?;
+    let max_calls = opt_arg(max_calls, "max_calls")?.unwrap_or(100);
+    let timeout = opt_arg(timeout_secs, "timeout_secs")?
+        .map_or(Duration::from_secs(config::get().command_timeout), Duration::from_secs);
+    let trace = GDB_MANAGER.trace_calls(&session_id, &functions, max_calls, timeout).await?;
+    Ok(tool_text_content!(format!("Call trace: {}", serde_json::to_string(&trace)?)))
+}
+
+#[tool(
+    name = "start_recording",
+    description = "Start recording the execution of the program in a session to later see how \
+                   it got where it stopped with get_function_call_history and \
+                   get_instruction_history. btrace uses the branch trace of the CPU, Intel PT \
+                   if supported, else BTS, full records every instruction in GDB, slowly",
+    params(session_id = "The ID of the GDB session", method = "btrace (default), pt, bts or full")
+)]
+pub async fn start_recording_tool(
+    session_id: String,
+    method: Option<String>,
+) -> Result<ToolResponseContent> {
+    let method = method.as_deref().unwrap_or("btrace").parse::<RecordMethod>()?;
+    let status = GDB_MANAGER.start_recording(&session_id, method).await?;
+    Ok(tool_text_content!(format!("Recording: {}", serde_json::to_string(&status)?)))
+}
+
+#[tool(
+    name = "stop_recording",
+    description = "Stop recording the execution of the program in a session, the recorded \
+                   history is discarded",
+    params(session_id = "The ID of the GDB session")
+)]
+pub async fn stop_recording_tool(session_id: String) -> Result<ToolResponseContent> {
+    GDB_MANAGER.stop_recording(&session_id).await?;
+    Ok(tool_text_content!("Stopped recording".to_string()))
+}
+
+#[tool(
+    name = "get_function_call_history",
+    description = "Get the functions the recorded execution went through, numbered from 1 for \
+                   the oldest, each with its call level, its range of instructions and of source \
+                   lines. A function is listed again each time the execution returns to it. \
+                   Needs btrace recording",
+    params(
+        session_id = "The ID of the GDB session",
+        from = "the first function number, defaults to 50 before the end",
+        to = "the last function number, defaults to the last recorded"
+    )
+)]
+pub async fn get_function_call_history_tool(
+    session_id: String,
+    from: Option<Arg<u64>>,
+    to: Option<Arg<u64>>,
+) -> Result<ToolResponseContent> {
+    let from = opt_arg(from, "from")?;
+    let to = opt_arg(to, "to")?;
+    let history = GDB_MANAGER.function_call_history(&session_id, from, to).await?;
+    Ok(tool_text_content!(format!("Function call history: {}", serde_json::to_string(&history)?)))
+}
+
+#[tool(
+    name = "get_instruction_history",
+    description = "Get the instructions of the recorded execution, numbered from 1 for the \
+                   oldest, with their address, symbol and disassembly",
+    params(
+        session_id = "The ID of the GDB session",
+        from = "the first instruction number, defaults to 50 before the end",
+        to = "the last instruction number, defaults to the last recorded"
+    )
+)]
+pub async fn get_instruction_history_tool(
+    session_id: String,
+    from: Option<Arg<u64>>,
+    to: Option<Arg<u64>>,
+) -> Result<ToolResponseContent> {
+    let from = opt_arg(from, "from")?;
+    let to = opt_arg(to, "to")?;
+    let history = GDB_MANAGER.instruction_history(&session_id, from, to).await?;
+    Ok(tool_text_content!(format!("Instruction history: {}", serde_json::to_string(&history)?)))
+}
+
+#[tool(
+    name = "continue_and_sample",
+    description = "Profile the program in a session without perf: it is run or continued for \
+                   duration_secs, interrupted every interval_ms to sample its backtrace and \
+                   resumed. Returns the functions by share of the samples they were on the \
+                   stack, with their own samples, and the most sampled stacks folded as for a \
+                   flame graph. The program is left running unless it stopped on its own",
+    params(
+        session_id = "The ID of the GDB session",
+        duration_secs = "how long to sample",
+        interval_ms = "the time between two samples, 100 by default",
+        depth = "the innermost frames kept per sample, 64 by default"
+    )
+)]
+pub async fn continue_and_sample_tool(
+    session_id: String,
+    duration_secs: Arg<u64>,
+    interval_ms: Option<Arg<u64>>,
+    depth: Option<Arg<usize>>,
+) -> Result<ToolResponseContent> {
+    let duration = Duration::from_secs(duration_secs.get("duration_secs")?);
+    let interval = Duration::from_millis(opt_arg(interval_ms, "interval_ms")?.unwrap_or(100));
+    let depth = opt_arg(depth, "depth")?.unwrap_or(64);
+    let profile = GDB_MANAGER.continue_and_sample(&session_id, duration, interval, depth).await?;
+    Ok(tool_text_content!(format!("Profile: {}", serde_json::to_string(&profile)?)))
+}
+
+#[tool(
+    name = "collect_coverage",
+    description = "Set a temporary breakpoint on every function, or every line of a source \
+                   file, run or continue the program until it exits and report which were \
+                   reached, e.g. to check whether an input reaches a function",
+    params(
+        session_id = "The ID of the GDB session",
+        mode = "functions (default) or lines",
+        file = "source file to cover, required for lines",
+        name_regexp = "only cover the functions matching this regular expression",
+        timeout_secs = "maximum time to run, defaults to the command timeout"
+    )
+)]
+pub async fn collect_coverage_tool(
+    session_id: String,
+    mode: Option<String>,
+    file: Option<String>,
+    name_regexp: Option<String>,
+    timeout_secs: Option<Arg<u64>>,
+) -> Result<ToolResponseContent> {
+    let mode = mode.as_deref().unwrap_or("functions").parse::<CoverageMode>()?;
+    let timeout = opt_arg(timeout_secs, "timeout_secs")?
+        .map_or(Duration::from_secs(config::get().command_timeout), Duration::from_secs);
+    let coverage = GDB_MANAGER
+        .collect_coverage(&session_id, mode, file.as_deref(), name_regexp.as_deref(), timeout)
+        .await?;
+    Ok(tool_text_content!(format!("Coverage: {}", serde_json::to_string(&coverage)?)))
+}
+
+#[tool(
+    name = "run_debug_plan",
+    description = "Run an ordered list of debugging operations in a session and return the \
+                   result of each, e.g. set a breakpoint, run, wait for the stop and capture the \
+                   locals in a single call. Operations are objects with an `op` field: \
+                   set_breakpoint {file, line}, delete_breakpoints {breakpoints}, breakpoints, \
+                   run, continue, step, next, interrupt, wait_for_stop {timeout_secs}, \
+                   backtrace, locals {frame_id}, registers {reg_list}, \
+                   read_memory {address, count, offset}, evaluate {expression}. Follow run and \
+                   continue with wait_for_stop before inspecting the program",
+    params(
+        session_id = "The ID of the GDB session",
+        steps = "the operations to run in order",
+        keep_going = "continue with the next operations after one fails, false by default"
+    )
+)]
+pub async fn run_debug_plan_tool(
+    session_id: String,
+    steps: Vec<PlanStep>,
+    keep_going: Option<Arg<bool>>,
+) -> Result<ToolResponseContent> {
+    let keep_going = opt_arg(keep_going, "keep_going")?.unwrap_or(false);
+    let results = GDB_MANAGER.run_debug_plan(&session_id, steps, keep_going).await?;
+    Ok(tool_text_content!(format!("Plan results: {}", serde_json::to_string(&results)?)))
+}
+
+#[tool(
+    name = "wait_for_stop",
+    description = "Wait until the program in a session stops, e.g. at a breakpoint after \
+                   continue_execution, and return the stop reason, the frame and the watch \
+                   expressions which changed. With context, the innermost frames, the locals, \
+                   the changed registers and the memory around $sp and $pc are added",
+    params(
+        session_id = "The ID of the GDB session",
+        timeout_secs = "how long to wait, defaults to the command timeout",
+        context = "add the context of the stop, as set in the configuration by default"
+    )
+)]
+pub async fn wait_for_stop_tool(
+    session_id: String,
+    timeout_secs: Option<Arg<u64>>,
+    context: Option<Arg<bool>>,
+) -> Result<ToolResponseContent> {
+    let timeout = opt_arg(timeout_secs, "timeout_secs")?
+        .map_or(Duration::from_secs(config::get().command_timeout), Duration::from_secs);
+    let config = &config::get().stop_context;
+    let context = opt_arg(context, "context")?.unwrap_or(config.enabled);
+    let mut stopped = GDB_MANAGER.wait_until_stopped(&session_id, timeout).await?;
+    if let Some(stopped) = stopped.as_object_mut() {
+        if !context {
+            stopped.remove("context");
+        } else if !stopped.contains_key("context") {
+            let context = GDB_MANAGER.stop_context(&session_id, config).await;
+            stopped.insert("context".to_string(), serde_json::to_value(context)?);
+        }
+    }
+    Ok(tool_text_content!(format!("Stopped: {}", serde_json::to_string(&stopped)?)))
+}
+
+#[tool(
+    name = "add_watch_expression",
+    description = "Watch an expression in a session, it is re-evaluated in the selected frame on \
+                   every stop and the changed values are reported by wait_for_stop in \
+                   watch_changes",
+    params(session_id = "The ID of the GDB session", expression = "the expression to watch")
+)]
+pub async fn add_watch_expression_tool(
+    session_id: String,
+    expression: String,
+) -> Result<ToolResponseContent> {
+    let watch = GDB_MANAGER.add_watch_expression(&session_id, &expression).await?;
+    Ok(tool_text_content!(format!("Watch expression: {}", serde_json::to_string(&watch)?)))
+}
+
+#[tool(
+    name = "remove_watch_expression",
+    description = "Stop watching an expression in a session",
+    params(
+        session_id = "The ID of the GDB session",
+        name = "the name returned by add_watch_expression"
+    )
+)]
+pub async fn remove_watch_expression_tool(
+    session_id: String,
+    name: String,
+) -> Result<ToolResponseContent> {
+    GDB_MANAGER.remove_watch_expression(&session_id, &name).await?;
+    Ok(tool_text_content!(format!("Removed watch expression {}", name)))
+}
+
+#[tool(
+    name = "watch_memory_region",
+    description = "Watch a region of memory in a session without a hardware watchpoint, it is \
+                   read again on every stop and the regions whose contents changed are reported \
+                   by wait_for_stop in memory_changes. The address is evaluated once",
+    params(
+        session_id = "The ID of the GDB session",
+        address = "the start of the region, a number or an expression such as &buffer",
+        length = "the length of the region in bytes, at most 65536"
+    )
+)]
+pub async fn watch_memory_region_tool(
+    session_id: String,
+    address: String,
+    length: Arg<usize>,
+) -> Result<ToolResponseContent> {
+    let length = length.get("length")?;
+    let watch = GDB_MANAGER.watch_memory_region(&session_id, &address, length).await?;
+    Ok(tool_text_content!(format!("Memory watch: {}", serde_json::to_string(&watch)?)))
+}
+
+#[tool(
+    name = "unwatch_memory_region",
+    description = "Stop watching a region of memory in a session",
+    params(
+        session_id = "The ID of the GDB session",
+        id = "the ID returned by watch_memory_region"
+    )
+)]
+pub async fn unwatch_memory_region_tool(
+    session_id: String,
+    id: Arg<u64>,
+) -> Result<ToolResponseContent> {
+    let id = id.get("id")?;
+    GDB_MANAGER.unwatch_memory_region(&session_id, id).await?;
+    Ok(tool_text_content!(format!("Removed memory watch {}", id)))
+}
+
+#[tool(
+    name = "start_debugging",
+    description = "Start debugging in a session",
+    params(session_id = "The ID of the GDB session")
+)]
+pub async fn start_debugging_tool(session_id: String) -> Result<ToolResponseContent> {
+    let ret = GDB_MANAGER.start_debugging(&session_id).await?;
+    Ok(tool_text_content!(format!("Started debugging: {}", ret)))
+}
+
+#[tool(
+    name = "start_program",
+    description = "Run the program until it reaches main, or another symbol, as the start \
+                   command of GDB does, and return the stop with its context: the innermost \
+                   frames, the locals, the registers and the memory around $sp and $pc. \
+                   reached is false if the program stopped before, e.g. at a breakpoint, or \
+                   exited",
+    params(
+        session_id = "The ID of the GDB session",
+        symbol = "where to stop, defaults to main",
+        timeout_secs = "maximum time to wait, defaults to the command timeout"
+    )
+)]
+pub async fn start_program_tool(
+    session_id: String,
+    symbol: Option<String>,
+    timeout_secs: Option<Arg<u64>>,
+) -> Result<ToolResponseContent> {
+    let timeout = opt_arg(timeout_secs, "timeout_secs")?
+        .map_or(Duration::from_secs(config::get().command_timeout), Duration::from_secs);
+    let started = GDB_MANAGER.start_program(&session_id, symbol.as_deref(), timeout).await?;
+    Ok(tool_text_content!(format!("Started: {}", serde_json::to_string(&started)?)))
+}
+
+#[tool(
+    name = "stop_debugging",
+    description = "Stop debugging in a session",
+    params(session_id = "The ID of the GDB session")
+)]
+pub async fn stop_debugging_tool(session_id: String) -> Result<ToolResponseContent> {
+    let ret = GDB_MANAGER.stop_debugging(&session_id).await?;
+    Ok(tool_text_content!(format!("Stopped debugging: {}", ret)))
+}
+
+#[tool(
+    name = "get_breakpoints",
+    description = "Get all breakpoints in the current GDB session, with the times each was hit \
+                   and the hits left to ignore",
+    params(
+        session_id = "The ID of the GDB session",
+        format = "json (default), or markdown for a table to show to the user"
+    )
+)]
+pub async fn get_breakpoints_tool(
+    session_id: String,
+    format: Option<String>,
+) -> Result<ToolResponseContent> {
+    let format = format.map(|format| format.parse::<OutputFormat>()).transpose()?;
+    let breakpoints = GDB_MANAGER.get_breakpoints(&session_id).await?;
+    Ok(tool_text_content!(match format.unwrap_or_default() {
+        OutputFormat::Json => format!("Breakpoints: {}", serde_json::to_string(&breakpoints)?),
+        OutputFormat::Markdown =>
+            format!("Breakpoints:\n\n{}", markdown::breakpoints(&breakpoints)),
+    }))
+}
+
+#[tool(
+    name = "set_breakpoint",
+    description = "Set a breakpoint in the code. Optional actions are run by the server every \
+                   time it is hit, before wait_for_stop returns the stop, each an operation of \
+                   run_debug_plan, e.g. evaluate {expression}, read_memory {address, count}, \
+                   backtrace, continue. A stop followed by continue is not returned by \
+                   wait_for_stop. The results are collected by get_breakpoint_hits",
+    params(
+        session_id = "The ID of the GDB session",
+        file = "Source file path",
+        line = "Line number",
+        actions = "the operations to run in order when the breakpoint is hit"
+    )
+)]
+pub async fn set_breakpoint_tool(
+    session_id: String,
+    file: String,
+    line: Arg<usize>,
+    actions: Option<Vec<PlanStep>>,
+) -> Result<ToolResponseContent> {
+    let line = line.get("line")?;
+    let breakpoint = GDB_MANAGER.set_breakpoint(&session_id, &PathBuf::from(file), line).await?;
+    if let Some(actions) = actions {
+        GDB_MANAGER
+            .set_breakpoint_actions(&session_id, &breakpoint.number.to_string(), actions)
+            .await?;
+    }
+    Ok(tool_text_content!(format!("Set breakpoint: {}", serde_json::to_string(&breakpoint)?)))
+}
+
+#[tool(
+    name = "get_breakpoint_hits",
+    description = "Get the results of the actions run when breakpoints set with actions were \
+                   hit, oldest first",
+    params(
+        session_id = "The ID of the GDB session",
+        breakpoint = "if provided, only the hits of this breakpoint number",
+        clear = "remove the returned hits, false by default"
+    )
+)]
+pub async fn get_breakpoint_hits_tool(
+    session_id: String,
+    breakpoint: Option<String>,
+    clear: Option<Arg<bool>>,
+) -> Result<ToolResponseContent> {
+    let clear = opt_arg(clear, "clear")?.unwrap_or(false);
+    let hits = GDB_MANAGER.get_breakpoint_hits(&session_id, breakpoint.as_deref(), clear).await?;
+    Ok(tool_text_content!(format!("Breakpoint hits: {}", serde_json::to_string(&hits)?)))
+}
+
+#[tool(
+    name = "run_to_hit_count",
+    description = "Continue the program until a breakpoint is hit for the given number of times, \
+                   the hits before are ignored by GDB without stopping. reached is false if the \
+                   program stopped before, e.g. at another breakpoint",
+    params(
+        session_id = "The ID of the GDB session",
+        breakpoint = "The number of the breakpoint",
+        hits = "the total number of hits to stop at, e.g. 37 to stop at its 37th hit",
+        timeout_secs = "maximum time to wait, defaults to the command timeout"
+    )
+)]
+pub async fn run_to_hit_count_tool(
+    session_id: String,
+    breakpoint: String,
+    hits: Arg<u32>,
+    timeout_secs: Option<Arg<u64>>,
+) -> Result<ToolResponseContent> {
+    let breakpoint = breakpoint.parse::<BreakPointNumber>()?;
+    let hits = hits.get("hits")?;
+    let timeout = opt_arg(timeout_secs, "timeout_secs")?
+        .map_or(Duration::from_secs(config::get().command_timeout), Duration::from_secs);
+    let result = GDB_MANAGER.run_to_hit_count(&session_id, breakpoint, hits, timeout).await?;
+    Ok(tool_text_content!(format!("Hit count: {}", serde_json::to_string(&result)?)))
+}
+
+#[tool(
+    name = "delete_breakpoint",
+    description = "Delete one or more breakpoints in the code",
+    params(
+        session_id = "The ID of the GDB session",
+        breakpoints = "The array of the breakpoint numbers to delete"
+    )
+)]
+pub async fn delete_breakpoint_tool(
+    session_id: String,
+    breakpoints: Arg<Vec<String>>,
+) -> Result<ToolResponseContent> {
+    let breakpoints = breakpoints.get("breakpoints")?;
+    GDB_MANAGER.delete_breakpoint(&session_id, breakpoints).await?;
+    Ok(tool_text_content!("Breakpoints deleted".to_string()))
+}
+
+#[tool(
+    name = "get_stack_frames",
+    description = "Get stack frames in the current GDB session, only the innermost max_frames \
+                   frames are counted and listed, with a truncated marker when the outer frames \
+                   are left out",
+    params(
+        session_id = "The ID of the GDB session",
+        max_frames = "The number of frames to list at most, defaults to 256",
+        format = "json (default), or markdown for a table to show to the user"
+    )
+)]
+pub async fn get_stack_frames_tool(
+    session_id: String,
+    max_frames: Option<Arg<usize>>,
+    format: Option<String>,
+) -> Result<ToolResponseContent> {
+    let max_frames = opt_arg(max_frames, "max_frames")?;
+    let format = format.map(|format| format.parse::<OutputFormat>()).transpose()?;
+    let backtrace = GDB_MANAGER.get_backtrace(&session_id, max_frames).await?;
+    Ok(tool_text_content!(match format.unwrap_or_default() {
+        OutputFormat::Json => format!("Stack frames: {}", serde_json::to_string(&backtrace)?),
+        OutputFormat::Markdown => {
+            let mut text =
+                format!("Stack frames:\n\n{}", markdown::stack_frames(&backtrace.frames));
+            if let Some(truncated) = backtrace.truncated {
+                text.push_str(&format!("\n_{}_", truncated));
+            }
+            text
+        }
+    }))
+}
+
+#[tool(
+    name = "demangle",
+    description = "Demangle Rust and C++ symbol names, names which are not mangled are returned \
+                   as is",
+    params(names = "the symbol names, e.g. [\"_ZN3foo3barE\"]")
+)]
+pub async fn demangle_tool(names: Arg<Vec<String>>) -> Result<ToolResponseContent> {
+    let names = names.get("names")?.iter().map(|name| demangle_or_keep(name)).collect::<Vec<_>>();
+    Ok(tool_text_content!(format!("Demangled: {}", serde_json::to_string(&names)?)))
+}
+
+#[tool(
+    name = "add_symbol_file",
+    description = "Load the symbols of a file, e.g. the detached debug file of a stripped binary \
+                   found with find_debug_files. Returns what GDB printed",
+    params(
+        session_id = "The ID of the GDB session",
+        path = "path of the symbol file",
+        text_addr = "address of the .text section of the object in memory, needed if it is \
+                     not loaded at its link address, e.g. a shared library or a PIE"
+    )
+)]
+pub async fn add_symbol_file_tool(
+    session_id: String,
+    path: Arg<PathBuf>,
+    text_addr: Option<String>,
+) -> Result<ToolResponseContent> {
+    let path = path.get("path")?;
+    let output = GDB_MANAGER.add_symbol_file(&session_id, &path, text_addr.as_deref()).await?;
+    Ok(tool_text_content!(format!("Symbol file added: {}", output)))
+}
+
+#[tool(
+    name = "find_debug_files",
+    description = "Tell whether a binary has debug information and where its detached debug \
+                   file may be, by build ID and .gnu_debuglink in /usr/lib/debug and next to the \
+                   binary. The first candidate which exists is returned as found, to load with \
+                   add_symbol_file",
+    params(
+        session_id = "The ID of the GDB session",
+        object = "path of the binary or library, the program of the session by default",
+        search_dirs = "more debug directories to look into, e.g. [\"/opt/app/debug\"]"
+    )
+)]
+pub async fn find_debug_files_tool(
+    session_id: String,
+    object: Option<Arg<PathBuf>>,
+    search_dirs: Option<Arg<Vec<String>>>,
+) -> Result<ToolResponseContent> {
+    let object = opt_arg(object, "object")?;
+    let search_dirs = opt_arg(search_dirs, "search_dirs")?
+        .unwrap_or_default()
+        .into_iter()
+        .map(PathBuf::from)
+        .collect::<Vec<_>>();
+    let debug_files = GDB_MANAGER.find_debug_files(&session_id, object, &search_dirs).await?;
+    Ok(tool_text_content!(format!("Debug files: {}", serde_json::to_string(&debug_files)?)))
+}
+
+#[tool(
+    name = "get_loaded_modules",
+    description = "List the program and the shared libraries loaded, with the address range of \
+                   their .text section in memory and their load bias, the runtime minus the link \
+                   address, to translate the static addresses of disassemblers like IDA or \
+                   Ghidra. The bias of the program is only known once it runs",
+    params(session_id = "The ID of the GDB session")
+)]
+pub async fn get_loaded_modules_tool(session_id: String) -> Result<ToolResponseContent> {
+    let modules = GDB_MANAGER.get_loaded_modules(&session_id).await?;
+    Ok(tool_text_content!(format!("Loaded modules: {}", serde_json::to_string(&modules)?)))
+}
+
+#[tool(
+    name = "rebase_address",
+    description = "Translate an address between the running program and its binaries, in either \
+                   direction: a runtime address to the module it belongs to with its static \
+                   address and file offset, or a static address or file offset, e.g. from \
+                   objdump, IDA or Ghidra, to where it is in memory once slid by ASLR. Returns \
+                   all of them with the load bias and the section",
+    params(
+        session_id = "The ID of the GDB session",
+        address = "the address, a runtime one may be an expression such as $pc",
+        kind = "what the address is: runtime (default), static or offset in the file",
+        module = "the module of a static address or offset, the path or file name of a \
+                  library, e.g. libc.so.6, the program by default"
+    )
+)]
+pub async fn rebase_address_tool(
+    session_id: String,
+    address: String,
+    kind: Option<String>,
+    module: Option<String>,
+) -> Result<ToolResponseContent> {
+    let kind = kind.as_deref().unwrap_or("runtime").parse::<AddressKind>()?;
+    let rebased =
+        GDB_MANAGER.rebase_address(&session_id, &address, kind, module.as_deref()).await?;
+    Ok(tool_text_content!(format!("Rebased address: {}", serde_json::to_string(&rebased)?)))
+}
+
+#[tool(
+    name = "import_annotations",
+    description = "Import names and comments of addresses from a static analysis, e.g. the \
+                   functions and labels exported from Ghidra or IDA. They are shown along the \
+                   addresses of the disassembly, the backtraces and the stack memory. Give the \
+                   annotations or a JSON file of them, as a list of {address, name, comment, \
+                   size, module}, the addresses inside a sized one are shown as name+offset. \
+                   Returns the number of annotations of the session",
+    params(
+        session_id = "The ID of the GDB session",
+        annotations = "the annotations, addresses in hexadecimal",
+        path = "path of a JSON file with the annotations",
+        module = "the module the addresses are static ones of, the path or file name of the \
+                  program or a library, they are shown where it is loaded. Runtime addresses \
+                  by default, an annotation may give its own module",
+        replace = "remove the annotations imported before, false by default"
+    )
+)]
+pub async fn import_annotations_tool(
+    session_id: String,
+    annotations: Option<Vec<Annotation>>,
+    path: Option<Arg<PathBuf>>,
+    module: Option<String>,
+    replace: Option<Arg<bool>>,
+) -> Result<ToolResponseContent> {
+    let mut annotations = annotations.unwrap_or_default();
+    if let Some(path) = opt_arg(path, "path")? {
+        let text = tokio::fs::read_to_string(&path).await?;
+        annotations.extend(serde_json::from_str::<Vec<Annotation>>(&text)?);
+    }
+    if annotations.is_empty() {
+        return Err(AppError::InvalidArgument(
+            "give the annotations or a file of them".to_string(),
+        )
+        .into());
+    }
+    let replace = opt_arg(replace, "replace")?.unwrap_or(false);
+    let count = GDB_MANAGER
+        .import_annotations(&session_id, annotations, module.as_deref(), replace)
+        .await?;
+    Ok(tool_text_content!(format!("Annotations imported, {} in the session", count)))
+}
+
+#[tool(
+    name = "export_annotations",
+    description = "Export the annotations of a session as they were imported, the static \
+                   addresses of a module along with it, in the format import_annotations takes",
+    params(
+        session_id = "The ID of the GDB session",
+        path = "path of a JSON file to write them to instead of returning them"
     )
 )]
-pub async fn create_session_tool(
-    program: Option<PathBuf>,
-    nh: Option<bool>,
-    nx: Option<bool>,
-    quiet: Option<bool>,
-    cd: Option<PathBuf>,
-    bps: Option<u32>,
-    symbol_file: Option<PathBuf>,
-    core_file: Option<PathBuf>,
-    proc_id: Option<u32>,
-    command: Option<PathBuf>,
-    source_dir: Option<PathBuf>,
-    args: Option<Vec<OsString>>,
-    tty: Option<PathBuf>,
-    gdb_path: Option<PathBuf>,
+pub async fn export_annotations_tool(
+    session_id: String,
+    path: Option<Arg<PathBuf>>,
 ) -> Result<ToolResponseContent> {
-    let session = GDB_MANAGER
-        .create_session(
-            program,
-            nh,
-            nx,
-            quiet,
-            cd,
-            bps,
-            symbol_file,
-            core_file,
-            proc_id,
-            command,
-            source_dir,
-            args,
-            tty,
-            gdb_path,
-        )
-        .await?;
-    Ok(tool_text_content!(format!("Created GDB session: {}", session)))
+    let annotations = GDB_MANAGER.export_annotations(&session_id).await?;
+    match opt_arg(path, "path")? {
+        Some(path) => {
+            tokio::fs::write(&path, serde_json::to_string_pretty(&annotations)?).await?;
+            Ok(tool_text_content!(format!(
+                "{} annotations written to {}",
+                annotations.len(),
+                path.display()
+            )))
+        }
+        None => {
+            Ok(tool_text_content!(format!("Annotations: {}", serde_json::to_string(&annotations)?)))
+        }
+    }
 }
 
 #[tool(
-    name = "get_session",
-    description = "Get a GDB debugging session by ID",
+    name = "get_process_info",
+    description = "Get the PID, command line, working directory and executable of the debugged \
+                   process, to confirm it is the expected one. For a process running on this \
+                   host, its state, thread count, memory usage and open files are included",
     params(session_id = "The ID of the GDB session")
 )]
-pub async fn get_session_tool(session_id: String) -> Result<ToolResponseContent> {
-    let session = GDB_MANAGER.get_session(&session_id).await?;
-    Ok(tool_text_content!(format!("Session: {}", serde_json::to_string(&session)?)))
+pub async fn get_process_info_tool(session_id: String) -> Result<ToolResponseContent> {
+    let info = GDB_MANAGER.get_process_info(&session_id).await?;
+    Ok(tool_text_content!(format!("Process: {}", serde_json::to_string(&info)?)))
 }
 
-#[tool(name = "get_all_sessions", description = "Get all GDB debugging sessions", params())]
-pub async fn get_all_sessions_tool() -> Result<ToolResponseContent> {
-    let sessions = GDB_MANAGER.get_all_sessions().await?;
-    Ok(tool_text_content!(format!("Sessions: {}", serde_json::to_string(&sessions)?)))
+#[tool(
+    name = "list_processes",
+    description = "List the processes of the target the session could attach to, with their PID, \
+                   user and command line. Pass a PID as proc_id to create_session to attach to it",
+    params(
+        session_id = "The ID of the GDB session",
+        name = "if provided, only list processes whose command line contains it, ignoring case"
+    )
+)]
+pub async fn list_processes_tool(
+    session_id: String,
+    name: Option<String>,
+) -> Result<ToolResponseContent> {
+    let processes = GDB_MANAGER.list_processes(&session_id, name.as_deref()).await?;
+    Ok(tool_text_content!(format!("Processes: {}", serde_json::to_string(&processes)?)))
 }
 
 #[tool(
-    name = "close_session",
-    description = "Close a GDB debugging session",
+    name = "list_threads",
+    description = "List the threads of the program. With the RTOS awareness enabled, the tasks \
+                   of the RTOS are listed in rtos_threads with their state, priority and stack \
+                   high-water mark",
     params(session_id = "The ID of the GDB session")
 )]
-pub async fn close_session_tool(session_id: String) -> Result<ToolResponseContent> {
-    GDB_MANAGER.close_session(&session_id).await?;
-    Ok(tool_text_content!("Closed GDB session".to_string()))
+pub async fn list_threads_tool(session_id: String) -> Result<ToolResponseContent> {
+    let threads = GDB_MANAGER.list_threads(&session_id).await?;
+    Ok(tool_text_content!(format!("Threads: {}", serde_json::to_string(&threads)?)))
 }
 
 #[tool(
-    name = "start_debugging",
-    description = "Start debugging in a session",
+    name = "get_thread_table",
+    description = "Get the threads and processes of a session as announced by GDB, including \
+                   those which exited and when, without interrupting the program",
     params(session_id = "The ID of the GDB session")
 )]
-pub async fn start_debugging_tool(session_id: String) -> Result<ToolResponseContent> {
-    let ret = GDB_MANAGER.start_debugging(&session_id).await?;
-    Ok(tool_text_content!(format!("Started debugging: {}", ret)))
+pub async fn get_thread_table_tool(session_id: String) -> Result<ToolResponseContent> {
+    let table = GDB_MANAGER.get_thread_table(&session_id).await?;
+    Ok(tool_text_content!(format!("Threads: {}", serde_json::to_string(&table)?)))
 }
 
 #[tool(
-    name = "stop_debugging",
-    description = "Stop debugging in a session",
+    name = "analyze_deadlock",
+    description = "Find the threads of a session deadlocked on pthread mutexes: the program is \
+                   interrupted if running, the backtraces of all threads are collected, the \
+                   owner of the mutex each blocked thread waits for is read and the cycle of \
+                   threads waiting for each other is reported if there is one. Linux and glibc \
+                   only",
     params(session_id = "The ID of the GDB session")
 )]
-pub async fn stop_debugging_tool(session_id: String) -> Result<ToolResponseContent> {
-    let ret = GDB_MANAGER.stop_debugging(&session_id).await?;
-    Ok(tool_text_content!(format!("Stopped debugging: {}", ret)))
+pub async fn analyze_deadlock_tool(session_id: String) -> Result<ToolResponseContent> {
+    let report = GDB_MANAGER.analyze_deadlock(&session_id).await?;
+    Ok(tool_text_content!(format!("Deadlock analysis: {}", serde_json::to_string(&report)?)))
 }
 
 #[tool(
-    name = "get_breakpoints",
-    description = "Get all breakpoints in the current GDB session",
-    params(session_id = "The ID of the GDB session")
+    name = "set_rtos",
+    description = "Enable the awareness of the RTOS running on a bare metal target: its tasks are \
+                   walked from the kernel structures and listed by list_threads",
+    params(
+        session_id = "The ID of the GDB session",
+        rtos = "freertos, zephyr, or off to disable it",
+        symbols = "if provided, overrides of the symbol and type names as role=symbol, e.g. \
+            [\"tcb_type=tskTCB\"]. FreeRTOS roles: current, ready, delayed1, delayed2, \
+            pending, suspended, terminating, tcb_type, list_item_type. Zephyr roles: kernel, \
+            thread_type",
+    )
 )]
-pub async fn get_breakpoints_tool(session_id: String) -> Result<ToolResponseContent> {
-    let breakpoints = GDB_MANAGER.get_breakpoints(&session_id).await?;
-    Ok(tool_text_content!(format!("Breakpoints: {}", serde_json::to_string(&breakpoints)?)))
+pub async fn set_rtos_tool(
+    session_id: String,
+    rtos: String,
+    symbols: Option<Arg<Vec<String>>>,
+) -> Result<ToolResponseContent> {
+    let symbols = opt_arg(symbols, "symbols")?.unwrap_or_default();
+    let state = match rtos.as_str() {
+        "off" => None,
+        kind => Some(RtosState::new(kind.parse::<RtosKind>()?, &symbols)?),
+    };
+    GDB_MANAGER.set_rtos(&session_id, state).await?;
+    Ok(tool_text_content!(format!("RTOS awareness: {}", rtos)))
 }
 
 #[tool(
-    name = "set_breakpoint",
-    description = "Set a breakpoint in the code",
+    name = "get_local_variables",
+    description = "Get local variables in the current stack frame",
     params(
         session_id = "The ID of the GDB session",
-        file = "Source file path",
-        line = "Line number"
+        frame_id = "The ID of the stack frame, defaults to 0, the topest frame",
+        print_values = "no-values for the names only, all-values for the names and values, or \
+                        simple-values (default) for the names, types and values but the \
+                        values of arrays, structures and unions"
     )
 )]
-pub async fn set_breakpoint_tool(
+pub async fn get_local_variables_tool(
     session_id: String,
-    file: String,
-    line: usize,
+    frame_id: Option<Arg<usize>>,
+    print_values: Option<String>,
 ) -> Result<ToolResponseContent> {
-    let breakpoint = GDB_MANAGER.set_breakpoint(&session_id, &PathBuf::from(file), line).await?;
-    Ok(tool_text_content!(format!("Set breakpoint: {}", serde_json::to_string(&breakpoint)?)))
+    let frame_id = opt_arg(frame_id, "frame_id")?;
+    let print_values = print_values.map(|values| values.parse::<PrintValue>()).transpose()?;
+    let variables = GDB_MANAGER.get_local_variables(&session_id, frame_id, print_values).await?;
+    Ok(tool_text_content!(format!("Local variables: {}", serde_json::to_string(&variables)?)))
 }
 
 #[tool(
-    name = "delete_breakpoint",
-    description = "Delete one or more breakpoints in the code",
+    name = "get_frame_details",
+    description = "Get where a stack frame lies on the stack, as info frame shows: its canonical \
+                   frame address (CFA), the saved PC it returns to, the addresses of the frames \
+                   of its caller and callee, of its arguments and locals, and where the \
+                   registers of the caller are saved. Useful when the stack is corrupted and the \
+                   backtrace is broken",
     params(
         session_id = "The ID of the GDB session",
-        breakpoints = "The array of the breakpoint numbers to delete"
+        frame_id = "The ID of the stack frame, defaults to the selected frame"
     )
 )]
-pub async fn delete_breakpoint_tool(
+pub async fn get_frame_details_tool(
     session_id: String,
-    breakpoints: Vec<String>,
+    frame_id: Option<Arg<usize>>,
 ) -> Result<ToolResponseContent> {
-    GDB_MANAGER.delete_breakpoint(&session_id, breakpoints).await?;
-    Ok(tool_text_content!("Breakpoints deleted".to_string()))
+    let frame_id = opt_arg(frame_id, "frame_id")?;
+    let details = GDB_MANAGER.get_frame_details(&session_id, frame_id).await?;
+    Ok(tool_text_content!(format!("Frame details: {}", serde_json::to_string(&details)?)))
 }
 
 #[tool(
-    name = "get_stack_frames",
-    description = "Get stack frames in the current GDB session",
-    params(session_id = "The ID of the GDB session")
+    name = "get_function_arguments",
+    description = "Get the arguments the functions of a range of stack frames were called with",
+    params(
+        session_id = "The ID of the GDB session",
+        low_frame = "The ID of the first stack frame, defaults to 0, the topest frame",
+        high_frame = "The ID of the last stack frame, defaults to 255 frames above low_frame, \
+                      frames past the outermost one are left out",
+        print_values = "no-values for the names only, all-values for the names and values, or \
+                        simple-values (default) for the names, types and values but the \
+                        values of arrays, structures and unions"
+    )
 )]
-pub async fn get_stack_frames_tool(session_id: String) -> Result<ToolResponseContent> {
-    let frames = GDB_MANAGER.get_stack_frames(&session_id).await?;
-    Ok(tool_text_content!(format!("Stack frames: {}", serde_json::to_string(&frames)?)))
+pub async fn get_function_arguments_tool(
+    session_id: String,
+    low_frame: Option<Arg<usize>>,
+    high_frame: Option<Arg<usize>>,
+    print_values: Option<String>,
+) -> Result<ToolResponseContent> {
+    let low_frame = opt_arg(low_frame, "low_frame")?;
+    let high_frame = opt_arg(high_frame, "high_frame")?;
+    let print_values = print_values.map(|values| values.parse::<PrintValue>()).transpose()?;
+    let arguments = GDB_MANAGER
+        .get_function_arguments(&session_id, low_frame, high_frame, print_values)
+        .await?;
+    Ok(tool_text_content!(format!("Function arguments: {}", serde_json::to_string(&arguments)?)))
 }
 
 #[tool(
-    name = "get_local_variables",
-    description = "Get local variables in the current stack frame",
+    name = "get_frame_context",
+    description = "Get a stack frame along with the arguments its function was called with and \
+                   its local variables, in one call",
     params(
         session_id = "The ID of the GDB session",
-        frame_id = "The ID of the stack frame, defaults to 0, the topest frame"
+        frame_id = "The ID of the stack frame, defaults to 0, the topest frame",
+        print_values = "no-values for the names only, all-values for the names and values, or \
+                        simple-values (default) for the names, types and values but the \
+                        values of arrays, structures and unions"
     )
 )]
-pub async fn get_local_variables_tool(
+pub async fn get_frame_context_tool(
     session_id: String,
-    frame_id: Option<usize>,
+    frame_id: Option<Arg<usize>>,
+    print_values: Option<String>,
 ) -> Result<ToolResponseContent> {
-    let variables = GDB_MANAGER.get_local_variables(&session_id, frame_id).await?;
-    Ok(tool_text_content!(format!("Local variables: {}", serde_json::to_string(&variables)?)))
+    let frame_id = opt_arg(frame_id, "frame_id")?;
+    let print_values = print_values.map(|values| values.parse::<PrintValue>()).transpose()?;
+    let context = GDB_MANAGER.get_frame_context(&session_id, frame_id, print_values).await?;
+    Ok(tool_text_content!(format!("Frame context: {}", serde_json::to_string(&context)?)))
 }
 
 #[tool(
     name = "get_registers",
-    description = "Get registers in the current GDB session",
+    description = "Get registers in the current GDB session, all of them unless filtered by \
+                   number, name or group",
     params(
         session_id = "The ID of the GDB session",
-        reg_list = "The array of the registers to get",
+        reg_list = "The array of the numbers of the registers to get",
+        names = "the names of the registers to get, e.g. [\"rip\", \"rsp\", \"rdi\"]",
+        group = "only the registers of a group: general, vector, float or system",
+        vector_format = "return the lanes of the vector registers (xmm, ymm, neon...) as \
+                         bytes, u32, u64, float or double",
+        format = "json (default), or markdown for a table to show to the user"
     )
 )]
 pub async fn get_registers_tool(
     session_id: String,
-    reg_list: Option<Vec<String>>,
+    reg_list: Option<Arg<Vec<String>>>,
+    names: Option<Arg<Vec<String>>>,
+    group: Option<String>,
+    vector_format: Option<String>,
+    format: Option<String>,
 ) -> Result<ToolResponseContent> {
-    let registers = GDB_MANAGER.get_registers(&session_id, reg_list).await?;
-    Ok(tool_text_content!(format!("Registers: {}", serde_json::to_string(&registers)?)))
+    let mut reg_list = opt_arg(reg_list, "reg_list")?;
+    let names = opt_arg(names, "names")?;
+    let group = group.map(|group| group.parse::<RegisterGroup>()).transpose()?;
+    if names.is_some() || group.is_some() {
+        let numbers = GDB_MANAGER.register_numbers(&session_id, names.as_deref(), group).await?;
+        let numbers = match reg_list {
+            Some(list) => numbers.into_iter().filter(|number| list.contains(number)).collect(),
+            None => numbers,
+        };
+        // an empty list would get all of them
+        if numbers.is_empty() {
+            return Err(AppError::NotFound("no register matches".to_string()).into());
+        }
+        reg_list = Some(numbers);
+    }
+    let vector_format = vector_format.map(|format| format.parse::<VectorFormat>()).transpose()?;
+    let format = format.map(|format| format.parse::<OutputFormat>()).transpose()?;
+    let registers = GDB_MANAGER.get_registers(&session_id, reg_list, vector_format).await?;
+    Ok(tool_text_content!(match format.unwrap_or_default() {
+        OutputFormat::Json => format!("Registers: {}", serde_json::to_string(&registers)?),
+        OutputFormat::Markdown => format!("Registers:\n\n{}", markdown::registers(&registers)),
+    }))
 }
 
 #[tool(
@@ -217,12 +1652,130 @@ pub async fn get_registers_tool(
 )]
 pub async fn get_register_names_tool(
     session_id: String,
-    reg_list: Option<Vec<String>>,
+    reg_list: Option<Arg<Vec<String>>>,
 ) -> Result<ToolResponseContent> {
+    let reg_list = opt_arg(reg_list, "reg_list")?;
     let registers = GDB_MANAGER.get_register_names(&session_id, reg_list).await?;
     Ok(tool_text_content!(format!("Registers: {}", serde_json::to_string(&registers)?)))
 }
 
+#[tool(
+    name = "get_register_changes",
+    description = "Get the registers whose value changed between the previous stop and the \
+                   last one, with the old and new values. Once called, wait_for_stop also \
+                   reports them in register_changes",
+    params(session_id = "The ID of the GDB session")
+)]
+pub async fn get_register_changes_tool(session_id: String) -> Result<ToolResponseContent> {
+    let changes = GDB_MANAGER.get_register_changes(&session_id).await?;
+    Ok(tool_text_content!(format!("Register changes: {}", serde_json::to_string(&changes)?)))
+}
+
+#[tool(
+    name = "set_architecture",
+    description = "Set the architecture of a target which does not describe itself, e.g. a bare \
+                   metal remote stub debugged with gdb-multiarch. Returns the updated target \
+                   architecture",
+    params(
+        session_id = "The ID of the GDB session",
+        architecture = "An architecture name known to GDB, e.g. armv7e-m or riscv:rv32, or auto"
+    )
+)]
+pub async fn set_architecture_tool(
+    session_id: String,
+    architecture: String,
+) -> Result<ToolResponseContent> {
+    let arch = GDB_MANAGER.set_architecture(&session_id, &architecture).await?;
+    Ok(tool_text_content!(format!("Architecture: {}", serde_json::to_string(&arch)?)))
+}
+
+#[tool(
+    name = "set_endian",
+    description = "Set the byte order of a target which does not describe itself. Returns the \
+                   updated target architecture",
+    params(session_id = "The ID of the GDB session", endian = "little, big or auto")
+)]
+pub async fn set_endian_tool(session_id: String, endian: String) -> Result<ToolResponseContent> {
+    let endian = match endian.as_str() {
+        "auto" => None,
+        endian => Some(endian.parse::<Endian>()?),
+    };
+    let arch = GDB_MANAGER.set_endian(&session_id, endian).await?;
+    Ok(tool_text_content!(format!("Architecture: {}", serde_json::to_string(&arch)?)))
+}
+
+#[tool(
+    name = "load_svd",
+    description = "Load the CMSIS-SVD file describing the microcontroller debugged in a session, \
+                   so its peripheral registers can be read by name. Returns the peripherals",
+    params(session_id = "The ID of the GDB session", path = "Path to the SVD file")
+)]
+pub async fn load_svd_tool(session_id: String, path: Arg<PathBuf>) -> Result<ToolResponseContent> {
+    let path = path.get("path")?;
+    let peripherals = GDB_MANAGER.load_svd(&session_id, &path).await?;
+    Ok(tool_text_content!(format!("Peripherals: {}", serde_json::to_string(&peripherals)?)))
+}
+
+#[tool(
+    name = "list_peripherals",
+    description = "List the peripherals of the loaded SVD file with their base address",
+    params(session_id = "The ID of the GDB session")
+)]
+pub async fn list_peripherals_tool(session_id: String) -> Result<ToolResponseContent> {
+    let peripherals = GDB_MANAGER.list_peripherals(&session_id).await?;
+    Ok(tool_text_content!(format!("Peripherals: {}", serde_json::to_string(&peripherals)?)))
+}
+
+#[tool(
+    name = "read_peripheral_register",
+    description = "Read a register of a peripheral described by the loaded SVD file from the \
+                   target, and decode its bit fields",
+    params(
+        session_id = "The ID of the GDB session",
+        peripheral = "Name of the peripheral, e.g. GPIOA",
+        register = "Name of the register, e.g. MODER, all registers of the peripheral if omitted"
+    )
+)]
+pub async fn read_peripheral_register_tool(
+    session_id: String,
+    peripheral: String,
+    register: Option<String>,
+) -> Result<ToolResponseContent> {
+    let registers =
+        GDB_MANAGER.read_peripheral_register(&session_id, &peripheral, register.as_deref()).await?;
+    Ok(tool_text_content!(format!("Registers: {}", serde_json::to_string(&registers)?)))
+}
+
+#[tool(
+    name = "disassemble_current_function",
+    description = "Disassemble the function of the selected frame with the raw opcodes. The \
+                   instructions are grouped by the source line they come from when there is line \
+                   information, and the one at the PC is marked with is_pc",
+    params(session_id = "The ID of the GDB session")
+)]
+pub async fn disassemble_current_function_tool(session_id: String) -> Result<ToolResponseContent> {
+    let disassembly = GDB_MANAGER.disassemble_current_function(&session_id).await?;
+    Ok(tool_text_content!(format!("Disassembly: {}", serde_json::to_string(&disassembly)?)))
+}
+
+#[tool(
+    name = "get_current_location",
+    description = "Get where the program stopped in one call: the PC of the selected frame, its \
+                   function, file, line and source text, and the instructions around the PC",
+    params(
+        session_id = "The ID of the GDB session",
+        context = "The number of instructions before and after the PC, 3 by default"
+    )
+)]
+pub async fn get_current_location_tool(
+    session_id: String,
+    context: Option<Arg<usize>>,
+) -> Result<ToolResponseContent> {
+    let context = opt_arg(context, "context")?.unwrap_or(3);
+    let location = GDB_MANAGER.get_current_location(&session_id, context).await?;
+    Ok(tool_text_content!(format!("Location: {}", serde_json::to_string(&location)?)))
+}
+
 #[tool(
     name = "read_memory",
     description = "Read the memory in the current GDB session. \
@@ -243,8 +1796,8 @@ pub async fn get_register_names_tool(
             contents: The contents of the memory block, in hex bytes.",
     params(
         session_id = "The ID of the GDB session",
-        address = "An expression specifying the address of the first addressable memory unit to be read. \
-            Complex expressions containing embedded white space should be quoted using the C convention.",
+        address = "The address of the first addressable memory unit to be read, a number or an expression \
+            the server evaluates, e.g. &buffer, $sp or my_global + 0x10",
         count = "The number of addressable memory units to read. This should be an integer literal.",
         offset = "The offset relative to address at which to start reading. This should be an integer literal. \
             This option is provided so that a frontend is not required to first evaluate address and \
@@ -253,14 +1806,210 @@ pub async fn get_register_names_tool(
 )]
 pub async fn read_memory_tool(
     session_id: String,
-    address: String,
-    count: usize,
-    offset: Option<isize>,
+    address: Arg<String>,
+    count: Arg<usize>,
+    offset: Option<Arg<isize>>,
 ) -> Result<ToolResponseContent> {
+    let address = address.get("address")?;
+    let count = count.get("count")?;
+    let offset = opt_arg(offset, "offset")?;
     let memory = GDB_MANAGER.read_memory(&session_id, offset, address, count).await?;
     Ok(tool_text_content!(format!("Memory: {}", serde_json::to_string(&memory)?)))
 }
 
+#[tool(
+    name = "dump_memory",
+    description = "Write a memory region to a file as it is read, for regions too large to \
+                   return. The unreadable bytes are left as zeros in the file, the readable \
+                   and unreadable ranges are returned",
+    params(
+        session_id = "The ID of the GDB session",
+        address = "The address of the first byte, a number or an expression the server \
+                   evaluates, e.g. &buffer or $sp",
+        count = "The number of bytes to write",
+        path = "The file to write, replaced if it exists"
+    )
+)]
+pub async fn dump_memory_tool(
+    session_id: String,
+    address: String,
+    count: Arg<usize>,
+    path: Arg<PathBuf>,
+) -> Result<ToolResponseContent> {
+    let count = count.get("count")?;
+    let path = path.get("path")?;
+    let dump = GDB_MANAGER.dump_memory(&session_id, &address, count, &path).await?;
+    Ok(tool_text_content!(format!(
+        "Memory written to {}: {}",
+        path.display(),
+        serde_json::to_string(&dump)?
+    )))
+}
+
+#[tool(
+    name = "read_typed_memory",
+    description = "Read memory as an array of scalars, decoded in the byte order of the target",
+    params(
+        session_id = "The ID of the GDB session",
+        address = "The address of the first value, a number or an expression the server \
+            evaluates, e.g. &samples or $sp",
+        data_type = "The type of the values, u8, u16, u32, u64, f32 or f64",
+        count = "The number of values to read, at most 4096",
+        endian = "little or big, the byte order detected for the session by default",
+    )
+)]
+pub async fn read_typed_memory_tool(
+    session_id: String,
+    address: String,
+    data_type: String,
+    count: Arg<usize>,
+    endian: Option<String>,
+) -> Result<ToolResponseContent> {
+    let data_type = data_type.parse::<ScalarType>()?;
+    let count = count.get("count")?;
+    let endian = endian.map(|endian| endian.parse::<Endian>()).transpose()?;
+    let memory =
+        GDB_MANAGER.read_typed_memory(&session_id, &address, data_type, count, endian).await?;
+    Ok(tool_text_content!(format!("Typed memory: {}", serde_json::to_string(&memory)?)))
+}
+
+#[tool(
+    name = "read_string",
+    description = "Read a NUL terminated C string",
+    params(
+        session_id = "The ID of the GDB session",
+        address = "The address of the string, a number or an expression the server evaluates, \
+            e.g. argv[0] or &name",
+        max_length = "The most bytes read when no NUL is found, 256 by default",
+    )
+)]
+pub async fn read_string_tool(
+    session_id: String,
+    address: String,
+    max_length: Option<Arg<usize>>,
+) -> Result<ToolResponseContent> {
+    let max_length = opt_arg(max_length, "max_length")?.unwrap_or(256);
+    let string = GDB_MANAGER.read_string(&session_id, &address, max_length).await?;
+    Ok(tool_text_content!(format!("String: {}", serde_json::to_string(&string)?)))
+}
+
+#[tool(
+    name = "find_strings",
+    description = "Find the printable strings in a memory region, like the strings command, \
+        at most 1000 strings are returned",
+    params(
+        session_id = "The ID of the GDB session",
+        address = "The start of the region, a number or an expression the server evaluates",
+        length = "The size of the region in bytes, at most 1 MiB",
+        min_length = "The shortest string returned, 4 by default",
+    )
+)]
+pub async fn find_strings_tool(
+    session_id: String,
+    address: String,
+    length: Arg<usize>,
+    min_length: Option<Arg<usize>>,
+) -> Result<ToolResponseContent> {
+    let length = length.get("length")?;
+    let min_length = opt_arg(min_length, "min_length")?.unwrap_or(4);
+    let strings = GDB_MANAGER.find_strings(&session_id, &address, length, min_length).await?;
+    Ok(tool_text_content!(format!("Strings: {}", serde_json::to_string(&strings)?)))
+}
+
+#[tool(
+    name = "find_gadgets",
+    description = "Find ROP gadgets in the executable memory of the process: short sequences of \
+                   instructions ending with ret or an indirect jmp or call, in Intel syntax \
+                   separated by ' ; ', each with the first address it is found at. x86 only",
+    params(
+        session_id = "The ID of the GDB session",
+        filter = "if provided, a regex the instructions must match, e.g. '^pop rdi ; ret$'",
+        object = "if provided, only the mappings whose path contains it, e.g. libc",
+        max_instructions = "the most instructions in a gadget, 5 by default",
+        limit = "the most gadgets returned, 100 by default"
+    )
+)]
+pub async fn find_gadgets_tool(
+    session_id: String,
+    filter: Option<String>,
+    object: Option<String>,
+    max_instructions: Option<Arg<usize>>,
+    limit: Option<Arg<usize>>,
+) -> Result<ToolResponseContent> {
+    let max_instructions = opt_arg(max_instructions, "max_instructions")?.unwrap_or(5);
+    let limit = opt_arg(limit, "limit")?.unwrap_or(100);
+    let gadgets = GDB_MANAGER
+        .find_gadgets(&session_id, filter.as_deref(), object.as_deref(), max_instructions, limit)
+        .await?;
+    Ok(tool_text_content!(format!("Gadgets: {}", serde_json::to_string(&gadgets)?)))
+}
+
+#[tool(
+    name = "dump_got",
+    description = "List the GOT slots of the executable and the libraries loaded in the process: \
+                   the PLT slots and the GLOB_DAT relocations with the symbol each is bound to, \
+                   the value of the slot, the object and the symbol it points to, and whether it \
+                   is resolved, not bound yet or suspicious, i.e. pointing outside the code of \
+                   the loaded objects as after a GOT overwrite. The ELF files are read from the \
+                   disk, the process must run on this host",
+    params(
+        session_id = "The ID of the GDB session",
+        object = "if provided, only the objects whose path contains it, e.g. the executable name"
+    )
+)]
+pub async fn dump_got_tool(
+    session_id: String,
+    object: Option<String>,
+) -> Result<ToolResponseContent> {
+    let objects = GDB_MANAGER.dump_got(&session_id, object.as_deref()).await?;
+    Ok(tool_text_content!(format!("GOT: {}", serde_json::to_string(&objects)?)))
+}
+
+#[tool(
+    name = "get_stack_memory",
+    description = "Read the words of the stack from $sp, each with the values it points to \
+        and what they point into (stack, heap, exec or unknown), like the stack view of the TUI",
+    params(
+        session_id = "The ID of the GDB session",
+        words = "The number of words read, 16 by default, at most 1024",
+        depth = "The most pointers followed from a word, 4 by default",
+    )
+)]
+pub async fn get_stack_memory_tool(
+    session_id: String,
+    words: Option<Arg<usize>>,
+    depth: Option<Arg<usize>>,
+) -> Result<ToolResponseContent> {
+    let words = opt_arg(words, "words")?.unwrap_or(16).min(1024);
+    let depth = opt_arg(depth, "depth")?.unwrap_or(4);
+    let stack = GDB_MANAGER.get_stack_memory(&session_id, words, depth).await?;
+    Ok(tool_text_content!(format!("Stack: {}", serde_json::to_string(&stack)?)))
+}
+
+#[tool(
+    name = "analyze_stack_corruption",
+    description = "Rebuild a best-effort backtrace when the stack is corrupted and GDB gives up \
+                   unwinding it after frame 0: the words of the stack from $sp pointing into \
+                   executable mappings, right after a call instruction on x86, are taken as \
+                   return addresses and symbolized. The frames GDB could unwind are returned \
+                   along with them",
+    params(
+        session_id = "The ID of the GDB session",
+        words = "The number of words scanned, 2048 by default, at most 65536",
+        max_frames = "The number of frames rebuilt at most, 64 by default"
+    )
+)]
+pub async fn analyze_stack_corruption_tool(
+    session_id: String,
+    words: Option<Arg<usize>>,
+    max_frames: Option<Arg<usize>>,
+) -> Result<ToolResponseContent> {
+    let words = opt_arg(words, "words")?.unwrap_or(2048).min(65536);
+    let max_frames = opt_arg(max_frames, "max_frames")?.unwrap_or(64).max(1);
+    let report = GDB_MANAGER.analyze_stack_corruption(&session_id, words, max_frames).await?;
+    Ok(tool_text_content!(format!("Stack analysis: {}", serde_json::to_string(&report)?)))
+}
+
 #[tool(
     name = "continue_execution",
     description = "Continue program execution",
@@ -272,21 +2021,191 @@ pub async fn continue_execution_tool(session_id: String) -> Result<ToolResponseC
 }
 
 #[tool(
-    name = "step_execution",
-    description = "Step into next line",
+    name = "set_scheduler_locking",
+    description = "Set which threads run while one is resumed: on runs only the current thread, \
+                   step only while stepping, off all threads, replay only the current one when \
+                   replaying. Returns the previous mode",
+    params(session_id = "The ID of the GDB session", mode = "on, step, off or replay")
+)]
+pub async fn set_scheduler_locking_tool(
+    session_id: String,
+    mode: String,
+) -> Result<ToolResponseContent> {
+    let previous = GDB_MANAGER.set_scheduler_locking(&session_id, mode.parse()?).await?;
+    Ok(tool_text_content!(format!("Scheduler locking set to {}, was {}", mode, previous.as_str())))
+}
+
+#[tool(
+    name = "add_skip",
+    description = "Skip functions or files when stepping into, e.g. standard library internals. \
+                   Returns all the skip entries",
+    params(
+        session_id = "The ID of the GDB session",
+        function = "name of a function to skip",
+        function_regex = "regular expression of the functions to skip, e.g. ^std::",
+        file = "source file to skip",
+        file_glob = "glob pattern of the source files to skip, e.g. /usr/include/*",
+        preset = "rust or cpp to skip the internals of the standard library of the language"
+    )
+)]
+pub async fn add_skip_tool(
+    session_id: String,
+    function: Option<String>,
+    function_regex: Option<String>,
+    file: Option<String>,
+    file_glob: Option<String>,
+    preset: Option<String>,
+) -> Result<ToolResponseContent> {
+    let mut targets = match preset {
+        Some(preset) => preset.parse::<SkipPreset>()?.targets(),
+        None => vec![],
+    };
+    targets.extend(function.map(SkipTarget::Function));
+    targets.extend(function_regex.map(SkipTarget::FunctionRegex));
+    targets.extend(file.map(SkipTarget::File));
+    targets.extend(file_glob.map(SkipTarget::FileGlob));
+    if targets.is_empty() {
+        return Err(AppError::InvalidArgument(
+            "one of function, function_regex, file, file_glob or preset is required".to_string(),
+        )
+        .into());
+    }
+    let skips = GDB_MANAGER.add_skips(&session_id, &targets).await?;
+    Ok(tool_text_content!(format!("Skips: {}", serde_json::to_string(&skips)?)))
+}
+
+#[tool(
+    name = "list_skips",
+    description = "List the functions and files skipped when stepping into",
     params(session_id = "The ID of the GDB session")
 )]
-pub async fn step_execution_tool(session_id: String) -> Result<ToolResponseContent> {
-    let ret = GDB_MANAGER.step_execution(&session_id).await?;
-    Ok(tool_text_content!(format!("Stepped into next line: {}", ret)))
+pub async fn list_skips_tool(session_id: String) -> Result<ToolResponseContent> {
+    let skips = GDB_MANAGER.list_skips(&session_id).await?;
+    Ok(tool_text_content!(format!("Skips: {}", serde_json::to_string(&skips)?)))
+}
+
+#[tool(
+    name = "delete_skip",
+    description = "Delete a skip entry, or all of them. Returns the remaining entries",
+    params(
+        session_id = "The ID of the GDB session",
+        number = "number of the entry as listed by list_skips, all entries if not provided"
+    )
+)]
+pub async fn delete_skip_tool(
+    session_id: String,
+    number: Option<Arg<u32>>,
+) -> Result<ToolResponseContent> {
+    let skips = GDB_MANAGER.delete_skip(&session_id, opt_arg(number, "number")?).await?;
+    Ok(tool_text_content!(format!("Skips: {}", serde_json::to_string(&skips)?)))
+}
+
+#[tool(
+    name = "step_execution",
+    description = "Step into next line, or several lines with count, stopping early at a \
+                   breakpoint or the exit of the program",
+    params(
+        session_id = "The ID of the GDB session",
+        count = "number of lines to step, waits for each step to complete if provided",
+        collect = "return the address, function and line reached by each step",
+        scheduler_locking = "on or step to keep the other threads stopped during this step, \
+                             waits for the step to complete if provided"
+    )
+)]
+pub async fn step_execution_tool(
+    session_id: String,
+    count: Option<Arg<usize>>,
+    collect: Option<Arg<bool>>,
+    scheduler_locking: Option<String>,
+) -> Result<ToolResponseContent> {
+    let count = opt_arg(count, "count")?;
+    let collect = opt_arg(collect, "collect")?.unwrap_or(false);
+    let scheduler_locking =
+        scheduler_locking.map(|mode| mode.parse::<SchedulerLocking>()).transpose()?;
+    if count.is_none() && !collect && scheduler_locking.is_none() {
+        let ret = GDB_MANAGER.step_execution(&session_id).await?;
+        return Ok(tool_text_content!(format!("Stepped into next line: {}", ret)));
+    }
+    let trace = GDB_MANAGER
+        .step_many(&session_id, true, count.unwrap_or(1), collect, scheduler_locking)
+        .await?;
+    Ok(tool_text_content!(format!("Stepped into: {}", serde_json::to_string(&trace)?)))
 }
 
 #[tool(
     name = "next_execution",
-    description = "Step over next line",
-    params(session_id = "The ID of the GDB session")
+    description = "Step over next line, or several lines with count, stopping early at a \
+                   breakpoint or the exit of the program",
+    params(
+        session_id = "The ID of the GDB session",
+        count = "number of lines to step, waits for each step to complete if provided",
+        collect = "return the address, function and line reached by each step",
+        scheduler_locking = "on or step to keep the other threads stopped during this step, \
+                             waits for the step to complete if provided"
+    )
 )]
-pub async fn next_execution_tool(session_id: String) -> Result<ToolResponseContent> {
-    let ret = GDB_MANAGER.next_execution(&session_id).await?;
-    Ok(tool_text_content!(format!("Stepped over next line: {}", ret)))
+pub async fn next_execution_tool(
+    session_id: String,
+    count: Option<Arg<usize>>,
+    collect: Option<Arg<bool>>,
+    scheduler_locking: Option<String>,
+) -> Result<ToolResponseContent> {
+    let count = opt_arg(count, "count")?;
+    let collect = opt_arg(collect, "collect")?.unwrap_or(false);
+    let scheduler_locking =
+        scheduler_locking.map(|mode| mode.parse::<SchedulerLocking>()).transpose()?;
+    if count.is_none() && !collect && scheduler_locking.is_none() {
+        let ret = GDB_MANAGER.next_execution(&session_id).await?;
+        return Ok(tool_text_content!(format!("Stepped over next line: {}", ret)));
+    }
+    let trace = GDB_MANAGER
+        .step_many(&session_id, false, count.unwrap_or(1), collect, scheduler_locking)
+        .await?;
+    Ok(tool_text_content!(format!("Stepped over: {}", serde_json::to_string(&trace)?)))
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json::json;
+
+    use super::*;
+
+    fn arg<T>(value: Value) -> Arg<T> {
+        serde_json::from_value(value).unwrap()
+    }
+
+    #[test]
+    fn test_coerce_bool() {
+        assert!(arg::<bool>(json!(true)).get("nh").unwrap());
+        assert!(arg::<bool>(json!("True")).get("nh").unwrap());
+        assert!(!arg::<bool>(json!("false")).get("nh").unwrap());
+        assert!(arg::<bool>(json!("maybe")).get("nh").is_err());
+    }
+
+    #[test]
+    fn test_coerce_integer() {
+        assert_eq!(arg::<u32>(json!(1234)).get("proc_id").unwrap(), 1234);
+        assert_eq!(arg::<u32>(json!(" 1234 ")).get("proc_id").unwrap(), 1234);
+        assert_eq!(arg::<usize>(json!("0x10")).get("count").unwrap(), 16);
+        assert_eq!(arg::<isize>(json!("-8")).get("offset").unwrap(), -8);
+        assert!(arg::<u32>(json!(-1)).get("proc_id").is_err());
+    }
+
+    #[test]
+    fn test_coerce_list() {
+        assert_eq!(
+            arg::<Vec<String>>(json!(["a", 1])).get("args").unwrap(),
+            vec!["a".to_string(), "1".to_string()]
+        );
+        assert_eq!(
+            arg::<Vec<String>>(json!("--flag value")).get("args").unwrap(),
+            vec!["--flag".to_string(), "value".to_string()]
+        );
+    }
+
+    #[test]
+    fn test_coerce_error_names_parameter() {
+        let err = arg::<PathBuf>(json!(42)).get("program").unwrap_err();
+        assert!(err.to_string().contains("'program'"));
+    }
 }
@@ -5,8 +5,8 @@ use ratatui::style::Style;
 use ratatui::widgets::block::Title;
 use ratatui::widgets::{Block, Borders, Cell, Row, Table, TableState};
 
-use super::{GREEN, ORANGE, PURPLE};
-use crate::App;
+use super::{GREEN, HORIZONTAL_CONTROL_TEXT, ORANGE, PURPLE, scroll_x};
+use crate::{App, Mode};
 
 pub fn draw_asm<'a>(app: &App, f: &mut Frame<'a>, asm: Rect) {
     // Asm
@@ -40,24 +40,23 @@ pub fn draw_asm<'a>(app: &App, f: &mut Frame<'a>, asm: Rect) {
             row.push(Cell::from(""));
         }
 
+        let inst = scroll_x(&a.inst, app.asm_scroll.x);
         let inst_cell = if let Some(pc_index) = pc_index {
-            if pc_index == index {
-                Cell::from(a.inst.to_string()).fg(GREEN)
-            } else {
-                Cell::from(a.inst.to_string()).white()
-            }
+            if pc_index == index { Cell::from(inst).fg(GREEN) } else { Cell::from(inst).white() }
         } else {
-            Cell::from(a.inst.to_string()).dark_gray()
+            Cell::from(inst).dark_gray()
         };
         row.push(inst_cell);
 
         rows.push(Row::new(row));
     }
 
+    // only scrolled in its own view
+    let help = if app.mode == Mode::OnlyInstructions { HORIZONTAL_CONTROL_TEXT } else { "" };
     let tital = if let Some(function_name) = function_name {
-        Title::from(format!("Instructions ({})", function_name).fg(ORANGE))
+        Title::from(format!("Instructions ({}) {}", function_name, help).fg(ORANGE))
     } else {
-        Title::from("Instructions".fg(ORANGE))
+        Title::from(format!("Instructions {}", help).fg(ORANGE))
     };
     if let Some(pc_index) = pc_index {
         let widths = [
@@ -0,0 +1,58 @@
+use ratatui::Frame;
+use ratatui::layout::{Constraint, Rect};
+use ratatui::prelude::Stylize;
+use ratatui::style::Style;
+use ratatui::widgets::{Block, Borders, Row, Table, TableState};
+
+use super::{BLUE, GRAY_FG, GREEN, ORANGE};
+use crate::App;
+use crate::models::BreakPoint;
+
+pub const BREAKPOINT_CONTROL_TEXT: &str =
+    "(up(k), down(j), enable(e), disable(d), delete(x), refresh(r))";
+
+/// Breakpoints of the session in the order of their numbers
+pub fn sorted_breakpoints(app: &App) -> Vec<&BreakPoint> {
+    let mut breakpoints = app.breakpoints.values().collect::<Vec<_>>();
+    breakpoints.sort_by_key(|bp| (bp.number.major, bp.number.minor));
+    breakpoints
+}
+
+pub fn draw_breakpoints<'a>(app: &App, f: &mut Frame<'a>, area: Rect) {
+    let title = format!("Breakpoints {BREAKPOINT_CONTROL_TEXT}");
+    let header = Row::new(["Num", "Type", "Disp", "Enb", "Address", "Location"])
+        .style(Style::new().fg(BLUE));
+    let rows = sorted_breakpoints(app)
+        .into_iter()
+        .map(|bp| {
+            let row = Row::new([
+                bp.number.to_string(),
+                bp.r#type.clone(),
+                bp.display.clone(),
+                if bp.enabled.0 { "y" } else { "n" }.to_string(),
+                bp.address.map(String::from).unwrap_or_default(),
+                bp.src_pos
+                    .as_ref()
+                    .map(|pos| format!("{}:{}", pos.fullname.display(), pos.line))
+                    .unwrap_or_default(),
+            ]);
+            if bp.enabled.0 { row } else { row.style(Style::new().fg(GRAY_FG)) }
+        })
+        .collect::<Vec<_>>();
+
+    let widths = [
+        Constraint::Length(6),
+        Constraint::Length(16),
+        Constraint::Length(6),
+        Constraint::Length(4),
+        Constraint::Length(20),
+        Constraint::Fill(1),
+    ];
+    let table = Table::new(rows, widths)
+        .header(header)
+        .block(Block::default().borders(Borders::ALL).title(title.fg(ORANGE)))
+        .row_highlight_style(Style::new().fg(GREEN))
+        .highlight_symbol(">>");
+    let mut state = TableState::default().with_selected(app.breakpoint_selected);
+    f.render_stateful_widget(table, area, &mut state);
+}
@@ -2,29 +2,35 @@ use ratatui::Frame;
 use ratatui::layout::Rect;
 use ratatui::prelude::Stylize;
 use ratatui::style::Style;
-use ratatui::text::{Line, Span, Text};
-use ratatui::widgets::{Block, Borders, Paragraph};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, Borders, List, ListState};
 
-use super::{ORANGE, PURPLE};
+use super::{GREEN, ORANGE, PURPLE};
 use crate::App;
 
-pub fn draw_bt<'a>(app: &App, f: &mut Frame<'a>, bt_rect: Rect) {
-    let block = Block::default().borders(Borders::TOP).title("Backtrace".fg(ORANGE));
-    let mut lines = vec![];
-    if !app.bt.is_empty() {
-        for b in app.bt.iter() {
-            let loc_span =
-                Span::from(format!("  {:08x}", b.location,)).style(Style::new().fg(PURPLE));
+pub const BT_CONTROL_TEXT: &str = "(up(k), down(j), show locals(enter))";
 
-            let func_span = Span::from(format!("{}", b.function.clone().unwrap_or("".to_string())))
-                .style(Style::new().fg(ORANGE));
-            let spans = vec![loc_span, Span::from(" → "), func_span];
-            let line = Line::from(spans);
-            lines.push(line);
-        }
-    }
+/// Draw the backtrace, the first frames under the other views or all of them
+/// in its own view, with the selected frame highlighted
+pub fn draw_bt<'a>(app: &App, f: &mut Frame<'a>, bt_rect: Rect, full: bool) {
+    let block = if full {
+        Block::default()
+            .borders(Borders::ALL)
+            .title(format!("Backtrace {BT_CONTROL_TEXT}").fg(ORANGE))
+    } else {
+        Block::default().borders(Borders::TOP).title("Backtrace".fg(ORANGE))
+    };
+    let lines = app.bt.iter().map(|b| {
+        let loc_span = Span::from(format!("{:08x}", b.location)).style(Style::new().fg(PURPLE));
+        let func_span =
+            Span::from(b.function.clone().unwrap_or_default()).style(Style::new().fg(ORANGE));
+        Line::from(vec![loc_span, Span::from(" → "), func_span])
+    });
 
-    let text = Text::from(lines);
-    let paragraph = Paragraph::new(text).block(block);
-    f.render_widget(paragraph, bt_rect);
+    let list = List::new(lines)
+        .block(block)
+        .highlight_style(Style::new().fg(GREEN))
+        .highlight_symbol(">>");
+    let mut state = ListState::default().with_selected(Some(app.bt_selected));
+    f.render_stateful_widget(list, bt_rect, &mut state);
 }
@@ -1,10 +1,13 @@
 use ratatui::Frame;
-use ratatui::layout::{Constraint, Flex, Layout, Rect};
-use ratatui::style::{Color, Style, Stylize};
+use ratatui::layout::Rect;
+use ratatui::style::{Color, Modifier, Style, Stylize};
 use ratatui::text::{Line, Span};
 use ratatui::widgets::{Block, Borders, Paragraph, Scrollbar, ScrollbarOrientation};
 
-use super::{BLUE, DARK_GRAY, GREEN, ORANGE, SCROLL_CONTROL_TEXT, YELLOW};
+use super::search::SEARCH_CONTROL_TEXT;
+use super::{
+    BLUE, DARK_GRAY, GREEN, MATCH_BG, ORANGE, RED, SCROLL_CONTROL_TEXT, SELECTED_MATCH_BG, YELLOW,
+};
 use crate::models::{RegisterRaw, TrackedRegister};
 use crate::{App, Endian};
 
@@ -21,18 +24,35 @@ fn to_hexdump_str<'a>(
     let mut lines = Vec::new();
     for (offset, chunk) in buffer.chunks(16).skip(skip).take(take).enumerate() {
         let mut hex_spans = Vec::new();
+        let line_start = (offset + skip) * HEXDUMP_WIDTH;
+        let style = |i: usize, byte: u8| {
+            // changed since the previous stop
+            let changed = app
+                .hexdump_previous
+                .as_ref()
+                .is_some_and(|previous| previous.get(line_start + i) != Some(&byte));
+            let style = if changed {
+                Style::default().fg(RED).add_modifier(Modifier::BOLD)
+            } else {
+                Style::default().fg(color(byte))
+            };
+            match app.hexdump_search.as_ref() {
+                Some(search) if search.is_current(line_start + i) => style.bg(SELECTED_MATCH_BG),
+                Some(search) if search.contains(line_start + i) => style.bg(MATCH_BG),
+                _ => style,
+            }
+        };
         // bytes
-        for byte in chunk.iter() {
-            let color = color(*byte);
-            hex_spans.push(Span::styled(format!("{:02x} ", byte), Style::default().fg(color)));
+        for (i, byte) in chunk.iter().enumerate() {
+            hex_spans.push(Span::styled(format!("{:02x}", byte), style(i, *byte)));
+            hex_spans.push(Span::raw(" "));
         }
 
         // ascii
         hex_spans.push(Span::raw("| "));
-        for byte in chunk.iter() {
+        for (i, byte) in chunk.iter().enumerate() {
             let ascii_char = if byte.is_ascii_graphic() { *byte as char } else { '.' };
-            let color = color(*byte);
-            hex_spans.push(Span::styled(ascii_char.to_string(), Style::default().fg(color)));
+            hex_spans.push(Span::styled(ascii_char.to_string(), style(i, *byte)));
         }
 
         // check if value has a register reference
@@ -141,17 +161,13 @@ fn color(byte: u8) -> Color {
     }
 }
 
-fn popup_area(area: Rect, percent_x: u16) -> Rect {
-    let vertical = Layout::vertical([Constraint::Length(3)]).flex(Flex::Center);
-    let horizontal = Layout::horizontal([Constraint::Percentage(percent_x)]).flex(Flex::Center);
-    let [area] = vertical.areas(area);
-    let [area] = horizontal.areas(area);
-    area
-}
-
 fn block(pos: &str) -> Block {
     let block = Block::default().borders(Borders::ALL).title(
-        format!("Hexdump{pos} {SCROLL_CONTROL_TEXT}, Save(S), HEAP(H), STACK(T))").fg(ORANGE),
+        format!(
+            "Hexdump{pos} {SCROLL_CONTROL_TEXT}, Save(S), HEAP(H), STACK(T), Address(a), \
+             Register(r/R), {SEARCH_CONTROL_TEXT})"
+        )
+        .fg(ORANGE),
     );
     block
 }
@@ -165,15 +181,14 @@ pub fn draw_hexdump<'a>(app: &mut App, f: &mut Frame<'a>, hexdump: Rect) {
         pos = format!("(0x{:02x?})", r.0);
         let data = &r.1;
 
-        let skip = app.hexdump_scroll.scroll;
-        let take = hexdump.height;
-        let lines = to_hexdump_str(app, r.0, data, skip as usize, take as usize);
-        let content_len = data.len() / HEXDUMP_WIDTH;
+        // without the borders
+        let take = hexdump.height.saturating_sub(2) as usize;
+        let content_len = data.len().div_ceil(HEXDUMP_WIDTH);
+        let skip = app.hexdump_scroll.page(content_len, take);
+        let lines = to_hexdump_str(app, r.0, data, skip, take);
 
         let lines: Vec<Line> = lines.into_iter().collect();
         let hexdump_scroll = &mut app.hexdump_scroll;
-        hexdump_scroll.scroll = content_len;
-        hexdump_scroll.state.last();
         let paragraph =
             Paragraph::new(lines).block(block(&pos)).style(Style::default().fg(Color::White));
 
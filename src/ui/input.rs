@@ -0,0 +1,163 @@
+use ratatui::Frame;
+use ratatui::layout::{Position, Rect};
+use ratatui::prelude::Stylize;
+use ratatui::style::Style;
+use ratatui::widgets::{Block, Borders, Paragraph};
+
+use super::{BLUE, GRAY_FG, GREEN};
+use crate::App;
+
+/// Number of commands kept in the history
+const HISTORY_SIZE: usize = 100;
+
+/// Line where GDB CLI commands are typed
+#[derive(Default)]
+pub struct InputBar {
+    /// Keys go to the input bar instead of the views
+    pub active: bool,
+    text: String,
+    /// Cursor position in chars
+    cursor: usize,
+    history: Vec<String>,
+    /// Entry of the history being browsed
+    history_index: Option<usize>,
+}
+
+impl InputBar {
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    pub fn insert(&mut self, c: char) {
+        let index = self.byte_index();
+        self.text.insert(index, c);
+        self.cursor += 1;
+    }
+
+    pub fn backspace(&mut self) {
+        if self.cursor > 0 {
+            self.cursor -= 1;
+            let index = self.byte_index();
+            self.text.remove(index);
+        }
+    }
+
+    pub fn delete(&mut self) {
+        if self.cursor < self.text.chars().count() {
+            let index = self.byte_index();
+            self.text.remove(index);
+        }
+    }
+
+    pub fn left(&mut self) {
+        self.cursor = self.cursor.saturating_sub(1);
+    }
+
+    pub fn right(&mut self) {
+        self.cursor = (self.cursor + 1).min(self.text.chars().count());
+    }
+
+    pub fn home(&mut self) {
+        self.cursor = 0;
+    }
+
+    pub fn end(&mut self) {
+        self.cursor = self.text.chars().count();
+    }
+
+    /// Show the previous command of the history
+    pub fn previous(&mut self) {
+        let index = match self.history_index {
+            Some(0) => 0,
+            Some(index) => index - 1,
+            None if self.history.is_empty() => return,
+            None => self.history.len() - 1,
+        };
+        self.recall(Some(index));
+    }
+
+    /// Show the next command of the history, or an empty line after the last
+    pub fn next(&mut self) {
+        match self.history_index {
+            Some(index) if index + 1 < self.history.len() => self.recall(Some(index + 1)),
+            Some(_) => self.recall(None),
+            None => (),
+        }
+    }
+
+    /// Take the typed command and save it in the history, None if the line is
+    /// blank
+    pub fn submit(&mut self) -> Option<String> {
+        let command = std::mem::take(&mut self.text).trim().to_string();
+        self.cursor = 0;
+        self.history_index = None;
+        if command.is_empty() {
+            return None;
+        }
+        if self.history.last() != Some(&command) {
+            if self.history.len() == HISTORY_SIZE {
+                self.history.remove(0);
+            }
+            self.history.push(command.clone());
+        }
+        Some(command)
+    }
+
+    fn recall(&mut self, index: Option<usize>) {
+        self.history_index = index;
+        self.text = index.map(|index| self.history[index].clone()).unwrap_or_default();
+        self.end();
+    }
+
+    fn byte_index(&self) -> usize {
+        self.text.char_indices().nth(self.cursor).map_or(self.text.len(), |(i, _)| i)
+    }
+}
+
+pub fn draw_input<'a>(app: &App, f: &mut Frame<'a>, area: Rect) {
+    let input = &app.input;
+    let (title, style) = if input.active {
+        ("Command (enter to run, esc to leave, up/down for history)", Style::new().fg(GREEN))
+    } else {
+        ("Command (: to type a GDB command)", Style::new().fg(GRAY_FG))
+    };
+    let paragraph = Paragraph::new(input.text())
+        .style(style)
+        .block(Block::default().borders(Borders::ALL).title(title.fg(BLUE)));
+    f.render_widget(paragraph, area);
+
+    if input.active {
+        // inside the border
+        let x = area.x + 1 + input.cursor as u16;
+        f.set_cursor_position(Position::new(x.min(area.right().saturating_sub(2)), area.y + 1));
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_input_bar() {
+        let mut input = InputBar::default();
+        "info reg".chars().for_each(|c| input.insert(c));
+        input.left();
+        input.left();
+        input.backspace();
+        assert_eq!(input.text(), "info eg");
+        assert_eq!(input.submit().as_deref(), Some("info eg"));
+        "bt".chars().for_each(|c| input.insert(c));
+        assert_eq!(input.submit().as_deref(), Some("bt"));
+        assert_eq!(input.submit(), None);
+
+        input.previous();
+        assert_eq!(input.text(), "bt");
+        input.previous();
+        input.previous();
+        assert_eq!(input.text(), "info eg");
+        input.next();
+        assert_eq!(input.text(), "bt");
+        input.next();
+        assert_eq!(input.text(), "");
+    }
+}
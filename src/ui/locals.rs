@@ -0,0 +1,68 @@
+use ratatui::Frame;
+use ratatui::layout::Constraint::{Fill, Length};
+use ratatui::layout::{Constraint, Layout, Rect};
+use ratatui::prelude::Stylize;
+use ratatui::style::Style;
+use ratatui::widgets::{Block, Borders, Row, Table, TableState};
+
+use super::{BLUE, GRAY_FG, GREEN, ORANGE};
+use crate::App;
+use crate::gdb::watches::WatchValue;
+
+pub const WATCH_CONTROL_TEXT: &str = "(up(k), down(j), expand/collapse(enter), refresh(r))";
+
+/// A line of the tree of the watch expressions
+#[derive(Debug, Clone)]
+pub struct WatchRow {
+    /// 0 for a watch expression, 1 for its children...
+    pub depth: usize,
+    pub watch: WatchValue,
+}
+
+pub fn draw_locals<'a>(app: &App, f: &mut Frame<'a>, area: Rect) {
+    let locals_height = (app.locals.len() as u16 + 3).min(area.height / 2);
+    let vertical = Layout::vertical([Length(locals_height), Fill(1)]);
+    let [locals_area, watches_area] = vertical.areas(area);
+
+    let widths = [Constraint::Length(30), Constraint::Length(30), Constraint::Fill(1)];
+    let rows = app.locals.iter().map(|local| {
+        Row::new([
+            local.name.clone(),
+            local.r#type.clone().unwrap_or_default(),
+            local.value.clone().unwrap_or_default(),
+        ])
+    });
+    let table = Table::new(rows, widths)
+        .header(Row::new(["Name", "Type", "Value"]).style(Style::new().fg(BLUE)))
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title(format!("Locals of frame {}", app.locals_frame).fg(ORANGE)),
+        );
+    f.render_widget(table, locals_area);
+
+    let rows = app.watches.iter().map(|WatchRow { depth, watch }| {
+        let marker = match (watch.numchild, app.watch_expanded.contains(&watch.name)) {
+            (0, _) => " ",
+            (_, true) => "▾",
+            (_, false) => "▸",
+        };
+        let row = Row::new([
+            format!("{}{} {}", "  ".repeat(*depth), marker, watch.expression),
+            watch.type_.clone().unwrap_or_default(),
+            watch.value.clone().unwrap_or_else(|| "<out of scope>".to_string()),
+        ]);
+        if watch.value.is_some() { row } else { row.style(Style::new().fg(GRAY_FG)) }
+    });
+    let table = Table::new(rows, widths)
+        .header(Row::new(["Expression", "Type", "Value"]).style(Style::new().fg(BLUE)))
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title(format!("Watches {WATCH_CONTROL_TEXT}").fg(ORANGE)),
+        )
+        .row_highlight_style(Style::new().fg(GREEN))
+        .highlight_symbol(">>");
+    let mut state = TableState::default().with_selected(app.watch_selected);
+    f.render_stateful_widget(table, watches_area, &mut state);
+}
@@ -2,19 +2,20 @@ use ratatui::Frame;
 use ratatui::layout::{Constraint, Rect};
 use ratatui::prelude::Stylize;
 use ratatui::style::Style;
-use ratatui::widgets::{Block, Borders, Row, Scrollbar, ScrollbarOrientation, Table};
+use ratatui::widgets::{Block, Borders, Row, Scrollbar, ScrollbarOrientation, Table, TableState};
 
-use super::{BLUE, ORANGE, SCROLL_CONTROL_TEXT};
+use super::{BLUE, GREEN, HORIZONTAL_CONTROL_TEXT, ORANGE, SCROLL_CONTROL_TEXT, scroll_x};
 use crate::App;
 
 pub fn draw_mapping<'a>(app: &mut App, f: &mut Frame<'a>, mapping_rect: Rect) {
-    let title = format!("Memory Mapping {SCROLL_CONTROL_TEXT}");
+    let title = format!(
+        "Memory Mapping {SCROLL_CONTROL_TEXT}, paths {HORIZONTAL_CONTROL_TEXT}, hexdump(enter)"
+    );
 
-    let mut rows = vec![];
-    rows.push(
+    let header =
         Row::new(["Start Address", "End Address", "Size", "Offset", "Permissions", "Path"])
-            .style(Style::new().fg(BLUE)),
-    );
+            .style(Style::new().fg(BLUE));
+    let mut rows = vec![];
     if let Some(memory_map) = app.memory_map.as_ref() {
         for m in memory_map {
             let row = Row::new([
@@ -23,19 +24,20 @@ pub fn draw_mapping<'a>(app: &mut App, f: &mut Frame<'a>, mapping_rect: Rect) {
                 format!("0x{:08x}", m.size),
                 format!("0x{:08x}", m.offset),
                 m.permissions.clone().unwrap_or("".to_string()),
-                m.path.as_ref().map_or("".to_string(), |p| p.to_string_lossy().to_string()),
+                m.path.as_ref().map_or("".to_string(), |p| {
+                    scroll_x(&p.to_string_lossy(), app.memory_map_scroll.x)
+                }),
             ]);
             rows.push(row);
         }
     }
     let len = rows.len();
-    let max = mapping_rect.height;
-    let skip = if len <= max as usize { 0 } else { app.memory_map_scroll.scroll };
 
+    // the mappings may have been reloaded since the selection
     let memory_map_scroll = &mut app.memory_map_scroll;
-    memory_map_scroll.scroll = len;
-    memory_map_scroll.state.last();
-    let rows: Vec<Row> = rows.into_iter().skip(skip).take(max as usize).collect();
+    memory_map_scroll.scroll = memory_map_scroll.scroll.min(len.saturating_sub(1));
+    memory_map_scroll.state =
+        memory_map_scroll.state.content_length(len).position(memory_map_scroll.scroll);
 
     let widths = [
         Constraint::Length(20),
@@ -46,8 +48,13 @@ pub fn draw_mapping<'a>(app: &mut App, f: &mut Frame<'a>, mapping_rect: Rect) {
         Constraint::Fill(1),
     ];
     let block = Block::default().borders(Borders::ALL).title(title.fg(ORANGE));
-    let table = Table::new(rows, widths).block(block);
-    f.render_widget(table, mapping_rect);
+    let table = Table::new(rows, widths)
+        .header(header)
+        .block(block)
+        .row_highlight_style(Style::new().fg(GREEN))
+        .highlight_symbol(">>");
+    let mut table_state = TableState::default().with_selected(memory_map_scroll.scroll);
+    f.render_stateful_widget(table, mapping_rect, &mut table_state);
     f.render_stateful_widget(
         Scrollbar::new(ScrollbarOrientation::VerticalRight),
         mapping_rect,
@@ -1,21 +1,29 @@
 use std::path::Path;
 
 use ratatui::Frame;
-use ratatui::layout::Constraint::{Fill, Length, Min};
-use ratatui::layout::Layout;
+use ratatui::layout::Constraint::{Fill, Length, Min, Percentage};
+use ratatui::layout::{Flex, Layout, Rect};
+use ratatui::prelude::Stylize;
 use ratatui::style::{Color, Style};
 use ratatui::text::Span;
+use ratatui::widgets::{Block, Borders, Clear, Paragraph};
 
+use crate::gdb::memory::is_text;
 use crate::models::{MemoryType, ResolveSymbol};
-use crate::{App, Mode};
+use crate::{App, Mode, Popup};
 
 pub mod asm;
+pub mod breakpoints;
 pub mod bt;
 pub mod hexdump;
+pub mod input;
+pub mod locals;
 pub mod mapping;
 pub mod output;
 pub mod registers;
+pub mod search;
 pub mod stack;
+pub mod status;
 pub mod title;
 
 // Ayu bell colors
@@ -28,6 +36,8 @@ const RED: Color = Color::Rgb(0xff, 0x33, 0x33);
 const DARK_GRAY: Color = Color::Rgb(0x20, 0x27, 0x34);
 const GRAY: Color = Color::Rgb(0x44, 0x44, 0x44);
 const GRAY_FG: Color = Color::Rgb(100, 100, 100);
+const MATCH_BG: Color = Color::Rgb(0x3d, 0x42, 0x4d);
+const SELECTED_MATCH_BG: Color = Color::Rgb(0x80, 0x60, 0x20);
 
 const HEAP_COLOR: Color = GREEN;
 const STACK_COLOR: Color = PURPLE;
@@ -37,36 +47,79 @@ const ASM_COLOR: Color = ORANGE;
 
 const SAVED_OUTPUT: usize = 10;
 
+/// Height of the command input bar with its borders
+const INPUT_HEIGHT: u16 = 3;
+
 /// Amount of stack addresses we save/display
 pub const SAVED_STACK: u16 = 14;
 
+/// Amount of frames we display in the backtrace under the other views
+const SAVED_BT: usize = 10;
+
 pub const SCROLL_CONTROL_TEXT: &str = "(up(k), down(j), 50 up(K), 50 down(J), top(g), bottom(G))";
 
+pub const HORIZONTAL_CONTROL_TEXT: &str = "(left(h), right(l))";
+
+/// The part of a line after the `x` columns scrolled out on the left
+fn scroll_x(line: &str, x: usize) -> String {
+    line.chars().skip(x).collect()
+}
+
 pub fn ui<'a>(f: &mut Frame<'a>, app: &mut App) {
+    draw_views(f, app);
+
+    if let Some((popup, input)) = app.popup.as_ref() {
+        let title = match popup {
+            Popup::GotoAddress => "Go to address or expression (enter to go, esc to cancel)",
+            Popup::Search => "Search, regex in the output, hex bytes or \"text\" in the hexdump",
+        };
+        let area = popup_area(f.area(), 50);
+        let paragraph = Paragraph::new(input.text())
+            .block(Block::default().borders(Borders::ALL).title(title.fg(ORANGE)));
+        f.render_widget(Clear, area);
+        f.render_widget(paragraph, area);
+    }
+}
+
+fn popup_area(area: Rect, percent_x: u16) -> Rect {
+    let vertical = Layout::vertical([Length(3)]).flex(Flex::Center);
+    let horizontal = Layout::horizontal([Percentage(percent_x)]).flex(Flex::Center);
+    let [area] = vertical.areas(area);
+    let [area] = horizontal.areas(area);
+    area
+}
+
+fn draw_views<'a>(f: &mut Frame<'a>, app: &mut App) {
+    let [area, status_area] = Layout::vertical([Fill(1), Length(1)]).areas(f.area());
+    status::draw_status(app, f, status_area);
+
     // TODO: register size should depend on arch
     let top_size = Fill(1);
 
     // If only output, then no top and fill all with output
     if let Mode::OnlyOutput = app.mode {
         let output_size = Fill(1);
-        let vertical = Layout::vertical([Length(2), output_size]);
-        let [title_area, output] = vertical.areas(f.area());
+        let vertical = Layout::vertical([Length(2), output_size, Length(INPUT_HEIGHT)]);
+        let [title_area, output, input_area] = vertical.areas(area);
 
         title::draw_title_area(app, f, title_area);
         output::draw_output(app, f, output, true);
+        input::draw_input(app, f, input_area);
         return;
     }
 
     // the rest will include the top
     let output_size = Length(SAVED_OUTPUT as u16);
 
-    let bt_len = app.bt.len();
+    // the backtrace view shows all the frames instead
+    let bt_len = if app.mode == Mode::OnlyBacktrace { 0 } else { app.bt.len().min(SAVED_BT) };
     let top = if bt_len == 0 {
-        let vertical = Layout::vertical([Length(2), top_size, output_size]);
-        let [title_area, top, output] = vertical.areas(f.area());
+        let vertical = Layout::vertical([Length(2), top_size, output_size, Length(INPUT_HEIGHT)]);
+        let [title_area, top, output, input_area] = vertical.areas(area);
 
         title::draw_title_area(app, f, title_area);
         output::draw_output(app, f, output, false);
+        input::draw_input(app, f, input_area);
 
         top
     } else {
@@ -75,13 +128,14 @@ pub fn ui<'a>(f: &mut Frame<'a>, app: &mut App) {
             top_size,
             Length(bt_len as u16 + 1),
             output_size,
-            Length(3),
+            Length(INPUT_HEIGHT),
         ]);
-        let [title_area, top, bt_area, output] = vertical.areas(f.area());
+        let [title_area, top, bt_area, output, input_area] = vertical.areas(area);
 
-        bt::draw_bt(app, f, bt_area);
+        bt::draw_bt(app, f, bt_area, false);
         title::draw_title_area(app, f, title_area);
         output::draw_output(app, f, output, false);
+        input::draw_input(app, f, input_area);
 
         top
     };
@@ -124,6 +178,21 @@ pub fn ui<'a>(f: &mut Frame<'a>, app: &mut App) {
             let [all] = vertical.areas(top);
             hexdump::draw_hexdump(app, f, all);
         }
+        Mode::OnlyBreakpoints => {
+            let vertical = Layout::vertical([Fill(1)]);
+            let [all] = vertical.areas(top);
+            breakpoints::draw_breakpoints(app, f, all);
+        }
+        Mode::OnlyLocals => {
+            let vertical = Layout::vertical([Fill(1)]);
+            let [all] = vertical.areas(top);
+            locals::draw_locals(app, f, all);
+        }
+        Mode::OnlyBacktrace => {
+            let vertical = Layout::vertical([Fill(1)]);
+            let [all] = vertical.areas(top);
+            bt::draw_bt(app, f, all, true);
+        }
         _ => (),
     }
 }
@@ -157,10 +226,7 @@ pub fn add_resolve_symbol_to_span<'a>(
         // check if ascii
         if *v > 0xff {
             let bytes = (*v).to_le_bytes();
-            if bytes
-                .iter()
-                .all(|a| a.is_ascii_alphabetic() || a.is_ascii_graphic() || a.is_ascii_whitespace())
-            {
+            if bytes.iter().all(|a| is_text(*a)) {
                 // if we detect it's ascii, the rest is ascii
                 let mut full_s = String::new();
                 for r in resolve_symbol.map.iter().skip(i) {
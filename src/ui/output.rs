@@ -1,39 +1,46 @@
 use ratatui::Frame;
 use ratatui::layout::Rect;
 use ratatui::prelude::Stylize;
+use ratatui::style::Style;
 use ratatui::text::{Line, Span};
 use ratatui::widgets::{Block, Borders, List, ListItem, Scrollbar, ScrollbarOrientation};
 
-use super::{BLUE, SCROLL_CONTROL_TEXT};
+use super::search::SEARCH_CONTROL_TEXT;
+use super::{
+    BLUE, HORIZONTAL_CONTROL_TEXT, MATCH_BG, SCROLL_CONTROL_TEXT, SELECTED_MATCH_BG, scroll_x,
+};
 use crate::App;
 
 pub fn draw_output<'a>(app: &mut App, f: &mut Frame<'a>, output: Rect, full: bool) {
     let len = app.output.len();
-    let max = output.height;
-    let skip = if full {
-        if len <= max as usize { 0 } else { app.output_scroll.scroll }
-    } else if len <= max as usize {
-        0
-    } else {
-        len - max as usize + 2
-    };
-
-    let output_scroll = &mut app.output_scroll;
-    output_scroll.scroll = len;
-    output_scroll.state.last();
+    // without the borders
+    let max = output.height.saturating_sub(2) as usize;
+    // the small view always shows the last lines
+    let skip = if full { app.output_scroll.page(len, max) } else { len.saturating_sub(max) };
 
+    let search = app.output_search.as_ref().filter(|_| full);
     let outputs: Vec<ListItem> = app
         .output
         .iter()
+        .enumerate()
         .skip(skip)
-        .take(max as usize)
-        .map(|m| {
-            let m = m.replace('\t', "    ");
-            let content = vec![Line::from(Span::raw(m.to_string()))];
+        .take(max)
+        .map(|(i, m)| {
+            let m = scroll_x(&m.replace('\t', "    "), app.output_scroll.x);
+            let style = match search {
+                Some(search) if search.is_current(i) => Style::new().bg(SELECTED_MATCH_BG),
+                Some(search) if search.contains(i) => Style::new().bg(MATCH_BG),
+                _ => Style::new(),
+            };
+            let content = vec![Line::from(Span::styled(m.to_string(), style))];
             ListItem::new(content)
         })
         .collect();
-    let help = if full { SCROLL_CONTROL_TEXT } else { "" };
+    let help = if full {
+        format!("{SCROLL_CONTROL_TEXT} {HORIZONTAL_CONTROL_TEXT} ({SEARCH_CONTROL_TEXT})")
+    } else {
+        String::new()
+    };
     let output_block = List::new(outputs)
         .block(Block::default().borders(Borders::ALL).title(format!("Output {help}").fg(BLUE)));
     f.render_widget(output_block, output);
@@ -43,7 +50,7 @@ pub fn draw_output<'a>(app: &mut App, f: &mut Frame<'a>, output: Rect, full: boo
         f.render_stateful_widget(
             Scrollbar::new(ScrollbarOrientation::VerticalRight),
             output,
-            &mut output_scroll.state,
+            &mut app.output_scroll.state,
         );
     }
 }
@@ -9,7 +9,7 @@ use ratatui::widgets::{Block, Borders, Paragraph};
 
 use super::{ORANGE, PURPLE, RED, add_resolve_symbol_to_span, apply_val_color};
 use crate::App;
-use crate::models::{RegisterRaw, TrackedRegister};
+use crate::models::{RegisterRaw, TrackedRegister, flag_layout};
 
 /// Registers
 pub fn draw_registers<'a>(app: &App, f: &mut Frame<'a>, register: Rect) {
@@ -75,6 +75,14 @@ pub fn draw_registers<'a>(app: &App, f: &mut Frame<'a>, register: Rect) {
                         reg_name = reg_name.style(Style::new().fg(RED));
                     }
                     let mut line = Line::from(vec![reg_name, span]);
+                    if let Some(layout) = flag_layout(name) {
+                        let set: Vec<&str> = layout
+                            .iter()
+                            .filter(|(_, bit)| val.0 >> bit & 1 == 1)
+                            .map(|(flag, _)| *flag)
+                            .collect();
+                        line.spans.push(Span::from(format!("[ {} ]", set.join(" "))));
+                    }
                     line.spans.append(&mut extra_derefs);
                     lines.push(line);
                 }
@@ -0,0 +1,116 @@
+use regex::Regex;
+
+use crate::error::{AppError, AppResult};
+
+pub const SEARCH_CONTROL_TEXT: &str = "search(/), next(n), previous(N)";
+
+/// Matches of a search, lines of the output or offsets in the hexdump
+#[derive(Debug, Clone, Default)]
+pub struct Search {
+    pub matches: Vec<usize>,
+    /// Length of a match in bytes, in the hexdump
+    pub len: usize,
+    /// Index of the match scrolled to
+    pub current: usize,
+}
+
+impl Search {
+    /// Output lines matching a regular expression
+    pub fn lines(lines: &[String], pattern: &str) -> AppResult<Self> {
+        let regex = Regex::new(pattern)
+            .map_err(|e| AppError::InvalidArgument(format!("invalid regex {}: {}", pattern, e)))?;
+        let matches = lines
+            .iter()
+            .enumerate()
+            .filter(|(_, line)| regex.is_match(line))
+            .map(|(i, _)| i)
+            .collect();
+        Ok(Self { matches, len: 1, current: 0 })
+    }
+
+    /// Offsets of a byte pattern: hex bytes like `de ad be ef` or `0xdeadbeef`,
+    /// or text, quoted with `"` to search text which looks like hex
+    pub fn bytes(bytes: &[u8], pattern: &str) -> AppResult<Self> {
+        let needle = parse_pattern(pattern);
+        if needle.is_empty() {
+            return Err(AppError::InvalidArgument("empty search pattern".to_string()));
+        }
+        let matches = bytes
+            .windows(needle.len())
+            .enumerate()
+            .filter(|(_, window)| *window == needle.as_slice())
+            .map(|(i, _)| i)
+            .collect();
+        Ok(Self { matches, len: needle.len(), current: 0 })
+    }
+
+    pub fn current(&self) -> Option<usize> {
+        self.matches.get(self.current).copied()
+    }
+
+    pub fn next(&mut self) -> Option<usize> {
+        if !self.matches.is_empty() {
+            self.current = (self.current + 1) % self.matches.len();
+        }
+        self.current()
+    }
+
+    pub fn previous(&mut self) -> Option<usize> {
+        if !self.matches.is_empty() {
+            self.current = (self.current + self.matches.len() - 1) % self.matches.len();
+        }
+        self.current()
+    }
+
+    /// A byte offset or a line is part of a match
+    pub fn contains(&self, position: usize) -> bool {
+        // the last match starting at or before the position
+        let index = self.matches.partition_point(|&start| start <= position);
+        index > 0 && position < self.matches[index - 1] + self.len
+    }
+
+    /// A byte offset or a line is part of the current match
+    pub fn is_current(&self, position: usize) -> bool {
+        self.current().is_some_and(|start| (start..start + self.len).contains(&position))
+    }
+}
+
+fn parse_pattern(pattern: &str) -> Vec<u8> {
+    if let Some(text) = pattern.strip_prefix('"') {
+        return text.strip_suffix('"').unwrap_or(text).as_bytes().to_vec();
+    }
+    let hex = pattern.strip_prefix("0x").unwrap_or(pattern).replace(' ', "");
+    let bytes = (0..hex.len())
+        .step_by(2)
+        .map(|i| hex.get(i..i + 2).and_then(|byte| u8::from_str_radix(byte, 16).ok()))
+        .collect::<Option<Vec<_>>>();
+    match bytes {
+        Some(bytes) if hex.len().is_multiple_of(2) => bytes,
+        _ => pattern.as_bytes().to_vec(),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_search_bytes() {
+        let bytes = b"\x00\xde\xad\xbe\xef hello \xde\xad";
+        let mut search = Search::bytes(bytes, "de ad").unwrap();
+        assert_eq!(search.matches, vec![1, 12]);
+        assert!(search.contains(2));
+        assert!(!search.contains(3));
+        assert_eq!(search.next(), Some(12));
+        assert_eq!(search.next(), Some(1));
+        assert_eq!(search.previous(), Some(12));
+
+        assert_eq!(Search::bytes(bytes, "0xdeadbeef").unwrap().matches, vec![1]);
+        assert_eq!(Search::bytes(bytes, "hello").unwrap().matches, vec![6]);
+        assert_eq!(Search::bytes(b"cafe", "\"cafe\"").unwrap().matches, vec![0]);
+
+        let lines = vec!["(gdb) bt".to_string(), "#0 main".to_string(), "#1 start".to_string()];
+        assert_eq!(Search::lines(&lines, "^#\\d").unwrap().matches, vec![1, 2]);
+        assert!(Search::lines(&lines, "(").is_err());
+    }
+}
@@ -0,0 +1,50 @@
+use ratatui::Frame;
+use ratatui::layout::Constraint::{Fill, Length};
+use ratatui::layout::{Layout, Rect};
+use ratatui::style::Style;
+use ratatui::text::{Line, Span};
+use ratatui::widgets::Paragraph;
+
+use super::{BLUE, GRAY_FG, RED, STACK_COLOR};
+use crate::models::Endian;
+use crate::{App, TransportType};
+
+pub fn draw_status(app: &App, f: &mut Frame, area: Rect) {
+    let separator = || Span::styled(" | ", Style::default().fg(GRAY_FG));
+
+    let transport = match app.transport {
+        Some(TransportType::Stdio) => "stdio",
+        Some(TransportType::Sse) => "sse",
+        None => "starting",
+    };
+    let clients = match app.clients {
+        Some(1) => "1 client".to_string(),
+        Some(clients) => format!("{} clients", clients),
+        None => "? clients".to_string(),
+    };
+    let mut spans = vec![
+        Span::styled(transport, Style::default().fg(BLUE)),
+        separator(),
+        Span::raw(clients),
+        separator(),
+        Span::raw(format!(
+            "{}-bit {}(W/E)",
+            if app.bit32 { 32 } else { 64 },
+            if app.endian == Some(Endian::Big) { "BE" } else { "LE" }
+        )),
+        separator(),
+        Span::raw(app.status.clone()),
+    ];
+    if let Some(command) = &app.activity.last_command {
+        spans.extend([separator(), Span::styled(command.clone(), Style::default().fg(GRAY_FG))]);
+    }
+    if let Some(error) = &app.activity.last_error {
+        spans.extend([separator(), Span::styled(error.clone(), Style::default().fg(RED))]);
+    }
+
+    let stop = Line::from(Span::styled(app.async_result.clone(), Style::default().fg(STACK_COLOR)));
+    let horizontal = Layout::horizontal([Fill(1), Length(stop.width() as u16)]);
+    let [left, right] = horizontal.areas(area);
+    f.render_widget(Paragraph::new(Line::from(spans)), left);
+    f.render_widget(Paragraph::new(stop), right);
+}
@@ -66,6 +66,9 @@ pub fn draw_title_area(app: &App, f: &mut Frame, title_area: Rect) {
         "F5 Output",
         "F6 Mapping",
         "F7 Hexdump",
+        "F8 Breakpoints",
+        "F9 Locals",
+        "F10 Backtrace",
     ])
     .block(Block::new().title_alignment(Alignment::Center))
     .style(Style::default())
@@ -0,0 +1,197 @@
+//! End to end tests driving the server over the stdio transport against a
+//! real GDB and the `test_app` fixture. They need GDB in PATH and only run
+//! when `GDB_INTEGRATION_TESTS` is set
+
+use std::process::Stdio;
+use std::time::Duration;
+
+use serde_json::{Value, json};
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader, Lines};
+use tokio::process::{Child, ChildStdin, ChildStdout, Command};
+
+const TIMEOUT: Duration = Duration::from_secs(30);
+
+struct Server {
+    _child: Child,
+    stdin: ChildStdin,
+    stdout: Lines<BufReader<ChildStdout>>,
+    next_id: u64,
+}
+
+impl Server {
+    /// Spawn the server and initialize the MCP connection, None when the
+    /// tests are not enabled
+    async fn start() -> Option<Server> {
+        if std::env::var_os("GDB_INTEGRATION_TESTS").is_none() {
+            eprintln!("GDB_INTEGRATION_TESTS is not set, skipping");
+            return None;
+        }
+        let log_dir = std::env::temp_dir().join("mcp-server-gdb-tests");
+        let mut child = Command::new(env!("CARGO_BIN_EXE_mcp-server-gdb"))
+            .arg("--log-dir")
+            .arg(log_dir)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .kill_on_drop(true)
+            .spawn()
+            .expect("failed to start the server");
+        let stdin = child.stdin.take().unwrap();
+        let stdout = BufReader::new(child.stdout.take().unwrap()).lines();
+        let mut server = Server { _child: child, stdin, stdout, next_id: 1 };
+
+        server
+            .request(
+                "initialize",
+                json!({
+                    "protocolVersion": "2024-11-05",
+                    "capabilities": {},
+                    "clientInfo": {"name": "integration-tests", "version": "1.0"}
+                }),
+            )
+            .await;
+        server.send(json!({"jsonrpc": "2.0", "method": "notifications/initialized"})).await;
+        Some(server)
+    }
+
+    async fn send(&mut self, message: Value) {
+        let line = format!("{}\n", message);
+        self.stdin.write_all(line.as_bytes()).await.unwrap();
+        self.stdin.flush().await.unwrap();
+    }
+
+    /// Send a request and return its result, skipping the notifications
+    async fn request(&mut self, method: &str, params: Value) -> Value {
+        let id = self.next_id;
+        self.next_id += 1;
+        self.send(json!({"jsonrpc": "2.0", "id": id, "method": method, "params": params})).await;
+        loop {
+            let line = tokio::time::timeout(TIMEOUT, self.stdout.next_line())
+                .await
+                .expect("no response from the server")
+                .unwrap()
+                .expect("the server exited");
+            let Ok(message) = serde_json::from_str::<Value>(&line) else {
+                continue;
+            };
+            if message.get("id") != Some(&json!(id)) {
+                continue;
+            }
+            if let Some(error) = message.get("error") {
+                panic!("{} failed: {}", method, error);
+            }
+            return message["result"].clone();
+        }
+    }
+
+    /// Call a tool and return its text after the label, e.g. the JSON after
+    /// `Stopped: `
+    async fn call(&mut self, tool: &str, arguments: Value) -> String {
+        let result =
+            self.request("tools/call", json!({"name": tool, "arguments": arguments})).await;
+        let text = result["content"][0]["text"].as_str().unwrap_or_default().to_string();
+        assert_ne!(result.get("isError"), Some(&json!(true)), "{} failed: {}", tool, text);
+        text.split_once(": ").map_or(text.clone(), |(_, value)| value.to_string())
+    }
+
+    async fn create_session(&mut self, args: &[&str]) -> String {
+        self.call(
+            "create_session",
+            json!({"program": env!("CARGO_BIN_EXE_test_app"), "args": args}),
+        )
+        .await
+    }
+}
+
+/// Line of the fixture marked `// breakpoint`
+fn breakpoint_line() -> usize {
+    let source = include_str!("../src/bin/test_app.rs");
+    source.lines().position(|line| line.ends_with("// breakpoint")).unwrap() + 1
+}
+
+#[tokio::test]
+async fn test_breakpoint_backtrace_memory() {
+    let Some(mut server) = Server::start().await else {
+        return;
+    };
+    let session = server.create_session(&[]).await;
+    let session = json!(session);
+
+    server
+        .call(
+            "set_breakpoint",
+            json!({"session_id": session, "file": "test_app.rs", "line": breakpoint_line()}),
+        )
+        .await;
+    server.call("start_debugging", json!({"session_id": session})).await;
+    let stopped: Value =
+        serde_json::from_str(&server.call("wait_for_stop", json!({"session_id": session})).await)
+            .unwrap();
+    assert_eq!(stopped["reason"], "breakpoint-hit");
+
+    let frames: Value = serde_json::from_str(
+        &server.call("get_stack_frames", json!({"session_id": session})).await,
+    )
+    .unwrap();
+    let functions = frames
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|frame| frame["func"].as_str().unwrap_or_default().to_string())
+        .collect::<Vec<_>>();
+    assert!(functions[0].ends_with("compute"), "{:?}", functions);
+    assert!(functions.iter().any(|function| function.ends_with("main")), "{:?}", functions);
+
+    let locals = server.call("get_local_variables", json!({"session_id": session})).await;
+    assert!(locals.contains("doubled") && locals.contains("40"), "{}", locals);
+
+    let memory: Value = serde_json::from_str(
+        &server
+            .call(
+                "read_memory",
+                json!({"session_id": session, "address": "&test_app::MESSAGE", "count": 5}),
+            )
+            .await,
+    )
+    .unwrap();
+    // Hello
+    assert_eq!(memory[0]["contents"], "48656c6c6f");
+
+    server.call("continue_execution", json!({"session_id": session})).await;
+    let stopped: Value =
+        serde_json::from_str(&server.call("wait_for_stop", json!({"session_id": session})).await)
+            .unwrap();
+    assert_eq!(stopped["reason"], "exited-normally");
+    server.call("close_session", json!({"session_id": session})).await;
+}
+
+#[tokio::test]
+async fn test_interrupt() {
+    let Some(mut server) = Server::start().await else {
+        return;
+    };
+    let session = server.create_session(&["--wait"]).await;
+    let session = json!(session);
+
+    server.call("start_debugging", json!({"session_id": session})).await;
+    tokio::time::sleep(Duration::from_secs(1)).await;
+    server.call("stop_debugging", json!({"session_id": session})).await;
+    let stopped: Value =
+        serde_json::from_str(&server.call("wait_for_stop", json!({"session_id": session})).await)
+            .unwrap();
+    assert_eq!(stopped["reason"], "signal-received");
+
+    let frames: Value = serde_json::from_str(
+        &server.call("get_stack_frames", json!({"session_id": session})).await,
+    )
+    .unwrap();
+    assert!(
+        frames
+            .as_array()
+            .unwrap()
+            .iter()
+            .any(|frame| frame["func"].as_str().is_some_and(|function| function.ends_with("main"))),
+        "{}",
+        frames
+    );
+    server.call("close_session", json!({"session_id": session})).await;
+}